@@ -0,0 +1,160 @@
+//! Compares scoring every Polymarket × Kalshi pair with `MatchIndex`.
+//!
+//! Run with `cargo bench --bench matching`.
+
+use chrono::{Duration, TimeZone, Utc};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use polymarket_kalshi_arbitrage_bot::{
+    config::MatchingConfig,
+    matching::{MarketFeatures, MarketMatcher, MatchIndex},
+    models::{Market, Platform},
+};
+use rust_decimal::Decimal;
+
+const SYLLABLES: &[&str] = &["ka", "lo", "mi", "ra", "te", "su", "no", "vi", "da", "re"];
+const MONTHS: &[&str] = &[
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const TOPICS: &[&str] = &[
+    "approval rating",
+    "poll lead",
+    "fundraising total",
+    "follower count",
+    "vote share",
+];
+
+/// A made-up capitalized name, different for every `n`.
+fn name(mut n: usize) -> String {
+    let mut name = String::new();
+    loop {
+        name.push_str(SYLLABLES[n % SYLLABLES.len()]);
+        n /= SYLLABLES.len();
+        if n == 0 {
+            break;
+        }
+    }
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+fn market(platform: Platform, id: String, question: String, day: i64) -> Market {
+    Market {
+        id,
+        question,
+        platform,
+        yes_price: Decimal::new(50, 2),
+        no_price: Decimal::new(50, 2),
+        volume: Decimal::ZERO,
+        liquidity: Decimal::ZERO,
+        end_time: Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap() + Duration::days(day),
+        yes_token_id: None,
+        no_token_id: None,
+        rules: None,
+        resolution_source: None,
+        settlement_timer_seconds: None,
+        event_id: None,
+        event_title: None,
+        outcome_name: None,
+        neg_risk: false,
+        condition_id: None,
+    }
+}
+
+/// `n` markets per venue about `n` people, every fourth Polymarket market
+/// with a Kalshi counterpart worded differently.
+fn universe(n: usize) -> (Vec<Market>, Vec<Market>) {
+    let mut polymarket = Vec::with_capacity(n);
+    let mut kalshi = Vec::with_capacity(n);
+    for i in 0..n {
+        let person = format!("{} {}", name(i + 100), name(i * 7 + 1000));
+        let topic = TOPICS[i % TOPICS.len()];
+        let day = (i * 7 % 365) as i64;
+        let month = MONTHS[day as usize / 31 % MONTHS.len()];
+        let date = day % 28 + 1;
+        let threshold = 10 + i % 80;
+
+        polymarket.push(market(
+            Platform::Polymarket,
+            format!("poly-{}", i),
+            format!(
+                "Will {}'s {} be above {}% on {} {}?",
+                person, topic, threshold, month, date
+            ),
+            day,
+        ));
+        let question = if i % 4 == 0 {
+            format!(
+                "{} {} above {} percent on {} {}, 2025",
+                person, topic, threshold, month, date
+            )
+        } else {
+            format!(
+                "Will {} win the {} primary by {} points?",
+                person,
+                MONTHS[i % MONTHS.len()],
+                threshold
+            )
+        };
+        kalshi.push(market(Platform::Kalshi, format!("KX-{}", i), question, day));
+    }
+    (polymarket, kalshi)
+}
+
+/// What `match_markets` did before the index: extract every title and score
+/// every pair.
+fn exhaustive(matcher: &MarketMatcher, polymarket: &[&Market], kalshi: &[&Market]) -> usize {
+    let kalshi_features: Vec<MarketFeatures> = kalshi
+        .iter()
+        .map(|market| MarketFeatures::extract(market))
+        .collect();
+    let mut matches = 0;
+    for poly_market in polymarket {
+        let features = MarketFeatures::extract(poly_market);
+        for other in &kalshi_features {
+            if matcher.score_features(&features, other).is_some() {
+                matches += 1;
+            }
+        }
+    }
+    matches
+}
+
+fn bench_matching(c: &mut Criterion) {
+    let matcher = MarketMatcher::new(&MatchingConfig::default());
+    let mut group = c.benchmark_group("match_markets");
+    group.sample_size(10);
+
+    for n in [1_000, 5_000, 20_000] {
+        let (polymarket, kalshi) = universe(n);
+        let polymarket: Vec<&Market> = polymarket.iter().collect();
+        let kalshi: Vec<&Market> = kalshi.iter().collect();
+
+        // Every pair takes minutes at the larger sizes
+        if n <= 1_000 {
+            group.bench_with_input(BenchmarkId::new("exhaustive", n), &n, |b, _| {
+                b.iter(|| exhaustive(&matcher, &polymarket, &kalshi))
+            });
+        }
+        group.bench_with_input(BenchmarkId::new("index_cold", n), &n, |b, _| {
+            b.iter(|| {
+                MatchIndex::new()
+                    .matches(&matcher, &polymarket, &kalshi)
+                    .len()
+            })
+        });
+
+        let mut index = MatchIndex::new();
+        index.matches(&matcher, &polymarket, &kalshi);
+        group.bench_with_input(BenchmarkId::new("index_cached", n), &n, |b, _| {
+            b.iter(|| index.matches(&matcher, &polymarket, &kalshi).len())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_matching);
+criterion_main!(benches);
@@ -0,0 +1,104 @@
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+
+pub type ApiResult<T> = std::result::Result<T, ApiError>;
+
+/// Failure talking to a venue, classified so callers can tell a request
+/// worth retrying from one that will never succeed.
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("rate limited (retry after {retry_after:?})")]
+    RateLimited { retry_after: Option<Duration> },
+    /// Missing, invalid or insufficient credentials.
+    #[error("unauthorized")]
+    Unauthorized,
+    #[error("insufficient funds")]
+    InsufficientFunds,
+    /// The venue refused the order or request as specified.
+    #[error("invalid order: {reason}")]
+    InvalidOrder { reason: String },
+    #[error("market closed")]
+    MarketClosed,
+    #[error("network error: {0}")]
+    Network(#[from] reqwest::Error),
+    /// The response could not be interpreted.
+    #[error("unexpected response: {body}")]
+    Decode { body: String },
+    #[error("server error: {status}")]
+    Server { status: u16 },
+}
+
+impl ApiError {
+    /// Whether the same request may succeed if sent again later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ApiError::RateLimited { .. } | ApiError::Network(_) | ApiError::Server { .. }
+        )
+    }
+
+    /// Classification by status alone, for errors whose payload carries
+    /// nothing more specific.
+    pub(crate) fn from_status(
+        status: StatusCode,
+        retry_after: Option<Duration>,
+        reason: String,
+    ) -> Self {
+        match status {
+            StatusCode::TOO_MANY_REQUESTS => ApiError::RateLimited { retry_after },
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ApiError::Unauthorized,
+            status if status.is_server_error() => ApiError::Server {
+                status: status.as_u16(),
+            },
+            _ => ApiError::InvalidOrder { reason },
+        }
+    }
+}
+
+/// Reads `response` as JSON on success, or hands the status, `Retry-After`
+/// and body to the venue's `map_error` otherwise.
+pub(crate) async fn read_json<T: DeserializeOwned>(
+    response: Response,
+    map_error: impl FnOnce(StatusCode, Option<Duration>, &str) -> ApiError,
+) -> ApiResult<T> {
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .map(Duration::from_secs);
+    let body = response.text().await?;
+
+    if !status.is_success() {
+        return Err(map_error(status, retry_after, &body));
+    }
+
+    serde_json::from_str(&body).map_err(|_| ApiError::Decode { body })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_status() {
+        assert!(matches!(
+            ApiError::from_status(StatusCode::TOO_MANY_REQUESTS, Some(Duration::from_secs(2)), String::new()),
+            ApiError::RateLimited { retry_after: Some(d) } if d == Duration::from_secs(2)
+        ));
+        assert!(matches!(
+            ApiError::from_status(StatusCode::FORBIDDEN, None, String::new()),
+            ApiError::Unauthorized
+        ));
+        assert!(matches!(
+            ApiError::from_status(StatusCode::BAD_GATEWAY, None, String::new()),
+            ApiError::Server { status: 502 }
+        ));
+        assert!(matches!(
+            ApiError::from_status(StatusCode::BAD_REQUEST, None, "bad price".to_string()),
+            ApiError::InvalidOrder { reason } if reason == "bad price"
+        ));
+    }
+}
@@ -0,0 +1,51 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+
+use super::error::ApiResult;
+use crate::models::{
+    Event, Fill, Market, Order, OrderBook, OrderRequest, Platform, Position, Settlement,
+};
+
+/// A trading venue, in the normalized model types: prices in dollars per
+/// contract and sizes in contracts.
+#[async_trait]
+pub trait Exchange: Send + Sync {
+    fn platform(&self) -> Platform;
+
+    async fn get_markets(&self) -> ApiResult<Vec<Market>>;
+
+    /// Open markets grouped into their events. Venues with an event listing
+    /// also say which events' outcomes are mutually exclusive.
+    async fn get_events(&self) -> ApiResult<Vec<Event>> {
+        Ok(Event::group(&self.get_markets().await?))
+    }
+
+    async fn get_order_book(&self, market: &Market) -> ApiResult<OrderBook>;
+
+    /// Submits a limit order and returns the venue's order ID.
+    async fn place_order(&self, order: &OrderRequest) -> ApiResult<String>;
+
+    async fn cancel_order(&self, order_id: &str) -> ApiResult<()>;
+
+    async fn get_order(&self, order_id: &str) -> ApiResult<Order>;
+
+    /// The order placed for `order`, looked up by its client order ID, or
+    /// `None` if the venue never took it. For when the reply to
+    /// `place_order` was lost.
+    async fn find_order(&self, order: &OrderRequest) -> ApiResult<Option<Order>>;
+
+    /// Executions against one order, oldest first.
+    async fn list_fills(&self, order_id: &str) -> ApiResult<Vec<Fill>>;
+
+    async fn get_positions(&self) -> ApiResult<Vec<Position>>;
+
+    /// Markets settled from `since` on. Venues that do not list
+    /// settlements report none.
+    async fn get_settlements(&self, _since: DateTime<Utc>) -> ApiResult<Vec<Settlement>> {
+        Ok(Vec::new())
+    }
+
+    /// Cash available to trade, in dollars.
+    async fn get_balance(&self) -> ApiResult<Decimal>;
+}
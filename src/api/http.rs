@@ -0,0 +1,257 @@
+use log::warn;
+use rand::Rng;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::de::DeserializeOwned;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+use super::error::{read_json, ApiError, ApiResult};
+use crate::config::HttpConfig;
+
+/// Which rate limit a request counts against. Venues budget order entry
+/// separately from market data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndpointClass {
+    Read,
+    Write,
+}
+
+/// Token bucket holding up to `burst` requests, refilled continuously at
+/// `rate` per second.
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    updated_at: Instant,
+    /// Set from `Retry-After`; nothing is released before it.
+    paused_until: Option<Instant>,
+}
+
+impl TokenBucket {
+    pub fn new(rate: f64, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+        Self {
+            rate: rate.max(f64::MIN_POSITIVE),
+            burst,
+            state: Mutex::new(BucketState {
+                tokens: burst,
+                updated_at: Instant::now(),
+                paused_until: None,
+            }),
+        }
+    }
+
+    /// Waits until a request may be sent.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().expect("token bucket lock poisoned");
+                let now = Instant::now();
+
+                match state.paused_until {
+                    Some(until) if until > now => until - now,
+                    _ => {
+                        let elapsed = (now - state.updated_at).as_secs_f64();
+                        state.tokens = (state.tokens + elapsed * self.rate).min(self.burst);
+                        state.updated_at = now;
+
+                        if state.tokens >= 1.0 {
+                            state.tokens -= 1.0;
+                            return;
+                        }
+
+                        Duration::from_secs_f64((1.0 - state.tokens) / self.rate)
+                    }
+                }
+            };
+
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Holds back every request for `delay`, as a venue's `Retry-After`
+    /// asks.
+    pub fn pause(&self, delay: Duration) {
+        let mut state = self.state.lock().expect("token bucket lock poisoned");
+        let until = Instant::now() + delay;
+        if state.paused_until.is_none_or(|current| current < until) {
+            state.paused_until = Some(until);
+        }
+        state.tokens = 0.0;
+    }
+}
+
+/// HTTP transport shared by the venue clients: rate limits each endpoint
+/// class and retries reads that failed transiently.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: Client,
+    read_limiter: Arc<TokenBucket>,
+    write_limiter: Arc<TokenBucket>,
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl HttpClient {
+    pub fn new(config: &HttpConfig) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(config.timeout_seconds))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            client,
+            read_limiter: Arc::new(TokenBucket::new(
+                config.read_requests_per_second,
+                config.read_burst,
+            )),
+            write_limiter: Arc::new(TokenBucket::new(
+                config.write_requests_per_second,
+                config.write_burst,
+            )),
+            max_retries: config.max_retries,
+            initial_backoff: Duration::from_millis(config.initial_backoff_ms),
+            max_backoff: Duration::from_millis(config.max_backoff_ms),
+        }
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Sends an idempotent request built by `build`, retrying rate limits,
+    /// network failures and server errors with jittered exponential
+    /// backoff. `build` runs once per attempt so signatures stay fresh.
+    pub async fn read<T: DeserializeOwned>(
+        &self,
+        build: impl Fn() -> RequestBuilder,
+        map_error: impl Fn(StatusCode, Option<Duration>, &str) -> ApiError,
+    ) -> ApiResult<T> {
+        let mut attempt = 0;
+
+        loop {
+            let result = self.send(EndpointClass::Read, &build, &map_error).await;
+
+            match result {
+                Err(e) if e.is_retryable() && attempt < self.max_retries => {
+                    // Rate limits already paused the bucket; other failures
+                    // back off here
+                    let delay = match e {
+                        ApiError::RateLimited { .. } => Duration::ZERO,
+                        _ => self.backoff(attempt),
+                    };
+                    attempt += 1;
+                    warn!(
+                        "Request failed ({}); retry {} of {} in {:?}",
+                        e, attempt, self.max_retries, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Sends a request that must not be repeated, such as placing an order:
+    /// a failure may still have reached the venue, so it is reported rather
+    /// than retried.
+    pub async fn write<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+        map_error: impl Fn(StatusCode, Option<Duration>, &str) -> ApiError,
+    ) -> ApiResult<T> {
+        self.send(EndpointClass::Write, || request, &map_error).await
+    }
+
+    /// Builds the request only once the limiter lets it through, so a
+    /// signature made in `build` is not aged by the wait.
+    async fn send<T: DeserializeOwned>(
+        &self,
+        class: EndpointClass,
+        build: impl FnOnce() -> RequestBuilder,
+        map_error: &impl Fn(StatusCode, Option<Duration>, &str) -> ApiError,
+    ) -> ApiResult<T> {
+        let limiter = match class {
+            EndpointClass::Read => &self.read_limiter,
+            EndpointClass::Write => &self.write_limiter,
+        };
+        limiter.acquire().await;
+
+        let response = build().send().await?;
+        let result = read_json(response, map_error).await;
+
+        if let Err(ApiError::RateLimited { retry_after }) = &result {
+            limiter.pause(retry_after.unwrap_or(self.initial_backoff));
+        }
+
+        result
+    }
+
+    /// Exponential delay for the given retry, with the upper half jittered
+    /// so that clients do not retry in lockstep.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff);
+        let half = delay / 2;
+
+        half + half.mul_f64(rand::thread_rng().gen::<f64>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_token_bucket_paces_after_burst() {
+        let bucket = TokenBucket::new(20.0, 2);
+        let start = Instant::now();
+
+        for _ in 0..4 {
+            bucket.acquire().await;
+        }
+
+        // Two from the burst, then two at 50ms each
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(90), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(500), "{:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn test_token_bucket_pause() {
+        let bucket = TokenBucket::new(1000.0, 10);
+        bucket.pause(Duration::from_millis(100));
+
+        let start = Instant::now();
+        bucket.acquire().await;
+
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_backoff_is_capped_and_jittered() {
+        let client = HttpClient::new(&HttpConfig {
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1000,
+            ..HttpConfig::default()
+        });
+
+        for attempt in 0..10 {
+            let expected = Duration::from_millis((100u64 << attempt).min(1000));
+            let delay = client.backoff(attempt);
+            assert!(delay >= expected / 2 && delay <= expected, "{:?}", delay);
+        }
+    }
+}
@@ -1,35 +1,189 @@
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use log::{debug, error};
-use reqwest::Client;
-use rust_decimal::Decimal;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use log::{debug, error, warn};
+use reqwest::{Method, RequestBuilder, StatusCode};
+use rsa::{
+    pkcs1::DecodeRsaPrivateKey,
+    pkcs8::DecodePrivateKey,
+    pss::BlindedSigningKey,
+    signature::{RandomizedSigner, SignatureEncoding},
+    RsaPrivateKey,
+};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use sha2::Sha256;
+use std::{fmt, fs, sync::Arc, time::Duration};
 
-use crate::models::Market;
+use super::{
+    error::{ApiError, ApiResult},
+    exchange::Exchange,
+    http::HttpClient,
+};
+use crate::{
+    config::HttpConfig,
+    models::{
+        Event, Fill, Market, Order, OrderBook, OrderRequest, OrderStatus, Outcome, Platform,
+        Position, PriceLevel, Settlement, TradeSide,
+    },
+};
+
+/// Kalshi lists at most this many events per request.
+const MAX_EVENTS_PAGE_SIZE: u32 = 200;
 
 #[derive(Debug, Clone)]
 pub struct KalshiClient {
-    client: Client,
+    http: HttpClient,
     api_key: String,
-    api_secret: String,
+    signer: Option<Arc<KalshiSigner>>,
+    market_query: KalshiMarketQuery,
     base_url: String,
 }
 
+/// Signs trade API requests with the account's RSA key.
+///
+/// Kalshi expects an RSA-PSS (SHA-256, salt length equal to the digest)
+/// signature over `timestamp_ms + METHOD + path`, base64 encoded.
+pub struct KalshiSigner {
+    key: BlindedSigningKey<Sha256>,
+}
+
+impl KalshiSigner {
+    /// Accepts both PKCS#8 (`BEGIN PRIVATE KEY`) and PKCS#1
+    /// (`BEGIN RSA PRIVATE KEY`) PEM encodings.
+    pub fn from_pem(pem: &str) -> Result<Self> {
+        let key = RsaPrivateKey::from_pkcs8_pem(pem)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+            .context("Invalid Kalshi private key")?;
+
+        Ok(Self {
+            key: BlindedSigningKey::new(key),
+        })
+    }
+
+    pub fn from_file(path: &str) -> Result<Self> {
+        let pem = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read Kalshi private key from {}", path))?;
+        Self::from_pem(&pem)
+    }
+
+    pub fn signing_message(timestamp_ms: i64, method: &str, path: &str) -> String {
+        format!("{}{}{}", timestamp_ms, method, path)
+    }
+
+    pub fn sign(&self, timestamp_ms: i64, method: &str, path: &str) -> String {
+        let message = Self::signing_message(timestamp_ms, method, path);
+        let signature = self
+            .key
+            .sign_with_rng(&mut rand::thread_rng(), message.as_bytes());
+        BASE64.encode(signature.to_bytes())
+    }
+}
+
+impl fmt::Debug for KalshiSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KalshiSigner").finish_non_exhaustive()
+    }
+}
+
+/// Which open markets `get_markets` lists and how far it pages.
+#[derive(Debug, Clone)]
+pub struct KalshiMarketQuery {
+    pub series_ticker: Option<String>,
+    pub event_ticker: Option<String>,
+    /// Markets per request; Kalshi allows up to 1000.
+    pub page_size: u32,
+    pub max_pages: u32,
+}
+
+impl Default for KalshiMarketQuery {
+    fn default() -> Self {
+        Self {
+            series_ticker: None,
+            event_ticker: None,
+            page_size: 1000,
+            max_pages: 50,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct KalshiMarketsResponse {
     markets: Vec<KalshiMarket>,
+    /// Empty or absent on the last page.
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KalshiEventsResponse {
+    events: Vec<KalshiEvent>,
+    /// Empty or absent on the last page.
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KalshiEvent {
+    event_ticker: String,
+    title: String,
+    #[serde(default)]
+    mutually_exclusive: bool,
+    /// Listed with `with_nested_markets`, settled outcomes included.
+    #[serde(default)]
+    markets: Vec<KalshiMarket>,
 }
 
 #[derive(Debug, Deserialize)]
 struct KalshiMarket {
     ticker: String,
     title: String,
+    #[serde(default)]
+    event_ticker: String,
+    /// The outcome YES stands for, e.g. a candidate's name.
+    #[serde(default)]
+    yes_sub_title: String,
+    #[serde(default)]
+    status: String,
     yes_bid: f64,
     yes_ask: f64,
     volume: f64,
     open_interest: f64,
     close_time: String,
+    #[serde(default)]
+    rules_primary: String,
+    #[serde(default)]
+    rules_secondary: String,
+    #[serde(default)]
+    settlement_timer_seconds: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KalshiErrorResponse {
+    error: KalshiErrorBody,
+}
+
+#[derive(Debug, Deserialize)]
+struct KalshiErrorBody {
+    #[serde(default)]
+    code: String,
+    #[serde(default)]
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct KalshiOrderBookResponse {
+    orderbook: KalshiOrderBook,
+}
+
+/// Kalshi only publishes bids, as `[price_cents, quantity]` pairs. Empty
+/// sides come back as `null`.
+#[derive(Debug, Deserialize)]
+struct KalshiOrderBook {
+    #[serde(default)]
+    yes: Option<Vec<[i64; 2]>>,
+    #[serde(default)]
+    no: Option<Vec<[i64; 2]>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -37,139 +191,972 @@ struct CreateOrderRequest {
     ticker: String,
     action: String,
     side: String,
-    count: i32,
+    count: i64,
     #[serde(rename = "type")]
     order_type: String,
-    yes_price: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    yes_price: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    no_price: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_order_id: Option<String>,
 }
 
-impl KalshiClient {
-    pub fn new(api_key: String, api_secret: String, base_url: String) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .build()
-            .expect("Failed to create HTTP client");
+#[derive(Debug, Deserialize)]
+struct KalshiOrderResponse {
+    order: KalshiOrder,
+}
+
+#[derive(Debug, Deserialize)]
+struct KalshiOrder {
+    order_id: String,
+    #[serde(default)]
+    client_order_id: Option<String>,
+    #[serde(default)]
+    ticker: String,
+    /// `resting`, `pending`, `executed` or `canceled`.
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    side: String,
+    #[serde(default)]
+    action: String,
+    #[serde(default)]
+    yes_price: i64,
+    #[serde(default)]
+    no_price: i64,
+    #[serde(default)]
+    initial_count: Option<i64>,
+    #[serde(default)]
+    fill_count: Option<i64>,
+    #[serde(default)]
+    remaining_count: Option<i64>,
+    #[serde(default)]
+    created_time: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KalshiOrdersResponse {
+    #[serde(default)]
+    orders: Vec<KalshiOrder>,
+    #[serde(default)]
+    cursor: Option<String>,
+}
 
+#[derive(Debug, Deserialize)]
+struct KalshiPositionsResponse {
+    #[serde(default)]
+    market_positions: Vec<KalshiMarketPosition>,
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+/// `position` is signed: positive holds YES, negative holds NO.
+/// `market_exposure` is the position's cost in cents.
+#[derive(Debug, Deserialize)]
+struct KalshiMarketPosition {
+    ticker: String,
+    position: i64,
+    #[serde(default)]
+    market_exposure: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct KalshiFillsResponse {
+    #[serde(default)]
+    fills: Vec<KalshiFill>,
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KalshiFill {
+    trade_id: String,
+    order_id: String,
+    ticker: String,
+    side: String,
+    action: String,
+    count: i64,
+    yes_price: i64,
+    no_price: i64,
+    #[serde(default)]
+    created_time: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KalshiSettlementsResponse {
+    #[serde(default)]
+    settlements: Vec<KalshiSettlement>,
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+/// Costs and revenue are in cents.
+#[derive(Debug, Deserialize)]
+struct KalshiSettlement {
+    ticker: String,
+    #[serde(default)]
+    yes_total_cost: i64,
+    #[serde(default)]
+    no_total_cost: i64,
+    revenue: i64,
+    settled_time: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct KalshiBalanceResponse {
+    /// Cents.
+    balance: i64,
+}
+
+impl KalshiClient {
+    /// Without a signer only public market data endpoints can be used.
+    pub fn new(api_key: String, signer: Option<KalshiSigner>, base_url: String) -> Self {
         Self {
-            client,
+            http: HttpClient::new(&HttpConfig::default()),
             api_key,
-            api_secret,
+            signer: signer.map(Arc::new),
+            market_query: KalshiMarketQuery::default(),
             base_url,
         }
     }
 
-    pub async fn get_markets(&self) -> Result<Vec<Market>> {
-        debug!("Fetching Kalshi markets");
+    pub fn with_market_query(mut self, market_query: KalshiMarketQuery) -> Self {
+        self.market_query = market_query;
+        self
+    }
 
-        let url = format!("{}/trade-api/v2/markets", self.base_url);
-        
-        let response = self
-            .client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .send()
-            .await
-            .context("Failed to fetch markets from Kalshi")?;
+    pub fn with_http_config(mut self, config: &HttpConfig) -> Self {
+        self.http = HttpClient::new(config);
+        self
+    }
+
+    /// Builds a request for `path`, signed if a private key is configured.
+    /// `path` must not include the query string, which Kalshi excludes from
+    /// the signature.
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let url = format!("{}{}", self.base_url, path);
+        let mut request = self.http.client().request(method.clone(), &url);
 
-        if !response.status().is_success() {
-            error!("Kalshi API error: {}", response.status());
-            return Err(anyhow::anyhow!("API request failed"));
+        for (name, value) in self.auth_headers(method.as_str(), path) {
+            request = request.header(name, value);
         }
 
-        let data: KalshiMarketsResponse = response
-            .json()
-            .await
-            .context("Failed to parse Kalshi response")?;
+        request
+    }
 
-        let markets = data
-            .markets
-            .into_iter()
-            .filter_map(|m| self.parse_market(m).ok())
-            .collect();
+    /// `KALSHI-ACCESS-*` headers for `method` and `path`, or none when no
+    /// private key is configured. Also used for the websocket handshake.
+    pub fn auth_headers(&self, method: &str, path: &str) -> Vec<(&'static str, String)> {
+        let Some(signer) = &self.signer else {
+            return Vec::new();
+        };
+
+        let timestamp = chrono::Utc::now().timestamp_millis();
+        vec![
+            ("KALSHI-ACCESS-KEY", self.api_key.clone()),
+            ("KALSHI-ACCESS-SIGNATURE", signer.sign(timestamp, method, path)),
+            ("KALSHI-ACCESS-TIMESTAMP", timestamp.to_string()),
+        ]
+    }
+
+    /// Like `request`, but for endpoints that require authentication.
+    fn signed_request(&self, method: Method, path: &str) -> ApiResult<RequestBuilder> {
+        self.require_signer()?;
+        Ok(self.request(method, path))
+    }
+
+    fn require_signer(&self) -> ApiResult<()> {
+        if self.signer.is_none() {
+            error!("Kalshi private key not configured");
+            return Err(ApiError::Unauthorized);
+        }
+        Ok(())
+    }
 
+    /// Lists open markets, following the cursor until the last page or
+    /// `max_pages`.
+    pub async fn get_markets(&self) -> ApiResult<Vec<Market>> {
+        debug!("Fetching Kalshi markets");
+
+        let mut markets = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        for _ in 0..self.market_query.max_pages {
+            let page = self.get_markets_page(cursor.as_deref()).await?;
+
+            markets.extend(
+                page.markets
+                    .into_iter()
+                    .filter_map(|m| self.parse_market(m).ok()),
+            );
+
+            match page.cursor.filter(|c| !c.is_empty()) {
+                Some(next) => cursor = Some(next),
+                None => return Ok(markets),
+            }
+        }
+
+        warn!(
+            "Stopped listing Kalshi markets after {} pages ({} markets)",
+            self.market_query.max_pages,
+            markets.len()
+        );
         Ok(markets)
     }
 
+    async fn get_markets_page(&self, cursor: Option<&str>) -> ApiResult<KalshiMarketsResponse> {
+        let query = &self.market_query;
+        let mut params = vec![
+            ("status", "open".to_string()),
+            ("limit", query.page_size.to_string()),
+        ];
+        if let Some(series_ticker) = &query.series_ticker {
+            params.push(("series_ticker", series_ticker.clone()));
+        }
+        if let Some(event_ticker) = &query.event_ticker {
+            params.push(("event_ticker", event_ticker.clone()));
+        }
+        if let Some(cursor) = cursor {
+            params.push(("cursor", cursor.to_string()));
+        }
+
+        self.http
+            .read(
+                || self.request(Method::GET, "/trade-api/v2/markets").query(&params),
+                kalshi_error,
+            )
+            .await
+    }
+
     fn parse_market(&self, market: KalshiMarket) -> Result<Market> {
         let yes_price = Decimal::try_from(market.yes_ask / 100.0)?;
         let no_price = Decimal::try_from((100.0 - market.yes_bid) / 100.0)?;
+        let rules: Vec<&str> = [&market.rules_primary, &market.rules_secondary]
+            .into_iter()
+            .map(|rules| rules.trim())
+            .filter(|rules| !rules.is_empty())
+            .collect();
 
         Ok(Market {
             id: market.ticker,
             question: market.title,
-            platform: crate::models::Platform::Kalshi,
+            platform: Platform::Kalshi,
             yes_price,
             no_price,
             volume: Decimal::try_from(market.volume)?,
             liquidity: Decimal::try_from(market.open_interest)?,
             end_time: chrono::DateTime::parse_from_rfc3339(&market.close_time)?
                 .with_timezone(&chrono::Utc),
+            yes_token_id: None,
+            no_token_id: None,
+            rules: (!rules.is_empty()).then(|| rules.join("\n\n")),
+            resolution_source: None,
+            settlement_timer_seconds: market.settlement_timer_seconds,
+            event_id: non_empty(market.event_ticker),
+            event_title: None,
+            outcome_name: non_empty(market.yes_sub_title),
+            neg_risk: false,
+            condition_id: None,
         })
     }
 
-    pub async fn place_order(
-        &self,
-        ticker: &str,
-        side: &str,
-        price_cents: i32,
-        count: i32,
-    ) -> Result<String> {
-        debug!("Placing order on Kalshi: {} contracts {} @ ${}", count, side, price_cents);
-
-        let url = format!("{}/trade-api/v2/portfolio/orders", self.base_url);
-        
-        let request = CreateOrderRequest {
-            ticker: ticker.to_string(),
-            action: "buy".to_string(),
-            side: side.to_string(),
-            count,
-            order_type: "limit".to_string(),
-            yes_price: Some(price_cents),
+    /// Lists open events with their open markets, following the cursor
+    /// until the last page or `max_pages`. The events endpoint takes no
+    /// event ticker, so a configured one filters each page instead.
+    pub async fn get_events(&self) -> ApiResult<Vec<Event>> {
+        debug!("Fetching Kalshi events");
+
+        let mut events = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        for _ in 0..self.market_query.max_pages {
+            let page = self.get_events_page(cursor.as_deref()).await?;
+
+            events.extend(
+                page.events
+                    .into_iter()
+                    .filter(|e| {
+                        self.market_query
+                            .event_ticker
+                            .as_ref()
+                            .is_none_or(|ticker| *ticker == e.event_ticker)
+                    })
+                    .map(|e| self.parse_event(e)),
+            );
+
+            match page.cursor.filter(|c| !c.is_empty()) {
+                Some(next) => cursor = Some(next),
+                None => return Ok(events),
+            }
+        }
+
+        warn!(
+            "Stopped listing Kalshi events after {} pages ({} events)",
+            self.market_query.max_pages,
+            events.len()
+        );
+        Ok(events)
+    }
+
+    async fn get_events_page(&self, cursor: Option<&str>) -> ApiResult<KalshiEventsResponse> {
+        let query = &self.market_query;
+        let mut params = vec![
+            ("status", "open".to_string()),
+            ("limit", query.page_size.min(MAX_EVENTS_PAGE_SIZE).to_string()),
+            ("with_nested_markets", "true".to_string()),
+        ];
+        if let Some(series_ticker) = &query.series_ticker {
+            params.push(("series_ticker", series_ticker.clone()));
+        }
+        if let Some(cursor) = cursor {
+            params.push(("cursor", cursor.to_string()));
+        }
+
+        self.http
+            .read(
+                || self.request(Method::GET, "/trade-api/v2/events").query(&params),
+                kalshi_error,
+            )
+            .await
+    }
+
+    fn parse_event(&self, event: KalshiEvent) -> Event {
+        let markets = event
+            .markets
+            .into_iter()
+            .filter(|m| m.status.is_empty() || m.status == "active")
+            .filter_map(|m| self.parse_market(m).ok())
+            .map(|market| Market {
+                event_id: Some(event.event_ticker.clone()),
+                event_title: Some(event.title.clone()),
+                ..market
+            })
+            .collect();
+
+        Event {
+            id: event.event_ticker,
+            platform: Platform::Kalshi,
+            title: event.title,
+            mutually_exclusive: event.mutually_exclusive,
+            markets,
+        }
+    }
+
+    pub async fn get_order_book(&self, market: &Market) -> ApiResult<OrderBook> {
+        debug!("Fetching Kalshi order book for {}", market.id);
+
+        let path = format!("/trade-api/v2/markets/{}/orderbook", market.id);
+
+        let data: KalshiOrderBookResponse = self
+            .http
+            .read(|| self.request(Method::GET, &path), kalshi_error)
+            .await?;
+
+        Ok(self.parse_order_book(&market.id, data.orderbook))
+    }
+
+    fn parse_order_book(&self, ticker: &str, book: KalshiOrderBook) -> OrderBook {
+        let levels = |bids: Option<Vec<[i64; 2]>>| {
+            bids.unwrap_or_default()
+                .into_iter()
+                .map(|[price_cents, quantity]| (price_cents, quantity))
         };
 
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&request)
-            .send()
+        order_book_from_bids(ticker, levels(book.yes), levels(book.no))
+    }
+
+    /// Places a limit order. Kalshi trades whole contracts priced in cents,
+    /// so the request must be expressible in both.
+    pub async fn place_order(&self, order: &OrderRequest) -> ApiResult<String> {
+        let request = create_order_request(order)?;
+        debug!(
+            "Placing order on Kalshi: {} {} {} {} @ {:?}{:?}c",
+            request.action,
+            request.count,
+            request.ticker,
+            request.side,
+            request.yes_price,
+            request.no_price
+        );
+
+        let data: KalshiOrderResponse = self
+            .http
+            .write(
+                self.signed_request(Method::POST, "/trade-api/v2/portfolio/orders")?
+                    .json(&request),
+                kalshi_error,
+            )
+            .await?;
+        Ok(data.order.order_id)
+    }
+
+    pub async fn cancel_order(&self, order_id: &str) -> ApiResult<()> {
+        debug!("Cancelling Kalshi order {}", order_id);
+
+        let path = format!("/trade-api/v2/portfolio/orders/{}", order_id);
+        let _: KalshiOrderResponse = self
+            .http
+            .write(self.signed_request(Method::DELETE, &path)?, kalshi_error)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_order(&self, order_id: &str) -> ApiResult<Order> {
+        self.require_signer()?;
+
+        let path = format!("/trade-api/v2/portfolio/orders/{}", order_id);
+        let data: KalshiOrderResponse = self
+            .http
+            .read(|| self.request(Method::GET, &path), kalshi_error)
             .await?;
 
-        if !response.status().is_success() {
-            return Err(anyhow::anyhow!("Failed to place order"));
+        parse_order(data.order)
+    }
+
+    /// Looks through the market's orders, following the cursor up to
+    /// `max_pages`, for the one placed with `order`'s client order ID.
+    pub async fn find_order(&self, order: &OrderRequest) -> ApiResult<Option<Order>> {
+        self.require_signer()?;
+        let client_order_id = order.client_order_id.as_deref().ok_or_else(|| {
+            ApiError::InvalidOrder {
+                reason: "the order has no client order ID to look up".to_string(),
+            }
+        })?;
+
+        let mut cursor: Option<String> = None;
+        for _ in 0..self.market_query.max_pages {
+            let mut params = vec![
+                ("ticker", order.market_id.clone()),
+                ("limit", self.market_query.page_size.to_string()),
+            ];
+            if let Some(cursor) = &cursor {
+                params.push(("cursor", cursor.clone()));
+            }
+
+            let page: KalshiOrdersResponse = self
+                .http
+                .read(
+                    || {
+                        self.request(Method::GET, "/trade-api/v2/portfolio/orders")
+                            .query(&params)
+                    },
+                    kalshi_error,
+                )
+                .await?;
+
+            if let Some(found) = page
+                .orders
+                .into_iter()
+                .find(|placed| placed.client_order_id.as_deref() == Some(client_order_id))
+            {
+                return parse_order(found).map(Some);
+            }
+
+            match page.cursor.filter(|c| !c.is_empty()) {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub async fn list_fills(&self, order_id: &str) -> ApiResult<Vec<Fill>> {
+        self.require_signer()?;
+
+        let mut fills = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        for _ in 0..self.market_query.max_pages {
+            let mut params = vec![
+                ("order_id", order_id.to_string()),
+                ("limit", self.market_query.page_size.to_string()),
+            ];
+            if let Some(cursor) = &cursor {
+                params.push(("cursor", cursor.clone()));
+            }
+
+            let page: KalshiFillsResponse = self
+                .http
+                .read(
+                    || {
+                        self.request(Method::GET, "/trade-api/v2/portfolio/fills")
+                            .query(&params)
+                    },
+                    kalshi_error,
+                )
+                .await?;
+
+            for fill in page.fills {
+                fills.push(parse_fill(fill)?);
+            }
+
+            match page.cursor.filter(|c| !c.is_empty()) {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        // Kalshi lists newest first
+        fills.sort_by_key(|fill| fill.filled_at);
+        Ok(fills)
+    }
+
+    /// Lists non-zero market positions, following the cursor up to
+    /// `max_pages`.
+    pub async fn get_positions(&self) -> ApiResult<Vec<Position>> {
+        self.require_signer()?;
+
+        let mut positions = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        for _ in 0..self.market_query.max_pages {
+            let mut params = vec![("limit", self.market_query.page_size.to_string())];
+            if let Some(cursor) = &cursor {
+                params.push(("cursor", cursor.clone()));
+            }
+
+            let page: KalshiPositionsResponse = self
+                .http
+                .read(
+                    || {
+                        self.request(Method::GET, "/trade-api/v2/portfolio/positions")
+                            .query(&params)
+                    },
+                    kalshi_error,
+                )
+                .await?;
+
+            positions.extend(page.market_positions.into_iter().filter_map(parse_position));
+
+            match page.cursor.filter(|c| !c.is_empty()) {
+                Some(next) => cursor = Some(next),
+                None => return Ok(positions),
+            }
+        }
+
+        warn!(
+            "Stopped listing Kalshi positions after {} pages",
+            self.market_query.max_pages
+        );
+        Ok(positions)
+    }
+
+    /// Lists markets settled from `since` on, following the cursor up to
+    /// `max_pages`.
+    pub async fn get_settlements(
+        &self,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> ApiResult<Vec<Settlement>> {
+        self.require_signer()?;
+
+        let mut settlements = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        for _ in 0..self.market_query.max_pages {
+            let mut params = vec![
+                ("min_ts", since.timestamp().to_string()),
+                ("limit", self.market_query.page_size.to_string()),
+            ];
+            if let Some(cursor) = &cursor {
+                params.push(("cursor", cursor.clone()));
+            }
+
+            let page: KalshiSettlementsResponse = self
+                .http
+                .read(
+                    || {
+                        self.request(Method::GET, "/trade-api/v2/portfolio/settlements")
+                            .query(&params)
+                    },
+                    kalshi_error,
+                )
+                .await?;
+
+            for settlement in page.settlements {
+                settlements.push(parse_settlement(settlement)?);
+            }
+
+            match page.cursor.filter(|c| !c.is_empty()) {
+                Some(next) => cursor = Some(next),
+                None => return Ok(settlements),
+            }
         }
 
-        let order_id = response
-            .json::<serde_json::Value>()
-            .await?
-            .get("order")
-            .and_then(|o| o.get("order_id"))
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow::anyhow!("Invalid response"))?
-            .to_string();
+        warn!(
+            "Stopped listing Kalshi settlements after {} pages",
+            self.market_query.max_pages
+        );
+        Ok(settlements)
+    }
+
+    pub async fn get_balance(&self) -> ApiResult<Decimal> {
+        self.require_signer()?;
+
+        let data: KalshiBalanceResponse = self
+            .http
+            .read(
+                || self.request(Method::GET, "/trade-api/v2/portfolio/balance"),
+                kalshi_error,
+            )
+            .await?;
+
+        Ok(Decimal::new(data.balance, 2))
+    }
+}
+
+/// Kalshi sends absent text fields as empty strings.
+fn non_empty(text: String) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+fn create_order_request(order: &OrderRequest) -> ApiResult<CreateOrderRequest> {
+    let invalid = |reason: &str| ApiError::InvalidOrder {
+        reason: format!("{} (price {}, size {})", reason, order.price, order.size),
+    };
+
+    let price_cents = (order.price * Decimal::ONE_HUNDRED)
+        .to_i64()
+        .filter(|cents| Decimal::from(*cents) == order.price * Decimal::ONE_HUNDRED)
+        .filter(|cents| (1..=99).contains(cents))
+        .ok_or_else(|| invalid("Kalshi prices are whole cents between 1 and 99"))?;
+    let count = order
+        .size
+        .to_i64()
+        .filter(|count| Decimal::from(*count) == order.size && *count > 0)
+        .ok_or_else(|| invalid("Kalshi orders are for a positive whole number of contracts"))?;
+
+    Ok(CreateOrderRequest {
+        ticker: order.market_id.clone(),
+        action: order.side.as_str().to_string(),
+        side: order.outcome.as_str().to_string(),
+        count,
+        order_type: "limit".to_string(),
+        yes_price: (order.outcome == Outcome::Yes).then_some(price_cents),
+        no_price: (order.outcome == Outcome::No).then_some(price_cents),
+        client_order_id: order.client_order_id.clone(),
+    })
+}
+
+fn parse_order(order: KalshiOrder) -> ApiResult<Order> {
+    let decode = |field: &str, value: &str| ApiError::Decode {
+        body: format!("Kalshi order {} has {} {:?}", order.order_id, field, value),
+    };
+
+    let outcome = match order.side.as_str() {
+        "yes" => Outcome::Yes,
+        "no" => Outcome::No,
+        other => return Err(decode("side", other)),
+    };
+    let side = match order.action.as_str() {
+        "buy" => TradeSide::Buy,
+        "sell" => TradeSide::Sell,
+        other => return Err(decode("action", other)),
+    };
+
+    let filled = order.fill_count.unwrap_or(0);
+    let size = order
+        .initial_count
+        .unwrap_or(filled + order.remaining_count.unwrap_or(0));
+
+    let status = match order.status.as_str() {
+        "executed" => OrderStatus::Filled,
+        "canceled" => OrderStatus::Cancelled,
+        "resting" | "pending" if filled > 0 => OrderStatus::PartiallyFilled,
+        "resting" | "pending" => OrderStatus::New,
+        other => return Err(decode("status", other)),
+    };
+
+    let price_cents = match outcome {
+        Outcome::Yes => order.yes_price,
+        Outcome::No => order.no_price,
+    };
+
+    Ok(Order {
+        id: order.order_id,
+        platform: Platform::Kalshi,
+        market_id: order.ticker,
+        outcome,
+        side,
+        price: Decimal::new(price_cents, 2),
+        size: Decimal::from(size),
+        filled_size: Decimal::from(filled),
+        status,
+        created_at: order
+            .created_time
+            .as_deref()
+            .and_then(|time| chrono::DateTime::parse_from_rfc3339(time).ok())
+            .map(|time| time.with_timezone(&chrono::Utc)),
+    })
+}
+
+fn parse_fill(fill: KalshiFill) -> ApiResult<Fill> {
+    let decode = |field: &str, value: &str| ApiError::Decode {
+        body: format!("Kalshi fill {} has {} {:?}", fill.trade_id, field, value),
+    };
 
-        Ok(order_id)
+    let outcome = match fill.side.as_str() {
+        "yes" => Outcome::Yes,
+        "no" => Outcome::No,
+        other => return Err(decode("side", other)),
+    };
+    let side = match fill.action.as_str() {
+        "buy" => TradeSide::Buy,
+        "sell" => TradeSide::Sell,
+        other => return Err(decode("action", other)),
+    };
+    let price_cents = match outcome {
+        Outcome::Yes => fill.yes_price,
+        Outcome::No => fill.no_price,
+    };
+
+    Ok(Fill {
+        id: fill.trade_id,
+        order_id: fill.order_id,
+        platform: Platform::Kalshi,
+        market_id: fill.ticker,
+        outcome,
+        side,
+        price: Decimal::new(price_cents, 2),
+        size: Decimal::from(fill.count),
+        filled_at: fill
+            .created_time
+            .as_deref()
+            .and_then(|time| chrono::DateTime::parse_from_rfc3339(time).ok())
+            .map(|time| time.with_timezone(&chrono::Utc)),
+    })
+}
+
+fn parse_position(position: KalshiMarketPosition) -> Option<Position> {
+    if position.position == 0 {
+        return None;
     }
 
-    fn generate_signature(&self, path: &str, body: &str) -> String {
-        use hmac::{Hmac, Mac};
-        use sha2::Sha256;
+    let outcome = if position.position > 0 {
+        Outcome::Yes
+    } else {
+        Outcome::No
+    };
+    let amount = Decimal::from(position.position.abs());
+    let cost = Decimal::new(position.market_exposure, 2);
+
+    Some(Position {
+        platform: Platform::Kalshi,
+        market_id: position.ticker,
+        outcome,
+        amount,
+        entry_price: cost / amount,
+        current_value: cost,
+    })
+}
+
+fn parse_settlement(settlement: KalshiSettlement) -> ApiResult<Settlement> {
+    let settled_at = chrono::DateTime::parse_from_rfc3339(&settlement.settled_time)
+        .map_err(|_| ApiError::Decode {
+            body: format!(
+                "Kalshi settlement of {} has settled_time {:?}",
+                settlement.ticker, settlement.settled_time
+            ),
+        })?
+        .with_timezone(&chrono::Utc);
+
+    Ok(Settlement {
+        platform: Platform::Kalshi,
+        market_id: settlement.ticker,
+        revenue: Decimal::new(settlement.revenue, 2),
+        cost: Decimal::new(settlement.yes_total_cost + settlement.no_total_cost, 2),
+        settled_at,
+    })
+}
+
+/// Maps Kalshi's `{"error": {"code", "message"}}` payloads, falling back to
+/// the status for anything unrecognized.
+fn kalshi_error(status: StatusCode, retry_after: Option<Duration>, body: &str) -> ApiError {
+    error!("Kalshi API error: {} {}", status, body);
 
-        type HmacSha256 = Hmac<Sha256>;
+    let (code, message) = match serde_json::from_str::<KalshiErrorResponse>(body) {
+        Ok(response) => (response.error.code, response.error.message),
+        Err(_) => (String::new(), body.to_string()),
+    };
 
-        let mut mac = HmacSha256::new_from_slice(self.api_secret.as_bytes())
-            .expect("HMAC can take key of any size");
-        
-        mac.update(format!("{}{}", path, body).as_bytes());
-        
-        let result = mac.finalize();
-        hex::encode(result.into_bytes())
+    match code.as_str() {
+        "insufficient_balance" => ApiError::InsufficientFunds,
+        "market_closed" | "market_not_active" | "trading_is_paused" | "exchange_closed" => {
+            ApiError::MarketClosed
+        }
+        _ if message.is_empty() => ApiError::from_status(status, retry_after, code),
+        _ => ApiError::from_status(status, retry_after, message),
     }
 }
 
+/// Builds a normalized book from Kalshi's `(price_cents, quantity)` bids.
+pub(crate) fn order_book_from_bids(
+    ticker: &str,
+    yes_bids: impl IntoIterator<Item = (i64, i64)>,
+    no_bids: impl IntoIterator<Item = (i64, i64)>,
+) -> OrderBook {
+    // A NO bid at p cents is a YES ask at 100 - p, and vice versa
+    let to_asks = |bids: Vec<(i64, i64)>| -> Vec<PriceLevel> {
+        bids.into_iter()
+            .map(|(price_cents, quantity)| PriceLevel {
+                price: Decimal::new(100 - price_cents, 2),
+                size: Decimal::from(quantity),
+            })
+            .collect()
+    };
+
+    OrderBook::new(
+        Platform::Kalshi,
+        ticker.to_string(),
+        to_asks(no_bids.into_iter().collect()),
+        to_asks(yes_bids.into_iter().collect()),
+    )
+}
+
 #[async_trait]
-impl super::polymarket::MarketDataProvider for KalshiClient {
-    async fn get_markets(&self) -> Result<Vec<Market>> {
+impl Exchange for KalshiClient {
+    fn platform(&self) -> Platform {
+        Platform::Kalshi
+    }
+
+    async fn get_markets(&self) -> ApiResult<Vec<Market>> {
         self.get_markets().await
     }
+
+    async fn get_events(&self) -> ApiResult<Vec<Event>> {
+        self.get_events().await
+    }
+
+    async fn get_order_book(&self, market: &Market) -> ApiResult<OrderBook> {
+        self.get_order_book(market).await
+    }
+
+    async fn place_order(&self, order: &OrderRequest) -> ApiResult<String> {
+        self.place_order(order).await
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> ApiResult<()> {
+        self.cancel_order(order_id).await
+    }
+
+    async fn get_order(&self, order_id: &str) -> ApiResult<Order> {
+        self.get_order(order_id).await
+    }
+
+    async fn find_order(&self, order: &OrderRequest) -> ApiResult<Option<Order>> {
+        self.find_order(order).await
+    }
+
+    async fn list_fills(&self, order_id: &str) -> ApiResult<Vec<Fill>> {
+        self.list_fills(order_id).await
+    }
+
+    async fn get_positions(&self) -> ApiResult<Vec<Position>> {
+        self.get_positions().await
+    }
+
+    async fn get_settlements(
+        &self,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> ApiResult<Vec<Settlement>> {
+        self.get_settlements(since).await
+    }
+
+    async fn get_balance(&self) -> ApiResult<Decimal> {
+        self.get_balance().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsa::{
+        pkcs1::EncodeRsaPrivateKey,
+        pkcs8::LineEnding,
+        pss::{Signature, VerifyingKey},
+        signature::Verifier,
+    };
+
+    const TEST_KEY: &str = include_str!("../../tests/fixtures/kalshi_test_key.pem");
+
+    /// Produced with `openssl dgst -sha256 -sign kalshi_test_key.pem
+    /// -sigopt rsa_padding_mode:pss -sigopt rsa_pss_saltlen:digest`.
+    const OPENSSL_SIGNATURE: &str = concat!(
+        "BRaOViWjfvMZyxxlDe+uGjMT1AgBoKIW8IesO2aSEHxyyGC7xUrTWC7zzRVdjntZ",
+        "gJGwREktIG2cjNkbq1XJ1AjIGukBYCynu2+HOn9mrN9uI3XFiWY3OPHfo0ZFiUYZ",
+        "po2hzgDZ5Xf7KmVZ5oW/n0XkFwHyQOXoKL/J9FL/PXuWs8S4B87NOZjTVaAtQUIq",
+        "cZM3yJNzxEbTTLh0OcM6I10G+FIEylrdrKWWeIFwiM5fM3rwABd+akpnkcDBEyGT",
+        "WL7VE/V8tdf1w2lXTaofYGQSf1JFFbwrFD3ERMWiKfuYl1M0b+dSXRronyD+Lrbe",
+        "RUsXG0aM6g2RB1fYLK0r6A==",
+    );
+
+    fn verifying_key() -> VerifyingKey<Sha256> {
+        let key = RsaPrivateKey::from_pkcs8_pem(TEST_KEY).unwrap();
+        VerifyingKey::new(key.to_public_key())
+    }
+
+    fn verify(message: &str, signature_b64: &str) -> bool {
+        let bytes = BASE64.decode(signature_b64).unwrap();
+        let signature = Signature::try_from(bytes.as_slice()).unwrap();
+        verifying_key().verify(message.as_bytes(), &signature).is_ok()
+    }
+
+    #[test]
+    fn test_signing_message_format() {
+        assert_eq!(
+            KalshiSigner::signing_message(1703123456789, "GET", "/trade-api/v2/portfolio/balance"),
+            "1703123456789GET/trade-api/v2/portfolio/balance"
+        );
+    }
+
+    #[test]
+    fn test_signature_verifies() {
+        let signer = KalshiSigner::from_pem(TEST_KEY).unwrap();
+        let signature = signer.sign(1703123456789, "POST", "/trade-api/v2/portfolio/orders");
+
+        assert!(verify("1703123456789POST/trade-api/v2/portfolio/orders", &signature));
+        assert!(!verify("1703123456790POST/trade-api/v2/portfolio/orders", &signature));
+    }
+
+    #[test]
+    fn test_openssl_vector_uses_same_scheme() {
+        // Confirms the padding and salt length match what Kalshi's reference
+        // clients produce
+        assert!(verify(
+            "1703123456789GET/trade-api/v2/portfolio/balance",
+            OPENSSL_SIGNATURE
+        ));
+    }
+
+    #[test]
+    fn test_pkcs1_key_loads() {
+        let key = RsaPrivateKey::from_pkcs8_pem(TEST_KEY).unwrap();
+        let pkcs1 = key.to_pkcs1_pem(LineEnding::LF).unwrap();
+
+        let signer = KalshiSigner::from_pem(&pkcs1).unwrap();
+        let signature = signer.sign(1, "GET", "/");
+        assert!(verify("1GET/", &signature));
+
+        assert!(KalshiSigner::from_pem("not a key").is_err());
+    }
+
+    #[test]
+    fn test_parse_order_book_inverts_bids() {
+        let client = KalshiClient::new(String::new(), None, String::new());
+        let book: KalshiOrderBook =
+            serde_json::from_str(r#"{"yes": [[40, 10], [42, 5]], "no": null}"#).unwrap();
+
+        let book = client.parse_order_book("TICKER", book);
+
+        assert!(book.yes_asks.is_empty());
+        assert_eq!(
+            book.no_asks,
+            vec![
+                PriceLevel { price: Decimal::new(58, 2), size: Decimal::from(5) },
+                PriceLevel { price: Decimal::new(60, 2), size: Decimal::from(10) },
+            ]
+        );
+    }
 }
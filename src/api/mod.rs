@@ -1,5 +1,11 @@
+pub mod error;
+pub mod exchange;
+pub mod http;
 pub mod kalshi;
 pub mod polymarket;
+pub mod polymarket_signing;
 
-pub use kalshi::KalshiClient;
-pub use polymarket::PolymarketClient;
+pub use error::{ApiError, ApiResult};
+pub use exchange::Exchange;
+pub use kalshi::{KalshiClient, KalshiMarketQuery, KalshiSigner};
+pub use polymarket::{PolymarketClient, PolymarketMarketQuery};
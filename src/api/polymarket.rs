@@ -1,28 +1,117 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use async_trait::async_trait;
-use log::{debug, error};
-use reqwest::Client;
+use log::{debug, error, warn};
+use reqwest::{Method, RequestBuilder, StatusCode};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use crate::models::Market;
+use super::{
+    error::{ApiError, ApiResult},
+    http::HttpClient,
+    polymarket_signing::{
+        format_address, ApiCredentials, OrderArgs, OrderSide, OrderSigner, SignedOrder,
+    },
+};
+use super::exchange::Exchange;
+use crate::{
+    config::HttpConfig,
+    models::{
+        Event, Fill, Market, Order, OrderBook, OrderRequest, OrderStatus, Outcome, Platform,
+        Position, PriceLevel, TradeSide,
+    },
+};
+
+/// The CLOB rejects good-til-date orders expiring within this many seconds.
+const GTD_EXPIRATION_BUFFER_SECONDS: u64 = 60;
+
+/// Public data API, which serves positions.
+pub const DATA_API_URL: &str = "https://data-api.polymarket.com";
+
+/// USDC and outcome token amounts have six decimals.
+const USDC_DECIMALS: u32 = 6;
 
 #[derive(Debug, Clone)]
 pub struct PolymarketClient {
-    client: Client,
-    api_key: String,
+    http: HttpClient,
+    credentials: ApiCredentials,
+    signer: Option<Arc<OrderSigner>>,
+    fee_rate_bps: u64,
+    order_ttl_seconds: u64,
+    market_query: PolymarketMarketQuery,
     base_url: String,
+    data_api_url: String,
+    /// Hashes of orders whose placement went unanswered, by client order
+    /// ID, so `find_order` can ask the CLOB about them.
+    unanswered: Arc<Mutex<HashMap<String, String>>>,
+}
+
+/// Cursor the CLOB returns once there are no more pages (base64 of "-1").
+const END_CURSOR: &str = "LTE=";
+
+/// Which open markets `get_markets` lists and how far it pages.
+#[derive(Debug, Clone)]
+pub struct PolymarketMarketQuery {
+    pub tag_id: Option<String>,
+    pub page_size: u32,
+    pub max_pages: u32,
+}
+
+impl Default for PolymarketMarketQuery {
+    fn default() -> Self {
+        Self {
+            tag_id: None,
+            page_size: 500,
+            max_pages: 50,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 struct PolymarketMarketResponse {
     markets: Vec<PolymarketMarket>,
+    /// Present on cursor-paginated endpoints; offset pagination is used
+    /// otherwise.
+    #[serde(default)]
+    next_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PolymarketEventResponse {
+    events: Vec<PolymarketEvent>,
+    #[serde(default)]
+    next_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PolymarketEvent {
+    id: String,
+    title: String,
+    /// Negative-risk events have exactly one winning outcome.
+    #[serde(rename = "negRisk", default)]
+    neg_risk: bool,
+    /// Closed outcomes included.
+    #[serde(default)]
+    markets: Vec<PolymarketMarket>,
+}
+
+/// The event a market belongs to, as embedded in a market listing.
+#[derive(Debug, Deserialize)]
+struct PolymarketEventRef {
+    id: String,
+    #[serde(default)]
+    title: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct PolymarketMarket {
     id: String,
+    #[serde(rename = "conditionId", default)]
+    condition_id: Option<String>,
     question: String,
     #[serde(rename = "bestBid")]
     best_bid: String,
@@ -32,126 +121,915 @@ struct PolymarketMarket {
     liquidity: String,
     #[serde(rename = "endDate")]
     end_date: String,
+    /// JSON-encoded array of CLOB token IDs, YES first.
+    #[serde(rename = "clobTokenIds", default)]
+    clob_token_ids: Option<String>,
+    /// The market's resolution rules.
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(rename = "resolutionSource", default)]
+    resolution_source: Option<String>,
+    /// The outcome YES stands for within its event, e.g. a candidate.
+    #[serde(rename = "groupItemTitle", default)]
+    group_item_title: Option<String>,
+    #[serde(rename = "negRisk", default)]
+    neg_risk: bool,
+    #[serde(default)]
+    events: Vec<PolymarketEventRef>,
+    #[serde(default)]
+    closed: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct PolymarketBookResponse {
+    #[serde(default)]
+    bids: Vec<PolymarketBookLevel>,
+    #[serde(default)]
+    asks: Vec<PolymarketBookLevel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PolymarketBookLevel {
+    price: String,
+    size: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PostOrderRequest {
+    order: SignedOrder,
+    owner: String,
+    order_type: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PolymarketErrorResponse {
+    error: String,
 }
 
 #[derive(Debug, Serialize)]
-struct PlaceOrderRequest {
-    market_id: String,
+struct CancelOrderRequest<'a> {
+    #[serde(rename = "orderID")]
+    order_id: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct CancelOrderResponse {
+    #[serde(default)]
+    canceled: Vec<String>,
+    #[serde(default)]
+    not_canceled: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PolymarketOrder {
+    id: String,
+    /// `LIVE`, `MATCHED`, `CANCELED`, `DELAYED` or `UNMATCHED`.
+    status: String,
+    /// Condition ID.
+    #[serde(default)]
+    market: String,
     side: String,
+    original_size: String,
+    size_matched: String,
     price: String,
-    amount: String,
+    #[serde(default)]
+    outcome: String,
+    /// Unix seconds, as a string or number; zero when good-til-cancelled.
+    #[serde(default)]
+    expiration: Option<serde_json::Value>,
+    #[serde(default)]
+    created_at: Option<serde_json::Value>,
+    /// IDs of the trades that filled this order.
+    #[serde(default)]
+    associate_trades: Vec<String>,
 }
 
-impl PolymarketClient {
-    pub fn new(api_key: String, base_url: String) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .build()
-            .expect("Failed to create HTTP client");
+#[derive(Debug, Deserialize)]
+struct TradesResponse {
+    #[serde(default)]
+    data: Vec<PolymarketTrade>,
+}
+
+/// A match between one taker order and the maker orders it crossed.
+#[derive(Debug, Deserialize)]
+struct PolymarketTrade {
+    id: String,
+    taker_order_id: String,
+    market: String,
+    side: String,
+    size: String,
+    price: String,
+    outcome: String,
+    /// Unix seconds.
+    #[serde(default)]
+    match_time: Option<String>,
+    #[serde(default)]
+    maker_orders: Vec<MakerOrder>,
+}
 
+#[derive(Debug, Deserialize)]
+struct MakerOrder {
+    order_id: String,
+    matched_amount: String,
+    price: String,
+    outcome: String,
+    side: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct BalanceAllowanceResponse {
+    /// Micro-USDC.
+    balance: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DataApiPosition {
+    condition_id: String,
+    outcome: String,
+    size: f64,
+    avg_price: f64,
+    current_value: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PostOrderResponse {
+    #[serde(default)]
+    success: bool,
+    #[serde(rename = "orderID", default)]
+    order_id: String,
+    #[serde(default)]
+    error_msg: String,
+}
+
+impl PolymarketClient {
+    /// Without a signer only public market data endpoints can be used.
+    pub fn new(credentials: ApiCredentials, signer: Option<OrderSigner>, base_url: String) -> Self {
         Self {
-            client,
-            api_key,
+            http: HttpClient::new(&HttpConfig::default()),
+            credentials,
+            signer: signer.map(Arc::new),
+            fee_rate_bps: 0,
+            order_ttl_seconds: 0,
+            market_query: PolymarketMarketQuery::default(),
             base_url,
+            data_api_url: DATA_API_URL.to_string(),
+            unanswered: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    pub async fn get_markets(&self) -> Result<Vec<Market>> {
+    pub fn with_data_api_url(mut self, data_api_url: String) -> Self {
+        self.data_api_url = data_api_url;
+        self
+    }
+
+    pub fn with_market_query(mut self, market_query: PolymarketMarketQuery) -> Self {
+        self.market_query = market_query;
+        self
+    }
+
+    pub fn with_http_config(mut self, config: &HttpConfig) -> Self {
+        self.http = HttpClient::new(config);
+        self
+    }
+
+    /// Fee rate embedded in signed orders, and how long they rest before
+    /// expiring (zero for good-til-cancelled).
+    pub fn with_order_settings(mut self, fee_rate_bps: u64, order_ttl_seconds: u64) -> Self {
+        self.fee_rate_bps = fee_rate_bps;
+        self.order_ttl_seconds = order_ttl_seconds;
+        self
+    }
+
+    /// Lists active, unclosed markets, following `next_cursor` (or offsets
+    /// when the endpoint has no cursor) until the last page or `max_pages`.
+    pub async fn get_markets(&self) -> ApiResult<Vec<Market>> {
         debug!("Fetching Polymarket markets");
 
-        let url = format!("{}/markets", self.base_url);
-        
-        let response = self
-            .client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .send()
-            .await
-            .context("Failed to fetch markets from Polymarket")?;
+        let mut markets = Vec::new();
+        let mut cursor: Option<String> = None;
+        let mut offset = 0;
 
-        if !response.status().is_success() {
-            error!("Polymarket API error: {}", response.status());
-            return Err(anyhow::anyhow!("API request failed"));
-        }
+        for _ in 0..self.market_query.max_pages {
+            let page = self.get_markets_page(cursor.as_deref(), offset).await?;
+            let count = page.markets.len();
 
-        let data: PolymarketMarketResponse = response
-            .json()
-            .await
-            .context("Failed to parse Polymarket response")?;
+            markets.extend(
+                page.markets
+                    .into_iter()
+                    .filter_map(|m| self.parse_market(m).ok()),
+            );
 
-        let markets = data
-            .markets
-            .into_iter()
-            .filter_map(|m| self.parse_market(m).ok())
-            .collect();
+            match page.next_cursor {
+                Some(next) if next.is_empty() || next == END_CURSOR => return Ok(markets),
+                Some(next) => cursor = Some(next),
+                None => {
+                    if count < self.market_query.page_size as usize {
+                        return Ok(markets);
+                    }
+                    offset += count;
+                }
+            }
+        }
 
+        warn!(
+            "Stopped listing Polymarket markets after {} pages ({} markets)",
+            self.market_query.max_pages,
+            markets.len()
+        );
         Ok(markets)
     }
 
+    async fn get_markets_page(
+        &self,
+        cursor: Option<&str>,
+        offset: usize,
+    ) -> ApiResult<PolymarketMarketResponse> {
+        let url = format!("{}/markets", self.base_url);
+
+        let query = &self.market_query;
+        let mut params = vec![
+            ("active", "true".to_string()),
+            ("closed", "false".to_string()),
+            ("limit", query.page_size.to_string()),
+        ];
+        if let Some(tag_id) = &query.tag_id {
+            params.push(("tag_id", tag_id.clone()));
+        }
+        match cursor {
+            Some(cursor) => params.push(("next_cursor", cursor.to_string())),
+            None => params.push(("offset", offset.to_string())),
+        }
+
+        self.http
+            .read(
+                || self.http.client().get(&url).query(&params),
+                polymarket_error,
+            )
+            .await
+    }
+
     fn parse_market(&self, market: PolymarketMarket) -> Result<Market> {
+        let token_ids: Vec<String> = match &market.clob_token_ids {
+            Some(ids) => serde_json::from_str(ids)?,
+            None => Vec::new(),
+        };
+        let event = market.events.into_iter().next();
+
         Ok(Market {
             id: market.id,
             question: market.question,
-            platform: crate::models::Platform::Polymarket,
+            platform: Platform::Polymarket,
+            // Selling YES at the bid is equivalent to buying NO at 1 - bid
             yes_price: market.best_ask.parse()?,
-            no_price: market.best_bid.parse()?,
+            no_price: Decimal::ONE - market.best_bid.parse::<Decimal>()?,
             volume: market.volume.parse()?,
             liquidity: market.liquidity.parse()?,
             end_time: chrono::DateTime::parse_from_rfc3339(&market.end_date)?
                 .with_timezone(&chrono::Utc),
+            yes_token_id: token_ids.first().cloned(),
+            no_token_id: token_ids.get(1).cloned(),
+            rules: non_empty(market.description),
+            resolution_source: non_empty(market.resolution_source),
+            settlement_timer_seconds: None,
+            event_id: event.as_ref().map(|event| event.id.clone()),
+            event_title: event.and_then(|event| non_empty(event.title)),
+            outcome_name: non_empty(market.group_item_title),
+            neg_risk: market.neg_risk,
+            condition_id: non_empty(market.condition_id),
         })
     }
 
-    pub async fn place_order(
+    /// Lists active, unclosed events with their open markets, paging as
+    /// `get_markets` does.
+    pub async fn get_events(&self) -> ApiResult<Vec<Event>> {
+        debug!("Fetching Polymarket events");
+
+        let mut events = Vec::new();
+        let mut cursor: Option<String> = None;
+        let mut offset = 0;
+
+        for _ in 0..self.market_query.max_pages {
+            let page = self.get_events_page(cursor.as_deref(), offset).await?;
+            let count = page.events.len();
+
+            events.extend(page.events.into_iter().map(|e| self.parse_event(e)));
+
+            match page.next_cursor {
+                Some(next) if next.is_empty() || next == END_CURSOR => return Ok(events),
+                Some(next) => cursor = Some(next),
+                None => {
+                    if count < self.market_query.page_size as usize {
+                        return Ok(events);
+                    }
+                    offset += count;
+                }
+            }
+        }
+
+        warn!(
+            "Stopped listing Polymarket events after {} pages ({} events)",
+            self.market_query.max_pages,
+            events.len()
+        );
+        Ok(events)
+    }
+
+    async fn get_events_page(
         &self,
-        market_id: &str,
-        side: &str,
-        price: Decimal,
-        amount: Decimal,
-    ) -> Result<String> {
-        debug!("Placing order on Polymarket: {} {} @ {}", amount, side, price);
-
-        let url = format!("{}/orders", self.base_url);
-        
-        let request = PlaceOrderRequest {
-            market_id: market_id.to_string(),
-            side: side.to_string(),
-            price: price.to_string(),
-            amount: amount.to_string(),
+        cursor: Option<&str>,
+        offset: usize,
+    ) -> ApiResult<PolymarketEventResponse> {
+        let url = format!("{}/events", self.base_url);
+
+        let query = &self.market_query;
+        let mut params = vec![
+            ("active", "true".to_string()),
+            ("closed", "false".to_string()),
+            ("limit", query.page_size.to_string()),
+        ];
+        if let Some(tag_id) = &query.tag_id {
+            params.push(("tag_id", tag_id.clone()));
+        }
+        match cursor {
+            Some(cursor) => params.push(("next_cursor", cursor.to_string())),
+            None => params.push(("offset", offset.to_string())),
+        }
+
+        self.http
+            .read(
+                || self.http.client().get(&url).query(&params),
+                polymarket_error,
+            )
+            .await
+    }
+
+    fn parse_event(&self, event: PolymarketEvent) -> Event {
+        let markets = event
+            .markets
+            .into_iter()
+            .filter(|m| !m.closed)
+            .filter_map(|m| self.parse_market(m).ok())
+            .map(|market| Market {
+                event_id: Some(event.id.clone()),
+                event_title: Some(event.title.clone()),
+                neg_risk: market.neg_risk || event.neg_risk,
+                ..market
+            })
+            .collect();
+
+        Event {
+            id: event.id,
+            platform: Platform::Polymarket,
+            title: event.title,
+            mutually_exclusive: event.neg_risk,
+            markets,
+        }
+    }
+
+    /// Fetches the YES token's book. Its bids are the NO side's asks, so one
+    /// request covers both outcomes.
+    pub async fn get_order_book(&self, market: &Market) -> ApiResult<OrderBook> {
+        debug!("Fetching Polymarket order book for {}", market.id);
+
+        let token_id = market.yes_token_id.as_deref().ok_or_else(|| ApiError::InvalidOrder {
+            reason: format!("Market {} has no CLOB token", market.id),
+        })?;
+
+        let url = format!("{}/book", self.base_url);
+
+        let data: PolymarketBookResponse = self
+            .http
+            .read(
+                || self.http.client().get(&url).query(&[("token_id", token_id)]),
+                polymarket_error,
+            )
+            .await?;
+
+        self.parse_order_book(&market.id, data)
+            .map_err(|e| ApiError::Decode { body: e.to_string() })
+    }
+
+    fn parse_order_book(&self, market_id: &str, book: PolymarketBookResponse) -> Result<OrderBook> {
+        let levels = |levels: &[PolymarketBookLevel]| {
+            levels
+                .iter()
+                .map(|level| {
+                    Ok(PriceLevel {
+                        price: level.price.parse()?,
+                        size: level.size.parse()?,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()
         };
 
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&request)
-            .send()
+        Ok(order_book_from_yes_levels(
+            market_id,
+            levels(&book.bids)?,
+            levels(&book.asks)?,
+        ))
+    }
+
+    /// Places a limit order for the outcome token in `order.token_id`.
+    pub async fn place_order(&self, order: &OrderRequest) -> ApiResult<String> {
+        let token_id = order.token_id.as_deref().ok_or_else(|| ApiError::InvalidOrder {
+            reason: format!(
+                "No {} token for Polymarket market {}",
+                order.outcome.as_str(),
+                order.market_id
+            ),
+        })?;
+        debug!(
+            "Placing order on Polymarket: {} {} of {} @ {}",
+            order.side.as_str(),
+            order.size,
+            token_id,
+            order.price
+        );
+
+        let signer = self.signer()?;
+
+        let now = chrono::Utc::now().timestamp();
+        let (expiration, order_type) = if self.order_ttl_seconds > 0 {
+            let expiration =
+                now as u64 + GTD_EXPIRATION_BUFFER_SECONDS + self.order_ttl_seconds;
+            (expiration, "GTD")
+        } else {
+            (0, "GTC")
+        };
+
+        // Kept below 2^53 so the salt survives JSON number parsing
+        let salt = rand::random::<u64>() >> 11;
+
+        let signed_order = signer
+            .build_order(&OrderArgs {
+                token_id: token_id.to_string(),
+                side: match order.side {
+                    TradeSide::Buy => OrderSide::Buy,
+                    TradeSide::Sell => OrderSide::Sell,
+                },
+                price: order.price,
+                size: order.size,
+                fee_rate_bps: self.fee_rate_bps,
+                expiration,
+                salt,
+                neg_risk: order.neg_risk,
+            })
+            .map_err(|e| ApiError::InvalidOrder {
+                reason: e.to_string(),
+            })?;
+
+        let hash = signed_order.hash.clone();
+        let body = serde_json::to_string(&PostOrderRequest {
+            order: signed_order,
+            owner: self.credentials.api_key.clone(),
+            order_type: order_type.to_string(),
+        })
+        .expect("order request serializes");
+
+        let result: ApiResult<PostOrderResponse> = self
+            .http
+            .write(
+                self.l2_request(Method::POST, "/order", body),
+                polymarket_error,
+            )
+            .await;
+        if let (Some(client_order_id), Err(e)) = (&order.client_order_id, &result) {
+            if e.is_retryable() {
+                self.unanswered
+                    .lock()
+                    .expect("unanswered order lock poisoned")
+                    .insert(client_order_id.clone(), hash);
+            }
+        }
+
+        let data = result?;
+        if !data.success || data.order_id.is_empty() {
+            error!("Polymarket order rejected: {}", data.error_msg);
+            return Err(order_rejection(&data.error_msg));
+        }
+
+        Ok(data.order_id)
+    }
+
+    pub async fn cancel_order(&self, order_id: &str) -> ApiResult<()> {
+        debug!("Cancelling Polymarket order {}", order_id);
+        self.signer()?;
+
+        let body = serde_json::to_string(&CancelOrderRequest { order_id })
+            .expect("cancel request serializes");
+
+        let data: CancelOrderResponse = self
+            .http
+            .write(
+                self.l2_request(Method::DELETE, "/order", body),
+                polymarket_error,
+            )
             .await?;
 
-        if !response.status().is_success() {
-            return Err(anyhow::anyhow!("Failed to place order"));
+        if data.canceled.iter().any(|id| id == order_id) {
+            return Ok(());
+        }
+
+        let reason = data
+            .not_canceled
+            .get(order_id)
+            .cloned()
+            .unwrap_or_else(|| format!("Order {} was not cancelled", order_id));
+        Err(order_rejection(&reason))
+    }
+
+    pub async fn get_order(&self, order_id: &str) -> ApiResult<Order> {
+        parse_order(self.fetch_order(order_id).await?)
+    }
+
+    /// Asks the CLOB about an order whose placement went unanswered, by the
+    /// hash it was signed with. Every other order was refused or given its
+    /// ID when placed, so is not looked for.
+    pub async fn find_order(&self, order: &OrderRequest) -> ApiResult<Option<Order>> {
+        self.signer()?;
+        let client_order_id = order.client_order_id.as_deref().ok_or_else(|| {
+            ApiError::InvalidOrder {
+                reason: "the order has no client order ID to look up".to_string(),
+            }
+        })?;
+        let Some(hash) = self
+            .unanswered
+            .lock()
+            .expect("unanswered order lock poisoned")
+            .get(client_order_id)
+            .cloned()
+        else {
+            return Ok(None);
+        };
+
+        let path = format!("/data/order/{}", hash);
+        let data: Option<PolymarketOrder> = self
+            .http
+            .read(
+                || self.l2_request(Method::GET, &path, String::new()),
+                polymarket_error,
+            )
+            .await?;
+        data.map(parse_order).transpose()
+    }
+
+    /// Looks up each trade the order took part in; an order can be the
+    /// taker of a trade or one of its makers.
+    pub async fn list_fills(&self, order_id: &str) -> ApiResult<Vec<Fill>> {
+        let order = self.fetch_order(order_id).await?;
+        let mut fills = Vec::new();
+
+        for trade_id in &order.associate_trades {
+            let data: TradesResponse = self
+                .http
+                .read(
+                    || {
+                        self.l2_request(Method::GET, "/data/trades", String::new())
+                            .query(&[("id", trade_id.as_str())])
+                    },
+                    polymarket_error,
+                )
+                .await?;
+
+            for trade in data.data {
+                fills.push(parse_fill(order_id, trade)?);
+            }
+        }
+
+        fills.sort_by_key(|fill| fill.filled_at);
+        Ok(fills)
+    }
+
+    async fn fetch_order(&self, order_id: &str) -> ApiResult<PolymarketOrder> {
+        self.signer()?;
+        let path = format!("/data/order/{}", order_id);
+
+        self.http
+            .read(
+                || self.l2_request(Method::GET, &path, String::new()),
+                polymarket_error,
+            )
+            .await
+    }
+
+    /// Positions held by the funding wallet. `market_id` is the market's
+    /// condition ID.
+    pub async fn get_positions(&self) -> ApiResult<Vec<Position>> {
+        let user = format_address(&self.signer()?.funder());
+        let url = format!("{}/positions", self.data_api_url);
+
+        let data: Vec<DataApiPosition> = self
+            .http
+            .read(
+                || {
+                    self.http
+                        .client()
+                        .get(&url)
+                        .query(&[("user", user.as_str()), ("sizeThreshold", "0")])
+                },
+                polymarket_error,
+            )
+            .await?;
+
+        data.into_iter().map(parse_position).collect()
+    }
+
+    /// USDC available to the funding wallet.
+    pub async fn get_balance(&self) -> ApiResult<Decimal> {
+        let signature_type = self.signer()?.signature_type().as_u8().to_string();
+        let data: BalanceAllowanceResponse = self
+            .http
+            .read(
+                || {
+                    self.l2_request(Method::GET, "/balance-allowance", String::new())
+                        .query(&[
+                            ("asset_type", "COLLATERAL"),
+                            ("signature_type", signature_type.as_str()),
+                        ])
+                },
+                polymarket_error,
+            )
+            .await?;
+
+        let micro_usdc: Decimal = data.balance.parse().map_err(|_| ApiError::Decode {
+            body: data.balance.clone(),
+        })?;
+        Ok(micro_usdc / Decimal::from(10u64.pow(USDC_DECIMALS)))
+    }
+
+    fn signer(&self) -> ApiResult<&OrderSigner> {
+        self.signer.as_deref().ok_or_else(|| {
+            error!("Polymarket private key not configured");
+            ApiError::Unauthorized
+        })
+    }
+
+    /// Builds a request carrying the `POLY_*` L2 headers, signed as it is
+    /// built so each retry carries a fresh timestamp. `path` excludes the
+    /// query string, which is not signed.
+    fn l2_request(&self, method: Method, path: &str, body: String) -> RequestBuilder {
+        let url = format!("{}{}", self.base_url, path);
+        let mut request = self.http.client().request(method.clone(), &url);
+        for (name, value) in self.l2_headers(method.as_str(), path, &body) {
+            request = request.header(name, value);
+        }
+        if !body.is_empty() {
+            request = request
+                .header("Content-Type", "application/json")
+                .body(body);
+        }
+
+        request
+    }
+
+    /// `POLY_*` headers for `method`, `path` and `body`, or none when no
+    /// private key is configured or the credentials cannot sign, in which
+    /// case the venue refuses the request.
+    fn l2_headers(&self, method: &str, path: &str, body: &str) -> Vec<(&'static str, String)> {
+        let Some(signer) = &self.signer else {
+            return Vec::new();
+        };
+
+        let timestamp = chrono::Utc::now().timestamp();
+        self.credentials
+            .headers(&signer.address(), timestamp, method, path, body)
+            .unwrap_or_else(|e| {
+                error!("Failed to sign Polymarket request: {}", e);
+                Vec::new()
+            })
+    }
+}
+
+/// Gamma sends absent text fields as empty strings.
+fn non_empty(text: Option<String>) -> Option<String> {
+    text.filter(|text| !text.trim().is_empty())
+}
+
+fn parse_outcome(outcome: &str) -> Option<Outcome> {
+    match outcome.to_ascii_lowercase().as_str() {
+        "yes" => Some(Outcome::Yes),
+        "no" => Some(Outcome::No),
+        _ => None,
+    }
+}
+
+fn parse_order(order: PolymarketOrder) -> ApiResult<Order> {
+    let decode = |field: &str, value: &str| ApiError::Decode {
+        body: format!("Polymarket order {} has {} {:?}", order.id, field, value),
+    };
+    let decimal = |field: &str, value: &str| {
+        value
+            .parse::<Decimal>()
+            .map_err(|_| decode(field, value))
+    };
+    let unix_seconds = |value: &Option<serde_json::Value>| match value {
+        Some(serde_json::Value::Number(n)) => n.as_i64(),
+        Some(serde_json::Value::String(s)) => s.parse().ok(),
+        _ => None,
+    };
+
+    let outcome = parse_outcome(&order.outcome).ok_or_else(|| decode("outcome", &order.outcome))?;
+    let side = parse_side(&order.side).ok_or_else(|| decode("side", &order.side))?;
+    let size = decimal("original_size", &order.original_size)?;
+    let filled_size = decimal("size_matched", &order.size_matched)?;
+    let expiration = unix_seconds(&order.expiration).filter(|expiration| *expiration > 0);
+
+    let status = match order.status.to_ascii_uppercase().as_str() {
+        "MATCHED" => OrderStatus::Filled,
+        "CANCELED" | "CANCELLED"
+            if expiration.is_some_and(|e| e <= chrono::Utc::now().timestamp()) =>
+        {
+            OrderStatus::Expired
+        }
+        "CANCELED" | "CANCELLED" => OrderStatus::Cancelled,
+        "LIVE" | "DELAYED" | "UNMATCHED" if filled_size > Decimal::ZERO => {
+            OrderStatus::PartiallyFilled
         }
+        "LIVE" | "DELAYED" | "UNMATCHED" => OrderStatus::New,
+        _ => return Err(decode("status", &order.status)),
+    };
 
-        let order_id = response
-            .json::<serde_json::Value>()
-            .await?
-            .get("id")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow::anyhow!("Invalid response"))?
-            .to_string();
+    Ok(Order {
+        price: decimal("price", &order.price)?,
+        id: order.id,
+        platform: Platform::Polymarket,
+        market_id: order.market,
+        outcome,
+        side,
+        size,
+        filled_size,
+        status,
+        created_at: unix_seconds(&order.created_at)
+            .and_then(|seconds| chrono::DateTime::from_timestamp(seconds, 0)),
+    })
+}
 
-        Ok(order_id)
+fn parse_side(side: &str) -> Option<TradeSide> {
+    match side.to_ascii_uppercase().as_str() {
+        "BUY" => Some(TradeSide::Buy),
+        "SELL" => Some(TradeSide::Sell),
+        _ => None,
     }
 }
 
-#[async_trait]
-pub trait MarketDataProvider {
-    async fn get_markets(&self) -> Result<Vec<Market>>;
+/// The part of `trade` that filled `order_id`.
+fn parse_fill(order_id: &str, trade: PolymarketTrade) -> ApiResult<Fill> {
+    let decode = |field: &str, value: &str| ApiError::Decode {
+        body: format!("Polymarket trade {} has {} {:?}", trade.id, field, value),
+    };
+    let decimal = |field: &str, value: &str| {
+        value
+            .parse::<Decimal>()
+            .map_err(|_| decode(field, value))
+    };
+
+    let (side, outcome, price, size) = if trade.taker_order_id == order_id {
+        (&trade.side, &trade.outcome, &trade.price, &trade.size)
+    } else {
+        let maker = trade
+            .maker_orders
+            .iter()
+            .find(|maker| maker.order_id == order_id)
+            .ok_or_else(|| decode("no part of order", order_id))?;
+        (&maker.side, &maker.outcome, &maker.price, &maker.matched_amount)
+    };
+
+    Ok(Fill {
+        order_id: order_id.to_string(),
+        platform: Platform::Polymarket,
+        market_id: trade.market.clone(),
+        outcome: parse_outcome(outcome).ok_or_else(|| decode("outcome", outcome))?,
+        side: parse_side(side).ok_or_else(|| decode("side", side))?,
+        price: decimal("price", price)?,
+        size: decimal("size", size)?,
+        filled_at: trade
+            .match_time
+            .as_deref()
+            .and_then(|seconds| seconds.parse().ok())
+            .and_then(|seconds| chrono::DateTime::from_timestamp(seconds, 0)),
+        id: trade.id.clone(),
+    })
+}
+
+fn parse_position(position: DataApiPosition) -> ApiResult<Position> {
+    let outcome = parse_outcome(&position.outcome).ok_or_else(|| ApiError::Decode {
+        body: format!(
+            "Polymarket position in {} has outcome {:?}",
+            position.condition_id, position.outcome
+        ),
+    })?;
+    let decimal = |value: f64| {
+        Decimal::try_from(value).map_err(|_| ApiError::Decode {
+            body: value.to_string(),
+        })
+    };
+
+    Ok(Position {
+        platform: Platform::Polymarket,
+        market_id: position.condition_id,
+        outcome,
+        amount: decimal(position.size)?,
+        entry_price: decimal(position.avg_price)?,
+        current_value: decimal(position.current_value)?,
+    })
+}
+
+/// Maps Polymarket's `{"error": "..."}` payloads, falling back to the
+/// status for anything unrecognized.
+fn polymarket_error(status: StatusCode, retry_after: Option<Duration>, body: &str) -> ApiError {
+    error!("Polymarket API error: {} {}", status, body);
+
+    let message = serde_json::from_str::<PolymarketErrorResponse>(body)
+        .map(|response| response.error)
+        .unwrap_or_else(|_| body.to_string());
+
+    match order_rejection(&message) {
+        ApiError::InvalidOrder { reason } => ApiError::from_status(status, retry_after, reason),
+        error => error,
+    }
+}
+
+/// Classifies the CLOB's free-text order errors, e.g. `not enough
+/// balance / allowance` or `the market is not yet ready to process new
+/// orders`.
+fn order_rejection(message: &str) -> ApiError {
+    let lower = message.to_lowercase();
+
+    if lower.contains("balance") || lower.contains("allowance") {
+        ApiError::InsufficientFunds
+    } else if lower.contains("closed") || lower.contains("not yet ready") {
+        ApiError::MarketClosed
+    } else {
+        ApiError::InvalidOrder {
+            reason: message.to_string(),
+        }
+    }
+}
+
+/// Builds a normalized book from the YES token's bids and asks. A YES bid
+/// at `p` is an ask for NO at `1 - p`.
+pub(crate) fn order_book_from_yes_levels(
+    market_id: &str,
+    bids: Vec<PriceLevel>,
+    asks: Vec<PriceLevel>,
+) -> OrderBook {
+    let no_asks = bids
+        .into_iter()
+        .map(|level| PriceLevel {
+            price: Decimal::ONE - level.price,
+            size: level.size,
+        })
+        .collect();
+
+    OrderBook::new(Platform::Polymarket, market_id.to_string(), asks, no_asks)
 }
 
 #[async_trait]
-impl MarketDataProvider for PolymarketClient {
-    async fn get_markets(&self) -> Result<Vec<Market>> {
+impl Exchange for PolymarketClient {
+    fn platform(&self) -> Platform {
+        Platform::Polymarket
+    }
+
+    async fn get_markets(&self) -> ApiResult<Vec<Market>> {
         self.get_markets().await
     }
+
+    async fn get_events(&self) -> ApiResult<Vec<Event>> {
+        self.get_events().await
+    }
+
+    async fn get_order_book(&self, market: &Market) -> ApiResult<OrderBook> {
+        self.get_order_book(market).await
+    }
+
+    async fn place_order(&self, order: &OrderRequest) -> ApiResult<String> {
+        self.place_order(order).await
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> ApiResult<()> {
+        self.cancel_order(order_id).await
+    }
+
+    async fn get_order(&self, order_id: &str) -> ApiResult<Order> {
+        self.get_order(order_id).await
+    }
+
+    async fn find_order(&self, order: &OrderRequest) -> ApiResult<Option<Order>> {
+        self.find_order(order).await
+    }
+
+    async fn list_fills(&self, order_id: &str) -> ApiResult<Vec<Fill>> {
+        self.list_fills(order_id).await
+    }
+
+    async fn get_positions(&self) -> ApiResult<Vec<Position>> {
+        self.get_positions().await
+    }
+
+    async fn get_balance(&self) -> ApiResult<Decimal> {
+        self.get_balance().await
+    }
 }
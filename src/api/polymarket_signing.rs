@@ -0,0 +1,862 @@
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::URL_SAFE as BASE64_URL, Engine};
+use hmac::{Hmac, Mac};
+use k256::ecdsa::SigningKey;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sha3::{Digest, Keccak256};
+use std::fmt;
+
+use crate::config::PolymarketConfig;
+
+/// CTF Exchange contract on Polygon that settles CLOB orders.
+pub const CTF_EXCHANGE_ADDRESS: &str = "0x4bFb41d5B3570DeFd03C39a9A4D8dE6Bd8B8982E";
+/// NegRisk CTF Exchange contract on Polygon, which settles orders on
+/// neg-risk markets instead.
+pub const NEG_RISK_CTF_EXCHANGE_ADDRESS: &str = "0xC5d563A36AE78145C45a50134d48A1215220f80a";
+pub const POLYGON_CHAIN_ID: u64 = 137;
+
+const EXCHANGE_DOMAIN_NAME: &str = "Polymarket CTF Exchange";
+const EXCHANGE_DOMAIN_VERSION: &str = "1";
+const DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+const ORDER_TYPE: &str = "Order(uint256 salt,address maker,address signer,address taker,\
+uint256 tokenId,uint256 makerAmount,uint256 takerAmount,uint256 expiration,uint256 nonce,\
+uint256 feeRateBps,uint8 side,uint8 signatureType)";
+
+/// USDC and outcome tokens both use six decimals on-chain.
+const TOKEN_DECIMALS: u32 = 6;
+
+pub type Address = [u8; 20];
+
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+pub fn parse_address(address: &str) -> Result<Address> {
+    let hex = address.strip_prefix("0x").unwrap_or(address);
+    let bytes = decode_hex(hex).with_context(|| format!("Invalid address: {}", address))?;
+    bytes
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Address must be 20 bytes: {}", address))
+}
+
+/// EIP-55 mixed-case checksum encoding.
+pub fn format_address(address: &Address) -> String {
+    let lower = encode_hex(address);
+    let hash = keccak256(lower.as_bytes());
+
+    let checksummed: String = lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            if c.is_ascii_alphabetic() && nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect();
+
+    format!("0x{}", checksummed)
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(anyhow::anyhow!("Odd-length hex string"));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).context("Invalid hex digit"))
+        .collect()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn encode_uint(value: u128) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[16..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// Encodes a base-10 string such as a CLOB token ID as a uint256 word.
+fn encode_decimal_uint(value: &str) -> Result<[u8; 32]> {
+    if value.is_empty() {
+        return Err(anyhow::anyhow!("Empty integer"));
+    }
+
+    let mut word = [0u8; 32];
+    for c in value.chars() {
+        let digit = c
+            .to_digit(10)
+            .ok_or_else(|| anyhow::anyhow!("Invalid integer: {}", value))?;
+
+        let mut carry = digit;
+        for byte in word.iter_mut().rev() {
+            let next = *byte as u32 * 10 + carry;
+            *byte = (next & 0xff) as u8;
+            carry = next >> 8;
+        }
+        if carry != 0 {
+            return Err(anyhow::anyhow!("Integer overflows uint256: {}", value));
+        }
+    }
+
+    Ok(word)
+}
+
+fn encode_address(address: &Address) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(address);
+    word
+}
+
+/// `hashStruct` for a type whose fields have already been ABI-encoded into
+/// 32-byte words.
+pub fn hash_struct(type_string: &str, fields: &[[u8; 32]]) -> [u8; 32] {
+    let mut encoded = Vec::with_capacity(32 * (fields.len() + 1));
+    encoded.extend_from_slice(&keccak256(type_string.as_bytes()));
+    for field in fields {
+        encoded.extend_from_slice(field);
+    }
+    keccak256(&encoded)
+}
+
+/// Final digest signed for EIP-712 typed data.
+pub fn typed_data_digest(domain_separator: &[u8; 32], struct_hash: &[u8; 32]) -> [u8; 32] {
+    let mut encoded = Vec::with_capacity(66);
+    encoded.extend_from_slice(&[0x19, 0x01]);
+    encoded.extend_from_slice(domain_separator);
+    encoded.extend_from_slice(struct_hash);
+    keccak256(&encoded)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Eip712Domain {
+    pub name: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub verifying_contract: Address,
+}
+
+impl Eip712Domain {
+    pub fn ctf_exchange(chain_id: u64, verifying_contract: Address) -> Self {
+        Self {
+            name: EXCHANGE_DOMAIN_NAME.to_string(),
+            version: EXCHANGE_DOMAIN_VERSION.to_string(),
+            chain_id,
+            verifying_contract,
+        }
+    }
+
+    pub fn separator(&self) -> [u8; 32] {
+        hash_struct(
+            DOMAIN_TYPE,
+            &[
+                keccak256(self.name.as_bytes()),
+                keccak256(self.version.as_bytes()),
+                encode_uint(self.chain_id as u128),
+                encode_address(&self.verifying_contract),
+            ],
+        )
+    }
+}
+
+/// How the order's maker address relates to the signing key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureType {
+    /// The signing key's own address holds the funds.
+    #[default]
+    Eoa,
+    /// Funds sit in a Polymarket proxy wallet controlled by the key.
+    PolyProxy,
+    /// Funds sit in a Gnosis Safe controlled by the key.
+    PolyGnosisSafe,
+}
+
+impl SignatureType {
+    pub fn as_u8(self) -> u8 {
+        match self {
+            SignatureType::Eoa => 0,
+            SignatureType::PolyProxy => 1,
+            SignatureType::PolyGnosisSafe => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+impl OrderSide {
+    fn as_u8(self) -> u8 {
+        match self {
+            OrderSide::Buy => 0,
+            OrderSide::Sell => 1,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            OrderSide::Buy => "BUY",
+            OrderSide::Sell => "SELL",
+        }
+    }
+}
+
+/// The order struct the CTF Exchange verifies on-chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClobOrder {
+    pub salt: u64,
+    pub maker: Address,
+    pub signer: Address,
+    pub taker: Address,
+    pub token_id: String,
+    pub maker_amount: u128,
+    pub taker_amount: u128,
+    /// Unix seconds; zero means good-til-cancelled.
+    pub expiration: u64,
+    pub nonce: u64,
+    pub fee_rate_bps: u64,
+    pub side: OrderSide,
+    pub signature_type: SignatureType,
+}
+
+impl ClobOrder {
+    pub fn struct_hash(&self) -> Result<[u8; 32]> {
+        Ok(hash_struct(
+            ORDER_TYPE,
+            &[
+                encode_uint(self.salt as u128),
+                encode_address(&self.maker),
+                encode_address(&self.signer),
+                encode_address(&self.taker),
+                encode_decimal_uint(&self.token_id)?,
+                encode_uint(self.maker_amount),
+                encode_uint(self.taker_amount),
+                encode_uint(self.expiration as u128),
+                encode_uint(self.nonce as u128),
+                encode_uint(self.fee_rate_bps as u128),
+                encode_uint(self.side.as_u8() as u128),
+                encode_uint(self.signature_type.as_u8() as u128),
+            ],
+        ))
+    }
+}
+
+/// On-chain amounts for an order of `size` outcome tokens at `price`.
+///
+/// Buys pay USDC (maker amount) for tokens (taker amount); sells are the
+/// reverse. Size is truncated to 2 decimals and the USDC leg to 4, which is
+/// the precision the CLOB accepts at a 0.01 tick.
+pub fn order_amounts(side: OrderSide, price: Decimal, size: Decimal) -> Result<(u128, u128)> {
+    let size = size.round_dp_with_strategy(2, RoundingStrategy::ToZero);
+    let notional = (price * size).round_dp_with_strategy(4, RoundingStrategy::ToZero);
+
+    let to_units = |value: Decimal| -> Result<u128> {
+        let units = value * Decimal::from(10u64.pow(TOKEN_DECIMALS));
+        units
+            .trunc()
+            .to_string()
+            .parse()
+            .with_context(|| format!("Invalid order amount: {}", value))
+    };
+
+    let (tokens, usdc) = (to_units(size)?, to_units(notional)?);
+    if tokens == 0 || usdc == 0 {
+        return Err(anyhow::anyhow!("Order amount rounds to zero"));
+    }
+
+    Ok(match side {
+        OrderSide::Buy => (usdc, tokens),
+        OrderSide::Sell => (tokens, usdc),
+    })
+}
+
+/// secp256k1 key that signs CLOB orders.
+#[derive(Clone)]
+pub struct PolymarketSigner {
+    key: SigningKey,
+    address: Address,
+}
+
+impl PolymarketSigner {
+    pub fn from_hex(private_key: &str) -> Result<Self> {
+        let hex = private_key.strip_prefix("0x").unwrap_or(private_key);
+        let bytes = decode_hex(hex).context("Invalid Polymarket private key")?;
+        let key = SigningKey::from_slice(&bytes).context("Invalid Polymarket private key")?;
+
+        let public_key = key.verifying_key().to_encoded_point(false);
+        let hash = keccak256(&public_key.as_bytes()[1..]);
+        let mut address = [0u8; 20];
+        address.copy_from_slice(&hash[12..]);
+
+        Ok(Self { key, address })
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// 65-byte `r || s || v` signature with `v` in {27, 28}.
+    pub fn sign_digest(&self, digest: &[u8; 32]) -> Result<[u8; 65]> {
+        let (signature, recovery_id) = self
+            .key
+            .sign_prehash_recoverable(digest)
+            .context("Failed to sign digest")?;
+
+        let mut bytes = [0u8; 65];
+        bytes[..64].copy_from_slice(&signature.to_bytes());
+        bytes[64] = 27 + recovery_id.to_byte();
+        Ok(bytes)
+    }
+
+    pub fn sign_order(&self, domain: &Eip712Domain, order: &ClobOrder) -> Result<String> {
+        let digest = typed_data_digest(&domain.separator(), &order.struct_hash()?);
+        Ok(format!("0x{}", encode_hex(&self.sign_digest(&digest)?)))
+    }
+}
+
+impl fmt::Debug for PolymarketSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PolymarketSigner")
+            .field("address", &format_address(&self.address))
+            .finish_non_exhaustive()
+    }
+}
+
+/// JSON shape of a signed order as the CLOB's `POST /order` expects it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedOrder {
+    pub salt: u64,
+    pub maker: String,
+    pub signer: String,
+    pub taker: String,
+    pub token_id: String,
+    pub maker_amount: String,
+    pub taker_amount: String,
+    pub expiration: String,
+    pub nonce: String,
+    pub fee_rate_bps: String,
+    pub side: String,
+    pub signature_type: u8,
+    pub signature: String,
+    /// The order's EIP-712 hash, which the CLOB uses as its order ID.
+    #[serde(skip)]
+    pub hash: String,
+}
+
+impl SignedOrder {
+    pub fn new(order: &ClobOrder, signature: String, hash: [u8; 32]) -> Self {
+        Self {
+            salt: order.salt,
+            maker: format_address(&order.maker),
+            signer: format_address(&order.signer),
+            taker: format_address(&order.taker),
+            token_id: order.token_id.clone(),
+            maker_amount: order.maker_amount.to_string(),
+            taker_amount: order.taker_amount.to_string(),
+            expiration: order.expiration.to_string(),
+            nonce: order.nonce.to_string(),
+            fee_rate_bps: order.fee_rate_bps.to_string(),
+            side: order.side.as_str().to_string(),
+            signature_type: order.signature_type.as_u8(),
+            signature,
+            hash: format!("0x{}", encode_hex(&hash)),
+        }
+    }
+}
+
+/// Everything needed to build and sign orders for one trading account.
+#[derive(Debug, Clone)]
+pub struct OrderSigner {
+    signer: PolymarketSigner,
+    maker: Address,
+    signature_type: SignatureType,
+    domain: Eip712Domain,
+    neg_risk_domain: Eip712Domain,
+}
+
+impl OrderSigner {
+    /// Builds the signer described by `config`, or `None` when no private
+    /// key is configured.
+    pub fn from_config(config: &PolymarketConfig) -> Result<Option<Self>> {
+        if config.private_key.is_empty() {
+            return Ok(None);
+        }
+
+        let signer = PolymarketSigner::from_hex(&config.private_key)?;
+        let funder = if config.wallet_address.is_empty() {
+            None
+        } else {
+            Some(parse_address(&config.wallet_address)?)
+        };
+        let domain =
+            Eip712Domain::ctf_exchange(config.chain_id, parse_address(&config.exchange_address)?);
+        let neg_risk_domain = Eip712Domain::ctf_exchange(
+            config.chain_id,
+            parse_address(&config.neg_risk_exchange_address)?,
+        );
+
+        Self::new(signer, funder, config.signature_type, domain)
+            .map(|signer| signer.with_neg_risk_domain(neg_risk_domain))
+            .map(Some)
+    }
+
+    /// `funder` is the address holding the funds. It must be the signing
+    /// key's own address for EOA signatures, and the proxy or Safe address
+    /// otherwise.
+    pub fn new(
+        signer: PolymarketSigner,
+        funder: Option<Address>,
+        signature_type: SignatureType,
+        domain: Eip712Domain,
+    ) -> Result<Self> {
+        let maker = match (signature_type, funder) {
+            (SignatureType::Eoa, Some(funder)) if funder != signer.address() => {
+                return Err(anyhow::anyhow!(
+                    "EOA orders must be made by the signing key's address {}",
+                    format_address(&signer.address())
+                ));
+            }
+            (SignatureType::Eoa, _) => signer.address(),
+            (_, Some(funder)) => funder,
+            (_, None) => {
+                return Err(anyhow::anyhow!(
+                    "Proxy signature types require the funder wallet address"
+                ));
+            }
+        };
+
+        let neg_risk_domain = Eip712Domain::ctf_exchange(
+            domain.chain_id,
+            parse_address(NEG_RISK_CTF_EXCHANGE_ADDRESS)?,
+        );
+        Ok(Self {
+            signer,
+            maker,
+            signature_type,
+            domain,
+            neg_risk_domain,
+        })
+    }
+
+    /// Signs orders on neg-risk markets for `domain` rather than the
+    /// NegRisk CTF Exchange on Polygon.
+    pub fn with_neg_risk_domain(mut self, domain: Eip712Domain) -> Self {
+        self.neg_risk_domain = domain;
+        self
+    }
+
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    /// Address holding the funds and positions.
+    pub fn funder(&self) -> Address {
+        self.maker
+    }
+
+    pub fn signature_type(&self) -> SignatureType {
+        self.signature_type
+    }
+
+    /// Builds an open order (zero taker) and signs it.
+    pub fn build_order(&self, args: &OrderArgs) -> Result<SignedOrder> {
+        let (maker_amount, taker_amount) = order_amounts(args.side, args.price, args.size)?;
+
+        let order = ClobOrder {
+            salt: args.salt,
+            maker: self.maker,
+            signer: self.signer.address(),
+            taker: [0u8; 20],
+            token_id: args.token_id.clone(),
+            maker_amount,
+            taker_amount,
+            expiration: args.expiration,
+            nonce: 0,
+            fee_rate_bps: args.fee_rate_bps,
+            side: args.side,
+            signature_type: self.signature_type,
+        };
+
+        let domain = if args.neg_risk {
+            &self.neg_risk_domain
+        } else {
+            &self.domain
+        };
+        let signature = self.signer.sign_order(domain, &order)?;
+        let hash = typed_data_digest(&domain.separator(), &order.struct_hash()?);
+        Ok(SignedOrder::new(&order, signature, hash))
+    }
+}
+
+/// Trade-level inputs to `OrderSigner::build_order`.
+#[derive(Debug, Clone)]
+pub struct OrderArgs {
+    pub token_id: String,
+    pub side: OrderSide,
+    pub price: Decimal,
+    pub size: Decimal,
+    pub fee_rate_bps: u64,
+    /// Unix seconds; zero means good-til-cancelled.
+    pub expiration: u64,
+    pub salt: u64,
+    /// The market settles through the NegRisk CTF Exchange.
+    pub neg_risk: bool,
+}
+
+/// CLOB API key triple used for L2 (HMAC) authentication.
+#[derive(Clone, Default)]
+pub struct ApiCredentials {
+    pub api_key: String,
+    pub secret: String,
+    pub passphrase: String,
+}
+
+impl fmt::Debug for ApiCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApiCredentials")
+            .field("api_key", &self.api_key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ApiCredentials {
+    pub fn from_config(config: &PolymarketConfig) -> Self {
+        Self {
+            api_key: config.api_key.clone(),
+            secret: config.api_secret.clone(),
+            passphrase: config.api_passphrase.clone(),
+        }
+    }
+
+    /// URL-safe base64 HMAC-SHA256 of `timestamp + method + path + body`,
+    /// keyed with the URL-safe base64 decoded secret.
+    pub fn signature(&self, timestamp: i64, method: &str, path: &str, body: &str) -> Result<String> {
+        let secret = BASE64_URL
+            .decode(&self.secret)
+            .context("Invalid Polymarket API secret")?;
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(&secret)
+            .expect("HMAC can take key of any size");
+        mac.update(format!("{}{}{}{}", timestamp, method, path, body).as_bytes());
+
+        Ok(BASE64_URL.encode(mac.finalize().into_bytes()))
+    }
+
+    /// `POLY_*` headers for an authenticated request made by `address`.
+    pub fn headers(
+        &self,
+        address: &Address,
+        timestamp: i64,
+        method: &str,
+        path: &str,
+        body: &str,
+    ) -> Result<Vec<(&'static str, String)>> {
+        Ok(vec![
+            ("POLY_ADDRESS", format_address(address)),
+            ("POLY_SIGNATURE", self.signature(timestamp, method, path, body)?),
+            ("POLY_TIMESTAMP", timestamp.to_string()),
+            ("POLY_API_KEY", self.api_key.clone()),
+            ("POLY_PASSPHRASE", self.passphrase.clone()),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex32(hex: &str) -> [u8; 32] {
+        decode_hex(hex.strip_prefix("0x").unwrap()).unwrap().try_into().unwrap()
+    }
+
+    /// The "Ether Mail" example from the EIP-712 specification.
+    mod eip712_spec {
+        use super::*;
+
+        const PERSON_TYPE: &str = "Person(string name,address wallet)";
+        const MAIL_TYPE: &str =
+            "Mail(Person from,Person to,string contents)Person(string name,address wallet)";
+
+        fn person(name: &str, wallet: &str) -> [u8; 32] {
+            hash_struct(
+                PERSON_TYPE,
+                &[
+                    keccak256(name.as_bytes()),
+                    encode_address(&parse_address(wallet).unwrap()),
+                ],
+            )
+        }
+
+        fn domain() -> Eip712Domain {
+            Eip712Domain {
+                name: "Ether Mail".to_string(),
+                version: "1".to_string(),
+                chain_id: 1,
+                verifying_contract: parse_address("0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC")
+                    .unwrap(),
+            }
+        }
+
+        fn mail_hash() -> [u8; 32] {
+            hash_struct(
+                MAIL_TYPE,
+                &[
+                    person("Cow", "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"),
+                    person("Bob", "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"),
+                    keccak256(b"Hello, Bob!"),
+                ],
+            )
+        }
+
+        #[test]
+        fn test_domain_separator() {
+            assert_eq!(
+                domain().separator(),
+                hex32("0xf2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+            );
+        }
+
+        #[test]
+        fn test_digest() {
+            assert_eq!(
+                mail_hash(),
+                hex32("0xc52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e")
+            );
+            assert_eq!(
+                typed_data_digest(&domain().separator(), &mail_hash()),
+                hex32("0xbe609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2")
+            );
+        }
+
+        #[test]
+        fn test_signature() {
+            let signer = PolymarketSigner::from_hex(&encode_hex(&keccak256(b"cow"))).unwrap();
+            assert_eq!(
+                format_address(&signer.address()),
+                "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"
+            );
+
+            let digest = typed_data_digest(&domain().separator(), &mail_hash());
+            let signature = signer.sign_digest(&digest).unwrap();
+
+            assert_eq!(
+                signature[..32],
+                hex32("0x4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d")
+            );
+            assert_eq!(
+                signature[32..64],
+                hex32("0x07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b91562")
+            );
+            assert_eq!(signature[64], 28);
+        }
+    }
+
+    const TEST_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const TEST_ADDRESS: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+    const TOKEN_ID: &str =
+        "71321045679252212594626385532706912750332728571942532289631379312455583992563";
+
+    fn order_signer(signature_type: SignatureType, funder: Option<Address>) -> Result<OrderSigner> {
+        OrderSigner::new(
+            PolymarketSigner::from_hex(TEST_KEY).unwrap(),
+            funder,
+            signature_type,
+            Eip712Domain::ctf_exchange(
+                POLYGON_CHAIN_ID,
+                parse_address(CTF_EXCHANGE_ADDRESS).unwrap(),
+            ),
+        )
+    }
+
+    #[test]
+    fn test_order_type_hash() {
+        // ORDER_TYPEHASH in the CTF Exchange contract
+        assert_eq!(
+            keccak256(ORDER_TYPE.as_bytes()),
+            hex32("0xa852566c4e14d00869b6db0220888a9090a13eccdaea03713ff0a3d27bf9767c")
+        );
+    }
+
+    #[test]
+    fn test_signer_address() {
+        let signer = PolymarketSigner::from_hex(TEST_KEY).unwrap();
+        assert_eq!(format_address(&signer.address()), TEST_ADDRESS);
+    }
+
+    #[test]
+    fn test_decimal_uint_encoding() {
+        assert_eq!(encode_decimal_uint("0").unwrap(), [0u8; 32]);
+        assert_eq!(encode_decimal_uint("258").unwrap(), encode_uint(258));
+        assert_eq!(
+            encode_decimal_uint(&u128::MAX.to_string()).unwrap(),
+            encode_uint(u128::MAX)
+        );
+        assert!(encode_decimal_uint("12a").is_err());
+        // 2^256
+        assert!(encode_decimal_uint(
+            "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_order_amounts() {
+        let price = Decimal::new(45, 2);
+        let size = Decimal::new(10057, 2);
+
+        // 100.57 tokens for 45.2565 USDC
+        assert_eq!(
+            order_amounts(OrderSide::Buy, price, size).unwrap(),
+            (45_256_500, 100_570_000)
+        );
+        assert_eq!(
+            order_amounts(OrderSide::Sell, price, size).unwrap(),
+            (100_570_000, 45_256_500)
+        );
+        assert!(order_amounts(OrderSide::Buy, price, Decimal::new(1, 3)).is_err());
+    }
+
+    #[test]
+    fn test_signed_order_vector() {
+        let signer = order_signer(SignatureType::Eoa, None).unwrap();
+        let order = signer
+            .build_order(&OrderArgs {
+                token_id: TOKEN_ID.to_string(),
+                side: OrderSide::Buy,
+                price: Decimal::new(45, 2),
+                size: Decimal::from(100),
+                fee_rate_bps: 0,
+                expiration: 0,
+                salt: 479249096354,
+                neg_risk: false,
+            })
+            .unwrap();
+
+        assert_eq!(order.maker, TEST_ADDRESS);
+        assert_eq!(order.signer, TEST_ADDRESS);
+        assert_eq!(order.taker, "0x0000000000000000000000000000000000000000");
+        assert_eq!(order.maker_amount, "45000000");
+        assert_eq!(order.taker_amount, "100000000");
+        assert_eq!(order.side, "BUY");
+        assert_eq!(order.signature_type, 0);
+        assert_eq!(order.signature, "0x594f4222902c79ac315d0c086e3786dde4e39a463c168ee9d84e2cf0bea835112db3af0f885224ca96d97e05b968816d7fe9b85e4d19d6007fd0a6f077c345521b");
+    }
+
+    #[test]
+    fn test_neg_risk_order_vector() {
+        let ctf = Eip712Domain::ctf_exchange(
+            POLYGON_CHAIN_ID,
+            parse_address(CTF_EXCHANGE_ADDRESS).unwrap(),
+        );
+        let neg_risk = Eip712Domain::ctf_exchange(
+            POLYGON_CHAIN_ID,
+            parse_address(NEG_RISK_CTF_EXCHANGE_ADDRESS).unwrap(),
+        );
+        assert_eq!(
+            ctf.separator(),
+            hex32("0x1a573e3617c78403b5b4b892827992f027b03d4eaf570048b8ee8cdd84d151be")
+        );
+        assert_eq!(
+            neg_risk.separator(),
+            hex32("0x82cb6aa85babb812f4b521a12b10f0cbc68d2b44be7bc02c047004f544adb49f")
+        );
+
+        // The order of test_signed_order_vector
+        let maker = parse_address(TEST_ADDRESS).unwrap();
+        let order = ClobOrder {
+            salt: 479249096354,
+            maker,
+            signer: maker,
+            taker: parse_address("0x0000000000000000000000000000000000000000").unwrap(),
+            token_id: TOKEN_ID.to_string(),
+            maker_amount: 45_000_000,
+            taker_amount: 100_000_000,
+            expiration: 0,
+            nonce: 0,
+            fee_rate_bps: 0,
+            side: OrderSide::Buy,
+            signature_type: SignatureType::Eoa,
+        };
+        let struct_hash = order.struct_hash().unwrap();
+        assert_eq!(
+            typed_data_digest(&ctf.separator(), &struct_hash),
+            hex32("0x3d0b643c94dcc34d1396bc3a911c31560e63aadefb1175b0ced9b51881503618")
+        );
+        let digest = typed_data_digest(&neg_risk.separator(), &struct_hash);
+        assert_eq!(
+            digest,
+            hex32("0x44db28d9443db6225811c2e289294de43f2b80ad951d850758e3ab6275f0632f")
+        );
+
+        let signer = order_signer(SignatureType::Eoa, None).unwrap();
+        let signed = signer
+            .build_order(&OrderArgs {
+                token_id: TOKEN_ID.to_string(),
+                side: OrderSide::Buy,
+                price: Decimal::new(45, 2),
+                size: Decimal::from(100),
+                fee_rate_bps: 0,
+                expiration: 0,
+                salt: 479249096354,
+                neg_risk: true,
+            })
+            .unwrap();
+        let expected = PolymarketSigner::from_hex(TEST_KEY)
+            .unwrap()
+            .sign_digest(&digest)
+            .unwrap();
+        assert_eq!(signed.signature, format!("0x{}", encode_hex(&expected)));
+    }
+
+    #[test]
+    fn test_proxy_maker() {
+        let funder = parse_address("0x1111111111111111111111111111111111111111").unwrap();
+
+        let signer = order_signer(SignatureType::PolyProxy, Some(funder)).unwrap();
+        let order = signer
+            .build_order(&OrderArgs {
+                token_id: TOKEN_ID.to_string(),
+                side: OrderSide::Buy,
+                price: Decimal::new(5, 1),
+                size: Decimal::from(10),
+                fee_rate_bps: 0,
+                expiration: 0,
+                salt: 1,
+                neg_risk: false,
+            })
+            .unwrap();
+        assert_eq!(order.maker, format_address(&funder));
+        assert_eq!(order.signer, TEST_ADDRESS);
+        assert_eq!(order.signature_type, 1);
+
+        assert!(order_signer(SignatureType::PolyProxy, None).is_err());
+        assert!(order_signer(SignatureType::Eoa, Some(funder)).is_err());
+    }
+
+    #[test]
+    fn test_l2_signature_vector() {
+        let credentials = ApiCredentials {
+            api_key: "key".to_string(),
+            secret: "c2VjcmV0LWtleS1mb3ItdGVzdHM=".to_string(),
+            passphrase: "pass".to_string(),
+        };
+
+        assert_eq!(
+            credentials
+                .signature(1700000000, "POST", "/order", r#"{"owner":"key"}"#)
+                .unwrap(),
+            "Qwprus5rQnkiCf4-Dw4bQ9WfGsbOK2jBZxlcA6xqaCY="
+        );
+    }
+}
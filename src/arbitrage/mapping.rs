@@ -0,0 +1,188 @@
+use anyhow::Result;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use crate::models::Market;
+
+/// A Polymarket market and the Kalshi market known to resolve with it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MappedPair {
+    /// Condition ID, or either outcome's CLOB token ID.
+    pub polymarket: String,
+    /// Market ticker.
+    pub kalshi: String,
+    /// YES on one venue is NO on the other.
+    #[serde(default)]
+    pub inverted: bool,
+}
+
+/// A Polymarket and a Kalshi market that must never be paired.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DeniedPair {
+    pub polymarket: String,
+    pub kalshi: String,
+}
+
+/// Curated pairs, which take precedence over fuzzy matching.
+///
+/// A market named in `pair` is only ever paired as listed there, and a
+/// pair in `deny` is never paired at all.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PairMapping {
+    #[serde(default, rename = "pair")]
+    pub pairs: Vec<MappedPair>,
+    #[serde(default, rename = "deny")]
+    pub denied: Vec<DeniedPair>,
+}
+
+impl PairMapping {
+    /// Reads `path` as JSON when it ends in `.json` and as TOML otherwise.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)?;
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            Ok(serde_json::from_str(&content)?)
+        } else {
+            Ok(toml::from_str(&content)?)
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty() && self.denied.is_empty()
+    }
+
+    /// Whether `market` appears in a curated pair.
+    pub fn is_mapped(&self, market: &Market) -> bool {
+        self.pairs
+            .iter()
+            .any(|pair| identifies(&pair.polymarket, market) || identifies(&pair.kalshi, market))
+    }
+
+    pub fn is_denied(&self, poly_market: &Market, kalshi_market: &Market) -> bool {
+        self.denied.iter().any(|pair| {
+            identifies(&pair.polymarket, poly_market) && identifies(&pair.kalshi, kalshi_market)
+        })
+    }
+
+    /// Every curated pair whose markets are both listed, with whether it is
+    /// inverted.
+    pub fn resolve<'a>(
+        &self,
+        poly_markets: &'a [Market],
+        kalshi_markets: &'a [Market],
+    ) -> Vec<(&'a Market, &'a Market, bool)> {
+        let mut polymarket: HashMap<&str, &Market> = HashMap::new();
+        for market in poly_markets {
+            polymarket.insert(&market.id, market);
+            for id in [&market.condition_id, &market.yes_token_id, &market.no_token_id]
+                .into_iter()
+                .flatten()
+            {
+                polymarket.insert(id, market);
+            }
+        }
+        let kalshi: HashMap<&str, &Market> = kalshi_markets
+            .iter()
+            .map(|market| (market.id.as_str(), market))
+            .collect();
+
+        let mut seen = HashSet::new();
+        self.pairs
+            .iter()
+            .filter_map(|pair| {
+                let poly_market = *polymarket.get(pair.polymarket.as_str())?;
+                let kalshi_market = *kalshi.get(pair.kalshi.as_str())?;
+                seen.insert((&poly_market.id, &kalshi_market.id))
+                    .then_some((poly_market, kalshi_market, pair.inverted))
+            })
+            .collect()
+    }
+}
+
+/// Whether a mapping file's `id` names `market`.
+fn identifies(id: &str, market: &Market) -> bool {
+    market.id == id
+        || market.condition_id.as_deref() == Some(id)
+        || market.yes_token_id.as_deref() == Some(id)
+        || market.no_token_id.as_deref() == Some(id)
+}
+
+/// The mapping last read and the file version it was read from.
+struct Loaded {
+    version: Option<(SystemTime, u64)>,
+    mapping: Arc<PairMapping>,
+}
+
+/// A `PairMapping` file, read again whenever it changes.
+///
+/// A missing file is an empty mapping. A file that fails to parse is
+/// logged and the previous mapping kept.
+pub struct MappingFile {
+    path: PathBuf,
+    loaded: Mutex<Loaded>,
+}
+
+impl MappingFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let file = Self {
+            path: path.into(),
+            loaded: Mutex::new(Loaded {
+                version: None,
+                mapping: Arc::new(PairMapping::default()),
+            }),
+        };
+        file.current();
+        file
+    }
+
+    /// The mapping as of the file's latest version.
+    pub fn current(&self) -> Arc<PairMapping> {
+        let version = fs::metadata(&self.path)
+            .ok()
+            .and_then(|metadata| Some((metadata.modified().ok()?, metadata.len())));
+
+        let mut loaded = self.loaded.lock().expect("pair mapping lock poisoned");
+        if version == loaded.version {
+            return loaded.mapping.clone();
+        }
+        loaded.version = version;
+
+        if version.is_none() {
+            if !loaded.mapping.is_empty() {
+                warn!(
+                    "{} removed; pairing by fuzzy matching only",
+                    self.path.display()
+                );
+            }
+            loaded.mapping = Arc::new(PairMapping::default());
+            return loaded.mapping.clone();
+        }
+
+        match PairMapping::load(&self.path) {
+            Ok(mapping) => {
+                info!(
+                    "Loaded {} curated pairs and {} denied pairs from {}",
+                    mapping.pairs.len(),
+                    mapping.denied.len(),
+                    self.path.display()
+                );
+                loaded.mapping = Arc::new(mapping);
+            }
+            Err(e) => warn!(
+                "Failed to read {}; keeping the previous pairs: {:#}",
+                self.path.display(),
+                e
+            ),
+        }
+        loaded.mapping.clone()
+    }
+}
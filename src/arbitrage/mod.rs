@@ -1,57 +1,244 @@
 use anyhow::Result;
-use log::{info, warn};
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
 use rust_decimal::Decimal;
-use std::collections::HashMap;
-use tokio::time::{interval, Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::{
+    sync::mpsc,
+    task::JoinHandle,
+    time::{interval, Duration},
+};
+
+pub mod mapping;
+
+pub use mapping::{MappingFile, PairMapping};
 
 use crate::{
-    api::{KalshiClient, PolymarketClient},
+    api::{
+        polymarket_signing::{ApiCredentials, OrderSigner},
+        ApiError, ApiResult, Exchange, KalshiClient, KalshiMarketQuery, KalshiSigner,
+        PolymarketClient, PolymarketMarketQuery,
+    },
+    backtest::RecordingExchange,
     config::Config,
     database::Database,
-    models::{ArbitrageOpportunity, Market, Platform},
+    execution::{ExecutionCoordinator, OrderTracker},
+    fees::{FeeModel, KalshiFeeModel, Liquidity, PolymarketFeeModel},
+    matching::{match_events, MarketMatcher, MatchIndex},
+    models::{
+        book_depth, sweep_cost, ArbitrageOpportunity, Event, Market, MarketMatch, MatchStatus,
+        OrderBook, Platform, PriceLevel, TripReason,
+    },
+    paper::PaperExchange,
+    risk::{CircuitBreaker, RiskCode, RiskDecision, RiskManager},
+    streaming::{BookEvent, BookStore, KalshiStream, PolymarketStream},
 };
 
+/// Amount a winning contract pays out on either venue.
+const CONTRACT_PAYOUT: Decimal = Decimal::ONE;
+
+#[derive(Debug, Clone)]
+struct ProfitBreakdown {
+    position_size: Decimal,
+    yes_price: Decimal,
+    no_price: Decimal,
+    yes_limit_price: Decimal,
+    no_limit_price: Decimal,
+    gross_edge: Decimal,
+    total_fees: Decimal,
+    net_edge: Decimal,
+    profit_percentage: Decimal,
+}
+
+impl ProfitBreakdown {
+    fn net_profit(&self) -> Decimal {
+        self.net_edge * self.position_size
+    }
+}
+
+/// A venue's market listing and when it was fetched.
+#[derive(Debug, Clone)]
+pub struct MarketSnapshot {
+    pub markets: Vec<Market>,
+    /// The same markets grouped into the venue's events.
+    pub events: Vec<Event>,
+    pub fetched_at: DateTime<Utc>,
+    /// The latest fetch failed and this is the last good listing.
+    pub stale: bool,
+}
+
+impl MarketSnapshot {
+    pub fn age(&self) -> chrono::Duration {
+        Utc::now() - self.fetched_at
+    }
+}
+
+/// Two markets the engine has paired.
+#[derive(Debug, Clone)]
+struct MarketPair {
+    polymarket: Market,
+    kalshi: Market,
+    /// YES on Polymarket is NO on Kalshi.
+    inverted: bool,
+    /// Curated in the mapping file or approved in review. Only approved
+    /// pairs are traded.
+    approved: bool,
+}
+
 pub struct ArbitrageEngine {
-    polymarket: PolymarketClient,
-    kalshi: KalshiClient,
+    polymarket: Arc<dyn Exchange>,
+    kalshi: Arc<dyn Exchange>,
+    polymarket_fees: Box<dyn FeeModel>,
+    kalshi_fees: Box<dyn FeeModel>,
     database: Database,
+    tracker: OrderTracker,
+    coordinator: ExecutionCoordinator,
+    risk: RiskManager,
+    breaker: CircuitBreaker,
+    mapping: MappingFile,
+    matcher: MarketMatcher,
+    match_index: Mutex<MatchIndex>,
     config: Config,
-    execution_enabled: bool,
-    running: bool,
+    /// Cleared when the venue reports an account problem that every
+    /// further order would hit too.
+    execution_enabled: AtomicBool,
+    running: AtomicBool,
+    market_cache: Mutex<HashMap<Platform, MarketSnapshot>>,
 }
 
 impl ArbitrageEngine {
     pub async fn new(config: Config, database: Database, execution_enabled: bool) -> Result<Self> {
-        let polymarket = PolymarketClient::new(
-            config.polymarket.api_key.clone(),
-            config.polymarket.base_url.clone(),
-        );
+        let polymarket = recorded(&config, &database, Arc::new(polymarket_client(&config)?));
+        let kalshi = recorded(&config, &database, Arc::new(kalshi_client(&config)?));
+
+        Ok(Self::with_exchanges(
+            config,
+            database,
+            execution_enabled,
+            polymarket,
+            kalshi,
+        ))
+    }
+
+    /// Builds an engine that trades against simulated venues: market data
+    /// comes from the configured clients while orders fill against their
+    /// books in the database's paper account.
+    pub async fn paper(config: Config, database: Database) -> Result<Self> {
+        let polymarket = Arc::new(PaperExchange::new(
+            recorded(&config, &database, Arc::new(polymarket_client(&config)?)),
+            Box::new(PolymarketFeeModel::new(&config.fees.polymarket)),
+            database.clone(),
+            &config.paper,
+        ));
+        let kalshi = Arc::new(PaperExchange::new(
+            recorded(&config, &database, Arc::new(kalshi_client(&config)?)),
+            Box::new(KalshiFeeModel::new(&config.fees.kalshi)),
+            database.clone(),
+            &config.paper,
+        ));
 
-        let kalshi = KalshiClient::new(
-            config.kalshi.api_key.clone(),
-            config.kalshi.api_secret.clone(),
-            config.kalshi.base_url.clone(),
+        Ok(Self::with_exchanges(config, database, true, polymarket, kalshi))
+    }
+
+    /// Builds an engine around the given venue clients instead of the ones
+    /// described by `config`.
+    pub fn with_exchanges(
+        config: Config,
+        database: Database,
+        execution_enabled: bool,
+        polymarket: Arc<dyn Exchange>,
+        kalshi: Arc<dyn Exchange>,
+    ) -> Self {
+        let polymarket_fees = Box::new(PolymarketFeeModel::new(&config.fees.polymarket));
+        let kalshi_fees = Box::new(KalshiFeeModel::new(&config.fees.kalshi));
+        let tracker = OrderTracker::new(
+            polymarket.clone(),
+            kalshi.clone(),
+            database.clone(),
+            &config.bot,
         );
+        let breaker = CircuitBreaker::new(tracker.clone(), database.clone(), &config.breaker);
+        let coordinator = ExecutionCoordinator::new(
+            polymarket.clone(),
+            kalshi.clone(),
+            tracker.clone(),
+            database.clone(),
+            &config.execution,
+        )
+        .with_breaker(breaker.clone());
+        let risk = RiskManager::new(
+            polymarket.clone(),
+            kalshi.clone(),
+            tracker.clone(),
+            database.clone(),
+            &config.risk,
+        )
+        .with_fees(&config.fees);
 
-        Ok(Self {
+        Self {
             polymarket,
             kalshi,
+            polymarket_fees,
+            kalshi_fees,
             database,
+            tracker,
+            coordinator,
+            risk,
+            breaker,
+            mapping: MappingFile::new(&config.matching.mapping_file),
+            matcher: MarketMatcher::new(&config.matching),
+            match_index: Mutex::new(MatchIndex::new()),
             config,
-            execution_enabled,
-            running: false,
-        })
+            execution_enabled: AtomicBool::new(execution_enabled),
+            running: AtomicBool::new(false),
+            market_cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The task following every order this engine sends.
+    pub fn tracker(&self) -> &OrderTracker {
+        &self.tracker
+    }
+
+    /// Halts trading when something goes wrong, or on operator request.
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.breaker
     }
 
     pub async fn run(&mut self) -> Result<()> {
-        self.running = true;
+        // Picks up a halt left over from a previous run before trading
+        if let Err(e) = self.breaker.check().await {
+            warn!("Failed to check circuit breaker: {:#}", e);
+        }
+        let tracker = tokio::spawn(self.tracker.clone().run());
+        let breaker = tokio::spawn(self.breaker.clone().run());
+
+        let result = if self.config.bot.streaming {
+            self.run_streaming().await
+        } else {
+            self.run_polling().await
+        };
+
+        tracker.abort();
+        breaker.abort();
+        result
+    }
+
+    async fn run_polling(&self) -> Result<()> {
+        self.running.store(true, Ordering::SeqCst);
         let mut check_interval = interval(Duration::from_secs(
             self.config.bot.check_interval_seconds,
         ));
 
         info!("Arbitrage engine started");
 
-        while self.running {
+        while self.running.load(Ordering::SeqCst) {
             check_interval.tick().await;
 
             if let Err(e) = self.check_opportunities().await {
@@ -62,231 +249,964 @@ impl ArbitrageEngine {
         Ok(())
     }
 
-    async fn check_opportunities(&self) -> Result<()> {
-        // Fetch markets from both platforms
-        let polymarket_markets = self.polymarket.get_markets().await?;
-        let kalshi_markets = self.kalshi.get_markets().await?;
+    /// One polling cycle: lists both venues, matches their markets and
+    /// acts on every opportunity their order books offer.
+    pub async fn check_opportunities(&self) -> Result<()> {
+        // Every pair needs both venues, so one venue being unavailable
+        // skips the cycle's evaluation instead of failing the loop
+        let (Some(polymarket), Some(kalshi)) = self.fetch_markets().await else {
+            return Ok(());
+        };
 
         info!(
-            "Fetched {} Polymarket markets and {} Kalshi markets",
-            polymarket_markets.len(),
-            kalshi_markets.len()
+            "Fetched {} Polymarket markets{} and {} Kalshi markets{}",
+            polymarket.markets.len(),
+            if polymarket.stale { " (stale)" } else { "" },
+            kalshi.markets.len(),
+            if kalshi.stale { " (stale)" } else { "" },
         );
 
         // Find matching markets
-        let matched_markets = self.match_markets(&polymarket_markets, &kalshi_markets);
+        let pairs = self.pair_markets(&polymarket, &kalshi).await;
 
         // Identify arbitrage opportunities
-        for (poly_market, kalshi_market) in matched_markets {
-            if let Some(opportunity) = self.calculate_arbitrage(&poly_market, &kalshi_market) {
+        for pair in &pairs {
+            let (poly_market, kalshi_market) = (&pair.polymarket, &pair.kalshi);
+            let complement = if pair.inverted {
+                Self::has_complement(poly_market, &kalshi_market.inverted())
+            } else {
+                Self::has_complement(poly_market, kalshi_market)
+            };
+            if !complement {
+                continue;
+            }
+
+            let (poly_book, kalshi_book) = tokio::join!(
+                self.polymarket.get_order_book(poly_market),
+                self.kalshi.get_order_book(kalshi_market),
+            );
+            self.record_venue_call(Platform::Polymarket, &poly_book).await;
+            self.record_venue_call(Platform::Kalshi, &kalshi_book).await;
+            let (poly_book, kalshi_book) = match (poly_book, kalshi_book) {
+                (Ok(poly_book), Ok(kalshi_book)) => (poly_book, kalshi_book),
+                (Err(e), _) | (_, Err(e)) => {
+                    warn!(
+                        "Failed to fetch order books for {} / {}: {}",
+                        poly_market.id, kalshi_market.id, e
+                    );
+                    continue;
+                }
+            };
+
+            if let Some(opportunity) = self.pair_arbitrage(pair, &poly_book, &kalshi_book) {
+                if let Err(e) = self.handle_opportunity(&opportunity, pair).await {
+                    warn!(
+                        "Error handling opportunity for {} / {}: {}",
+                        poly_market.id, kalshi_market.id, e
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn handle_opportunity(
+        &self,
+        opportunity: &ArbitrageOpportunity,
+        pair: &MarketPair,
+    ) -> Result<()> {
+        info!(
+            "Found opportunity: {}% profit - {} vs {}",
+            opportunity.profit_percentage, pair.polymarket.question, pair.kalshi.question
+        );
+
+        // Save to database
+        let opportunity_id = self.database.save_opportunity(opportunity).await?;
+
+        // Execute if enabled and not halted
+        if let Some(trip) = self.breaker.active_trip() {
+            info!(
+                "Trading halted [{}], not executing: {}",
+                trip.reason.as_str(),
+                trip.detail
+            );
+        } else if self.execution_enabled.load(Ordering::SeqCst) {
+            if !pair.approved {
                 info!(
-                    "Found opportunity: {}% profit - {} vs {}",
-                    opportunity.profit_percentage,
-                    poly_market.question,
-                    kalshi_market.question
+                    "Pair {} / {} awaiting review, not executing",
+                    pair.polymarket.id, pair.kalshi.id
                 );
+            } else if let Err(e) = self.execute_opportunity(opportunity, opportunity_id).await {
+                self.handle_execution_error(&e);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_execution_error(&self, error: &anyhow::Error) {
+        match error.downcast_ref::<ApiError>() {
+            Some(ApiError::Unauthorized | ApiError::InsufficientFunds) => {
+                error!("Disabling execution: {}", error);
+                self.execution_enabled.store(false, Ordering::SeqCst);
+            }
+            Some(ApiError::RateLimited { retry_after }) => {
+                warn!("Rate limited placing orders, retry after {:?}", retry_after);
+            }
+            Some(ApiError::MarketClosed | ApiError::InvalidOrder { .. }) => {
+                warn!("Order rejected, skipping opportunity: {}", error);
+            }
+            _ => error!("Error executing opportunity: {}", error),
+        }
+    }
+
+    /// Event-driven counterpart of `run`: markets are listed and matched
+    /// every `market_refresh_seconds`, both venues' books are kept current
+    /// over websockets, and each book update re-evaluates only the pairs
+    /// that contain that market.
+    async fn run_streaming(&self) -> Result<()> {
+        self.running.store(true, Ordering::SeqCst);
+        let mut refresh = interval(Duration::from_secs(self.config.bot.market_refresh_seconds));
+
+        let (events_tx, mut events_rx) = mpsc::channel(1024);
+        let mut store = BookStore::default();
+        let mut streams: Vec<JoinHandle<()>> = Vec::new();
+        let mut subscriptions = (Vec::new(), Vec::new());
+
+        let mut pairs: Vec<MarketPair> = Vec::new();
+        let mut pair_index: HashMap<(Platform, String), Vec<usize>> = HashMap::new();
+        // Last opportunity reported per pair, so an unchanged book does not
+        // record (or trade) the same opportunity on every update
+        let mut reported: HashMap<usize, (Platform, Decimal, Decimal)> = HashMap::new();
+
+        info!("Arbitrage engine started in streaming mode");
+
+        while self.running.load(Ordering::SeqCst) {
+            tokio::select! {
+                _ = refresh.tick() => {
+                    let new_pairs = match self.refresh_pairs().await {
+                        Ok(new_pairs) => new_pairs,
+                        Err(e) => {
+                            warn!("Error refreshing markets: {}", e);
+                            continue;
+                        }
+                    };
+
+                    let new_subscriptions = Self::subscriptions(&new_pairs);
+                    if new_subscriptions != subscriptions {
+                        for stream in streams.drain(..) {
+                            stream.abort();
+                        }
+                        store = BookStore::default();
+                        streams = self.spawn_streams(&new_subscriptions, &store, &events_tx);
+                        subscriptions = new_subscriptions;
+                    }
+
+                    pair_index = Self::index_pairs(&new_pairs);
+                    pairs = new_pairs;
+                    reported.clear();
+                }
+                Some(event) = events_rx.recv() => {
+                    // Coalesce whatever else has queued up behind this event
+                    let mut dirty = HashSet::new();
+                    let mut next = Some(event);
+                    while let Some(event) = next {
+                        if let Some(indices) = pair_index.get(&(event.platform, event.market_id)) {
+                            dirty.extend(indices.iter().copied());
+                        }
+                        next = events_rx.try_recv().ok();
+                    }
+
+                    for index in dirty {
+                        let pair = &pairs[index];
+                        let (Some(poly_book), Some(kalshi_book)) = (
+                            store.get(&Platform::Polymarket, &pair.polymarket.id),
+                            store.get(&Platform::Kalshi, &pair.kalshi.id),
+                        ) else {
+                            continue;
+                        };
+
+                        let Some(opportunity) = self.pair_arbitrage(pair, &poly_book, &kalshi_book)
+                        else {
+                            reported.remove(&index);
+                            continue;
+                        };
 
-                // Save to database
-                self.database.save_opportunity(&opportunity).await?;
+                        let key = (
+                            opportunity.yes_platform.clone(),
+                            opportunity.position_size,
+                            opportunity.net_edge,
+                        );
+                        if reported.get(&index) == Some(&key) {
+                            continue;
+                        }
+                        reported.insert(index, key);
 
-                // Execute if enabled
-                if self.execution_enabled {
-                    self.execute_opportunity(&opportunity).await?;
+                        if let Err(e) = self.handle_opportunity(&opportunity, pair).await {
+                            warn!("Error handling opportunity: {}", e);
+                        }
+                    }
                 }
             }
         }
 
+        for stream in streams {
+            stream.abort();
+        }
+
         Ok(())
     }
 
-    fn match_markets<'a>(
+    /// Lists both venues' markets, grouped into events, concurrently. A
+    /// venue whose fetch fails falls back to its last good listing, marked
+    /// stale, until that is older than `max_market_staleness_seconds`;
+    /// after that it is `None`.
+    pub async fn fetch_markets(&self) -> (Option<MarketSnapshot>, Option<MarketSnapshot>) {
+        let (polymarket, kalshi) =
+            tokio::join!(self.polymarket.get_events(), self.kalshi.get_events());
+        self.record_venue_call(Platform::Polymarket, &polymarket).await;
+        self.record_venue_call(Platform::Kalshi, &kalshi).await;
+
+        (
+            self.update_market_cache(Platform::Polymarket, polymarket),
+            self.update_market_cache(Platform::Kalshi, kalshi),
+        )
+    }
+
+    /// Feeds a venue call's outcome to the circuit breaker. A closed market
+    /// or a refused request says nothing about the venue's health.
+    async fn record_venue_call<T>(&self, platform: Platform, result: &ApiResult<T>) {
+        let ok = !matches!(
+            result,
+            Err(e) if !matches!(e, ApiError::MarketClosed | ApiError::InvalidOrder { .. })
+        );
+        if let Err(e) = self.breaker.record_venue_call(platform, ok).await {
+            warn!("Failed to record circuit breaker trip: {:#}", e);
+        }
+    }
+
+    fn update_market_cache(
         &self,
-        poly_markets: &'a [Market],
-        kalshi_markets: &'a [Market],
-    ) -> Vec<(&'a Market, &'a Market)> {
-        let mut matches = Vec::new();
+        platform: Platform,
+        fetched: ApiResult<Vec<Event>>,
+    ) -> Option<MarketSnapshot> {
+        let mut cache = self.market_cache.lock().expect("market cache lock poisoned");
+
+        let error = match fetched {
+            Ok(events) => {
+                let snapshot = MarketSnapshot {
+                    markets: events
+                        .iter()
+                        .flat_map(|event| event.markets.iter().cloned())
+                        .collect(),
+                    events,
+                    fetched_at: Utc::now(),
+                    stale: false,
+                };
+                cache.insert(platform, snapshot.clone());
+                return Some(snapshot);
+            }
+            Err(e) => e,
+        };
+
+        let Some(snapshot) = cache.get(&platform) else {
+            warn!("Failed to fetch {:?} markets and none cached: {}", platform, error);
+            return None;
+        };
+
+        let age = snapshot.age();
+        let max_staleness = chrono::Duration::seconds(
+            self.config.bot.max_market_staleness_seconds as i64,
+        );
+        if age > max_staleness {
+            warn!(
+                "Failed to fetch {:?} markets and the last listing is {}s old; skipping its pairs: {}",
+                platform,
+                age.num_seconds(),
+                error
+            );
+            return None;
+        }
+
+        warn!(
+            "Failed to fetch {:?} markets; using listing from {}s ago: {}",
+            platform,
+            age.num_seconds(),
+            error
+        );
+
+        Some(MarketSnapshot {
+            stale: true,
+            ..snapshot.clone()
+        })
+    }
+
+    async fn refresh_pairs(&self) -> Result<Vec<MarketPair>> {
+        let (Some(polymarket), Some(kalshi)) = self.fetch_markets().await else {
+            anyhow::bail!("Market listings unavailable");
+        };
+        let pairs = self.pair_markets(&polymarket, &kalshi).await;
+
+        info!(
+            "Streaming {} matched pairs from {} Polymarket and {} Kalshi markets",
+            pairs.len(),
+            polymarket.markets.len(),
+            kalshi.markets.len()
+        );
+
+        Ok(pairs)
+    }
+
+    /// Kalshi tickers and Polymarket (YES token, market id) pairs to
+    /// subscribe to, sorted so that an unchanged set compares equal.
+    fn subscriptions(pairs: &[MarketPair]) -> (Vec<String>, Vec<(String, String)>) {
+        let mut tickers: Vec<String> = pairs.iter().map(|pair| pair.kalshi.id.clone()).collect();
+        let mut assets: Vec<(String, String)> = pairs
+            .iter()
+            .filter_map(|pair| {
+                pair.polymarket
+                    .yes_token_id
+                    .clone()
+                    .map(|token_id| (token_id, pair.polymarket.id.clone()))
+            })
+            .collect();
+
+        tickers.sort();
+        tickers.dedup();
+        assets.sort();
+        assets.dedup();
+
+        (tickers, assets)
+    }
+
+    fn spawn_streams(
+        &self,
+        (tickers, assets): &(Vec<String>, Vec<(String, String)>),
+        store: &BookStore,
+        events: &mpsc::Sender<BookEvent>,
+    ) -> Vec<JoinHandle<()>> {
+        let mut streams = Vec::new();
 
-        for poly_market in poly_markets {
-            for kalshi_market in kalshi_markets {
-                if self.markets_match(poly_market, kalshi_market) {
-                    matches.push((poly_market, kalshi_market));
+        if !tickers.is_empty() {
+            // The websocket handshake is signed with the account's key
+            match kalshi_client(&self.config) {
+                Ok(client) => {
+                    let stream = KalshiStream::new(
+                        client,
+                        self.config.kalshi.ws_url.clone(),
+                        tickers.clone(),
+                        store.clone(),
+                        events.clone(),
+                    );
+                    streams.push(tokio::spawn(stream.run()));
                 }
+                Err(e) => error!("Cannot stream Kalshi order books: {}", e),
             }
         }
 
-        matches
+        if !assets.is_empty() {
+            let stream = PolymarketStream::new(
+                self.config.polymarket.ws_url.clone(),
+                assets.iter().cloned().collect(),
+                store.clone(),
+                events.clone(),
+            );
+            streams.push(tokio::spawn(stream.run()));
+        }
+
+        streams
     }
 
-    fn markets_match(&self, market1: &Market, market2: &Market) -> bool {
-        // Simple matching based on question similarity
-        // In production, use more sophisticated matching
-        let q1 = market1.question.to_lowercase();
-        let q2 = market2.question.to_lowercase();
+    fn index_pairs(pairs: &[MarketPair]) -> HashMap<(Platform, String), Vec<usize>> {
+        let mut index: HashMap<(Platform, String), Vec<usize>> = HashMap::new();
 
-        let similarity = self.calculate_similarity(&q1, &q2);
-        similarity > 0.7
+        for (i, pair) in pairs.iter().enumerate() {
+            index
+                .entry((Platform::Polymarket, pair.polymarket.id.clone()))
+                .or_default()
+                .push(i);
+            index
+                .entry((Platform::Kalshi, pair.kalshi.id.clone()))
+                .or_default()
+                .push(i);
+        }
+
+        index
     }
 
-    fn calculate_similarity(&self, s1: &str, s2: &str) -> f64 {
-        // Simple word overlap metric
-        let words1: std::collections::HashSet<_> = s1.split_whitespace().collect();
-        let words2: std::collections::HashSet<_> = s2.split_whitespace().collect();
+    /// Pairs the venues' markets. The mapping file's curated pairs come
+    /// first and are approved. Fuzzy matches, outcome by outcome for
+    /// matched events and market by market for the rest, are recorded for
+    /// review in `market_matches`; rejected ones are dropped and the rest
+    /// carry their decision.
+    async fn pair_markets(
+        &self,
+        polymarket: &MarketSnapshot,
+        kalshi: &MarketSnapshot,
+    ) -> Vec<MarketPair> {
+        let (poly_markets, kalshi_markets) = (&polymarket.markets, &kalshi.markets);
+        let mapping = self.mapping.current();
+        let mut pairs: Vec<MarketPair> = mapping
+            .resolve(poly_markets, kalshi_markets)
+            .into_iter()
+            .map(|(poly_market, kalshi_market, inverted)| MarketPair {
+                polymarket: poly_market.clone(),
+                kalshi: kalshi_market.clone(),
+                inverted,
+                approved: true,
+            })
+            .collect();
 
-        let intersection = words1.intersection(&words2).count();
-        let union = words1.union(&words2).count();
+        for (poly_market, kalshi_market, candidate) in
+            self.match_markets(polymarket, kalshi, &mapping)
+        {
+            let reviewed = match self.database.record_match_candidate(&candidate).await {
+                Ok(reviewed) => reviewed,
+                Err(e) => {
+                    warn!(
+                        "Failed to record match {} / {}: {:#}",
+                        poly_market.id, kalshi_market.id, e
+                    );
+                    candidate
+                }
+            };
 
-        if union == 0 {
-            0.0
-        } else {
-            intersection as f64 / union as f64
+            if reviewed.status != MatchStatus::Rejected {
+                pairs.push(MarketPair {
+                    polymarket: poly_market.clone(),
+                    kalshi: kalshi_market.clone(),
+                    inverted: reviewed.inverted,
+                    approved: reviewed.status == MatchStatus::Approved,
+                });
+            }
         }
+
+        pairs
     }
 
-    fn calculate_arbitrage(
+    /// Fuzzy-matched pairs as pending candidates, leaving out markets the
+    /// mapping file pairs and the pairs it denies.
+    ///
+    /// Multi-outcome events are matched first and their outcomes paired
+    /// by name, since the outcome markets of one event often share a
+    /// title. Their markets are then left out of matching market by
+    /// market.
+    fn match_markets<'a>(
+        &self,
+        polymarket: &'a MarketSnapshot,
+        kalshi: &'a MarketSnapshot,
+        mapping: &PairMapping,
+    ) -> Vec<(&'a Market, &'a Market, MarketMatch)> {
+        let poly_unmapped: Vec<&Market> = polymarket
+            .markets
+            .iter()
+            .filter(|market| !mapping.is_mapped(market))
+            .collect();
+        let kalshi_unmapped: Vec<&Market> = kalshi
+            .markets
+            .iter()
+            .filter(|market| !mapping.is_mapped(market))
+            .collect();
+
+        let now = Utc::now();
+        let mut matches = Vec::new();
+        let mut in_events: HashSet<(Platform, &str)> = HashSet::new();
+
+        // The venues' events without the markets the mapping file pairs
+        let multi_outcome = |events: &[Event]| -> Vec<Event> {
+            events
+                .iter()
+                .filter(|event| event.is_multi_outcome())
+                .map(|event| Event {
+                    id: event.id.clone(),
+                    platform: event.platform.clone(),
+                    title: event.title.clone(),
+                    mutually_exclusive: event.mutually_exclusive,
+                    markets: event
+                        .markets
+                        .iter()
+                        .filter(|market| !mapping.is_mapped(market))
+                        .cloned()
+                        .collect(),
+                })
+                .filter(Event::is_multi_outcome)
+                .collect()
+        };
+        let (poly_events, kalshi_events) = (
+            multi_outcome(&polymarket.events),
+            multi_outcome(&kalshi.events),
+        );
+        let poly_by_id: HashMap<&str, &Market> = poly_unmapped
+            .iter()
+            .map(|market| (market.id.as_str(), *market))
+            .collect();
+        let kalshi_by_id: HashMap<&str, &Market> = kalshi_unmapped
+            .iter()
+            .map(|market| (market.id.as_str(), *market))
+            .collect();
+        for event_match in match_events(&self.matcher, &poly_events, &kalshi_events) {
+            for event in [event_match.polymarket, event_match.kalshi] {
+                in_events.insert((event.platform.clone(), event.id.as_str()));
+            }
+            for outcome in &event_match.outcomes {
+                // Back to the listed markets, which outlive the events
+                let (Some(&poly_market), Some(&kalshi_market)) = (
+                    poly_by_id.get(outcome.polymarket.id.as_str()),
+                    kalshi_by_id.get(outcome.kalshi.id.as_str()),
+                ) else {
+                    continue;
+                };
+                let explanation = format!(
+                    "outcome {} of \"{}\" / \"{}\": {}",
+                    outcome.name,
+                    event_match.polymarket.title,
+                    event_match.kalshi.title,
+                    event_match
+                );
+                matches.push((
+                    poly_market,
+                    kalshi_market,
+                    candidate(
+                        poly_market,
+                        kalshi_market,
+                        event_match.score,
+                        explanation,
+                        &outcome.resolution_risk,
+                        now,
+                    ),
+                ));
+            }
+        }
+
+        let in_event = |market: &&Market| {
+            market
+                .event_id
+                .as_deref()
+                .is_some_and(|event_id| in_events.contains(&(market.platform.clone(), event_id)))
+        };
+        let poly_rest: Vec<&Market> = poly_unmapped
+            .iter()
+            .copied()
+            .filter(|market| !in_event(market))
+            .collect();
+        let kalshi_rest: Vec<&Market> = kalshi_unmapped
+            .iter()
+            .copied()
+            .filter(|market| !in_event(market))
+            .collect();
+
+        matches.extend(
+            self.match_index
+                .lock()
+                .expect("match index lock poisoned")
+                .matches(&self.matcher, &poly_rest, &kalshi_rest)
+                .into_iter()
+                .map(|(i, j, confidence)| {
+                    let (poly_market, kalshi_market) = (poly_rest[i], kalshi_rest[j]);
+                    let candidate = candidate(
+                        poly_market,
+                        kalshi_market,
+                        confidence.score,
+                        confidence.to_string(),
+                        &confidence.resolution_risk,
+                        now,
+                    );
+                    (poly_market, kalshi_market, candidate)
+                }),
+        );
+
+        matches
+            .into_iter()
+            .filter(|(poly_market, kalshi_market, _)| !mapping.is_denied(poly_market, kalshi_market))
+            .collect()
+    }
+
+    /// `calculate_arbitrage` for a matched pair, reading the Kalshi
+    /// market's outcomes swapped when the pair is inverted.
+    fn pair_arbitrage(
+        &self,
+        pair: &MarketPair,
+        poly_book: &OrderBook,
+        kalshi_book: &OrderBook,
+    ) -> Option<ArbitrageOpportunity> {
+        if !pair.inverted {
+            return self.calculate_arbitrage(&pair.polymarket, &pair.kalshi, poly_book, kalshi_book);
+        }
+
+        let mut opportunity = self.calculate_arbitrage(
+            &pair.polymarket,
+            &pair.kalshi.inverted(),
+            poly_book,
+            &kalshi_book.inverted(),
+        )?;
+        opportunity.kalshi_inverted = true;
+        Some(opportunity)
+    }
+
+    /// Looks for a cross-venue complement: buying YES on one venue and NO on
+    /// the other for less than the guaranteed payout. Both directions are
+    /// sized against the order books and the one with the larger net profit
+    /// is returned.
+    pub fn calculate_arbitrage(
         &self,
         poly_market: &Market,
         kalshi_market: &Market,
+        poly_book: &OrderBook,
+        kalshi_book: &OrderBook,
     ) -> Option<ArbitrageOpportunity> {
-        // Check both directions of arbitrage
-
-        // Direction 1: Buy on Polymarket, Sell on Kalshi
-        let profit1 = self.calculate_profit(
-            poly_market.yes_price,
-            kalshi_market.yes_price,
-            Platform::Polymarket,
-            Platform::Kalshi,
-        );
+        // Direction 1: YES on Polymarket, NO on Kalshi
+        let direction1 = self
+            .size_position(
+                &poly_book.yes_asks,
+                &kalshi_book.no_asks,
+                Platform::Polymarket,
+                Platform::Kalshi,
+            )
+            .map(|profit| (profit, Platform::Polymarket, Platform::Kalshi));
 
-        // Direction 2: Buy on Kalshi, Sell on Polymarket
-        let profit2 = self.calculate_profit(
-            kalshi_market.yes_price,
-            poly_market.yes_price,
-            Platform::Kalshi,
-            Platform::Polymarket,
-        );
+        // Direction 2: YES on Kalshi, NO on Polymarket
+        let direction2 = self
+            .size_position(
+                &kalshi_book.yes_asks,
+                &poly_book.no_asks,
+                Platform::Kalshi,
+                Platform::Polymarket,
+            )
+            .map(|profit| (profit, Platform::Kalshi, Platform::Polymarket));
 
-        let (profit_percentage, buy_platform, sell_platform, buy_price, sell_price) =
-            if profit1 > profit2 {
-                (
-                    profit1,
-                    Platform::Polymarket,
-                    Platform::Kalshi,
-                    poly_market.yes_price,
-                    kalshi_market.yes_price,
-                )
-            } else {
-                (
-                    profit2,
-                    Platform::Kalshi,
-                    Platform::Polymarket,
-                    kalshi_market.yes_price,
-                    poly_market.yes_price,
-                )
-            };
+        let (profit, yes_platform, no_platform) = match (direction1, direction2) {
+            (Some(d1), Some(d2)) => {
+                if d1.0.net_profit() >= d2.0.net_profit() {
+                    d1
+                } else {
+                    d2
+                }
+            }
+            (Some(d), None) | (None, Some(d)) => d,
+            (None, None) => return None,
+        };
 
-        let min_profit = Decimal::try_from(self.config.bot.min_profit_percentage / 100.0).ok()?;
+        let polymarket_token_id = match yes_platform {
+            Platform::Polymarket => poly_market.yes_token_id.clone(),
+            Platform::Kalshi => poly_market.no_token_id.clone(),
+        };
 
-        if profit_percentage > min_profit {
-            let position_size = Decimal::try_from(self.config.bot.max_position_size).ok()?;
-            let estimated_profit = position_size * profit_percentage;
-
-            Some(ArbitrageOpportunity {
-                id: None,
-                polymarket_market_id: poly_market.id.clone(),
-                kalshi_market_id: kalshi_market.id.clone(),
-                buy_platform,
-                sell_platform,
-                buy_price,
-                sell_price,
-                profit_percentage,
-                estimated_profit,
-                position_size,
-                detected_at: chrono::Utc::now(),
-                executed: false,
-            })
-        } else {
-            None
-        }
+        Some(ArbitrageOpportunity {
+            id: None,
+            polymarket_market_id: poly_market.id.clone(),
+            kalshi_market_id: kalshi_market.id.clone(),
+            polymarket_token_id,
+            polymarket_neg_risk: poly_market.neg_risk,
+            polymarket_condition_id: poly_market.condition_id.clone(),
+            yes_platform,
+            no_platform,
+            yes_price: profit.yes_price,
+            no_price: profit.no_price,
+            yes_limit_price: profit.yes_limit_price,
+            no_limit_price: profit.no_limit_price,
+            payout: CONTRACT_PAYOUT,
+            gross_edge: profit.gross_edge,
+            total_fees: profit.total_fees,
+            net_edge: profit.net_edge,
+            profit_percentage: profit.profit_percentage,
+            estimated_profit: profit.net_profit(),
+            position_size: profit.position_size,
+            detected_at: chrono::Utc::now(),
+            executed: false,
+            kalshi_inverted: false,
+            kalshi_event_id: kalshi_market.event_id.clone(),
+        })
+    }
+
+    /// Cheap top-of-book check used to skip fetching order books for pairs
+    /// that cannot be an arbitrage in either direction.
+    fn has_complement(poly_market: &Market, kalshi_market: &Market) -> bool {
+        let crosses = |yes_price: Decimal, no_price: Decimal| {
+            yes_price > Decimal::ZERO
+                && no_price > Decimal::ZERO
+                && yes_price + no_price < CONTRACT_PAYOUT
+        };
+
+        crosses(poly_market.yes_price, kalshi_market.no_price)
+            || crosses(kalshi_market.yes_price, poly_market.no_price)
     }
 
-    fn calculate_profit(
+    /// Finds the largest whole-contract size at which sweeping both books
+    /// still clears `min_profit_percentage` net of fees, capped so the legs
+    /// cost no more than `max_position_size`.
+    fn size_position(
         &self,
-        buy_price: Decimal,
-        sell_price: Decimal,
-        _buy_platform: Platform,
-        _sell_platform: Platform,
-    ) -> Decimal {
-        if sell_price > buy_price {
-            (sell_price - buy_price) / buy_price
-        } else {
-            Decimal::ZERO
+        yes_asks: &[PriceLevel],
+        no_asks: &[PriceLevel],
+        yes_platform: Platform,
+        no_platform: Platform,
+    ) -> Option<ProfitBreakdown> {
+        let (Some(best_yes), Some(best_no)) = (yes_asks.first(), no_asks.first()) else {
+            return None;
+        };
+        if best_yes.price + best_no.price >= CONTRACT_PAYOUT {
+            return None;
         }
-    }
 
-    async fn execute_opportunity(&self, opportunity: &ArbitrageOpportunity) -> Result<()> {
-        info!("Executing arbitrage opportunity: {:#?}", opportunity);
+        let min_profit = Decimal::try_from(self.config.bot.min_profit_percentage / 100.0).ok()?;
+        let max_position = Decimal::try_from(self.config.bot.max_position_size).ok()?;
+
+        let depth = book_depth(yes_asks).min(book_depth(no_asks)).floor();
+        let max_size = self.max_affordable_size(yes_asks, no_asks, depth, max_position);
+        if max_size.is_zero() {
+            return None;
+        }
 
-        // This is a simplified execution flow
-        // In production, add proper error handling, position tracking, etc.
+        // Marginal cost is constant between level boundaries, so profit is
+        // monotonic there; evaluating the boundaries and then bisecting the
+        // segment where it stops clearing finds the largest size.
+        let mut breakpoints = vec![Decimal::ONE, max_size];
+        for levels in [yes_asks, no_asks] {
+            let mut cumulative = Decimal::ZERO;
+            for level in levels {
+                cumulative += level.size;
+                let size = cumulative.floor();
+                if size >= Decimal::ONE && size < max_size {
+                    breakpoints.push(size);
+                }
+            }
+        }
+        breakpoints.sort();
+        breakpoints.dedup();
 
-        match opportunity.buy_platform {
-            Platform::Polymarket => {
-                self.polymarket
-                    .place_order(
-                        &opportunity.polymarket_market_id,
-                        "buy",
-                        opportunity.buy_price,
-                        opportunity.position_size,
-                    )
-                    .await?;
+        let evaluate = |size: Decimal| {
+            self.evaluate_size(yes_asks, no_asks, &yes_platform, &no_platform, size)
+                .filter(|profit| profit.profit_percentage > min_profit)
+        };
+
+        let mut best = None;
+        let mut failing_above = None;
+        for (i, size) in breakpoints.iter().enumerate() {
+            if let Some(profit) = evaluate(*size) {
+                best = Some(profit);
+                failing_above = breakpoints.get(i + 1).copied();
             }
-            Platform::Kalshi => {
-                let price_cents = (opportunity.buy_price * Decimal::from(100))
-                    .to_string()
-                    .parse()?;
-                self.kalshi
-                    .place_order(
-                        &opportunity.kalshi_market_id,
-                        "yes",
-                        price_cents,
-                        opportunity.position_size.to_string().parse()?,
-                    )
-                    .await?;
+        }
+
+        let mut best = best?;
+        if let Some(mut high) = failing_above {
+            let mut low = best.position_size;
+            while high - low > Decimal::ONE {
+                let mid = ((low + high) / Decimal::TWO).floor();
+                match evaluate(mid) {
+                    Some(profit) => {
+                        low = mid;
+                        best = profit;
+                    }
+                    None => high = mid,
+                }
             }
         }
 
-        match opportunity.sell_platform {
-            Platform::Polymarket => {
-                self.polymarket
-                    .place_order(
-                        &opportunity.polymarket_market_id,
-                        "sell",
-                        opportunity.sell_price,
-                        opportunity.position_size,
-                    )
-                    .await?;
+        Some(best)
+    }
+
+    /// Largest whole-contract size whose combined leg cost fits in `budget`.
+    fn max_affordable_size(
+        &self,
+        yes_asks: &[PriceLevel],
+        no_asks: &[PriceLevel],
+        depth: Decimal,
+        budget: Decimal,
+    ) -> Decimal {
+        let affordable = |size: Decimal| {
+            match (sweep_cost(yes_asks, size), sweep_cost(no_asks, size)) {
+                (Some((yes_cost, _)), Some((no_cost, _))) => yes_cost + no_cost <= budget,
+                _ => false,
             }
-            Platform::Kalshi => {
-                let price_cents = (opportunity.sell_price * Decimal::from(100))
-                    .to_string()
-                    .parse()?;
-                self.kalshi
-                    .place_order(
-                        &opportunity.kalshi_market_id,
-                        "no",
-                        price_cents,
-                        opportunity.position_size.to_string().parse()?,
-                    )
-                    .await?;
+        };
+
+        if affordable(depth) {
+            return depth;
+        }
+
+        let mut low = Decimal::ZERO;
+        let mut high = depth;
+        while high - low > Decimal::ONE {
+            let mid = ((low + high) / Decimal::TWO).floor();
+            if affordable(mid) {
+                low = mid;
+            } else {
+                high = mid;
             }
         }
 
+        low
+    }
+
+    /// Economics of sweeping `size` contracts from each book and holding
+    /// both legs to resolution, net of both venues' taker fees.
+    fn evaluate_size(
+        &self,
+        yes_asks: &[PriceLevel],
+        no_asks: &[PriceLevel],
+        yes_platform: &Platform,
+        no_platform: &Platform,
+        size: Decimal,
+    ) -> Option<ProfitBreakdown> {
+        let (yes_cost, yes_limit_price) = sweep_cost(yes_asks, size)?;
+        let (no_cost, no_limit_price) = sweep_cost(no_asks, size)?;
+
+        let yes_price = yes_cost / size;
+        let no_price = no_cost / size;
+
+        let total_fees = self
+            .fee_model(yes_platform)
+            .order_fee(yes_price, size, Liquidity::Taker)
+            + self
+                .fee_model(no_platform)
+                .order_fee(no_price, size, Liquidity::Taker);
+
+        let net_profit = CONTRACT_PAYOUT * size - yes_cost - no_cost - total_fees;
+        let capital = yes_cost + no_cost + total_fees;
+
+        Some(ProfitBreakdown {
+            position_size: size,
+            yes_price,
+            no_price,
+            yes_limit_price,
+            no_limit_price,
+            gross_edge: CONTRACT_PAYOUT - yes_price - no_price,
+            total_fees,
+            net_edge: net_profit / size,
+            profit_percentage: net_profit / capital,
+        })
+    }
+
+    fn fee_model(&self, platform: &Platform) -> &dyn FeeModel {
+        match platform {
+            Platform::Polymarket => self.polymarket_fees.as_ref(),
+            Platform::Kalshi => self.kalshi_fees.as_ref(),
+        }
+    }
+
+    async fn execute_opportunity(
+        &self,
+        opportunity: &ArbitrageOpportunity,
+        opportunity_id: i64,
+    ) -> Result<()> {
+        let size = match self.risk.check(opportunity).await {
+            RiskDecision::Approve { size, .. } => size,
+            RiskDecision::Reject {
+                code: RiskCode::DailyLoss,
+                reason,
+            } => {
+                self.breaker.trip(TripReason::DailyLoss, reason).await?;
+                return Ok(());
+            }
+            RiskDecision::Reject { .. } => return Ok(()),
+        };
+
+        if size < opportunity.position_size {
+            let resized = resize_opportunity(opportunity, size);
+            self.coordinator.execute(&resized, opportunity_id).await?;
+        } else {
+            self.coordinator.execute(opportunity, opportunity_id).await?;
+        }
         Ok(())
     }
 
     pub async fn shutdown(&mut self) -> Result<()> {
         info!("Shutting down arbitrage engine");
-        self.running = false;
+        self.running.store(false, Ordering::SeqCst);
         Ok(())
     }
 }
+
+/// `client`, saving the market data it fetches when
+/// `record_market_data` is set.
+fn recorded(config: &Config, database: &Database, client: Arc<dyn Exchange>) -> Arc<dyn Exchange> {
+    if config.bot.record_market_data {
+        Arc::new(RecordingExchange::new(client, database.clone()))
+    } else {
+        client
+    }
+}
+
+fn polymarket_client(config: &Config) -> Result<PolymarketClient> {
+    Ok(PolymarketClient::new(
+        ApiCredentials::from_config(&config.polymarket),
+        OrderSigner::from_config(&config.polymarket)?,
+        config.polymarket.base_url.clone(),
+    )
+    .with_order_settings(
+        config.fees.polymarket.taker_fee_bps as u64,
+        config.polymarket.order_ttl_seconds,
+    )
+    .with_http_config(&config.polymarket.http)
+    .with_data_api_url(config.polymarket.data_api_url.clone())
+    .with_market_query(PolymarketMarketQuery {
+        tag_id: config.polymarket.tag_id.clone(),
+        page_size: config.polymarket.page_size,
+        max_pages: config.polymarket.max_pages,
+    }))
+}
+
+fn kalshi_client(config: &Config) -> Result<KalshiClient> {
+    let signer = if config.kalshi.private_key_path.is_empty() {
+        None
+    } else {
+        Some(KalshiSigner::from_file(&config.kalshi.private_key_path)?)
+    };
+
+    Ok(KalshiClient::new(
+        config.kalshi.api_key.clone(),
+        signer,
+        config.kalshi.base_url.clone(),
+    )
+    .with_http_config(&config.kalshi.http)
+    .with_market_query(KalshiMarketQuery {
+        series_ticker: config.kalshi.series_ticker.clone(),
+        event_ticker: config.kalshi.event_ticker.clone(),
+        page_size: config.kalshi.page_size,
+        max_pages: config.kalshi.max_pages,
+    }))
+}
+
+/// A pending review candidate for a fuzzy-matched pair.
+fn candidate(
+    poly_market: &Market,
+    kalshi_market: &Market,
+    similarity: f64,
+    explanation: String,
+    resolution_risk: &[String],
+    now: DateTime<Utc>,
+) -> MarketMatch {
+    MarketMatch {
+        id: None,
+        polymarket_market_id: poly_market.id.clone(),
+        kalshi_market_id: kalshi_market.id.clone(),
+        polymarket_question: poly_market.question.clone(),
+        kalshi_question: kalshi_market.question.clone(),
+        polymarket_end_time: poly_market.end_time,
+        kalshi_end_time: kalshi_market.end_time,
+        similarity,
+        explanation,
+        polymarket_rules: poly_market.rules.clone(),
+        kalshi_rules: kalshi_market.rules.clone(),
+        resolution_risk: (!resolution_risk.is_empty()).then(|| resolution_risk.join(", ")),
+        status: MatchStatus::Pending,
+        inverted: false,
+        first_seen_at: now,
+        last_seen_at: now,
+        decided_at: None,
+    }
+}
+
+/// `opportunity` cut down to `size` contracts. The limit prices of the full
+/// size are kept and fees are scaled in proportion, which understates them
+/// slightly when a venue charges per order.
+fn resize_opportunity(opportunity: &ArbitrageOpportunity, size: Decimal) -> ArbitrageOpportunity {
+    ArbitrageOpportunity {
+        total_fees: opportunity.total_fees * size / opportunity.position_size,
+        estimated_profit: opportunity.net_edge * size,
+        position_size: size,
+        ..opportunity.clone()
+    }
+}
@@ -0,0 +1,7 @@
+//! Recording live market data and replaying it offline.
+
+pub mod recorder;
+pub mod replay;
+
+pub use recorder::RecordingExchange;
+pub use replay::{BacktestReport, Backtester, ReplayExchange};
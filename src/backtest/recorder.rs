@@ -0,0 +1,117 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use log::warn;
+use rust_decimal::Decimal;
+use std::sync::Arc;
+
+use crate::{
+    api::{ApiResult, Exchange},
+    database::Database,
+    models::{
+        Event, Fill, Market, MarketRecording, Order, OrderBook, OrderRequest, Platform, Position,
+        Settlement,
+    },
+};
+
+/// Passes every call through to a venue and saves each market listing and
+/// order book it returns, for the backtester to replay.
+///
+/// A recording that cannot be saved is logged and does not fail the call.
+pub struct RecordingExchange {
+    inner: Arc<dyn Exchange>,
+    database: Database,
+}
+
+impl RecordingExchange {
+    pub fn new(inner: Arc<dyn Exchange>, database: Database) -> Self {
+        Self { inner, database }
+    }
+
+    async fn record_markets(&self, markets: &[Market], exclusive_events: Vec<String>) {
+        let recording = MarketRecording {
+            platform: self.platform(),
+            recorded_at: Utc::now(),
+            markets: markets.to_vec(),
+            exclusive_events,
+        };
+        if let Err(e) = self.database.save_market_recording(&recording).await {
+            warn!(
+                "Failed to record {} markets: {:#}",
+                self.platform().as_str(),
+                e
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl Exchange for RecordingExchange {
+    fn platform(&self) -> Platform {
+        self.inner.platform()
+    }
+
+    async fn get_markets(&self) -> ApiResult<Vec<Market>> {
+        let markets = self.inner.get_markets().await?;
+        self.record_markets(&markets, Vec::new()).await;
+        Ok(markets)
+    }
+
+    /// Records the events' markets as the venue's listing, with which events
+    /// are mutually exclusive; a replay groups them back by event.
+    async fn get_events(&self) -> ApiResult<Vec<Event>> {
+        let events = self.inner.get_events().await?;
+        let markets: Vec<Market> = events
+            .iter()
+            .flat_map(|event| event.markets.iter().cloned())
+            .collect();
+        let exclusive = events
+            .iter()
+            .filter(|event| event.mutually_exclusive)
+            .map(|event| event.id.clone())
+            .collect();
+        self.record_markets(&markets, exclusive).await;
+        Ok(events)
+    }
+
+    async fn get_order_book(&self, market: &Market) -> ApiResult<OrderBook> {
+        let book = self.inner.get_order_book(market).await?;
+
+        if let Err(e) = self.database.save_book_recording(&book).await {
+            warn!("Failed to record order book for {}: {:#}", market.id, e);
+        }
+
+        Ok(book)
+    }
+
+    async fn place_order(&self, order: &OrderRequest) -> ApiResult<String> {
+        self.inner.place_order(order).await
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> ApiResult<()> {
+        self.inner.cancel_order(order_id).await
+    }
+
+    async fn get_order(&self, order_id: &str) -> ApiResult<Order> {
+        self.inner.get_order(order_id).await
+    }
+
+    async fn find_order(&self, order: &OrderRequest) -> ApiResult<Option<Order>> {
+        self.inner.find_order(order).await
+    }
+
+    async fn list_fills(&self, order_id: &str) -> ApiResult<Vec<Fill>> {
+        self.inner.list_fills(order_id).await
+    }
+
+    async fn get_positions(&self) -> ApiResult<Vec<Position>> {
+        self.inner.get_positions().await
+    }
+
+    async fn get_settlements(&self, since: DateTime<Utc>) -> ApiResult<Vec<Settlement>> {
+        self.inner.get_settlements(since).await
+    }
+
+    async fn get_balance(&self) -> ApiResult<Decimal> {
+        self.inner.get_balance().await
+    }
+}
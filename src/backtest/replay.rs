@@ -0,0 +1,385 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use rust_decimal::Decimal;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    api::{ApiError, ApiResult, Exchange},
+    arbitrage::ArbitrageEngine,
+    config::Config,
+    database::Database,
+    fees::{KalshiFeeModel, PolymarketFeeModel},
+    models::{
+        Event, Fill, Market, MarketMatch, MarketRecording, MatchStatus, Order, OrderBook, OrderRequest,
+        Outcome, Platform, Position,
+    },
+    paper::PaperExchange,
+};
+
+/// Everything recorded over a period, indexed for replay.
+struct Recording {
+    listings: HashMap<Platform, Vec<MarketRecording>>,
+    books: HashMap<(Platform, String), Vec<OrderBook>>,
+}
+
+impl Recording {
+    fn listing_at(&self, platform: &Platform, at: DateTime<Utc>) -> Option<&MarketRecording> {
+        self.listings
+            .get(platform)?
+            .iter()
+            .take_while(|recording| recording.recorded_at <= at)
+            .last()
+    }
+
+    fn book_at(
+        &self,
+        platform: &Platform,
+        market_id: &str,
+        at: DateTime<Utc>,
+    ) -> Option<&OrderBook> {
+        self.books
+            .get(&(platform.clone(), market_id.to_string()))?
+            .iter()
+            .take_while(|book| book.timestamp <= at)
+            .last()
+    }
+}
+
+/// Serves recorded market data as it stood at a shared virtual time.
+///
+/// Listings and books are the latest recorded at or before the clock; a
+/// market with no book recorded yet is reported closed. Orders cannot be
+/// sent, so the backtester wraps this in a `PaperExchange`.
+pub struct ReplayExchange {
+    platform: Platform,
+    recording: Arc<Recording>,
+    clock: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl ReplayExchange {
+    fn now(&self) -> DateTime<Utc> {
+        *self.clock.lock().expect("replay clock lock poisoned")
+    }
+}
+
+fn not_recorded() -> ApiError {
+    ApiError::InvalidOrder {
+        reason: "orders cannot be sent to a recording".to_string(),
+    }
+}
+
+#[async_trait]
+impl Exchange for ReplayExchange {
+    fn platform(&self) -> Platform {
+        self.platform.clone()
+    }
+
+    async fn get_markets(&self) -> ApiResult<Vec<Market>> {
+        self.recording
+            .listing_at(&self.platform, self.now())
+            .map(|recording| recording.markets.clone())
+            .ok_or(ApiError::Server { status: 503 })
+    }
+
+    async fn get_events(&self) -> ApiResult<Vec<Event>> {
+        let recording = self
+            .recording
+            .listing_at(&self.platform, self.now())
+            .ok_or(ApiError::Server { status: 503 })?;
+        let mut events = Event::group(&recording.markets);
+        for event in &mut events {
+            event.mutually_exclusive = recording.exclusive_events.contains(&event.id);
+        }
+        Ok(events)
+    }
+
+    async fn get_order_book(&self, market: &Market) -> ApiResult<OrderBook> {
+        self.recording
+            .book_at(&self.platform, &market.id, self.now())
+            .cloned()
+            .ok_or(ApiError::MarketClosed)
+    }
+
+    async fn place_order(&self, _order: &OrderRequest) -> ApiResult<String> {
+        Err(not_recorded())
+    }
+
+    async fn cancel_order(&self, _order_id: &str) -> ApiResult<()> {
+        Err(not_recorded())
+    }
+
+    async fn get_order(&self, _order_id: &str) -> ApiResult<Order> {
+        Err(not_recorded())
+    }
+
+    /// No order ever reaches a recording.
+    async fn find_order(&self, _order: &OrderRequest) -> ApiResult<Option<Order>> {
+        Ok(None)
+    }
+
+    async fn list_fills(&self, _order_id: &str) -> ApiResult<Vec<Fill>> {
+        Err(not_recorded())
+    }
+
+    async fn get_positions(&self) -> ApiResult<Vec<Position>> {
+        Ok(Vec::new())
+    }
+
+    async fn get_balance(&self) -> ApiResult<Decimal> {
+        Ok(Decimal::ZERO)
+    }
+}
+
+/// What one configuration did over a recording.
+#[derive(Debug, Clone)]
+pub struct BacktestReport {
+    pub label: String,
+    pub cycles: usize,
+    pub opportunities: usize,
+    pub executed: usize,
+    pub orders: usize,
+    pub fills: usize,
+    pub fees: Decimal,
+    /// Change in paper cash across both venues.
+    pub cash_pnl: Decimal,
+    /// `cash_pnl` plus what the positions are worth: each hedged pair at
+    /// its payout and anything left unhedged at cost.
+    pub pnl: Decimal,
+}
+
+/// Replays recorded market data through the engine's matching, arbitrage
+/// and execution code, filling orders in a throwaway paper account.
+///
+/// One cycle is evaluated per recorded polling cycle, at the moment before
+/// the next one began, so it sees the books that cycle fetched.
+pub struct Backtester {
+    recording: Arc<Recording>,
+    cycles: Vec<DateTime<Utc>>,
+    /// Review decisions on fuzzy matches, which gate execution in a replay
+    /// as they do live.
+    decisions: Vec<MarketMatch>,
+}
+
+impl Backtester {
+    /// Loads what was recorded from `from` up to but excluding `to`.
+    pub async fn load(database: &Database, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Self> {
+        let recordings = database.get_market_recordings(from, to).await?;
+
+        // A cycle lists each venue once; a venue listed again starts the next
+        let mut starts = Vec::new();
+        let mut listed = HashSet::new();
+        for recording in &recordings {
+            if listed.is_empty() || !listed.insert(recording.platform.clone()) {
+                starts.push(recording.recorded_at);
+                listed.clear();
+                listed.insert(recording.platform.clone());
+            }
+        }
+        let mut cycles: Vec<DateTime<Utc>> = starts
+            .iter()
+            .skip(1)
+            .map(|start| *start - chrono::Duration::nanoseconds(1))
+            .collect();
+        if !starts.is_empty() {
+            cycles.push(to);
+        }
+
+        let mut listings: HashMap<Platform, Vec<MarketRecording>> = HashMap::new();
+        for recording in recordings {
+            listings
+                .entry(recording.platform.clone())
+                .or_default()
+                .push(recording);
+        }
+        let mut books: HashMap<(Platform, String), Vec<OrderBook>> = HashMap::new();
+        for book in database.get_book_recordings(from, to).await? {
+            books
+                .entry((book.platform.clone(), book.market_id.clone()))
+                .or_default()
+                .push(book);
+        }
+
+        let decisions = database
+            .get_market_matches(None)
+            .await?
+            .into_iter()
+            .filter(|decision| decision.status != MatchStatus::Pending)
+            .collect();
+
+        Ok(Self {
+            recording: Arc::new(Recording { listings, books }),
+            cycles,
+            decisions,
+        })
+    }
+
+    /// Number of polling cycles in the recording.
+    pub fn cycles(&self) -> usize {
+        self.cycles.len()
+    }
+
+    /// Runs the recording through an engine configured by `config`.
+    ///
+    /// Paper fills are immediate, the breaker's halt and resume files are
+    /// ignored and legs time out after at most a second, so a replay does
+    /// not wait on the wall clock.
+    pub async fn run(&self, label: &str, config: &Config) -> Result<BacktestReport> {
+        let mut config = config.clone();
+        config.bot.streaming = false;
+        config.bot.record_market_data = false;
+        config.bot.order_poll_interval_ms = 10;
+        config.paper.latency_ms = 0;
+        config.execution.leg_timeout_seconds = config.execution.leg_timeout_seconds.min(1);
+        config.breaker.halt_file.clear();
+        config.breaker.resume_file.clear();
+
+        let database = Database::new("sqlite::memory:").await?;
+        database.run_migrations().await?;
+        for decision in &self.decisions {
+            let recorded = database.record_match_candidate(decision).await?;
+            if let Some(id) = recorded.id {
+                database
+                    .decide_market_match(
+                        id,
+                        decision.status,
+                        decision.inverted,
+                        decision.decided_at.unwrap_or(decision.last_seen_at),
+                    )
+                    .await?;
+            }
+        }
+
+        let clock = Arc::new(Mutex::new(DateTime::<Utc>::MIN_UTC));
+        let replay = |platform: Platform| -> Arc<dyn Exchange> {
+            Arc::new(ReplayExchange {
+                platform,
+                recording: self.recording.clone(),
+                clock: clock.clone(),
+            })
+        };
+        let polymarket = Arc::new(PaperExchange::new(
+            replay(Platform::Polymarket),
+            Box::new(PolymarketFeeModel::new(&config.fees.polymarket)),
+            database.clone(),
+            &config.paper,
+        ));
+        let kalshi = Arc::new(PaperExchange::new(
+            replay(Platform::Kalshi),
+            Box::new(KalshiFeeModel::new(&config.fees.kalshi)),
+            database.clone(),
+            &config.paper,
+        ));
+
+        let engine = ArbitrageEngine::with_exchanges(
+            config.clone(),
+            database.clone(),
+            true,
+            polymarket,
+            kalshi,
+        );
+        let tracker = tokio::spawn(engine.tracker().clone().run());
+
+        for at in &self.cycles {
+            *clock.lock().expect("replay clock lock poisoned") = *at;
+
+            if let Err(e) = engine.breaker().check().await {
+                warn!("Circuit breaker check failed: {:#}", e);
+            }
+            if let Err(e) = engine.check_opportunities().await {
+                warn!("Error checking opportunities at {}: {}", at.to_rfc3339(), e);
+            }
+        }
+        tracker.abort();
+
+        let report = report(label, self.cycles.len(), &database, &config).await?;
+        info!(
+            "Backtest {}: {} cycles, {} opportunities, {} executed, P&L {}",
+            report.label, report.cycles, report.opportunities, report.executed, report.pnl
+        );
+        Ok(report)
+    }
+}
+
+async fn report(
+    label: &str,
+    cycles: usize,
+    database: &Database,
+    config: &Config,
+) -> Result<BacktestReport> {
+    let opportunities = database.get_recent_opportunities(i64::MAX).await?;
+    let mut orders = 0;
+    for opportunity in &opportunities {
+        if let Some(id) = opportunity.id {
+            orders += database.get_trades_for_opportunity(id).await?.len();
+        }
+    }
+    let (fills, fees) = database.get_paper_fill_summary().await?;
+
+    let mut cash_pnl = Decimal::ZERO;
+    let mut held: HashMap<(Platform, String, Outcome), Position> = HashMap::new();
+    for platform in [Platform::Polymarket, Platform::Kalshi] {
+        let starting = Decimal::try_from(config.paper.starting_balance).unwrap_or_default();
+        let account = database.open_paper_account(&platform, starting).await?;
+        cash_pnl += account.balance - account.starting_balance;
+
+        for position in database.get_paper_positions(&platform).await? {
+            held.insert(
+                (
+                    position.platform.clone(),
+                    position.market_id.clone(),
+                    position.outcome,
+                ),
+                position,
+            );
+        }
+    }
+
+    // Opposite outcomes of a matched pair pay out once whichever way the
+    // market resolves
+    let mut value = Decimal::ZERO;
+    for (poly_market_id, kalshi_market_id) in database.get_market_pairs().await? {
+        for outcome in [Outcome::Yes, Outcome::No] {
+            let poly_key = (Platform::Polymarket, poly_market_id.clone(), outcome);
+            let kalshi_key = (
+                Platform::Kalshi,
+                kalshi_market_id.clone(),
+                outcome.opposite(),
+            );
+            let hedged = match (held.get(&poly_key), held.get(&kalshi_key)) {
+                (Some(poly), Some(kalshi)) => poly.amount.min(kalshi.amount),
+                _ => continue,
+            };
+
+            value += hedged;
+            for key in [&poly_key, &kalshi_key] {
+                if let Some(position) = held.get_mut(key) {
+                    position.amount -= hedged;
+                }
+            }
+        }
+    }
+    value += held
+        .values()
+        .map(|position| position.amount * position.entry_price)
+        .sum::<Decimal>();
+
+    Ok(BacktestReport {
+        label: label.to_string(),
+        cycles,
+        opportunities: opportunities.len(),
+        executed: opportunities
+            .iter()
+            .filter(|opportunity| opportunity.executed)
+            .count(),
+        orders,
+        fills,
+        fees,
+        cash_pnl,
+        pnl: cash_pnl + value,
+    })
+}
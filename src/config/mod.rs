@@ -2,6 +2,16 @@ use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::fs;
 
+use crate::{
+    api::{
+        polymarket::DATA_API_URL,
+        polymarket_signing::{
+            SignatureType, CTF_EXCHANGE_ADDRESS, NEG_RISK_CTF_EXCHANGE_ADDRESS, POLYGON_CHAIN_ID,
+        },
+    },
+    models::LegRecovery,
+};
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Config {
     pub polymarket: PolymarketConfig,
@@ -9,21 +19,152 @@ pub struct Config {
     pub bot: BotConfig,
     pub database: DatabaseConfig,
     pub risk: RiskConfig,
+    #[serde(default)]
+    pub fees: FeesConfig,
+    #[serde(default)]
+    pub execution: ExecutionConfig,
+    #[serde(default)]
+    pub breaker: BreakerConfig,
+    #[serde(default)]
+    pub paper: PaperConfig,
+    #[serde(default)]
+    pub matching: MatchingConfig,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PolymarketConfig {
     pub api_key: String,
+    #[serde(default)]
+    pub api_secret: String,
+    #[serde(default)]
+    pub api_passphrase: String,
+    /// Hex secp256k1 key that signs orders. Leave empty for market data only.
     pub private_key: String,
+    /// Address holding the funds: the key's own address for `eoa`, or the
+    /// proxy/Safe wallet for the other signature types.
     pub wallet_address: String,
+    #[serde(default)]
+    pub signature_type: SignatureType,
+    #[serde(default = "default_polymarket_chain_id")]
+    pub chain_id: u64,
+    #[serde(default = "default_polymarket_exchange_address")]
+    pub exchange_address: String,
+    /// Verifies orders on neg-risk markets, the outcomes of multi-outcome
+    /// events.
+    #[serde(default = "default_polymarket_neg_risk_exchange_address")]
+    pub neg_risk_exchange_address: String,
+    /// Orders expire after this many seconds; zero leaves them resting
+    /// until cancelled.
+    #[serde(default)]
+    pub order_ttl_seconds: u64,
+    /// Only list markets carrying this tag.
+    #[serde(default)]
+    pub tag_id: Option<String>,
+    #[serde(default = "default_polymarket_page_size")]
+    pub page_size: u32,
+    #[serde(default = "default_max_pages")]
+    pub max_pages: u32,
     pub base_url: String,
+    /// Serves positions, which the CLOB API does not.
+    #[serde(default = "default_polymarket_data_api_url")]
+    pub data_api_url: String,
+    /// CLOB market channel used when `bot.streaming` is on.
+    #[serde(default = "default_polymarket_ws_url")]
+    pub ws_url: String,
+    #[serde(default)]
+    pub http: HttpConfig,
+}
+
+fn default_polymarket_chain_id() -> u64 {
+    POLYGON_CHAIN_ID
+}
+
+fn default_polymarket_exchange_address() -> String {
+    CTF_EXCHANGE_ADDRESS.to_string()
+}
+
+fn default_polymarket_neg_risk_exchange_address() -> String {
+    NEG_RISK_CTF_EXCHANGE_ADDRESS.to_string()
+}
+
+fn default_polymarket_data_api_url() -> String {
+    DATA_API_URL.to_string()
+}
+
+fn default_polymarket_ws_url() -> String {
+    "wss://ws-subscriptions-clob.polymarket.com/ws/market".to_string()
+}
+
+fn default_polymarket_page_size() -> u32 {
+    500
+}
+
+fn default_max_pages() -> u32 {
+    50
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct KalshiConfig {
+    /// API key ID shown alongside the key in Kalshi's account settings.
     pub api_key: String,
-    pub api_secret: String,
+    /// PEM file holding the RSA private key used to sign requests. Leave
+    /// empty to use public market data only.
+    #[serde(default)]
+    pub private_key_path: String,
+    #[serde(default)]
+    pub series_ticker: Option<String>,
+    #[serde(default)]
+    pub event_ticker: Option<String>,
+    #[serde(default = "default_kalshi_page_size")]
+    pub page_size: u32,
+    #[serde(default = "default_max_pages")]
+    pub max_pages: u32,
     pub base_url: String,
+    /// Trade API websocket used when `bot.streaming` is on.
+    #[serde(default = "default_kalshi_ws_url")]
+    pub ws_url: String,
+    #[serde(default)]
+    pub http: HttpConfig,
+}
+
+fn default_kalshi_page_size() -> u32 {
+    1000
+}
+
+fn default_kalshi_ws_url() -> String {
+    "wss://api.elections.kalshi.com/trade-api/ws/v2".to_string()
+}
+
+/// Request pacing and retries for one venue's REST API.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// Market data requests.
+    pub read_requests_per_second: f64,
+    pub read_burst: u32,
+    /// Order entry requests.
+    pub write_requests_per_second: f64,
+    pub write_burst: u32,
+    /// Retries for failed reads. Orders are never resent.
+    pub max_retries: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub timeout_seconds: u64,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            read_requests_per_second: 10.0,
+            read_burst: 20,
+            write_requests_per_second: 5.0,
+            write_burst: 10,
+            max_retries: 3,
+            initial_backoff_ms: 250,
+            max_backoff_ms: 5000,
+            timeout_seconds: 30,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -32,6 +173,44 @@ pub struct BotConfig {
     pub max_position_size: f64,
     pub check_interval_seconds: u64,
     pub enable_execution: bool,
+    /// React to websocket order book updates instead of polling REST every
+    /// `check_interval_seconds`.
+    #[serde(default)]
+    pub streaming: bool,
+    /// How often the streaming engine re-lists markets and re-matches pairs.
+    #[serde(default = "default_market_refresh_seconds")]
+    pub market_refresh_seconds: u64,
+    /// When a venue's market listing fails, keep using its last good
+    /// listing for up to this long before skipping that venue's pairs.
+    #[serde(default = "default_max_market_staleness_seconds")]
+    pub max_market_staleness_seconds: u64,
+    /// How often each live order's status is polled.
+    #[serde(default = "default_order_poll_interval_ms")]
+    pub order_poll_interval_ms: u64,
+    /// Orders still resting after this many seconds are cancelled; zero
+    /// leaves them until the venue closes them.
+    #[serde(default = "default_order_timeout_seconds")]
+    pub order_timeout_seconds: u64,
+    /// Save every market listing and order book fetched over REST for
+    /// `--mode backtest` to replay.
+    #[serde(default)]
+    pub record_market_data: bool,
+}
+
+fn default_market_refresh_seconds() -> u64 {
+    300
+}
+
+fn default_max_market_staleness_seconds() -> u64 {
+    60
+}
+
+fn default_order_poll_interval_ms() -> u64 {
+    1000
+}
+
+fn default_order_timeout_seconds() -> u64 {
+    60
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -44,6 +223,181 @@ pub struct RiskConfig {
     pub max_daily_loss: f64,
     pub max_open_positions: usize,
     pub position_size_percentage: f64,
+    /// Most capital committed to any one venue market, in dollars; 0 means
+    /// no limit.
+    #[serde(default)]
+    pub max_market_exposure: f64,
+    /// Most capital committed across the markets of one event, in dollars;
+    /// 0 means no limit.
+    #[serde(default)]
+    pub max_event_exposure: f64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct FeesConfig {
+    #[serde(default)]
+    pub kalshi: KalshiFeeConfig,
+    #[serde(default)]
+    pub polymarket: PolymarketFeeConfig,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct KalshiFeeConfig {
+    pub taker_fee_rate: f64,
+    pub maker_fee_rate: f64,
+    pub fixed_fee_per_order: f64,
+}
+
+impl Default for KalshiFeeConfig {
+    fn default() -> Self {
+        Self {
+            taker_fee_rate: 0.07,
+            maker_fee_rate: 0.0175,
+            fixed_fee_per_order: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PolymarketFeeConfig {
+    pub taker_fee_bps: u32,
+    pub maker_fee_bps: u32,
+    /// Flat cost per order, e.g. gas for on-chain settlement.
+    pub fixed_fee_per_order: f64,
+}
+
+/// How arbitrage legs are sent and what happens when only one fills.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ExecutionConfig {
+    /// Both legs must be done within this many seconds; whatever is still
+    /// resting is then cancelled.
+    pub leg_timeout_seconds: u64,
+    pub leg_recovery: LegRecovery,
+    /// Chasing may pay at most this much per contract above the missing
+    /// leg's planned limit, and never more than would lose money against
+    /// the filled leg.
+    pub max_chase_slippage: f64,
+    /// Unwinding may sell at most this much per contract below the filled
+    /// leg's entry price.
+    pub max_unwind_slippage: f64,
+}
+
+impl Default for ExecutionConfig {
+    fn default() -> Self {
+        Self {
+            leg_timeout_seconds: 10,
+            leg_recovery: LegRecovery::Unwind,
+            max_chase_slippage: 0.02,
+            max_unwind_slippage: 0.05,
+        }
+    }
+}
+
+/// When trading halts by itself. A threshold of 0 disables that trip.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BreakerConfig {
+    /// Orders refused in a row, with nothing filling in between.
+    pub max_consecutive_rejections: u32,
+    /// Leg-risk events since midnight UTC or the last resume.
+    pub max_leg_risk_events: u32,
+    /// Share of a venue's last `error_window` calls that failed.
+    pub max_error_rate: f64,
+    pub error_window: usize,
+    /// Seconds a venue can go without a successful market fetch.
+    pub max_data_staleness_seconds: u64,
+    pub check_interval_ms: u64,
+    /// Trading halts while this file exists.
+    pub halt_file: String,
+    /// Creating this file resumes trading; it is removed once read.
+    pub resume_file: String,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            max_consecutive_rejections: 3,
+            max_leg_risk_events: 3,
+            max_error_rate: 0.5,
+            error_window: 20,
+            max_data_staleness_seconds: 300,
+            check_interval_ms: 1000,
+            halt_file: "HALT".to_string(),
+            resume_file: "RESUME".to_string(),
+        }
+    }
+}
+
+/// The simulated venues used by `--mode paper`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PaperConfig {
+    /// Cash each venue's paper account opens with, in dollars.
+    pub starting_balance: f64,
+    /// Delay between sending an order and matching it against the book.
+    pub latency_ms: u64,
+}
+
+impl Default for PaperConfig {
+    fn default() -> Self {
+        Self {
+            starting_balance: 1000.0,
+            latency_ms: 250,
+        }
+    }
+}
+
+/// How Polymarket and Kalshi markets are paired.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MatchingConfig {
+    /// TOML or JSON file of curated pairs and pairs never to match; a
+    /// missing file means fuzzy matching alone.
+    pub mapping_file: String,
+    /// Confidence, from 0 to 1, at which two titles whose structured
+    /// features agree are paired.
+    pub min_confidence: f64,
+    /// Markets closing further apart than this are never paired.
+    pub max_close_gap_days: f64,
+    /// How the titles' text similarity is scored.
+    pub scorers: ScorerWeights,
+}
+
+impl Default for MatchingConfig {
+    fn default() -> Self {
+        Self {
+            mapping_file: "config/pairs.toml".to_string(),
+            min_confidence: 0.7,
+            max_close_gap_days: 7.0,
+            scorers: ScorerWeights::default(),
+        }
+    }
+}
+
+/// Weight of each text similarity scorer in the matcher's text component.
+/// A zero weight leaves a scorer out.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ScorerWeights {
+    /// Shared words over all words.
+    pub jaccard: f64,
+    /// Cosine of the words weighted by how rare they are among open markets.
+    pub tfidf: f64,
+    /// Shared character trigrams, which see word forms and typos.
+    pub trigram: f64,
+}
+
+impl Default for ScorerWeights {
+    fn default() -> Self {
+        Self {
+            jaccard: 1.0,
+            tfidf: 0.0,
+            trigram: 0.0,
+        }
+    }
 }
 
 impl Config {
@@ -61,6 +415,12 @@ impl Config {
         if let Ok(val) = std::env::var("POLYMARKET_API_KEY") {
             self.polymarket.api_key = val;
         }
+        if let Ok(val) = std::env::var("POLYMARKET_API_SECRET") {
+            self.polymarket.api_secret = val;
+        }
+        if let Ok(val) = std::env::var("POLYMARKET_API_PASSPHRASE") {
+            self.polymarket.api_passphrase = val;
+        }
         if let Ok(val) = std::env::var("POLYMARKET_PRIVATE_KEY") {
             self.polymarket.private_key = val;
         }
@@ -70,8 +430,8 @@ impl Config {
         if let Ok(val) = std::env::var("KALSHI_API_KEY") {
             self.kalshi.api_key = val;
         }
-        if let Ok(val) = std::env::var("KALSHI_API_SECRET") {
-            self.kalshi.api_secret = val;
+        if let Ok(val) = std::env::var("KALSHI_PRIVATE_KEY_PATH") {
+            self.kalshi.private_key_path = val;
         }
         if let Ok(val) = std::env::var("MIN_PROFIT_PERCENTAGE") {
             if let Ok(num) = val.parse() {
@@ -0,0 +1,161 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::{sqlite::SqliteRow, Row};
+
+use super::Database;
+use crate::models::{MarketMatch, MatchStatus};
+
+/// Fuzzy-matched pairs awaiting, or carrying, an operator's decision.
+impl Database {
+    /// Records `candidate` as seen now: a new pair is saved as pending,
+    /// while a known one keeps its decision and has its questions, close
+    /// times, similarity, explanation and rules refreshed. Returns the pair as stored.
+    pub async fn record_match_candidate(&self, candidate: &MarketMatch) -> Result<MarketMatch> {
+        sqlx::query(
+            r#"
+            INSERT INTO market_matches (
+                polymarket_market_id,
+                kalshi_market_id,
+                polymarket_question,
+                kalshi_question,
+                polymarket_end_time,
+                kalshi_end_time,
+                similarity,
+                explanation,
+                polymarket_rules,
+                kalshi_rules,
+                resolution_risk,
+                status,
+                inverted,
+                first_seen_at,
+                last_seen_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 'pending', 0, ?, ?)
+            ON CONFLICT (polymarket_market_id, kalshi_market_id) DO UPDATE SET
+                polymarket_question = excluded.polymarket_question,
+                kalshi_question = excluded.kalshi_question,
+                polymarket_end_time = excluded.polymarket_end_time,
+                kalshi_end_time = excluded.kalshi_end_time,
+                similarity = excluded.similarity,
+                explanation = excluded.explanation,
+                polymarket_rules = excluded.polymarket_rules,
+                kalshi_rules = excluded.kalshi_rules,
+                resolution_risk = excluded.resolution_risk,
+                last_seen_at = excluded.last_seen_at
+            "#,
+        )
+        .bind(&candidate.polymarket_market_id)
+        .bind(&candidate.kalshi_market_id)
+        .bind(&candidate.polymarket_question)
+        .bind(&candidate.kalshi_question)
+        .bind(candidate.polymarket_end_time.to_rfc3339())
+        .bind(candidate.kalshi_end_time.to_rfc3339())
+        .bind(candidate.similarity)
+        .bind(&candidate.explanation)
+        .bind(&candidate.polymarket_rules)
+        .bind(&candidate.kalshi_rules)
+        .bind(&candidate.resolution_risk)
+        .bind(candidate.first_seen_at.to_rfc3339())
+        .bind(candidate.last_seen_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        let row = sqlx::query(
+            r#"
+            SELECT * FROM market_matches
+            WHERE polymarket_market_id = ? AND kalshi_market_id = ?
+            "#,
+        )
+        .bind(&candidate.polymarket_market_id)
+        .bind(&candidate.kalshi_market_id)
+        .fetch_one(&self.pool)
+        .await?;
+        market_match_from_row(&row)
+    }
+
+    /// Recorded pairs, best match first; only those with `status` if given.
+    pub async fn get_market_matches(
+        &self,
+        status: Option<MatchStatus>,
+    ) -> Result<Vec<MarketMatch>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM market_matches
+            WHERE ? IS NULL OR status = ?
+            ORDER BY similarity DESC, id
+            "#,
+        )
+        .bind(status.map(|status| status.as_str().to_string()))
+        .bind(status.map(|status| status.as_str().to_string()))
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(market_match_from_row).collect()
+    }
+
+    pub async fn get_market_match(&self, id: i64) -> Result<Option<MarketMatch>> {
+        let row = sqlx::query("SELECT * FROM market_matches WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.as_ref().map(market_match_from_row).transpose()
+    }
+
+    /// Sets pair `id`'s decision. Returns whether the pair exists.
+    pub async fn decide_market_match(
+        &self,
+        id: i64,
+        status: MatchStatus,
+        inverted: bool,
+        at: DateTime<Utc>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE market_matches
+            SET status = ?, inverted = ?, decided_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(status.as_str())
+        .bind(if inverted { 1 } else { 0 })
+        .bind(at.to_rfc3339())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+fn timestamp(row: &SqliteRow, column: &str) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(&row.get::<String, _>(column))?.with_timezone(&Utc))
+}
+
+fn market_match_from_row(row: &SqliteRow) -> Result<MarketMatch> {
+    Ok(MarketMatch {
+        id: Some(row.get("id")),
+        polymarket_market_id: row.get("polymarket_market_id"),
+        kalshi_market_id: row.get("kalshi_market_id"),
+        polymarket_question: row.get("polymarket_question"),
+        kalshi_question: row.get("kalshi_question"),
+        polymarket_end_time: timestamp(row, "polymarket_end_time")?,
+        kalshi_end_time: timestamp(row, "kalshi_end_time")?,
+        similarity: row.get("similarity"),
+        explanation: row.get("explanation"),
+        polymarket_rules: row.get("polymarket_rules"),
+        kalshi_rules: row.get("kalshi_rules"),
+        resolution_risk: row.get("resolution_risk"),
+        status: match row.get::<String, _>("status").as_str() {
+            "approved" => MatchStatus::Approved,
+            "rejected" => MatchStatus::Rejected,
+            _ => MatchStatus::Pending,
+        },
+        inverted: row.get::<i32, _>("inverted") == 1,
+        first_seen_at: timestamp(row, "first_seen_at")?,
+        last_seen_at: timestamp(row, "last_seen_at")?,
+        decided_at: row
+            .get::<Option<String>, _>("decided_at")
+            .map(|at| DateTime::parse_from_rfc3339(&at).map(|at| at.with_timezone(&Utc)))
+            .transpose()?,
+    })
+}
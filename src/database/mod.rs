@@ -1,8 +1,73 @@
 use anyhow::Result;
-use sqlx::{sqlite::SqlitePool, Row};
+use chrono::{DateTime, Utc};
+use sqlx::{
+    sqlite::{SqlitePool, SqliteRow},
+    Row,
+};
 
-use crate::models::ArbitrageOpportunity;
+use crate::models::{
+    ArbitrageOpportunity, BreakerTrip, LegRecovery, LegRiskEvent, OrderStatus, Outcome, Platform,
+    Trade, TradeSide, TripReason,
+};
 
+mod matches;
+mod paper;
+mod recording;
+
+/// Columns added to `trades` after its first release, created on databases
+/// that predate them.
+const TRADE_COLUMNS: &[(&str, &str)] = &[
+    ("order_id", "TEXT"),
+    ("outcome", "TEXT NOT NULL DEFAULT 'yes'"),
+    ("filled_amount", "TEXT NOT NULL DEFAULT '0'"),
+    ("fill_price", "TEXT"),
+    ("updated_at", "TEXT"),
+    ("client_order_id", "TEXT"),
+];
+
+/// Columns of `opportunities` renamed since its first release, old name
+/// first: the buy and sell legs became the YES and NO legs.
+const OPPORTUNITY_RENAMES: &[(&str, &str)] = &[
+    ("buy_platform", "yes_platform"),
+    ("sell_platform", "no_platform"),
+    ("buy_price", "yes_price"),
+    ("sell_price", "no_price"),
+];
+
+/// Columns added to `opportunities` after its first release.
+const OPPORTUNITY_COLUMNS: &[(&str, &str)] = &[
+    ("polymarket_token_id", "TEXT"),
+    ("yes_limit_price", "TEXT NOT NULL DEFAULT '0'"),
+    ("no_limit_price", "TEXT NOT NULL DEFAULT '0'"),
+    ("payout", "TEXT NOT NULL DEFAULT '1'"),
+    ("gross_edge", "TEXT NOT NULL DEFAULT '0'"),
+    ("total_fees", "TEXT NOT NULL DEFAULT '0'"),
+    ("net_edge", "TEXT NOT NULL DEFAULT '0'"),
+    ("kalshi_inverted", "INTEGER NOT NULL DEFAULT 0"),
+    ("polymarket_neg_risk", "INTEGER NOT NULL DEFAULT 0"),
+    ("kalshi_event_id", "TEXT"),
+    ("polymarket_condition_id", "TEXT"),
+];
+
+/// Added `opportunities` columns filled from an existing one: rows from
+/// before the order books were walked were quoted at their limit.
+const OPPORTUNITY_COPIES: &[(&str, &str)] = &[
+    ("yes_limit_price", "yes_price"),
+    ("no_limit_price", "no_price"),
+];
+
+/// Columns added to `market_matches` after its first release.
+const MATCH_COLUMNS: &[(&str, &str)] = &[
+    ("explanation", "TEXT NOT NULL DEFAULT ''"),
+    ("polymarket_rules", "TEXT"),
+    ("kalshi_rules", "TEXT"),
+    ("resolution_risk", "TEXT"),
+];
+
+/// Columns added to `market_snapshots` after its first release.
+const SNAPSHOT_COLUMNS: &[(&str, &str)] = &[("exclusive_events", "TEXT NOT NULL DEFAULT '[]'")];
+
+#[derive(Clone)]
 pub struct Database {
     pool: SqlitePool,
 }
@@ -20,15 +85,26 @@ impl Database {
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 polymarket_market_id TEXT NOT NULL,
                 kalshi_market_id TEXT NOT NULL,
-                buy_platform TEXT NOT NULL,
-                sell_platform TEXT NOT NULL,
-                buy_price TEXT NOT NULL,
-                sell_price TEXT NOT NULL,
+                polymarket_token_id TEXT,
+                polymarket_neg_risk INTEGER NOT NULL DEFAULT 0,
+                polymarket_condition_id TEXT,
+                yes_platform TEXT NOT NULL,
+                no_platform TEXT NOT NULL,
+                yes_price TEXT NOT NULL,
+                no_price TEXT NOT NULL,
+                yes_limit_price TEXT NOT NULL,
+                no_limit_price TEXT NOT NULL,
+                payout TEXT NOT NULL,
+                gross_edge TEXT NOT NULL,
+                total_fees TEXT NOT NULL,
+                net_edge TEXT NOT NULL,
                 profit_percentage TEXT NOT NULL,
                 estimated_profit TEXT NOT NULL,
                 position_size TEXT NOT NULL,
                 detected_at TEXT NOT NULL,
-                executed INTEGER NOT NULL DEFAULT 0
+                executed INTEGER NOT NULL DEFAULT 0,
+                kalshi_inverted INTEGER NOT NULL DEFAULT 0,
+                kalshi_event_id TEXT
             )
             "#,
         )
@@ -42,11 +118,17 @@ impl Database {
                 opportunity_id INTEGER NOT NULL,
                 platform TEXT NOT NULL,
                 market_id TEXT NOT NULL,
+                order_id TEXT,
+                client_order_id TEXT,
+                outcome TEXT NOT NULL DEFAULT 'yes',
                 side TEXT NOT NULL,
                 price TEXT NOT NULL,
                 amount TEXT NOT NULL,
+                filled_amount TEXT NOT NULL DEFAULT '0',
+                fill_price TEXT,
                 status TEXT NOT NULL,
                 created_at TEXT NOT NULL,
+                updated_at TEXT,
                 executed_at TEXT,
                 FOREIGN KEY (opportunity_id) REFERENCES opportunities(id)
             )
@@ -55,6 +137,259 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS leg_risk_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                opportunity_id INTEGER NOT NULL,
+                platform TEXT NOT NULL,
+                market_id TEXT NOT NULL,
+                outcome TEXT NOT NULL,
+                exposed_amount TEXT NOT NULL,
+                policy TEXT NOT NULL,
+                resolved_amount TEXT NOT NULL,
+                cost TEXT NOT NULL,
+                detected_at TEXT NOT NULL,
+                resolved_at TEXT NOT NULL,
+                FOREIGN KEY (opportunity_id) REFERENCES opportunities(id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS breaker_trips (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                reason TEXT NOT NULL,
+                detail TEXT NOT NULL,
+                tripped_at TEXT NOT NULL,
+                resumed_at TEXT
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Paper trading keeps its own account, apart from the live tables
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS paper_accounts (
+                platform TEXT PRIMARY KEY,
+                starting_balance TEXT NOT NULL,
+                balance TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS paper_orders (
+                id TEXT PRIMARY KEY,
+                platform TEXT NOT NULL,
+                market_id TEXT NOT NULL,
+                outcome TEXT NOT NULL,
+                side TEXT NOT NULL,
+                price TEXT NOT NULL,
+                size TEXT NOT NULL,
+                filled_size TEXT NOT NULL,
+                status TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS paper_fills (
+                id TEXT PRIMARY KEY,
+                order_id TEXT NOT NULL,
+                platform TEXT NOT NULL,
+                market_id TEXT NOT NULL,
+                outcome TEXT NOT NULL,
+                side TEXT NOT NULL,
+                price TEXT NOT NULL,
+                size TEXT NOT NULL,
+                fee TEXT NOT NULL,
+                filled_at TEXT NOT NULL,
+                FOREIGN KEY (order_id) REFERENCES paper_orders(id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS paper_positions (
+                platform TEXT NOT NULL,
+                market_id TEXT NOT NULL,
+                outcome TEXT NOT NULL,
+                amount TEXT NOT NULL,
+                entry_price TEXT NOT NULL,
+                PRIMARY KEY (platform, market_id, outcome)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Fuzzy-matched pairs and their review decisions
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS market_matches (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                polymarket_market_id TEXT NOT NULL,
+                kalshi_market_id TEXT NOT NULL,
+                polymarket_question TEXT NOT NULL,
+                kalshi_question TEXT NOT NULL,
+                polymarket_end_time TEXT NOT NULL,
+                kalshi_end_time TEXT NOT NULL,
+                similarity REAL NOT NULL,
+                explanation TEXT NOT NULL DEFAULT '',
+                polymarket_rules TEXT,
+                kalshi_rules TEXT,
+                resolution_risk TEXT,
+                status TEXT NOT NULL DEFAULT 'pending',
+                inverted INTEGER NOT NULL DEFAULT 0,
+                first_seen_at TEXT NOT NULL,
+                last_seen_at TEXT NOT NULL,
+                decided_at TEXT,
+                UNIQUE (polymarket_market_id, kalshi_market_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Market data captured for replay by the backtester
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS market_snapshots (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                platform TEXT NOT NULL,
+                recorded_at TEXT NOT NULL,
+                markets TEXT NOT NULL,
+                exclusive_events TEXT NOT NULL DEFAULT '[]'
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS book_snapshots (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                platform TEXT NOT NULL,
+                market_id TEXT NOT NULL,
+                recorded_at TEXT NOT NULL,
+                book TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE INDEX IF NOT EXISTS idx_book_snapshots_recorded
+            ON book_snapshots(recorded_at)
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        let existing: Vec<String> = sqlx::query("SELECT name FROM pragma_table_info('trades')")
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|row| row.get("name"))
+            .collect();
+        for (column, definition) in TRADE_COLUMNS {
+            if !existing.iter().any(|name| name == column) {
+                sqlx::query(&format!("ALTER TABLE trades ADD COLUMN {} {}", column, definition))
+                    .execute(&self.pool)
+                    .await?;
+            }
+        }
+
+        let existing: Vec<String> =
+            sqlx::query("SELECT name FROM pragma_table_info('opportunities')")
+                .fetch_all(&self.pool)
+                .await?
+                .iter()
+                .map(|row| row.get("name"))
+                .collect();
+        let has = |column: &str| existing.iter().any(|name| name == column);
+        for (old, new) in OPPORTUNITY_RENAMES {
+            if has(old) && !has(new) {
+                sqlx::query(&format!(
+                    "ALTER TABLE opportunities RENAME COLUMN {} TO {}",
+                    old, new
+                ))
+                .execute(&self.pool)
+                .await?;
+            }
+        }
+        for (column, definition) in OPPORTUNITY_COLUMNS {
+            if !has(column) {
+                sqlx::query(&format!(
+                    "ALTER TABLE opportunities ADD COLUMN {} {}",
+                    column, definition
+                ))
+                .execute(&self.pool)
+                .await?;
+
+                if let Some((_, source)) = OPPORTUNITY_COPIES.iter().find(|(to, _)| to == column) {
+                    sqlx::query(&format!("UPDATE opportunities SET {} = {}", column, source))
+                        .execute(&self.pool)
+                        .await?;
+                }
+            }
+        }
+
+        let existing: Vec<String> =
+            sqlx::query("SELECT name FROM pragma_table_info('market_matches')")
+                .fetch_all(&self.pool)
+                .await?
+                .iter()
+                .map(|row| row.get("name"))
+                .collect();
+        for (column, definition) in MATCH_COLUMNS {
+            if !existing.iter().any(|name| name == column) {
+                sqlx::query(&format!(
+                    "ALTER TABLE market_matches ADD COLUMN {} {}",
+                    column, definition
+                ))
+                .execute(&self.pool)
+                .await?;
+            }
+        }
+
+        let existing: Vec<String> =
+            sqlx::query("SELECT name FROM pragma_table_info('market_snapshots')")
+                .fetch_all(&self.pool)
+                .await?
+                .iter()
+                .map(|row| row.get("name"))
+                .collect();
+        for (column, definition) in SNAPSHOT_COLUMNS {
+            if !existing.iter().any(|name| name == column) {
+                sqlx::query(&format!(
+                    "ALTER TABLE market_snapshots ADD COLUMN {} {}",
+                    column, definition
+                ))
+                .execute(&self.pool)
+                .await?;
+            }
+        }
+
         sqlx::query(
             r#"
             CREATE INDEX IF NOT EXISTS idx_opportunities_detected 
@@ -73,29 +408,51 @@ impl Database {
             INSERT INTO opportunities (
                 polymarket_market_id,
                 kalshi_market_id,
-                buy_platform,
-                sell_platform,
-                buy_price,
-                sell_price,
+                polymarket_token_id,
+                polymarket_neg_risk,
+                polymarket_condition_id,
+                yes_platform,
+                no_platform,
+                yes_price,
+                no_price,
+                yes_limit_price,
+                no_limit_price,
+                payout,
+                gross_edge,
+                total_fees,
+                net_edge,
                 profit_percentage,
                 estimated_profit,
                 position_size,
                 detected_at,
-                executed
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                executed,
+                kalshi_inverted,
+                kalshi_event_id
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(&opportunity.polymarket_market_id)
         .bind(&opportunity.kalshi_market_id)
-        .bind(opportunity.buy_platform.as_str())
-        .bind(opportunity.sell_platform.as_str())
-        .bind(opportunity.buy_price.to_string())
-        .bind(opportunity.sell_price.to_string())
+        .bind(&opportunity.polymarket_token_id)
+        .bind(if opportunity.polymarket_neg_risk { 1 } else { 0 })
+        .bind(&opportunity.polymarket_condition_id)
+        .bind(opportunity.yes_platform.as_str())
+        .bind(opportunity.no_platform.as_str())
+        .bind(opportunity.yes_price.to_string())
+        .bind(opportunity.no_price.to_string())
+        .bind(opportunity.yes_limit_price.to_string())
+        .bind(opportunity.no_limit_price.to_string())
+        .bind(opportunity.payout.to_string())
+        .bind(opportunity.gross_edge.to_string())
+        .bind(opportunity.total_fees.to_string())
+        .bind(opportunity.net_edge.to_string())
         .bind(opportunity.profit_percentage.to_string())
         .bind(opportunity.estimated_profit.to_string())
         .bind(opportunity.position_size.to_string())
         .bind(opportunity.detected_at.to_rfc3339())
         .bind(if opportunity.executed { 1 } else { 0 })
+        .bind(if opportunity.kalshi_inverted { 1 } else { 0 })
+        .bind(&opportunity.kalshi_event_id)
         .execute(&self.pool)
         .await?;
 
@@ -120,22 +477,33 @@ impl Database {
                 id: Some(row.get("id")),
                 polymarket_market_id: row.get("polymarket_market_id"),
                 kalshi_market_id: row.get("kalshi_market_id"),
-                buy_platform: match row.get::<String, _>("buy_platform").as_str() {
+                polymarket_token_id: row.get("polymarket_token_id"),
+                polymarket_neg_risk: row.get::<i32, _>("polymarket_neg_risk") == 1,
+                polymarket_condition_id: row.get("polymarket_condition_id"),
+                yes_platform: match row.get::<String, _>("yes_platform").as_str() {
                     "polymarket" => crate::models::Platform::Polymarket,
                     _ => crate::models::Platform::Kalshi,
                 },
-                sell_platform: match row.get::<String, _>("sell_platform").as_str() {
+                no_platform: match row.get::<String, _>("no_platform").as_str() {
                     "polymarket" => crate::models::Platform::Polymarket,
                     _ => crate::models::Platform::Kalshi,
                 },
-                buy_price: row.get::<String, _>("buy_price").parse()?,
-                sell_price: row.get::<String, _>("sell_price").parse()?,
+                yes_price: row.get::<String, _>("yes_price").parse()?,
+                no_price: row.get::<String, _>("no_price").parse()?,
+                yes_limit_price: row.get::<String, _>("yes_limit_price").parse()?,
+                no_limit_price: row.get::<String, _>("no_limit_price").parse()?,
+                payout: row.get::<String, _>("payout").parse()?,
+                gross_edge: row.get::<String, _>("gross_edge").parse()?,
+                total_fees: row.get::<String, _>("total_fees").parse()?,
+                net_edge: row.get::<String, _>("net_edge").parse()?,
                 profit_percentage: row.get::<String, _>("profit_percentage").parse()?,
                 estimated_profit: row.get::<String, _>("estimated_profit").parse()?,
                 position_size: row.get::<String, _>("position_size").parse()?,
                 detected_at: chrono::DateTime::parse_from_rfc3339(&row.get::<String, _>("detected_at"))?
                     .with_timezone(&chrono::Utc),
                 executed: row.get::<i32, _>("executed") == 1,
+                kalshi_inverted: row.get::<i32, _>("kalshi_inverted") == 1,
+                kalshi_event_id: row.get("kalshi_event_id"),
             });
         }
 
@@ -156,4 +524,373 @@ impl Database {
 
         Ok(())
     }
+
+    pub async fn save_trade(&self, trade: &Trade) -> Result<i64> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO trades (
+                opportunity_id,
+                platform,
+                market_id,
+                order_id,
+                client_order_id,
+                outcome,
+                side,
+                price,
+                amount,
+                filled_amount,
+                fill_price,
+                status,
+                created_at,
+                updated_at,
+                executed_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(trade.opportunity_id)
+        .bind(trade.platform.as_str())
+        .bind(&trade.market_id)
+        .bind(&trade.order_id)
+        .bind(&trade.client_order_id)
+        .bind(trade.outcome.as_str())
+        .bind(trade.side.as_str())
+        .bind(trade.price.to_string())
+        .bind(trade.amount.to_string())
+        .bind(trade.filled_amount.to_string())
+        .bind(trade.fill_price.map(|price| price.to_string()))
+        .bind(trade.status.as_str())
+        .bind(trade.created_at.to_rfc3339())
+        .bind(trade.updated_at.to_rfc3339())
+        .bind(trade.executed_at.map(|time| time.to_rfc3339()))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Records the latest status and fills of a saved trade, and its order
+    /// ID once a pending placement is confirmed.
+    pub async fn update_trade_status(&self, trade: &Trade) -> Result<()> {
+        let id = trade
+            .id
+            .ok_or_else(|| anyhow::anyhow!("Trade for order {:?} was never saved", trade.order_id))?;
+
+        sqlx::query(
+            r#"
+            UPDATE trades
+            SET order_id = ?, status = ?, filled_amount = ?, fill_price = ?, updated_at = ?,
+                executed_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(&trade.order_id)
+        .bind(trade.status.as_str())
+        .bind(trade.filled_amount.to_string())
+        .bind(trade.fill_price.map(|price| price.to_string()))
+        .bind(trade.updated_at.to_rfc3339())
+        .bind(trade.executed_at.map(|time| time.to_rfc3339()))
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Every order sent for an opportunity, oldest first.
+    pub async fn get_trades_for_opportunity(&self, opportunity_id: i64) -> Result<Vec<Trade>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM trades
+            WHERE opportunity_id = ?
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(opportunity_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(trade_from_row).collect()
+    }
+
+    /// Orders sent from `from` up to but excluding `to`, oldest first.
+    pub async fn get_trades_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Trade>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM trades
+            WHERE created_at >= ? AND created_at < ?
+            ORDER BY created_at, id
+            "#,
+        )
+        .bind(from.to_rfc3339())
+        .bind(to.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(trade_from_row).collect()
+    }
+
+    pub async fn save_leg_risk_event(&self, event: &LegRiskEvent) -> Result<i64> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO leg_risk_events (
+                opportunity_id,
+                platform,
+                market_id,
+                outcome,
+                exposed_amount,
+                policy,
+                resolved_amount,
+                cost,
+                detected_at,
+                resolved_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(event.opportunity_id)
+        .bind(event.platform.as_str())
+        .bind(&event.market_id)
+        .bind(event.outcome.as_str())
+        .bind(event.exposed_amount.to_string())
+        .bind(event.policy.as_str())
+        .bind(event.resolved_amount.to_string())
+        .bind(event.cost.to_string())
+        .bind(event.detected_at.to_rfc3339())
+        .bind(event.resolved_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn get_recent_leg_risk_events(&self, limit: i64) -> Result<Vec<LegRiskEvent>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM leg_risk_events
+            ORDER BY detected_at DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(leg_risk_event_from_row).collect()
+    }
+
+    /// Leg-risk events detected at or after `from`, oldest first.
+    pub async fn get_leg_risk_events_since(
+        &self,
+        from: DateTime<Utc>,
+    ) -> Result<Vec<LegRiskEvent>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM leg_risk_events
+            WHERE detected_at >= ?
+            ORDER BY detected_at, id
+            "#,
+        )
+        .bind(from.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(leg_risk_event_from_row).collect()
+    }
+
+    /// Every Polymarket/Kalshi market pair an opportunity was recorded for,
+    /// the Polymarket market by the ID its positions are kept under.
+    pub async fn get_market_pairs(&self) -> Result<Vec<(String, String)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT DISTINCT
+                COALESCE(polymarket_condition_id, polymarket_market_id) AS polymarket_market_id,
+                kalshi_market_id
+            FROM opportunities
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| (row.get("polymarket_market_id"), row.get("kalshi_market_id")))
+            .collect())
+    }
+
+    /// The event ticker recorded for each Kalshi market an opportunity
+    /// was found in.
+    pub async fn get_kalshi_events(&self) -> Result<Vec<(String, String)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT DISTINCT kalshi_market_id, kalshi_event_id FROM opportunities
+            WHERE kalshi_event_id IS NOT NULL
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| (row.get("kalshi_market_id"), row.get("kalshi_event_id")))
+            .collect())
+    }
+
+    pub async fn save_breaker_trip(&self, trip: &BreakerTrip) -> Result<i64> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO breaker_trips (reason, detail, tripped_at, resumed_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(trip.reason.as_str())
+        .bind(&trip.detail)
+        .bind(trip.tripped_at.to_rfc3339())
+        .bind(trip.resumed_at.map(|time| time.to_rfc3339()))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// The earliest trip not yet resumed, if trading is halted.
+    pub async fn get_active_breaker_trip(&self) -> Result<Option<BreakerTrip>> {
+        let row = sqlx::query(
+            r#"
+            SELECT * FROM breaker_trips
+            WHERE resumed_at IS NULL
+            ORDER BY tripped_at, id
+            LIMIT 1
+            "#,
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        Ok(Some(BreakerTrip {
+            id: Some(row.get("id")),
+            reason: match row.get::<String, _>("reason").as_str() {
+                "consecutive_rejections" => TripReason::ConsecutiveRejections,
+                "leg_risk" => TripReason::LegRisk,
+                "daily_loss" => TripReason::DailyLoss,
+                "venue_errors" => TripReason::VenueErrors,
+                "stale_data" => TripReason::StaleData,
+                _ => TripReason::Manual,
+            },
+            detail: row.get("detail"),
+            tripped_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("tripped_at"))?
+                .with_timezone(&Utc),
+            resumed_at: None,
+        }))
+    }
+
+    /// Marks every open trip resumed at `at` and returns how many there were.
+    pub async fn resume_breaker_trips(&self, at: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE breaker_trips SET resumed_at = ?
+            WHERE resumed_at IS NULL
+            "#,
+        )
+        .bind(at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// When trading was last resumed, if it ever was.
+    pub async fn get_last_breaker_resume(&self) -> Result<Option<DateTime<Utc>>> {
+        let resumed_at: Option<String> =
+            sqlx::query_scalar("SELECT MAX(resumed_at) FROM breaker_trips")
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(match resumed_at {
+            Some(time) => Some(DateTime::parse_from_rfc3339(&time)?.with_timezone(&Utc)),
+            None => None,
+        })
+    }
+}
+
+fn leg_risk_event_from_row(row: &SqliteRow) -> Result<LegRiskEvent> {
+    Ok(LegRiskEvent {
+        id: Some(row.get("id")),
+        opportunity_id: row.get("opportunity_id"),
+        platform: match row.get::<String, _>("platform").as_str() {
+            "polymarket" => Platform::Polymarket,
+            _ => Platform::Kalshi,
+        },
+        market_id: row.get("market_id"),
+        outcome: match row.get::<String, _>("outcome").as_str() {
+            "yes" => Outcome::Yes,
+            _ => Outcome::No,
+        },
+        exposed_amount: row.get::<String, _>("exposed_amount").parse()?,
+        policy: match row.get::<String, _>("policy").as_str() {
+            "chase" => LegRecovery::Chase,
+            "unwind" => LegRecovery::Unwind,
+            _ => LegRecovery::Hold,
+        },
+        resolved_amount: row.get::<String, _>("resolved_amount").parse()?,
+        cost: row.get::<String, _>("cost").parse()?,
+        detected_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("detected_at"))?
+            .with_timezone(&Utc),
+        resolved_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("resolved_at"))?
+            .with_timezone(&Utc),
+    })
+}
+
+fn trade_from_row(row: &SqliteRow) -> Result<Trade> {
+    let timestamp = |column: &str| -> Result<Option<DateTime<Utc>>> {
+        row.get::<Option<String>, _>(column)
+            .map(|time| Ok(DateTime::parse_from_rfc3339(&time)?.with_timezone(&Utc)))
+            .transpose()
+    };
+    let created_at = timestamp("created_at")?.unwrap_or_default();
+
+    Ok(Trade {
+        id: Some(row.get("id")),
+        opportunity_id: row.get("opportunity_id"),
+        platform: match row.get::<String, _>("platform").as_str() {
+            "polymarket" => Platform::Polymarket,
+            _ => Platform::Kalshi,
+        },
+        market_id: row.get("market_id"),
+        order_id: row.get("order_id"),
+        client_order_id: row.get("client_order_id"),
+        outcome: match row.get::<String, _>("outcome").as_str() {
+            "yes" => Outcome::Yes,
+            _ => Outcome::No,
+        },
+        side: match row.get::<String, _>("side").as_str() {
+            "sell" => TradeSide::Sell,
+            _ => TradeSide::Buy,
+        },
+        price: row.get::<String, _>("price").parse()?,
+        amount: row.get::<String, _>("amount").parse()?,
+        filled_amount: row.get::<String, _>("filled_amount").parse()?,
+        fill_price: row
+            .get::<Option<String>, _>("fill_price")
+            .map(|price| price.parse())
+            .transpose()?,
+        status: match row.get::<String, _>("status").as_str() {
+            "pending" => OrderStatus::Pending,
+            "new" => OrderStatus::New,
+            "partially_filled" => OrderStatus::PartiallyFilled,
+            "filled" => OrderStatus::Filled,
+            "cancelled" => OrderStatus::Cancelled,
+            "rejected" => OrderStatus::Rejected,
+            "expired" => OrderStatus::Expired,
+            other => anyhow::bail!("Unknown trade status: {}", other),
+        },
+        created_at,
+        updated_at: timestamp("updated_at")?.unwrap_or(created_at),
+        executed_at: timestamp("executed_at")?,
+    })
 }
@@ -0,0 +1,304 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sqlx::{sqlite::SqliteRow, Row};
+
+use super::Database;
+use crate::models::{
+    Fill, Order, OrderStatus, Outcome, PaperAccount, Platform, Position, TradeSide,
+};
+
+/// Paper trading's simulated account: balances, orders, fills and
+/// positions, kept in `paper_*` tables apart from live trading.
+impl Database {
+    /// Creates `platform`'s paper account with `starting_balance` unless it
+    /// already exists.
+    pub async fn open_paper_account(
+        &self,
+        platform: &Platform,
+        starting_balance: Decimal,
+    ) -> Result<PaperAccount> {
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO paper_accounts (platform, starting_balance, balance)
+            VALUES (?, ?, ?)
+            "#,
+        )
+        .bind(platform.as_str())
+        .bind(starting_balance.to_string())
+        .bind(starting_balance.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        let row = sqlx::query("SELECT * FROM paper_accounts WHERE platform = ?")
+            .bind(platform.as_str())
+            .fetch_one(&self.pool)
+            .await?;
+        paper_account_from_row(&row)
+    }
+
+    pub async fn get_paper_accounts(&self) -> Result<Vec<PaperAccount>> {
+        let rows = sqlx::query("SELECT * FROM paper_accounts ORDER BY platform")
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter().map(paper_account_from_row).collect()
+    }
+
+    /// Inserts `order`, or replaces the row with the same ID.
+    pub async fn save_paper_order(&self, order: &Order) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO paper_orders (
+                id,
+                platform,
+                market_id,
+                outcome,
+                side,
+                price,
+                size,
+                filled_size,
+                status,
+                created_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&order.id)
+        .bind(order.platform.as_str())
+        .bind(&order.market_id)
+        .bind(order.outcome.as_str())
+        .bind(order.side.as_str())
+        .bind(order.price.to_string())
+        .bind(order.size.to_string())
+        .bind(order.filled_size.to_string())
+        .bind(order.status.as_str())
+        .bind(order.created_at.unwrap_or_else(Utc::now).to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_paper_order(&self, order_id: &str) -> Result<Option<Order>> {
+        let row = sqlx::query("SELECT * FROM paper_orders WHERE id = ?")
+            .bind(order_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.as_ref().map(paper_order_from_row).transpose()
+    }
+
+    /// Fills of one paper order, oldest first.
+    pub async fn get_paper_fills(&self, order_id: &str) -> Result<Vec<Fill>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM paper_fills
+            WHERE order_id = ?
+            ORDER BY filled_at, id
+            "#,
+        )
+        .bind(order_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(paper_fill_from_row).collect()
+    }
+
+    /// How many paper fills there have been and the fees they paid.
+    pub async fn get_paper_fill_summary(&self) -> Result<(usize, Decimal)> {
+        let rows = sqlx::query("SELECT fee FROM paper_fills")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut fees = Decimal::ZERO;
+        for row in &rows {
+            fees += row.get::<String, _>("fee").parse::<Decimal>()?;
+        }
+        Ok((rows.len(), fees))
+    }
+
+    /// Open paper positions on `platform`, valued at cost.
+    pub async fn get_paper_positions(&self, platform: &Platform) -> Result<Vec<Position>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM paper_positions
+            WHERE platform = ?
+            ORDER BY market_id, outcome
+            "#,
+        )
+        .bind(platform.as_str())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let positions = rows
+            .iter()
+            .map(paper_position_from_row)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(positions
+            .into_iter()
+            .filter(|position| position.amount > Decimal::ZERO)
+            .collect())
+    }
+
+    /// Records `fill` against `order` in one transaction: the order's new
+    /// filled size and status, the fill and its `fee`, the account's new
+    /// `balance` and the resulting `position`.
+    pub async fn record_paper_fill(
+        &self,
+        order: &Order,
+        fill: &Fill,
+        fee: Decimal,
+        balance: Decimal,
+        position: &Position,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("UPDATE paper_orders SET filled_size = ?, status = ? WHERE id = ?")
+            .bind(order.filled_size.to_string())
+            .bind(order.status.as_str())
+            .bind(&order.id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO paper_fills (
+                id,
+                order_id,
+                platform,
+                market_id,
+                outcome,
+                side,
+                price,
+                size,
+                fee,
+                filled_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&fill.id)
+        .bind(&fill.order_id)
+        .bind(fill.platform.as_str())
+        .bind(&fill.market_id)
+        .bind(fill.outcome.as_str())
+        .bind(fill.side.as_str())
+        .bind(fill.price.to_string())
+        .bind(fill.size.to_string())
+        .bind(fee.to_string())
+        .bind(fill.filled_at.unwrap_or_else(Utc::now).to_rfc3339())
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE paper_accounts SET balance = ? WHERE platform = ?")
+            .bind(balance.to_string())
+            .bind(order.platform.as_str())
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO paper_positions (
+                platform,
+                market_id,
+                outcome,
+                amount,
+                entry_price
+            ) VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(position.platform.as_str())
+        .bind(&position.market_id)
+        .bind(position.outcome.as_str())
+        .bind(position.amount.to_string())
+        .bind(position.entry_price.to_string())
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+fn platform(row: &SqliteRow) -> Platform {
+    match row.get::<String, _>("platform").as_str() {
+        "polymarket" => Platform::Polymarket,
+        _ => Platform::Kalshi,
+    }
+}
+
+fn outcome(row: &SqliteRow) -> Outcome {
+    match row.get::<String, _>("outcome").as_str() {
+        "yes" => Outcome::Yes,
+        _ => Outcome::No,
+    }
+}
+
+fn side(row: &SqliteRow) -> TradeSide {
+    match row.get::<String, _>("side").as_str() {
+        "sell" => TradeSide::Sell,
+        _ => TradeSide::Buy,
+    }
+}
+
+fn timestamp(row: &SqliteRow, column: &str) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(&row.get::<String, _>(column))?.with_timezone(&Utc))
+}
+
+fn paper_account_from_row(row: &SqliteRow) -> Result<PaperAccount> {
+    Ok(PaperAccount {
+        platform: platform(row),
+        starting_balance: row.get::<String, _>("starting_balance").parse()?,
+        balance: row.get::<String, _>("balance").parse()?,
+    })
+}
+
+fn paper_order_from_row(row: &SqliteRow) -> Result<Order> {
+    Ok(Order {
+        id: row.get("id"),
+        platform: platform(row),
+        market_id: row.get("market_id"),
+        outcome: outcome(row),
+        side: side(row),
+        price: row.get::<String, _>("price").parse()?,
+        size: row.get::<String, _>("size").parse()?,
+        filled_size: row.get::<String, _>("filled_size").parse()?,
+        status: match row.get::<String, _>("status").as_str() {
+            "new" => OrderStatus::New,
+            "partially_filled" => OrderStatus::PartiallyFilled,
+            "filled" => OrderStatus::Filled,
+            "cancelled" => OrderStatus::Cancelled,
+            "rejected" => OrderStatus::Rejected,
+            "expired" => OrderStatus::Expired,
+            other => anyhow::bail!("Unknown paper order status: {}", other),
+        },
+        created_at: Some(timestamp(row, "created_at")?),
+    })
+}
+
+fn paper_fill_from_row(row: &SqliteRow) -> Result<Fill> {
+    Ok(Fill {
+        id: row.get("id"),
+        order_id: row.get("order_id"),
+        platform: platform(row),
+        market_id: row.get("market_id"),
+        outcome: outcome(row),
+        side: side(row),
+        price: row.get::<String, _>("price").parse()?,
+        size: row.get::<String, _>("size").parse()?,
+        filled_at: Some(timestamp(row, "filled_at")?),
+    })
+}
+
+fn paper_position_from_row(row: &SqliteRow) -> Result<Position> {
+    let amount: Decimal = row.get::<String, _>("amount").parse()?;
+    let entry_price: Decimal = row.get::<String, _>("entry_price").parse()?;
+
+    Ok(Position {
+        platform: platform(row),
+        market_id: row.get("market_id"),
+        outcome: outcome(row),
+        amount,
+        entry_price,
+        current_value: amount * entry_price,
+    })
+}
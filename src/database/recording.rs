@@ -0,0 +1,105 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::Row;
+
+use super::Database;
+use crate::models::{MarketRecording, OrderBook, Platform};
+
+/// Market listings and order books captured for the backtester, stored as
+/// JSON with the time they were fetched.
+impl Database {
+    pub async fn save_market_recording(&self, recording: &MarketRecording) -> Result<i64> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO market_snapshots (platform, recorded_at, markets, exclusive_events)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(recording.platform.as_str())
+        .bind(recording.recorded_at.to_rfc3339())
+        .bind(serde_json::to_string(&recording.markets)?)
+        .bind(serde_json::to_string(&recording.exclusive_events)?)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Saves `book` as recorded at its `timestamp`.
+    pub async fn save_book_recording(&self, book: &OrderBook) -> Result<i64> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO book_snapshots (platform, market_id, recorded_at, book)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(book.platform.as_str())
+        .bind(&book.market_id)
+        .bind(book.timestamp.to_rfc3339())
+        .bind(serde_json::to_string(book)?)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    /// Listings recorded from `from` up to but excluding `to`, oldest first.
+    pub async fn get_market_recordings(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<MarketRecording>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM market_snapshots
+            WHERE recorded_at >= ? AND recorded_at < ?
+            ORDER BY recorded_at, id
+            "#,
+        )
+        .bind(from.to_rfc3339())
+        .bind(to.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut recordings = Vec::new();
+        for row in rows {
+            recordings.push(MarketRecording {
+                platform: match row.get::<String, _>("platform").as_str() {
+                    "polymarket" => Platform::Polymarket,
+                    _ => Platform::Kalshi,
+                },
+                recorded_at: DateTime::parse_from_rfc3339(&row.get::<String, _>("recorded_at"))?
+                    .with_timezone(&Utc),
+                markets: serde_json::from_str(&row.get::<String, _>("markets"))?,
+                exclusive_events: serde_json::from_str(
+                    &row.get::<String, _>("exclusive_events"),
+                )?,
+            });
+        }
+
+        Ok(recordings)
+    }
+
+    /// Books recorded from `from` up to but excluding `to`, oldest first.
+    pub async fn get_book_recordings(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<OrderBook>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT book FROM book_snapshots
+            WHERE recorded_at >= ? AND recorded_at < ?
+            ORDER BY recorded_at, id
+            "#,
+        )
+        .bind(from.to_rfc3339())
+        .bind(to.to_rfc3339())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| Ok(serde_json::from_str(&row.get::<String, _>("book"))?))
+            .collect()
+    }
+}
@@ -0,0 +1,657 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use log::{error, info, warn};
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::sync::Arc;
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    time::{timeout_at, Duration, Instant},
+};
+
+use super::tracker::OrderTracker;
+use crate::{
+    api::{ApiError, Exchange},
+    config::ExecutionConfig,
+    database::Database,
+    models::{
+        average_fill_price, ArbitrageOpportunity, LegRecovery, LegRiskEvent, OrderRequest,
+        OrderStatus, Outcome, Platform, Trade, TradeSide, TripReason,
+    },
+    risk::CircuitBreaker,
+};
+
+/// Cheapest and dearest prices either venue accepts.
+const MIN_PRICE: Decimal = Decimal::from_parts(1, 0, 0, false, 2);
+const MAX_PRICE: Decimal = Decimal::from_parts(99, 0, 0, false, 2);
+
+/// What became of an opportunity's orders.
+#[derive(Debug, Clone)]
+pub struct ExecutionReport {
+    /// Last known state of every order sent, recovery orders included.
+    pub trades: Vec<Trade>,
+    pub leg_risk: Option<LegRiskEvent>,
+    /// Both outcomes ended up holding the full position size.
+    pub executed: bool,
+}
+
+/// Sends both legs of an arbitrage and repairs the position when they do
+/// not fill alike.
+///
+/// Both legs go out at once and get `leg_timeout_seconds` to finish, after
+/// which whatever still rests is cancelled. If the legs then hold different
+/// numbers of contracts, the difference is a leg-risk event: depending on
+/// `leg_recovery` the missing contracts are bought at a capped price, the
+/// surplus is sold, or the position is held and an alert logged. Every
+/// event is saved with what recovery achieved and cost.
+///
+/// An order whose placement fails in a way that may still have reached the
+/// venue is recorded as pending and looked up by its client order ID. If
+/// the venue cannot say whether it took it, nothing is recovered and the
+/// circuit breaker trips for an operator to reconcile the position.
+pub struct ExecutionCoordinator {
+    polymarket: Arc<dyn Exchange>,
+    kalshi: Arc<dyn Exchange>,
+    tracker: OrderTracker,
+    database: Database,
+    config: ExecutionConfig,
+    breaker: Option<CircuitBreaker>,
+}
+
+impl ExecutionCoordinator {
+    pub fn new(
+        polymarket: Arc<dyn Exchange>,
+        kalshi: Arc<dyn Exchange>,
+        tracker: OrderTracker,
+        database: Database,
+        config: &ExecutionConfig,
+    ) -> Self {
+        Self {
+            polymarket,
+            kalshi,
+            tracker,
+            database,
+            config: config.clone(),
+            breaker: None,
+        }
+    }
+
+    pub fn with_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = Some(breaker);
+        self
+    }
+
+    /// Executes `opportunity`, which is saved as `opportunity_id`. A leg
+    /// the venue refused is returned as the error once the other leg has
+    /// been dealt with; an unconfirmed leg is an error before any recovery.
+    pub async fn execute(
+        &self,
+        opportunity: &ArbitrageOpportunity,
+        opportunity_id: i64,
+    ) -> Result<ExecutionReport> {
+        info!("Executing arbitrage opportunity: {:#?}", opportunity);
+
+        // Subscribed before placing so that no update is missed
+        let mut updates = self.tracker.subscribe();
+
+        let yes = leg_request(
+            opportunity,
+            &opportunity.yes_platform,
+            Outcome::Yes,
+            TradeSide::Buy,
+            opportunity.yes_limit_price,
+            opportunity.position_size,
+        );
+        let no = leg_request(
+            opportunity,
+            &opportunity.no_platform,
+            Outcome::No,
+            TradeSide::Buy,
+            opportunity.no_limit_price,
+            opportunity.position_size,
+        );
+        let (yes, no) = tokio::join!(
+            self.place(opportunity_id, &opportunity.yes_platform, yes),
+            self.place(opportunity_id, &opportunity.no_platform, no),
+        );
+
+        let mut placement_error = None;
+        let mut legs = Vec::new();
+        for result in [yes, no] {
+            match result {
+                Ok(trade) => legs.push(trade),
+                Err(e) => {
+                    placement_error.get_or_insert(e);
+                }
+            }
+        }
+
+        // What an unconfirmed leg holds is unknown, so recovering would be
+        // a guess; the breaker has tripped for an operator to reconcile it
+        let (mut legs, unconfirmed): (Vec<Trade>, Vec<Trade>) = legs
+            .into_iter()
+            .partition(|leg| leg.status != OrderStatus::Pending);
+        self.settle(&mut updates, &mut legs).await;
+        if let Some(leg) = unconfirmed.first() {
+            return Err(anyhow!(
+                "{} order for opportunity {} is unconfirmed; not recovering",
+                leg.platform.as_str(),
+                opportunity_id
+            ));
+        }
+
+        let yes_filled = net_filled(opportunity, &legs, Outcome::Yes);
+        let no_filled = net_filled(opportunity, &legs, Outcome::No);
+
+        let mut report = ExecutionReport {
+            trades: legs.clone(),
+            leg_risk: None,
+            executed: false,
+        };
+
+        if yes_filled != no_filled {
+            let exposed_outcome = if yes_filled > no_filled {
+                Outcome::Yes
+            } else {
+                Outcome::No
+            };
+            let exposed = legs
+                .iter()
+                .find(|leg| {
+                    opportunity.venue_outcome(&leg.platform, leg.outcome) == exposed_outcome
+                })
+                .cloned()
+                .expect("the leg with more contracts was placed");
+
+            let event = self
+                .recover(
+                    opportunity,
+                    opportunity_id,
+                    &exposed,
+                    (yes_filled - no_filled).abs(),
+                    &mut updates,
+                    &mut report.trades,
+                )
+                .await;
+            report.leg_risk = Some(event);
+        }
+
+        report.executed = [Outcome::Yes, Outcome::No]
+            .iter()
+            .all(|outcome| {
+                net_filled(opportunity, &report.trades, *outcome) == opportunity.position_size
+            });
+        if report.executed {
+            if let Err(e) = self.database.mark_opportunity_executed(opportunity_id).await {
+                error!("Failed to mark opportunity {} executed: {}", opportunity_id, e);
+            }
+        }
+
+        match placement_error {
+            Some(e) => Err(e.into()),
+            None => Ok(report),
+        }
+    }
+
+    /// Applies the configured recovery to `excess` unhedged contracts of
+    /// `exposed`'s outcome and records the event.
+    async fn recover(
+        &self,
+        opportunity: &ArbitrageOpportunity,
+        opportunity_id: i64,
+        exposed: &Trade,
+        excess: Decimal,
+        updates: &mut broadcast::Receiver<Trade>,
+        trades: &mut Vec<Trade>,
+    ) -> LegRiskEvent {
+        let detected_at = Utc::now();
+        let policy = self.config.leg_recovery;
+        warn!(
+            "Leg risk on opportunity {}: {} unhedged {} contracts on {}; recovering with {}",
+            opportunity_id,
+            excess,
+            exposed.outcome.as_str(),
+            exposed.platform.as_str(),
+            policy.as_str()
+        );
+
+        let entry_price = exposed.fill_price.unwrap_or(exposed.price);
+        let exposed_outcome = opportunity.venue_outcome(&exposed.platform, exposed.outcome);
+        let (resolved_amount, cost) = match policy {
+            LegRecovery::Chase => {
+                let (platform, outcome, planned_price) = match exposed_outcome {
+                    Outcome::Yes => (
+                        &opportunity.no_platform,
+                        Outcome::No,
+                        opportunity.no_limit_price,
+                    ),
+                    Outcome::No => (
+                        &opportunity.yes_platform,
+                        Outcome::Yes,
+                        opportunity.yes_limit_price,
+                    ),
+                };
+                let slippage =
+                    Decimal::try_from(self.config.max_chase_slippage).unwrap_or_default();
+                // Paying more than this would lose money on the pair
+                let break_even = opportunity.payout - entry_price;
+                let cap = (planned_price + slippage)
+                    .min(break_even)
+                    .round_dp_with_strategy(2, RoundingStrategy::ToZero)
+                    .min(MAX_PRICE);
+
+                if cap < MIN_PRICE {
+                    warn!("No price below break-even to chase at");
+                    (Decimal::ZERO, Decimal::ZERO)
+                } else {
+                    let request = leg_request(
+                        opportunity,
+                        platform,
+                        outcome,
+                        TradeSide::Buy,
+                        cap,
+                        tradable_size(platform, excess),
+                    );
+                    let (filled, price) = self
+                        .recovery_order(opportunity_id, platform, request, updates, trades)
+                        .await;
+                    (filled, (price - planned_price) * filled)
+                }
+            }
+            LegRecovery::Unwind => {
+                let slippage =
+                    Decimal::try_from(self.config.max_unwind_slippage).unwrap_or_default();
+                let floor = (entry_price - slippage)
+                    .round_dp_with_strategy(2, RoundingStrategy::AwayFromZero)
+                    .clamp(MIN_PRICE, MAX_PRICE);
+
+                let request = leg_request(
+                    opportunity,
+                    &exposed.platform,
+                    exposed_outcome,
+                    TradeSide::Sell,
+                    floor,
+                    tradable_size(&exposed.platform, excess),
+                );
+                let (filled, price) = self
+                    .recovery_order(opportunity_id, &exposed.platform, request, updates, trades)
+                    .await;
+                (filled, (entry_price - price) * filled)
+            }
+            LegRecovery::Hold => (Decimal::ZERO, Decimal::ZERO),
+        };
+
+        let mut event = LegRiskEvent {
+            id: None,
+            opportunity_id,
+            platform: exposed.platform.clone(),
+            market_id: exposed.market_id.clone(),
+            outcome: exposed.outcome,
+            exposed_amount: excess,
+            policy,
+            resolved_amount,
+            cost,
+            detected_at,
+            resolved_at: Utc::now(),
+        };
+
+        if event.remaining_amount() > Decimal::ZERO {
+            error!(
+                "ALERT: {} {} contracts of {} on {} remain unhedged (opportunity {})",
+                event.remaining_amount(),
+                event.outcome.as_str(),
+                event.market_id,
+                event.platform.as_str(),
+                opportunity_id
+            );
+        } else {
+            info!(
+                "Leg risk on opportunity {} resolved by {} at a cost of {}",
+                opportunity_id,
+                policy.as_str(),
+                event.cost
+            );
+        }
+
+        match self.database.save_leg_risk_event(&event).await {
+            Ok(id) => event.id = Some(id),
+            Err(e) => error!("Failed to record leg-risk event: {}", e),
+        }
+
+        event
+    }
+
+    /// Sends a recovery order and waits for it to finish. Returns how many
+    /// contracts filled and at what average price.
+    async fn recovery_order(
+        &self,
+        opportunity_id: i64,
+        platform: &Platform,
+        request: OrderRequest,
+        updates: &mut broadcast::Receiver<Trade>,
+        trades: &mut Vec<Trade>,
+    ) -> (Decimal, Decimal) {
+        if request.size <= Decimal::ZERO {
+            warn!(
+                "Unhedged size is below the smallest {} order",
+                platform.as_str()
+            );
+            return (Decimal::ZERO, Decimal::ZERO);
+        }
+
+        let limit_price = request.price;
+        let mut orders = match self.place(opportunity_id, platform, request).await {
+            Ok(trade) if trade.status == OrderStatus::Pending => {
+                trades.push(trade);
+                return (Decimal::ZERO, Decimal::ZERO);
+            }
+            Ok(trade) => vec![trade],
+            Err(e) => {
+                error!("Recovery order refused: {}", e);
+                return (Decimal::ZERO, Decimal::ZERO);
+            }
+        };
+
+        self.settle(updates, &mut orders).await;
+        let order = orders.remove(0);
+        let filled = (order.filled_amount, order.fill_price.unwrap_or(limit_price));
+        trades.push(order);
+
+        filled
+    }
+
+    /// Waits for `legs` to finish, cancelling whatever is still resting
+    /// after `leg_timeout_seconds`.
+    async fn settle(&self, updates: &mut broadcast::Receiver<Trade>, legs: &mut [Trade]) {
+        let timeout = Duration::from_secs(self.config.leg_timeout_seconds);
+
+        if wait_for_terminal(updates, legs, Instant::now() + timeout).await {
+            return;
+        }
+
+        for leg in legs.iter().filter(|leg| !leg.status.is_terminal()) {
+            let Some(order_id) = &leg.order_id else {
+                continue;
+            };
+            info!(
+                "Cancelling unfinished {} leg {}",
+                leg.platform.as_str(),
+                order_id
+            );
+            if let Err(e) = self.exchange(&leg.platform).cancel_order(order_id).await {
+                warn!("Failed to cancel order {}: {}", order_id, e);
+            }
+        }
+
+        if wait_for_terminal(updates, legs, Instant::now() + timeout).await {
+            return;
+        }
+
+        // Updates may have been missed; ask the venues directly
+        for leg in legs.iter_mut().filter(|leg| !leg.status.is_terminal()) {
+            let Some(order_id) = leg.order_id.clone() else {
+                continue;
+            };
+            let exchange = self.exchange(&leg.platform);
+            let order = match exchange.get_order(&order_id).await {
+                Ok(order) => order,
+                Err(e) => {
+                    warn!("Failed to check order {}: {}", order_id, e);
+                    continue;
+                }
+            };
+            if order.status == leg.status && order.filled_size == leg.filled_amount {
+                continue;
+            }
+
+            let now = Utc::now();
+            let mut last_fill = None;
+            if order.filled_size != leg.filled_amount {
+                match exchange.list_fills(&order_id).await {
+                    Ok(fills) => {
+                        leg.fill_price = average_fill_price(&fills).or(leg.fill_price);
+                        last_fill = fills.iter().filter_map(|fill| fill.filled_at).max();
+                    }
+                    Err(e) => warn!("Failed to list fills for order {}: {}", order_id, e),
+                }
+                if order.filled_size > Decimal::ZERO && leg.fill_price.is_none() {
+                    leg.fill_price = Some(order.price);
+                }
+            }
+
+            leg.status = order.status;
+            leg.filled_amount = order.filled_size;
+            leg.updated_at = now;
+            if leg.status == OrderStatus::Filled && leg.executed_at.is_none() {
+                leg.executed_at = Some(last_fill.unwrap_or(now));
+            }
+
+            let saved = match leg.id {
+                Some(_) => self.database.update_trade_status(leg).await,
+                None => self.database.save_trade(leg).await.map(|id| leg.id = Some(id)),
+            };
+            if let Err(e) = saved {
+                error!("Failed to record {} order {}: {}", leg.platform.as_str(), order_id, e);
+            }
+        }
+    }
+
+    /// Sends `request` and has the tracker record and follow it. Orders the
+    /// venue refused outright are recorded as rejected, and ones that may
+    /// have reached it are confirmed first.
+    async fn place(
+        &self,
+        opportunity_id: i64,
+        platform: &Platform,
+        request: OrderRequest,
+    ) -> Result<Trade, ApiError> {
+        let result = self.exchange(platform).place_order(&request).await;
+
+        let now = Utc::now();
+        let mut trade = Trade {
+            id: None,
+            opportunity_id,
+            platform: platform.clone(),
+            market_id: request.market_id.clone(),
+            order_id: None,
+            client_order_id: request.client_order_id.clone(),
+            outcome: request.outcome,
+            side: request.side,
+            price: request.price,
+            amount: request.size,
+            filled_amount: Decimal::ZERO,
+            fill_price: None,
+            status: OrderStatus::New,
+            created_at: now,
+            updated_at: now,
+            executed_at: None,
+        };
+
+        match result {
+            Ok(order_id) => {
+                trade.order_id = Some(order_id);
+                self.record(&mut trade).await;
+                Ok(trade)
+            }
+            // A transient failure may still have reached the venue, so only
+            // outright refusals are recorded as rejected
+            Err(e) if !e.is_retryable() => {
+                trade.status = OrderStatus::Rejected;
+                self.record(&mut trade).await;
+                Err(e)
+            }
+            Err(e) => {
+                trade.status = OrderStatus::Pending;
+                self.record(&mut trade).await;
+                self.confirm(trade, &request, e).await
+            }
+        }
+    }
+
+    /// Asks the venue whether it took a pending order. A placed order is
+    /// followed like any other and one never placed is recorded as
+    /// rejected with `error`. If the venue cannot tell, the order stays
+    /// pending and the breaker trips.
+    async fn confirm(
+        &self,
+        mut trade: Trade,
+        request: &OrderRequest,
+        error: ApiError,
+    ) -> Result<Trade, ApiError> {
+        let platform = trade.platform.as_str().to_string();
+        let client_order_id = trade.client_order_id.clone().unwrap_or_default();
+        warn!(
+            "{} order {} may have been placed despite {}; looking it up",
+            platform, client_order_id, error
+        );
+
+        let exchange = self.exchange(&trade.platform);
+        let order = match exchange.find_order(request).await {
+            Ok(order) => order,
+            Err(e) => {
+                let detail = format!(
+                    "{} order {} may have been placed ({}) and could not be looked up: {}",
+                    platform, client_order_id, error, e
+                );
+                error!("ALERT: {}", detail);
+                if let Some(breaker) = &self.breaker {
+                    if let Err(e) = breaker.trip(TripReason::LegRisk, detail).await {
+                        error!("Failed to trip the circuit breaker: {:#}", e);
+                    }
+                }
+                return Ok(trade);
+            }
+        };
+
+        let now = Utc::now();
+        trade.updated_at = now;
+        let Some(order) = order else {
+            trade.status = OrderStatus::Rejected;
+            self.update(&trade).await;
+            return Err(error);
+        };
+
+        info!("{} order {} was placed as {}", platform, client_order_id, order.id);
+        if order.filled_size > Decimal::ZERO {
+            let fills = match exchange.list_fills(&order.id).await {
+                Ok(fills) => fills,
+                Err(e) => {
+                    warn!("Failed to list fills for order {}: {}", order.id, e);
+                    Vec::new()
+                }
+            };
+            trade.fill_price = average_fill_price(&fills).or(Some(order.price));
+            if order.status == OrderStatus::Filled {
+                let last_fill = fills.iter().filter_map(|fill| fill.filled_at).max();
+                trade.executed_at = Some(last_fill.unwrap_or(now));
+            }
+        }
+        trade.order_id = Some(order.id);
+        trade.status = order.status;
+        trade.filled_amount = order.filled_size;
+        self.update(&trade).await;
+        Ok(trade)
+    }
+
+    async fn record(&self, trade: &mut Trade) {
+        match self.tracker.track(trade.clone()).await {
+            Ok(id) => trade.id = Some(id),
+            Err(e) => error!(
+                "Failed to record {} order {:?}: {}",
+                trade.platform.as_str(),
+                trade.order_id,
+                e
+            ),
+        }
+    }
+
+    async fn update(&self, trade: &Trade) {
+        if let Err(e) = self.tracker.update(trade.clone()).await {
+            error!(
+                "Failed to record {} order {:?}: {}",
+                trade.platform.as_str(),
+                trade.client_order_id,
+                e
+            );
+        }
+    }
+
+    fn exchange(&self, platform: &Platform) -> &dyn Exchange {
+        match platform {
+            Platform::Polymarket => self.polymarket.as_ref(),
+            Platform::Kalshi => self.kalshi.as_ref(),
+        }
+    }
+}
+
+/// An order for `opportunity`'s `outcome` on `platform`, which may trade it
+/// as the opposite outcome.
+fn leg_request(
+    opportunity: &ArbitrageOpportunity,
+    platform: &Platform,
+    outcome: Outcome,
+    side: TradeSide,
+    price: Decimal,
+    size: Decimal,
+) -> OrderRequest {
+    OrderRequest {
+        market_id: opportunity.position_id(platform).to_string(),
+        token_id: match platform {
+            Platform::Polymarket => opportunity.polymarket_token_id.clone(),
+            Platform::Kalshi => None,
+        },
+        outcome: opportunity.venue_outcome(platform, outcome),
+        side,
+        price,
+        size,
+        neg_risk: *platform == Platform::Polymarket && opportunity.polymarket_neg_risk,
+        client_order_id: Some(format!("{:032x}", rand::random::<u128>())),
+    }
+}
+
+/// Contracts of `opportunity`'s `outcome` bought less those sold across
+/// `trades`.
+fn net_filled(opportunity: &ArbitrageOpportunity, trades: &[Trade], outcome: Outcome) -> Decimal {
+    trades
+        .iter()
+        .filter(|trade| opportunity.venue_outcome(&trade.platform, trade.outcome) == outcome)
+        .map(|trade| match trade.side {
+            TradeSide::Buy => trade.filled_amount,
+            TradeSide::Sell => -trade.filled_amount,
+        })
+        .sum()
+}
+
+/// The largest order size no greater than `size` that `platform` accepts:
+/// whole contracts on Kalshi, hundredths on Polymarket.
+fn tradable_size(platform: &Platform, size: Decimal) -> Decimal {
+    let decimals = match platform {
+        Platform::Kalshi => 0,
+        Platform::Polymarket => 2,
+    };
+    size.round_dp_with_strategy(decimals, RoundingStrategy::ToZero)
+}
+
+/// Applies tracker updates to `legs` until all are terminal (true) or
+/// `deadline` passes (false).
+async fn wait_for_terminal(
+    updates: &mut broadcast::Receiver<Trade>,
+    legs: &mut [Trade],
+    deadline: Instant,
+) -> bool {
+    while !legs.iter().all(|leg| leg.status.is_terminal()) {
+        match timeout_at(deadline, updates.recv()).await {
+            Ok(Ok(update)) => {
+                if let Some(leg) = legs
+                    .iter_mut()
+                    .find(|leg| leg.id.is_some() && leg.id == update.id)
+                {
+                    *leg = update;
+                }
+            }
+            Ok(Err(RecvError::Lagged(skipped))) => warn!("Missed {} order updates", skipped),
+            Ok(Err(RecvError::Closed)) | Err(_) => return false,
+        }
+    }
+
+    true
+}
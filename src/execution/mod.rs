@@ -0,0 +1,7 @@
+//! Sending orders and following them after they are sent.
+
+pub mod coordinator;
+pub mod tracker;
+
+pub use coordinator::{ExecutionCoordinator, ExecutionReport};
+pub use tracker::OrderTracker;
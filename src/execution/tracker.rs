@@ -0,0 +1,277 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use log::{info, warn};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    sync::broadcast,
+    time::{interval, Duration, Instant, MissedTickBehavior},
+};
+
+use crate::{
+    api::Exchange,
+    config::BotConfig,
+    database::Database,
+    models::{average_fill_price, OrderStatus, Platform, Trade},
+};
+
+/// An order resting on a venue and the trade row that records it.
+#[derive(Debug, Clone)]
+struct LiveOrder {
+    trade: Trade,
+    placed_at: Instant,
+    cancel_requested: bool,
+}
+
+/// Follows every order the bot sends until it reaches a terminal state.
+///
+/// Each live order is polled every `order_poll_interval_ms`; whenever its
+/// status or filled size moves, the change is written to `trades` and
+/// published to subscribers. Orders resting longer than
+/// `order_timeout_seconds` are cancelled.
+#[derive(Clone)]
+pub struct OrderTracker {
+    polymarket: Arc<dyn Exchange>,
+    kalshi: Arc<dyn Exchange>,
+    database: Database,
+    poll_interval: Duration,
+    order_timeout: Option<Duration>,
+    live: Arc<Mutex<HashMap<i64, LiveOrder>>>,
+    updates: broadcast::Sender<Trade>,
+}
+
+impl OrderTracker {
+    pub fn new(
+        polymarket: Arc<dyn Exchange>,
+        kalshi: Arc<dyn Exchange>,
+        database: Database,
+        config: &BotConfig,
+    ) -> Self {
+        let (updates, _) = broadcast::channel(256);
+
+        Self {
+            polymarket,
+            kalshi,
+            database,
+            poll_interval: Duration::from_millis(config.order_poll_interval_ms.max(1)),
+            order_timeout: (config.order_timeout_seconds > 0)
+                .then(|| Duration::from_secs(config.order_timeout_seconds)),
+            live: Arc::new(Mutex::new(HashMap::new())),
+            updates,
+        }
+    }
+
+    /// Every saved trade and each later change to it.
+    pub fn subscribe(&self) -> broadcast::Receiver<Trade> {
+        self.updates.subscribe()
+    }
+
+    /// Saves `trade` and, if it is resting on a venue, follows it from now
+    /// on. Returns the trade's ID.
+    pub async fn track(&self, mut trade: Trade) -> Result<i64> {
+        let id = self.database.save_trade(&trade).await?;
+        trade.id = Some(id);
+        self.follow(id, trade);
+        Ok(id)
+    }
+
+    /// Records what became of a trade `track` saved, such as a pending
+    /// placement the venue confirmed, and follows it from now on if it is
+    /// resting.
+    pub async fn update(&self, trade: Trade) -> Result<()> {
+        let id = trade.id.context("Updated trade was never saved")?;
+        self.database.update_trade_status(&trade).await?;
+        self.follow(id, trade);
+        Ok(())
+    }
+
+    fn follow(&self, id: i64, trade: Trade) {
+        if trade.order_id.is_some() && !trade.status.is_terminal() {
+            self.live
+                .lock()
+                .expect("order tracker lock poisoned")
+                .insert(
+                    id,
+                    LiveOrder {
+                        trade: trade.clone(),
+                        placed_at: Instant::now(),
+                        cancel_requested: false,
+                    },
+                );
+        }
+
+        let _ = self.updates.send(trade);
+    }
+
+    /// Orders not yet in a terminal state.
+    pub fn live_orders(&self) -> Vec<Trade> {
+        self.live
+            .lock()
+            .expect("order tracker lock poisoned")
+            .values()
+            .map(|live| live.trade.clone())
+            .collect()
+    }
+
+    /// Requests cancellation of every live order on both venues and returns
+    /// how many were asked. The orders stay tracked until the venues
+    /// report them closed.
+    pub async fn cancel_all(&self) -> usize {
+        let live: Vec<(i64, Trade)> = self
+            .live
+            .lock()
+            .expect("order tracker lock poisoned")
+            .iter_mut()
+            .map(|(id, live)| {
+                live.cancel_requested = true;
+                (*id, live.trade.clone())
+            })
+            .collect();
+
+        for (_, trade) in &live {
+            let order_id = trade.order_id.as_deref().unwrap_or_default();
+            info!("Cancelling {} order {}", trade.platform.as_str(), order_id);
+            if let Err(e) = self.exchange(&trade.platform).cancel_order(order_id).await {
+                warn!("Failed to cancel order {}: {}", order_id, e);
+            }
+        }
+
+        live.len()
+    }
+
+    /// Polls until the task is dropped.
+    pub async fn run(self) {
+        let mut ticker = interval(self.poll_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            self.poll().await;
+        }
+    }
+
+    /// Checks every live order once.
+    pub async fn poll(&self) {
+        let live: Vec<(i64, Trade)> = self
+            .live
+            .lock()
+            .expect("order tracker lock poisoned")
+            .iter()
+            .map(|(id, live)| (*id, live.trade.clone()))
+            .collect();
+
+        for (id, trade) in live {
+            let order_id = trade.order_id.clone().unwrap_or_default();
+            if let Err(e) = self.refresh(id, trade).await {
+                warn!("Failed to check order {}: {:#}", order_id, e);
+            }
+        }
+    }
+
+    async fn refresh(&self, id: i64, mut trade: Trade) -> Result<()> {
+        let order_id = trade
+            .order_id
+            .clone()
+            .context("Tracked trade has no order ID")?;
+        let exchange = self.exchange(&trade.platform);
+
+        let order = exchange.get_order(&order_id).await?;
+
+        if order.status != trade.status && !trade.status.can_transition_to(order.status) {
+            warn!(
+                "Ignoring {} order {} moving from {} to {}",
+                trade.platform.as_str(),
+                order_id,
+                trade.status.as_str(),
+                order.status.as_str()
+            );
+            return Ok(());
+        }
+
+        if order.status != trade.status || order.filled_size != trade.filled_amount {
+            let now = Utc::now();
+            let mut last_fill = None;
+
+            if order.filled_size != trade.filled_amount {
+                match exchange.list_fills(&order_id).await {
+                    Ok(fills) => {
+                        trade.fill_price = average_fill_price(&fills).or(trade.fill_price);
+                        last_fill = fills.iter().filter_map(|fill| fill.filled_at).max();
+                    }
+                    Err(e) => warn!("Failed to list fills for order {}: {}", order_id, e),
+                }
+            }
+
+            trade.status = order.status;
+            trade.filled_amount = order.filled_size;
+            trade.updated_at = now;
+            if trade.status == OrderStatus::Filled && trade.executed_at.is_none() {
+                trade.executed_at = Some(last_fill.unwrap_or(now));
+            }
+
+            self.database.update_trade_status(&trade).await?;
+            info!(
+                "{} order {} is {} ({} of {} filled)",
+                trade.platform.as_str(),
+                order_id,
+                trade.status.as_str(),
+                trade.filled_amount,
+                trade.amount
+            );
+            let _ = self.updates.send(trade.clone());
+        }
+
+        if trade.status.is_terminal() {
+            self.live
+                .lock()
+                .expect("order tracker lock poisoned")
+                .remove(&id);
+            return Ok(());
+        }
+
+        // Only what the venue reported is written back: the entry may have
+        // changed while it was being asked, e.g. `cancel_all` flagging it
+        let cancel = {
+            let mut live = self.live.lock().expect("order tracker lock poisoned");
+            let Some(entry) = live.get_mut(&id) else {
+                return Ok(());
+            };
+            entry.trade.status = trade.status;
+            entry.trade.filled_amount = trade.filled_amount;
+            entry.trade.fill_price = trade.fill_price;
+            entry.trade.updated_at = trade.updated_at;
+            entry.trade.executed_at = trade.executed_at;
+
+            let timed_out = self
+                .order_timeout
+                .is_some_and(|timeout| entry.placed_at.elapsed() >= timeout);
+            // Marked either way: a failed cancel usually means the order
+            // already closed, which the next poll will show
+            let cancel = timed_out && !entry.cancel_requested;
+            entry.cancel_requested |= cancel;
+            cancel
+        };
+
+        if cancel {
+            info!(
+                "Cancelling {} order {} after timeout",
+                trade.platform.as_str(),
+                order_id
+            );
+            if let Err(e) = exchange.cancel_order(&order_id).await {
+                warn!("Failed to cancel order {}: {}", order_id, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn exchange(&self, platform: &Platform) -> &dyn Exchange {
+        match platform {
+            Platform::Polymarket => self.polymarket.as_ref(),
+            Platform::Kalshi => self.kalshi.as_ref(),
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use crate::{
+    config::{KalshiFeeConfig, PolymarketFeeConfig},
+    models::Platform,
+};
+
+/// Whether an order adds liquidity to the book or takes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Liquidity {
+    Maker,
+    Taker,
+}
+
+/// Trading cost schedule for a single venue.
+pub trait FeeModel: Send + Sync {
+    fn platform(&self) -> Platform;
+
+    /// Total fee in dollars for buying `contracts` at `price`.
+    fn order_fee(&self, price: Decimal, contracts: Decimal, liquidity: Liquidity) -> Decimal;
+}
+
+/// Kalshi charges `rate * contracts * price * (1 - price)`, rounded up to
+/// the next cent.
+#[derive(Debug, Clone)]
+pub struct KalshiFeeModel {
+    taker_fee_rate: Decimal,
+    maker_fee_rate: Decimal,
+    fixed_fee_per_order: Decimal,
+}
+
+impl KalshiFeeModel {
+    pub fn new(config: &KalshiFeeConfig) -> Self {
+        Self {
+            taker_fee_rate: Decimal::try_from(config.taker_fee_rate).unwrap_or_default(),
+            maker_fee_rate: Decimal::try_from(config.maker_fee_rate).unwrap_or_default(),
+            fixed_fee_per_order: Decimal::try_from(config.fixed_fee_per_order)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl FeeModel for KalshiFeeModel {
+    fn platform(&self) -> Platform {
+        Platform::Kalshi
+    }
+
+    fn order_fee(&self, price: Decimal, contracts: Decimal, liquidity: Liquidity) -> Decimal {
+        if contracts <= Decimal::ZERO {
+            return Decimal::ZERO;
+        }
+
+        let rate = match liquidity {
+            Liquidity::Maker => self.maker_fee_rate,
+            Liquidity::Taker => self.taker_fee_rate,
+        };
+
+        let fee = (rate * contracts * price * (Decimal::ONE - price))
+            .round_dp_with_strategy(2, RoundingStrategy::AwayFromZero);
+
+        fee + self.fixed_fee_per_order
+    }
+}
+
+/// Polymarket charges basis points on the order's notional value.
+#[derive(Debug, Clone)]
+pub struct PolymarketFeeModel {
+    taker_fee_bps: Decimal,
+    maker_fee_bps: Decimal,
+    fixed_fee_per_order: Decimal,
+}
+
+impl PolymarketFeeModel {
+    pub fn new(config: &PolymarketFeeConfig) -> Self {
+        Self {
+            taker_fee_bps: Decimal::from(config.taker_fee_bps),
+            maker_fee_bps: Decimal::from(config.maker_fee_bps),
+            fixed_fee_per_order: Decimal::try_from(config.fixed_fee_per_order)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl FeeModel for PolymarketFeeModel {
+    fn platform(&self) -> Platform {
+        Platform::Polymarket
+    }
+
+    fn order_fee(&self, price: Decimal, contracts: Decimal, liquidity: Liquidity) -> Decimal {
+        if contracts <= Decimal::ZERO {
+            return Decimal::ZERO;
+        }
+
+        let bps = match liquidity {
+            Liquidity::Maker => self.maker_fee_bps,
+            Liquidity::Taker => self.taker_fee_bps,
+        };
+
+        // USDC has six decimals
+        let fee = (price * contracts * bps / Decimal::from(10_000))
+            .round_dp_with_strategy(6, RoundingStrategy::AwayFromZero);
+
+        fee + self.fixed_fee_per_order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: f64) -> Decimal {
+        Decimal::try_from(value).unwrap()
+    }
+
+    #[test]
+    fn test_kalshi_fee_rounds_up_to_cent() {
+        let model = KalshiFeeModel::new(&KalshiFeeConfig::default());
+
+        // 0.07 * 1 * 0.45 * 0.55 = 0.017325
+        assert_eq!(model.order_fee(dec(0.45), dec(1.0), Liquidity::Taker), dec(0.02));
+        // 0.07 * 100 * 0.5 * 0.5 = 1.75 exactly
+        assert_eq!(model.order_fee(dec(0.50), dec(100.0), Liquidity::Taker), dec(1.75));
+        // 0.0175 * 100 * 0.5 * 0.5 = 0.4375
+        assert_eq!(model.order_fee(dec(0.50), dec(100.0), Liquidity::Maker), dec(0.44));
+    }
+
+    #[test]
+    fn test_kalshi_fixed_fee() {
+        let model = KalshiFeeModel::new(&KalshiFeeConfig {
+            fixed_fee_per_order: 0.10,
+            ..KalshiFeeConfig::default()
+        });
+
+        assert_eq!(model.order_fee(dec(0.50), dec(100.0), Liquidity::Taker), dec(1.85));
+        assert_eq!(model.order_fee(dec(0.50), Decimal::ZERO, Liquidity::Taker), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_polymarket_fee_bps() {
+        let model = PolymarketFeeModel::new(&PolymarketFeeConfig {
+            taker_fee_bps: 200,
+            maker_fee_bps: 0,
+            fixed_fee_per_order: 0.05,
+        });
+
+        // 2% of 0.40 * 100 = 0.80, plus 0.05 gas
+        assert_eq!(model.order_fee(dec(0.40), dec(100.0), Liquidity::Taker), dec(0.85));
+        assert_eq!(model.order_fee(dec(0.40), dec(100.0), Liquidity::Maker), dec(0.05));
+    }
+}
@@ -1,8 +1,15 @@
 pub mod api;
 pub mod arbitrage;
+pub mod backtest;
 pub mod config;
 pub mod database;
+pub mod execution;
+pub mod fees;
+pub mod matching;
 pub mod models;
+pub mod paper;
+pub mod risk;
+pub mod streaming;
 pub mod utils;
 
 pub use arbitrage::ArbitrageEngine;
@@ -1,21 +1,56 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use clap::Parser;
 use dotenv::dotenv;
 use env_logger::Env;
 use log::{error, info};
+use rust_decimal::Decimal;
 use polymarket_kalshi_arbitrage_bot::{
     arbitrage::ArbitrageEngine,
+    backtest::Backtester,
     config::Config,
     database::Database,
+    models::{BreakerTrip, MarketMatch, MatchStatus, TripReason},
 };
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Operating mode: monitor or execute
+    /// Operating mode: monitor, execute or paper (simulated fills); halt or
+    /// resume stop and restart trading in a running bot; backtest replays
+    /// recorded market data; matches reviews fuzzy-matched pairs
     #[arg(short, long, default_value = "monitor")]
     mode: String,
 
+    /// Record every market listing and order book fetched, for backtesting
+    #[arg(long)]
+    record: bool,
+
+    /// Backtest from this time (RFC 3339); defaults to the whole recording
+    #[arg(long)]
+    from: Option<String>,
+
+    /// Backtest up to this time (RFC 3339)
+    #[arg(long)]
+    to: Option<String>,
+
+    /// Also backtest with this configuration file; may be repeated
+    #[arg(long)]
+    compare: Vec<String>,
+
+    /// Approve the match with this ID for trading; may be repeated
+    #[arg(long)]
+    approve: Vec<i64>,
+
+    /// Approve the match with this ID with YES on one venue as NO on the
+    /// other; may be repeated
+    #[arg(long)]
+    invert: Vec<i64>,
+
+    /// Reject the match with this ID so it is never paired; may be repeated
+    #[arg(long)]
+    reject: Vec<i64>,
+
     /// Minimum profit percentage
     #[arg(short = 'p', long)]
     min_profit: Option<f64>,
@@ -45,19 +80,120 @@ async fn main() -> Result<()> {
     if let Some(min_profit) = args.min_profit {
         config.bot.min_profit_percentage = min_profit;
     }
+    if args.record {
+        config.bot.record_market_data = true;
+    }
 
     let execution_enabled = args.mode == "execute";
+    let paper = args.mode == "paper";
     
     info!("Mode: {}", args.mode);
-    info!("Execution: {}", if execution_enabled { "ENABLED" } else { "DISABLED" });
+    info!(
+        "Execution: {}",
+        if paper {
+            "PAPER"
+        } else if execution_enabled {
+            "ENABLED"
+        } else {
+            "DISABLED"
+        }
+    );
     info!("Min profit threshold: {}%", config.bot.min_profit_percentage);
 
     // Initialize database
     let database = Database::new(&config.database.url).await?;
     database.run_migrations().await?;
 
+    match args.mode.as_str() {
+        "halt" => {
+            database
+                .save_breaker_trip(&BreakerTrip {
+                    id: None,
+                    reason: TripReason::Manual,
+                    detail: "halted from the command line".to_string(),
+                    tripped_at: Utc::now(),
+                    resumed_at: None,
+                })
+                .await?;
+            info!("Trading halted");
+            return Ok(());
+        }
+        "resume" => {
+            let resumed = database.resume_breaker_trips(Utc::now()).await?;
+            info!("Trading resumed ({} halts cleared)", resumed);
+            return Ok(());
+        }
+        "matches" => {
+            let decisions = [
+                (&args.approve, MatchStatus::Approved, false),
+                (&args.invert, MatchStatus::Approved, true),
+                (&args.reject, MatchStatus::Rejected, false),
+            ];
+            for (ids, status, inverted) in decisions {
+                for id in ids {
+                    if database
+                        .decide_market_match(*id, status, inverted, Utc::now())
+                        .await?
+                    {
+                        info!(
+                            "Match {} {}{}",
+                            id,
+                            status.as_str(),
+                            if inverted { " (inverted)" } else { "" }
+                        );
+                    } else {
+                        error!("No match with ID {}", id);
+                    }
+                }
+            }
+
+            let pending = database.get_market_matches(Some(MatchStatus::Pending)).await?;
+            println!("{} matches pending review", pending.len());
+            for candidate in &pending {
+                print_match(candidate);
+            }
+            return Ok(());
+        }
+        "backtest" => {
+            let from = parse_time(args.from.as_deref())?.unwrap_or(DateTime::<Utc>::MIN_UTC);
+            let to = parse_time(args.to.as_deref())?.unwrap_or_else(Utc::now);
+            let backtester = Backtester::load(&database, from, to).await?;
+            info!("Replaying {} recorded cycles", backtester.cycles());
+
+            let mut runs = vec![(args.config.clone(), config)];
+            for path in &args.compare {
+                let mut compared = Config::load(path)?;
+                if let Some(min_profit) = args.min_profit {
+                    compared.bot.min_profit_percentage = min_profit;
+                }
+                runs.push((path.clone(), compared));
+            }
+
+            for (label, config) in &runs {
+                let report = backtester.run(label, config).await?;
+                info!(
+                    "{}: {} opportunities, {} executed, {} orders, {} fills, fees {}, cash {}, P&L {}",
+                    report.label,
+                    report.opportunities,
+                    report.executed,
+                    report.orders,
+                    report.fills,
+                    report.fees,
+                    report.cash_pnl,
+                    report.pnl
+                );
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
+
     // Create and run arbitrage engine
-    let mut engine = ArbitrageEngine::new(config, database, execution_enabled).await?;
+    let mut engine = if paper {
+        ArbitrageEngine::paper(config, database.clone()).await?
+    } else {
+        ArbitrageEngine::new(config, database.clone(), execution_enabled).await?
+    };
 
     // Handle shutdown gracefully
     let ctrl_c = tokio::signal::ctrl_c();
@@ -75,6 +211,112 @@ async fn main() -> Result<()> {
         }
     }
 
+    if paper {
+        for account in database.get_paper_accounts().await? {
+            let positions = database.get_paper_positions(&account.platform).await?;
+            let at_cost: Decimal = positions.iter().map(|position| position.current_value).sum();
+            info!(
+                "Paper {}: balance {} (started at {}), {} open positions costing {}",
+                account.platform.as_str(),
+                account.balance,
+                account.starting_balance,
+                positions.len(),
+                at_cost
+            );
+        }
+    }
+
     info!("Bot shutdown complete");
     Ok(())
 }
+
+/// Lines of each market's rules `print_match` shows.
+const RULES_LINES: usize = 6;
+
+/// Prints a candidate pair's two markets side by side.
+fn print_match(candidate: &MarketMatch) {
+    println!();
+    println!(
+        "#{}  confidence {:.2}  first seen {}",
+        candidate.id.unwrap_or_default(),
+        candidate.similarity,
+        candidate.first_seen_at.format("%Y-%m-%d %H:%M UTC")
+    );
+    if !candidate.explanation.is_empty() {
+        for line in wrap(&candidate.explanation, 83) {
+            println!("  {}", line);
+        }
+    }
+    if let Some(risk) = &candidate.resolution_risk {
+        for line in wrap(&format!("RESOLUTION RISK: {}", risk), 83) {
+            println!("  {}", line);
+        }
+    }
+    println!("  {:<40} | Kalshi", "Polymarket");
+    println!(
+        "  {:<40} | {}",
+        candidate.polymarket_market_id, candidate.kalshi_market_id
+    );
+    println!(
+        "  {:<40} | {}",
+        candidate.polymarket_end_time.format("closes %Y-%m-%d %H:%M UTC"),
+        candidate.kalshi_end_time.format("closes %Y-%m-%d %H:%M UTC")
+    );
+
+    let width = 40;
+    let poly_lines = wrap(&candidate.polymarket_question, width);
+    let kalshi_lines = wrap(&candidate.kalshi_question, width);
+    for i in 0..poly_lines.len().max(kalshi_lines.len()) {
+        println!(
+            "  {:<40} | {}",
+            poly_lines.get(i).map(String::as_str).unwrap_or(""),
+            kalshi_lines.get(i).map(String::as_str).unwrap_or("")
+        );
+    }
+
+    // Rules run long; the first few lines are enough to spot a difference
+    if candidate.polymarket_rules.is_some() || candidate.kalshi_rules.is_some() {
+        let rules = |rules: &Option<String>| {
+            let mut lines = wrap(rules.as_deref().unwrap_or("(no rules)"), width);
+            if lines.len() > RULES_LINES {
+                lines.truncate(RULES_LINES);
+                lines.push("...".to_string());
+            }
+            lines
+        };
+        let poly_lines = rules(&candidate.polymarket_rules);
+        let kalshi_lines = rules(&candidate.kalshi_rules);
+        println!("  {:-<40}-+-{:-<40}", "", "");
+        for i in 0..poly_lines.len().max(kalshi_lines.len()) {
+            println!(
+                "  {:<40} | {}",
+                poly_lines.get(i).map(String::as_str).unwrap_or(""),
+                kalshi_lines.get(i).map(String::as_str).unwrap_or("")
+            );
+        }
+    }
+}
+
+/// Splits `text` into lines of at most `width` characters at word breaks.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+fn parse_time(time: Option<&str>) -> Result<Option<DateTime<Utc>>> {
+    time.map(|time| Ok(DateTime::parse_from_rfc3339(time)?.with_timezone(&Utc)))
+        .transpose()
+}
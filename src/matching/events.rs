@@ -0,0 +1,301 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use super::{MarketFeatures, MarketMatcher, MatchConfidence, ResolutionTerms};
+use crate::models::{Event, Market};
+
+/// Outcomes two events must have in common to match.
+const MIN_OUTCOMES: usize = 2;
+/// Share of the smaller event's outcomes the other must have too.
+const MIN_OUTCOME_SHARE: f64 = 0.5;
+
+/// Two outcome markets of matched events that resolve on the same result,
+/// e.g. the same candidate winning the same race.
+#[derive(Debug, Clone)]
+pub struct OutcomeMatch<'a> {
+    /// The outcome as Polymarket names it.
+    pub name: String,
+    pub polymarket: &'a Market,
+    pub kalshi: &'a Market,
+    /// Where the two markets' rules disagree on how they resolve.
+    pub resolution_risk: Vec<String>,
+}
+
+/// A Polymarket and a Kalshi event asking the same question, with their
+/// outcomes mapped one to one.
+#[derive(Debug, Clone)]
+pub struct EventMatch<'a> {
+    pub polymarket: &'a Event,
+    pub kalshi: &'a Event,
+    /// Mean of the titles' confidence and the share of outcomes mapped.
+    pub score: f64,
+    /// How the event titles compare.
+    pub title: MatchConfidence,
+    pub outcomes: Vec<OutcomeMatch<'a>>,
+    /// Named outcomes of the event with fewer of them.
+    pub named: usize,
+}
+
+impl fmt::Display for EventMatch<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "event {:.2} ({}/{} outcomes mapped",
+            self.score,
+            self.outcomes.len(),
+            self.named
+        )?;
+        if self.mutually_exclusive() {
+            write!(f, ", mutually exclusive")?;
+        }
+        write!(f, "; title {})", self.title)
+    }
+}
+
+impl EventMatch<'_> {
+    /// Whether both venues say at most one outcome resolves YES.
+    pub fn mutually_exclusive(&self) -> bool {
+        self.polymarket.mutually_exclusive && self.kalshi.mutually_exclusive
+    }
+}
+
+/// A named outcome of an event: the index of its market and the name's
+/// tokens.
+struct Outcome {
+    market: usize,
+    tokens: HashSet<String>,
+}
+
+fn outcomes(event: &Event) -> Vec<Outcome> {
+    event
+        .markets
+        .iter()
+        .enumerate()
+        .filter_map(|(market, m)| {
+            let name = m.outcome_name.as_deref()?;
+            let tokens = MarketFeatures::from_title(name, m.end_time).tokens;
+            (!tokens.is_empty()).then_some(Outcome { market, tokens })
+        })
+        .collect()
+}
+
+/// The event's title, closing with its last outcome. Event titles are
+/// often in title case, where every word reads as a name, so entities are
+/// left out and the outcomes say who the event is about instead.
+fn title_features(event: &Event) -> MarketFeatures {
+    let closes = event
+        .markets
+        .iter()
+        .map(|market| market.end_time)
+        .max()
+        .unwrap_or_default();
+    let mut features = MarketFeatures::from_title(&event.title, closes);
+    features.entities.clear();
+    features
+}
+
+/// Matches multi-outcome events across the venues, each at most once.
+///
+/// Outcomes are mapped by name: the same words first, then a name within
+/// the other, as "Newsom" is within "Gavin Newsom", where that is the only
+/// reading either way. Outcomes closing too far apart are left unmapped.
+/// Two events match when at least two outcomes and half of the smaller
+/// event's map, their titles do not contradict each other on thresholds,
+/// dates or close, and the mean of the titles' confidence and the share
+/// mapped reaches the matcher's minimum. The titles alone rarely do:
+/// venues word the question differently, and it is the outcomes that pin
+/// it down.
+pub fn match_events<'a>(
+    matcher: &MarketMatcher,
+    poly_events: &'a [Event],
+    kalshi_events: &'a [Event],
+) -> Vec<EventMatch<'a>> {
+    let poly_outcomes: Vec<Vec<Outcome>> = poly_events.iter().map(outcomes).collect();
+    let kalshi_outcomes: Vec<Vec<Outcome>> = kalshi_events.iter().map(outcomes).collect();
+
+    let mut postings: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (j, outcomes) in kalshi_outcomes.iter().enumerate() {
+        if outcomes.len() < MIN_OUTCOMES {
+            continue;
+        }
+        let tokens: HashSet<&str> = outcomes
+            .iter()
+            .flat_map(|outcome| outcome.tokens.iter().map(String::as_str))
+            .collect();
+        for token in tokens {
+            postings.entry(token).or_default().push(j);
+        }
+    }
+
+    let mut candidates = Vec::new();
+    for (i, poly) in poly_outcomes.iter().enumerate() {
+        if poly.len() < MIN_OUTCOMES {
+            continue;
+        }
+
+        // Kalshi events sharing a word with at least two of the outcomes
+        let mut hits: HashMap<usize, usize> = HashMap::new();
+        for outcome in poly {
+            let events: HashSet<usize> = outcome
+                .tokens
+                .iter()
+                .filter_map(|token| postings.get(token.as_str()))
+                .flatten()
+                .copied()
+                .collect();
+            for j in events {
+                *hits.entry(j).or_default() += 1;
+            }
+        }
+
+        let poly_title = title_features(&poly_events[i]);
+        for (j, count) in hits {
+            if count < MIN_OUTCOMES {
+                continue;
+            }
+            let (poly_event, kalshi_event) = (&poly_events[i], &kalshi_events[j]);
+            let mapped: Vec<(usize, usize)> = map_outcomes(poly, &kalshi_outcomes[j])
+                .into_iter()
+                .filter(|&(p, k)| {
+                    let gap = poly_event.markets[p].end_time - kalshi_event.markets[k].end_time;
+                    gap.abs() <= matcher.max_close_gap()
+                })
+                .collect();
+            let named = poly.len().min(kalshi_outcomes[j].len());
+            let share = mapped.len() as f64 / named as f64;
+            if mapped.len() < MIN_OUTCOMES || share < MIN_OUTCOME_SHARE {
+                continue;
+            }
+
+            let title = matcher.compare(&poly_title, &title_features(kalshi_event));
+            let score = (title.score + share) / 2.0;
+            if !title.agrees() || score < matcher.min_confidence() {
+                continue;
+            }
+            candidates.push((score, i, j, mapped, named, title));
+        }
+    }
+
+    // Best first, so an event matching several takes its closest
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then((a.1, a.2).cmp(&(b.1, b.2))));
+    let mut poly_taken = HashSet::new();
+    let mut kalshi_taken = HashSet::new();
+    let mut matches = Vec::new();
+    for (score, i, j, mapped, named, title) in candidates {
+        if poly_taken.contains(&i) || kalshi_taken.contains(&j) {
+            continue;
+        }
+        poly_taken.insert(i);
+        kalshi_taken.insert(j);
+
+        let (polymarket, kalshi) = (&poly_events[i], &kalshi_events[j]);
+        let outcomes = mapped
+            .into_iter()
+            .map(|(p, k)| {
+                let (poly_market, kalshi_market) = (&polymarket.markets[p], &kalshi.markets[k]);
+                OutcomeMatch {
+                    name: poly_market.outcome_name.clone().unwrap_or_default(),
+                    polymarket: poly_market,
+                    kalshi: kalshi_market,
+                    resolution_risk: ResolutionTerms::extract(poly_market)
+                        .compare(&ResolutionTerms::extract(kalshi_market))
+                        .differences,
+                }
+            })
+            .collect();
+        matches.push(EventMatch {
+            polymarket,
+            kalshi,
+            score,
+            title,
+            outcomes,
+            named,
+        });
+    }
+    matches
+}
+
+/// Pairs of market indices whose outcome names agree, each outcome in at
+/// most one pair.
+fn map_outcomes(poly: &[Outcome], kalshi: &[Outcome]) -> Vec<(usize, usize)> {
+    let mut poly_mapped = vec![false; poly.len()];
+    let mut kalshi_mapped = vec![false; kalshi.len()];
+    let mut pairs = Vec::new();
+
+    let same = |a: &HashSet<String>, b: &HashSet<String>| a == b;
+    let within = |a: &HashSet<String>, b: &HashSet<String>| a.is_subset(b) || b.is_subset(a);
+    for agrees in [&same as &dyn Fn(_, _) -> bool, &within] {
+        let mut found = Vec::new();
+        for (p, poly_outcome) in poly.iter().enumerate() {
+            if poly_mapped[p] {
+                continue;
+            }
+            let mut matching = kalshi
+                .iter()
+                .enumerate()
+                .filter(|(k, kalshi_outcome)| {
+                    !kalshi_mapped[*k] && agrees(&poly_outcome.tokens, &kalshi_outcome.tokens)
+                })
+                .map(|(k, _)| k);
+            if let (Some(k), None) = (matching.next(), matching.next()) {
+                found.push((p, k));
+            }
+        }
+        // A Kalshi outcome more than one Polymarket outcome reads as is
+        // ambiguous, as "Trump" is between "Donald Trump" and "Donald
+        // Trump Jr."
+        for &(p, k) in &found {
+            if found.iter().filter(|(_, other)| *other == k).count() == 1 {
+                poly_mapped[p] = true;
+                kalshi_mapped[k] = true;
+                pairs.push((poly[p].market, kalshi[k].market));
+            }
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(market: usize, name: &str) -> Outcome {
+        Outcome {
+            market,
+            tokens: MarketFeatures::from_title(name, chrono::Utc::now()).tokens,
+        }
+    }
+
+    #[test]
+    fn test_outcomes_map_by_name_then_by_name_within() {
+        let poly = [
+            outcome(0, "Gavin Newsom"),
+            outcome(1, "Pete Buttigieg"),
+            outcome(2, "Alexandria Ocasio-Cortez"),
+        ];
+        let kalshi = [
+            outcome(5, "Buttigieg"),
+            outcome(6, "Gavin Newsom"),
+            outcome(7, "Josh Shapiro"),
+        ];
+        let mut pairs = map_outcomes(&poly, &kalshi);
+        pairs.sort();
+        assert_eq!(pairs, vec![(0, 6), (1, 5)]);
+    }
+
+    #[test]
+    fn test_ambiguous_outcome_names_stay_unmapped() {
+        let poly = [outcome(0, "Donald Trump"), outcome(1, "Donald Trump Jr.")];
+        let kalshi = [outcome(0, "Trump"), outcome(1, "Donald Trump Jr.")];
+        // "Donald Trump Jr." maps exactly; "Trump" is then only within
+        // "Donald Trump"
+        let mut pairs = map_outcomes(&poly, &kalshi);
+        pairs.sort();
+        assert_eq!(pairs, vec![(0, 0), (1, 1)]);
+
+        let kalshi = [outcome(0, "Trump"), outcome(1, "JD Vance")];
+        assert!(map_outcomes(&poly, &kalshi).is_empty());
+    }
+}
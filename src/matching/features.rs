@@ -0,0 +1,774 @@
+use chrono::{DateTime, Utc};
+use std::{collections::HashSet, fmt, sync::OnceLock};
+
+use super::{resolution::ResolutionTerms, text::trigrams};
+use crate::models::Market;
+
+/// Words too common in market titles to say anything about a match.
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "did", "do", "does", "for", "in", "is", "of",
+    "on", "or", "than", "that", "the", "this", "to", "was", "will", "with",
+];
+
+/// Capitalized words that do not name anything, besides stopwords:
+/// question words, and words capitalized only because they start a title.
+const NOT_ENTITIES: &[&str] = &[
+    "a",
+    "above",
+    "after",
+    "an",
+    "and",
+    "are",
+    "before",
+    "below",
+    "by",
+    "can",
+    "did",
+    "do",
+    "does",
+    "has",
+    "have",
+    "how",
+    "in",
+    "is",
+    "of",
+    "on",
+    "or",
+    "over",
+    "should",
+    "the",
+    "under",
+    "what",
+    "when",
+    "where",
+    "which",
+    "who",
+    "will",
+    "would",
+    "yes",
+    "no",
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+const MONTHS: &[&str] = &[
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// Comparison phrases, longest first wherever one contains another.
+const DIRECTIONS: &[(&[&str], Direction)] = &[
+    (&["no", "less", "than"], Direction::AtLeast),
+    (&["no", "more", "than"], Direction::AtMost),
+    (&["no", "fewer", "than"], Direction::AtLeast),
+    (&["at", "least"], Direction::AtLeast),
+    (&["or", "more"], Direction::AtLeast),
+    (&["or", "higher"], Direction::AtLeast),
+    (&["or", "above"], Direction::AtLeast),
+    (&["or", "greater"], Direction::AtLeast),
+    (&["at", "most"], Direction::AtMost),
+    (&["or", "less"], Direction::AtMost),
+    (&["or", "fewer"], Direction::AtMost),
+    (&["or", "lower"], Direction::AtMost),
+    (&["or", "below"], Direction::AtMost),
+    (&["more", "than"], Direction::Above),
+    (&["greater", "than"], Direction::Above),
+    (&["higher", "than"], Direction::Above),
+    (&["less", "than"], Direction::Below),
+    (&["fewer", "than"], Direction::Below),
+    (&["lower", "than"], Direction::Below),
+    (&["reach"], Direction::AtLeast),
+    (&["reaches"], Direction::AtLeast),
+    (&["hit"], Direction::AtLeast),
+    (&["hits"], Direction::AtLeast),
+    (&["above"], Direction::Above),
+    (&["over"], Direction::Above),
+    (&["exceed"], Direction::Above),
+    (&["exceeds"], Direction::Above),
+    (&["below"], Direction::Below),
+    (&["under"], Direction::Below),
+];
+
+/// What a threshold is quoted in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Usd,
+    Percent,
+    Degrees,
+}
+
+/// A number a market resolves against, e.g. the 40 in "above 40°F".
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Threshold {
+    pub value: f64,
+    pub unit: Option<Unit>,
+}
+
+impl Threshold {
+    /// The token the value adds to `MarketFeatures::tokens`.
+    pub fn token(&self) -> String {
+        number(self.value)
+    }
+
+    /// Same value, in the same unit where both give one.
+    pub fn agrees(&self, other: &Threshold) -> bool {
+        let units = match (self.unit, other.unit) {
+            (Some(unit), Some(other)) => unit == other,
+            _ => true,
+        };
+        units && (self.value - other.value).abs() <= 1e-9 * self.value.abs().max(1.0)
+    }
+}
+
+impl fmt::Display for Threshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.unit {
+            Some(Unit::Usd) => write!(f, "${}", number(self.value)),
+            Some(Unit::Percent) => write!(f, "{}%", number(self.value)),
+            Some(Unit::Degrees) => write!(f, "{}°", number(self.value)),
+            None => write!(f, "{}", number(self.value)),
+        }
+    }
+}
+
+/// Which side of its threshold a market's YES is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Above,
+    AtLeast,
+    Below,
+    AtMost,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Direction::Above => "above",
+            Direction::AtLeast => "at least",
+            Direction::Below => "below",
+            Direction::AtMost => "at most",
+        }
+    }
+
+    pub fn is_upward(&self) -> bool {
+        matches!(self, Direction::Above | Direction::AtLeast)
+    }
+}
+
+/// A date named in a title, with as much of it as was given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateMention {
+    pub year: Option<i32>,
+    pub month: Option<u32>,
+    pub day: Option<u32>,
+}
+
+impl DateMention {
+    /// No part given by both differs.
+    pub fn agrees(&self, other: &DateMention) -> bool {
+        fn same<T: PartialEq>(a: Option<T>, b: Option<T>) -> bool {
+            match (a, b) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            }
+        }
+        same(self.year, other.year) && same(self.month, other.month) && same(self.day, other.day)
+    }
+}
+
+impl fmt::Display for DateMention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(month) = self.month {
+            parts.push(MONTHS[month as usize - 1][..3].to_string());
+        }
+        if let Some(day) = self.day {
+            parts.push(day.to_string());
+        }
+        if let Some(year) = self.year {
+            parts.push(year.to_string());
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// What a market's title says, normalized for comparison with another
+/// venue's title.
+#[derive(Debug, Clone)]
+pub struct MarketFeatures {
+    /// Lowercase words, numbers and date parts, without stopwords. Month
+    /// abbreviations are spelled out and possessives dropped.
+    pub tokens: HashSet<String>,
+    /// Character trigrams of the tokens, worked out when first needed.
+    trigrams: OnceLock<HashSet<[char; 3]>>,
+    /// Runs of capitalized words, lowercased.
+    pub entities: Vec<String>,
+    pub thresholds: Vec<Threshold>,
+    pub direction: Option<Direction>,
+    pub dates: Vec<DateMention>,
+    pub closes: DateTime<Utc>,
+    /// What the market's rules say decides it; empty for a bare title.
+    pub resolution: ResolutionTerms,
+}
+
+impl MarketFeatures {
+    pub fn extract(market: &Market) -> Self {
+        Self {
+            resolution: ResolutionTerms::extract(market),
+            ..Self::from_title(&market.question, market.end_time)
+        }
+    }
+
+    pub fn from_title(title: &str, closes: DateTime<Utc>) -> Self {
+        let lexemes = merge_units(lex(title));
+
+        let mut features = Self {
+            tokens: HashSet::new(),
+            trigrams: OnceLock::new(),
+            entities: Vec::new(),
+            thresholds: Vec::new(),
+            direction: direction(&lexemes),
+            dates: Vec::new(),
+            closes,
+            resolution: ResolutionTerms::default(),
+        };
+
+        let mut entity: Vec<&str> = Vec::new();
+        let mut i = 0;
+        while i < lexemes.len() {
+            if let Some((date, next)) = date_at(&lexemes, i) {
+                features.tokens.extend(date_tokens(&date));
+                features.dates.push(date);
+                i = next;
+            } else {
+                match &lexemes[i] {
+                    Lexeme::Word { text, capitalized } => {
+                        let text = month(text)
+                            .map(|month| MONTHS[month - 1])
+                            .unwrap_or(text.as_str());
+                        if !STOPWORDS.contains(&text) {
+                            features.tokens.insert(text.to_string());
+                        }
+                        if *capitalized
+                            && !STOPWORDS.contains(&text)
+                            && !NOT_ENTITIES.contains(&text)
+                            && month(text).is_none()
+                        {
+                            entity.push(text);
+                            i += 1;
+                            continue;
+                        }
+                    }
+                    Lexeme::Number {
+                        value, unit: None, ..
+                    } if is_year(*value) => {
+                        features.tokens.insert(number(*value));
+                        features.dates.push(DateMention {
+                            year: Some(*value as i32),
+                            month: None,
+                            day: None,
+                        });
+                    }
+                    Lexeme::Number {
+                        value,
+                        unit,
+                        ordinal,
+                    } => {
+                        features.tokens.insert(number(*value));
+                        if !ordinal {
+                            features.thresholds.push(Threshold {
+                                value: *value,
+                                unit: *unit,
+                            });
+                        }
+                    }
+                    Lexeme::Date(date) => {
+                        features.tokens.extend(date_tokens(date));
+                        features.dates.push(*date);
+                    }
+                    Lexeme::Break(_) => {}
+                }
+                i += 1;
+            }
+
+            if !entity.is_empty() {
+                features.entities.push(entity.join(" "));
+                entity.clear();
+            }
+        }
+        if !entity.is_empty() {
+            features.entities.push(entity.join(" "));
+        }
+
+        features
+    }
+
+    pub fn trigrams(&self) -> &HashSet<[char; 3]> {
+        self.trigrams.get_or_init(|| trigrams(&self.tokens))
+    }
+
+    /// Every word of every entity.
+    pub fn entity_words(&self) -> HashSet<&str> {
+        self.entities
+            .iter()
+            .flat_map(|entity| entity.split(' '))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Lexeme {
+    Word {
+        text: String,
+        capitalized: bool,
+    },
+    Number {
+        value: f64,
+        unit: Option<Unit>,
+        ordinal: bool,
+    },
+    /// A date written in digits, e.g. 11/5/2024.
+    Date(DateMention),
+    /// Punctuation, which ends an entity.
+    Break(char),
+}
+
+/// Splits a title into words, numbers with their units, numeric dates and
+/// punctuation.
+pub(super) fn lex(title: &str) -> Vec<Lexeme> {
+    let chars: Vec<char> = title.replace('’', "'").chars().collect();
+    let at = |i: usize| chars.get(i).copied().unwrap_or(' ');
+
+    let mut lexemes = Vec::new();
+    let mut currency = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_ascii_digit() || (c == '.' && at(i + 1).is_ascii_digit()) {
+            let start = i;
+            while at(i).is_ascii_digit()
+                || (matches!(at(i), ',' | '.' | '/' | '-') && at(i + 1).is_ascii_digit())
+            {
+                i += 1;
+            }
+            let raw: String = chars[start..i].iter().collect();
+            if at(i) == ':' && at(i + 1).is_ascii_digit() {
+                // A time of day, e.g. 11:59 PM, is neither threshold nor date
+                while at(i) == ':' || at(i).is_ascii_digit() {
+                    i += 1;
+                }
+                currency = false;
+                continue;
+            }
+            let suffix_start = i;
+            while at(i).is_alphabetic() {
+                i += 1;
+            }
+            let suffix: String = chars[suffix_start..i].iter().collect();
+            lex_number(&raw, &suffix.to_lowercase(), currency, &mut lexemes);
+            currency = false;
+        } else if c.is_alphanumeric() {
+            let mut text = String::new();
+            loop {
+                while at(i).is_alphanumeric() {
+                    text.push(at(i));
+                    i += 1;
+                }
+                if at(i) != '\'' {
+                    break;
+                }
+                if at(i + 1) == 's' && !at(i + 2).is_alphanumeric() {
+                    // Possessive
+                    i += 2;
+                    break;
+                }
+                i += 1;
+                if !at(i).is_alphabetic() {
+                    break;
+                }
+            }
+            lexemes.push(Lexeme::Word {
+                capitalized: text.starts_with(char::is_uppercase),
+                text: text.to_lowercase(),
+            });
+            currency = false;
+        } else {
+            match c {
+                '$' => currency = true,
+                '%' => set_unit(&mut lexemes, Unit::Percent),
+                '°' | 'º' => {
+                    set_unit(&mut lexemes, Unit::Degrees);
+                    if matches!(at(i + 1), 'F' | 'C' | 'f' | 'c') && !at(i + 2).is_alphanumeric() {
+                        i += 1;
+                    }
+                }
+                '\'' | '-' | '"' => {}
+                c if c.is_whitespace() => {}
+                c => lexemes.push(Lexeme::Break(c)),
+            }
+            i += 1;
+        }
+    }
+
+    lexemes
+}
+
+fn lex_number(raw: &str, suffix: &str, currency: bool, lexemes: &mut Vec<Lexeme>) {
+    if raw.contains('/') || raw.contains('-') {
+        let parts: Vec<u32> = raw
+            .split(['/', '-'])
+            .filter_map(|part| part.parse().ok())
+            .collect();
+        let date = match parts[..] {
+            [year, month, day] if raw.contains('-') && year >= 1000 => {
+                Some((Some(year as i32), month, day))
+            }
+            [month, day, year] if raw.contains('/') => {
+                let year = if year < 100 { year + 2000 } else { year };
+                Some((Some(year as i32), month, day))
+            }
+            [month, day] if raw.contains('/') => Some((None, month, day)),
+            _ => None,
+        };
+        match date {
+            Some((year, month, day)) if (1..=12).contains(&month) && (1..=31).contains(&day) => {
+                lexemes.push(Lexeme::Date(DateMention {
+                    year,
+                    month: Some(month),
+                    day: Some(day),
+                }));
+            }
+            _ => {
+                // A range such as 10-15: keep each end
+                for part in raw.split(['/', '-']) {
+                    lex_number(part, "", currency, lexemes);
+                }
+            }
+        }
+        if !suffix.is_empty() {
+            push_word(lexemes, suffix);
+        }
+        return;
+    }
+
+    let Ok(mut value) = raw.replace(',', "").parse::<f64>() else {
+        return;
+    };
+    let mut unit = currency.then_some(Unit::Usd);
+    let mut ordinal = false;
+    match suffix {
+        "" => {}
+        "k" => value *= 1e3,
+        "m" | "mm" => value *= 1e6,
+        "b" | "bn" => value *= 1e9,
+        "t" => value *= 1e12,
+        "st" | "nd" | "rd" | "th" => ordinal = true,
+        "f" | "c" => unit = Some(Unit::Degrees),
+        "am" | "pm" => {
+            push_word(lexemes, suffix);
+            return;
+        }
+        suffix => {
+            lexemes.push(Lexeme::Number {
+                value,
+                unit,
+                ordinal,
+            });
+            push_word(lexemes, suffix);
+            return;
+        }
+    }
+    lexemes.push(Lexeme::Number {
+        value,
+        unit,
+        ordinal,
+    });
+}
+
+fn push_word(lexemes: &mut Vec<Lexeme>, text: &str) {
+    lexemes.push(Lexeme::Word {
+        text: text.to_string(),
+        capitalized: false,
+    });
+}
+
+fn set_unit(lexemes: &mut [Lexeme], to: Unit) {
+    if let Some(Lexeme::Number { unit, .. }) = lexemes.last_mut() {
+        *unit = Some(to);
+    }
+}
+
+/// Folds scale and unit words into the number before them, so "1.5
+/// million dollars" reads like "$1.5m". A number followed by "am" or "pm"
+/// is a time of day and dropped.
+pub(super) fn merge_units(lexemes: Vec<Lexeme>) -> Vec<Lexeme> {
+    let mut merged: Vec<Lexeme> = Vec::with_capacity(lexemes.len());
+    for lexeme in lexemes {
+        let word = match &lexeme {
+            Lexeme::Word { text, .. } => text.as_str(),
+            _ => "",
+        };
+        let Some(Lexeme::Number { value, unit, .. }) = merged.last_mut() else {
+            merged.push(lexeme);
+            continue;
+        };
+        match word {
+            "thousand" => *value *= 1e3,
+            "million" => *value *= 1e6,
+            "billion" => *value *= 1e9,
+            "trillion" => *value *= 1e12,
+            "percent" | "pct" => *unit = Some(Unit::Percent),
+            "degrees" | "degree" | "fahrenheit" | "celsius" => *unit = Some(Unit::Degrees),
+            "dollars" | "dollar" | "usd" => *unit = Some(Unit::Usd),
+            "am" | "pm" => {
+                merged.pop();
+                merged.push(lexeme);
+            }
+            _ => merged.push(lexeme),
+        }
+    }
+    merged
+}
+
+/// The first comparison phrase in the title.
+fn direction(lexemes: &[Lexeme]) -> Option<Direction> {
+    let words: Vec<&str> = lexemes
+        .iter()
+        .filter_map(|lexeme| match lexeme {
+            Lexeme::Word { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    (0..words.len()).find_map(|i| {
+        DIRECTIONS
+            .iter()
+            .find(|(phrase, _)| words[i..].starts_with(phrase))
+            .map(|(_, direction)| *direction)
+    })
+}
+
+/// A date written in words starting at `i`, e.g. "Nov 5, 2024", "5th of
+/// November" or "March 2025", with the index after it.
+pub(super) fn date_at(lexemes: &[Lexeme], i: usize) -> Option<(DateMention, usize)> {
+    let month_at = |i: usize| match lexemes.get(i) {
+        Some(Lexeme::Word { text, capitalized }) => {
+            // "may" and "march" are words too; a bare month must be capitalized
+            let followed = matches!(lexemes.get(i + 1), Some(Lexeme::Number { .. }));
+            month(text).filter(|_| *capitalized || followed || text.len() > 5)
+        }
+        _ => None,
+    };
+    let day_at = |i: usize| match lexemes.get(i) {
+        Some(Lexeme::Number {
+            value, unit: None, ..
+        }) if value.fract() == 0.0 && (1.0..=31.0).contains(value) => Some(*value as u32),
+        _ => None,
+    };
+    let year_at = |i: usize| {
+        let i = if lexemes.get(i) == Some(&Lexeme::Break(',')) {
+            i + 1
+        } else {
+            i
+        };
+        match lexemes.get(i) {
+            Some(Lexeme::Number {
+                value, unit: None, ..
+            }) if is_year(*value) => Some((*value as i32, i + 1)),
+            _ => None,
+        }
+    };
+
+    let (month, day, next) = if let Some(month) = month_at(i) {
+        match day_at(i + 1) {
+            Some(day) => (month, Some(day), i + 2),
+            None => (month, None, i + 1),
+        }
+    } else {
+        let day = day_at(i)?;
+        let of = matches!(lexemes.get(i + 1), Some(Lexeme::Word { text, .. }) if text == "of");
+        let at = if of { i + 2 } else { i + 1 };
+        let month = match lexemes.get(at) {
+            Some(Lexeme::Word { text, .. }) => month(text)?,
+            _ => return None,
+        };
+        (month, Some(day), at + 1)
+    };
+
+    let (year, next) = match year_at(next) {
+        Some((year, next)) => (Some(year), next),
+        None => (None, next),
+    };
+    Some((
+        DateMention {
+            year,
+            month: Some(month as u32),
+            day,
+        },
+        next,
+    ))
+}
+
+/// The month a word names, 1 for January.
+fn month(word: &str) -> Option<usize> {
+    if word.len() < 3 {
+        return None;
+    }
+    let word = if word == "sept" { "sep" } else { word };
+    MONTHS
+        .iter()
+        .position(|month| month.starts_with(word) && (word.len() == 3 || word == *month))
+        .map(|month| month + 1)
+}
+
+fn is_year(value: f64) -> bool {
+    value.fract() == 0.0 && (1900.0..=2100.0).contains(&value)
+}
+
+fn date_tokens(date: &DateMention) -> Vec<String> {
+    let mut tokens = Vec::new();
+    if let Some(month) = date.month {
+        tokens.push(MONTHS[month as usize - 1].to_string());
+    }
+    if let Some(day) = date.day {
+        tokens.push(day.to_string());
+    }
+    if let Some(year) = date.year {
+        tokens.push(year.to_string());
+    }
+    tokens
+}
+
+/// `value` without a trailing ".0".
+fn number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        format!("{}", value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(title: &str) -> MarketFeatures {
+        MarketFeatures::from_title(title, Utc::now())
+    }
+
+    fn threshold(value: f64, unit: Option<Unit>) -> Threshold {
+        Threshold { value, unit }
+    }
+
+    #[test]
+    fn test_possessives_and_punctuation_do_not_split_words() {
+        let trump = features("Trump's approval rating?");
+        assert!(trump.tokens.contains("trump"));
+        assert!(trump.tokens.contains("rating"));
+        assert_eq!(trump.entities, vec!["trump"]);
+    }
+
+    #[test]
+    fn test_dates_in_words_and_digits() {
+        let date = DateMention {
+            year: Some(2024),
+            month: Some(11),
+            day: Some(5),
+        };
+        assert_eq!(features("Winner on November 5, 2024?").dates, vec![date]);
+        assert_eq!(features("Winner on 11/5/2024?").dates, vec![date]);
+        assert_eq!(features("Winner on 2024-11-05?").dates, vec![date]);
+        assert_eq!(
+            features("Winner on the 5th of November 2024?").dates,
+            vec![date]
+        );
+
+        let short = features("Winner on Nov 5?");
+        assert_eq!(short.dates.len(), 1);
+        assert!(short.dates[0].agrees(&date));
+        assert!(short.tokens.contains("november"));
+        assert!(short.thresholds.is_empty());
+
+        // "may" is only a month when it reads like one
+        assert!(features("Fed may cut rates").dates.is_empty());
+        assert_eq!(features("Fed cut in May 2025?").dates[0].month, Some(5));
+    }
+
+    #[test]
+    fn test_thresholds_with_units_and_scales() {
+        assert_eq!(
+            features("Bitcoin above $100k?").thresholds,
+            vec![threshold(100_000.0, Some(Unit::Usd))]
+        );
+        assert_eq!(
+            features("Bitcoin above 100,000 dollars?").thresholds,
+            vec![threshold(100_000.0, Some(Unit::Usd))]
+        );
+        assert_eq!(
+            features("Revenue over $1.5 billion").thresholds,
+            vec![threshold(1.5e9, Some(Unit::Usd))]
+        );
+        assert_eq!(
+            features("Unemployment at 4.2% or higher").thresholds,
+            vec![threshold(4.2, Some(Unit::Percent))]
+        );
+        assert_eq!(
+            features("High above 40°F").thresholds,
+            vec![threshold(40.0, Some(Unit::Degrees))]
+        );
+        // A year and a time of day are not thresholds
+        assert!(features("Closes 5pm ET in 2025").thresholds.is_empty());
+    }
+
+    #[test]
+    fn test_direction() {
+        assert_eq!(features("BTC above 100k").direction, Some(Direction::Above));
+        assert_eq!(
+            features("BTC 100k or above").direction,
+            Some(Direction::AtLeast)
+        );
+        assert_eq!(
+            features("BTC at least 100k").direction,
+            Some(Direction::AtLeast)
+        );
+        assert_eq!(
+            features("Will BTC hit 100k").direction,
+            Some(Direction::AtLeast)
+        );
+        assert_eq!(
+            features("BTC less than 100k").direction,
+            Some(Direction::Below)
+        );
+        assert_eq!(
+            features("BTC no more than 100k").direction,
+            Some(Direction::AtMost)
+        );
+        assert_eq!(features("Who wins the election").direction, None);
+    }
+
+    #[test]
+    fn test_entities_are_capitalized_runs() {
+        let features = features("Will Donald Trump win the 2024 US Presidential Election?");
+        assert_eq!(
+            features.entities,
+            vec!["donald trump", "us presidential election"]
+        );
+        assert!(!features.tokens.contains("will"));
+        assert!(!features.tokens.contains("the"));
+    }
+}
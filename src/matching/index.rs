@@ -0,0 +1,304 @@
+use chrono::{DateTime, Utc};
+use log::debug;
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use super::{Corpus, MarketFeatures, MarketMatcher, MatchConfidence};
+use crate::models::{Market, Platform};
+
+/// A token in more than this share of a venue's markets does not block:
+/// its markets are only candidates through rarer tokens they share.
+const MAX_TOKEN_SHARE: f64 = 0.05;
+/// Tokens in up to this many markets always block, however small the venue.
+const MIN_BLOCK_SIZE: usize = 100;
+/// Indexed tokens a pair must share to be scored, where the Polymarket
+/// title has that many.
+const MIN_SHARED_TOKENS: usize = 2;
+
+/// Share of markets that may be new, changed or gone before the corpus
+/// is refit, for scorers weighing words by how rare they are.
+const MAX_CORPUS_DRIFT: f64 = 0.1;
+
+/// Features a pair can only agree on by sharing one of their tokens.
+const ENTITIES: u8 = 1;
+const THRESHOLDS: u8 = 2;
+
+fn gated(features: &MarketFeatures) -> u8 {
+    let mut gated = 0;
+    if !features.entities.is_empty() {
+        gated |= ENTITIES;
+    }
+    if !features.thresholds.is_empty() {
+        gated |= THRESHOLDS;
+    }
+    gated
+}
+
+struct CachedFeatures {
+    /// Stable for as long as the market stays listed.
+    id: u32,
+    version: u64,
+    features: Arc<MarketFeatures>,
+}
+
+struct CachedScore {
+    versions: (u64, u64),
+    confidence: Option<MatchConfidence>,
+}
+
+/// What the last `MatchIndex::matches` call did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatchStats {
+    /// Pairs found through the index.
+    pub candidates: usize,
+    /// Candidates scored because either market is new or changed.
+    pub scored: usize,
+    /// Candidates whose score was reused.
+    pub cached: usize,
+    pub matches: usize,
+    /// Whether the corpus was refit, so every candidate was rescored.
+    pub refit: bool,
+}
+
+/// Candidate generation and caching for `MarketMatcher`, so each cycle
+/// scores a few candidates per market rather than every pair.
+///
+/// A Polymarket market's candidates are the Kalshi markets sharing two
+/// tokens with it and closing within the matcher's maximum gap, found
+/// through an inverted index of Kalshi tokens whose postings are sorted by
+/// close day. Very common tokens are not indexed, so a pair sharing little
+/// else is not scored; a pair sharing no token at all cannot reach the
+/// default confidence anyway. Where both titles name entities, or both name
+/// thresholds, the shared tokens must include an entity word or a threshold
+/// value, since the matcher would find a conflict otherwise.
+///
+/// Features are cached per market and scores per pair across calls, keyed
+/// by each market's title, close time and rules, so only markets that
+/// changed are tokenized again and only pairs involving them rescored.
+/// Where the matcher weighs words by how rare they are, the corpus of open
+/// markets is refit once a tenth of them have come, gone or changed, and
+/// every pair rescored then.
+#[derive(Default)]
+pub struct MatchIndex {
+    features: HashMap<(Platform, String), CachedFeatures>,
+    scores: HashMap<(u32, u32), CachedScore>,
+    next_id: u32,
+    corpus: Corpus,
+    /// Markets new, changed or gone since the corpus was fit.
+    drift: usize,
+    stats: MatchStats,
+}
+
+impl MatchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every matching pair, as indices into `poly_markets` and
+    /// `kalshi_markets`, with its confidence.
+    pub fn matches(
+        &mut self,
+        matcher: &MarketMatcher,
+        poly_markets: &[&Market],
+        kalshi_markets: &[&Market],
+    ) -> Vec<(usize, usize, MatchConfidence)> {
+        let mut features = HashMap::with_capacity(poly_markets.len() + kalshi_markets.len());
+        let poly = self.refresh(poly_markets, &mut features);
+        let kalshi = self.refresh(kalshi_markets, &mut features);
+        // Markets no longer listed are forgotten
+        self.drift += self.features.len();
+        self.features = features;
+
+        let mut stats = MatchStats::default();
+        if matcher.uses_corpus()
+            && (self.corpus.documents() == 0
+                || self.drift as f64 > self.corpus.documents() as f64 * MAX_CORPUS_DRIFT)
+        {
+            self.corpus = Corpus::new(
+                poly.iter()
+                    .chain(&kalshi)
+                    .map(|(_, _, features)| features.as_ref()),
+            );
+            self.drift = 0;
+            self.scores.clear();
+            stats.refit = true;
+        }
+
+        let kalshi_gated: Vec<u8> = kalshi
+            .iter()
+            .map(|(_, _, features)| gated(features))
+            .collect();
+        let mut postings: HashMap<&str, Vec<(i64, usize)>> = HashMap::new();
+        for (j, (_, _, features)) in kalshi.iter().enumerate() {
+            let day = day(features.closes);
+            for token in &features.tokens {
+                postings.entry(token.as_str()).or_default().push((day, j));
+            }
+        }
+        for posting in postings.values_mut() {
+            posting.sort_unstable();
+        }
+        let max_block = MIN_BLOCK_SIZE.max((kalshi.len() as f64 * MAX_TOKEN_SHARE) as usize);
+        let gap_days = matcher.max_close_gap().num_days() + 1;
+
+        let mut matches = Vec::new();
+        let mut scores = HashMap::new();
+        // Indexed tokens each Kalshi market shares with the Polymarket
+        // market at hand, the gated features they cover, and which markets
+        // those are
+        let mut shared = vec![0; kalshi.len()];
+        let mut covered = vec![0u8; kalshi.len()];
+        let mut touched = Vec::new();
+        for (i, (poly_id, poly_version, poly_features)) in poly.iter().enumerate() {
+            let day = day(poly_features.closes);
+            let poly_gated = gated(poly_features);
+            let entity_words = poly_features.entity_words();
+            let threshold_tokens: Vec<String> = poly_features
+                .thresholds
+                .iter()
+                .map(|threshold| threshold.token())
+                .collect();
+            let mut indexed = 0;
+            for token in &poly_features.tokens {
+                let Some(posting) = postings.get(token.as_str()) else {
+                    continue;
+                };
+                if posting.len() > max_block {
+                    continue;
+                }
+                indexed += 1;
+                let mut covers = 0;
+                if entity_words.contains(token.as_str()) {
+                    covers |= ENTITIES;
+                }
+                if threshold_tokens.contains(token) {
+                    covers |= THRESHOLDS;
+                }
+                let start = posting.partition_point(|(close, _)| *close < day - gap_days);
+                let end = posting.partition_point(|(close, _)| *close <= day + gap_days);
+                for &(_, j) in &posting[start..end] {
+                    if shared[j] == 0 {
+                        touched.push(j);
+                    }
+                    shared[j] += 1;
+                    covered[j] |= covers;
+                }
+            }
+
+            let needed = MIN_SHARED_TOKENS.min(indexed).max(1);
+            for j in touched.drain(..) {
+                let required = poly_gated & kalshi_gated[j];
+                let enough = shared[j] >= needed && covered[j] & required == required;
+                shared[j] = 0;
+                covered[j] = 0;
+                if !enough {
+                    continue;
+                }
+                stats.candidates += 1;
+
+                let (kalshi_id, kalshi_version, kalshi_features) = &kalshi[j];
+                let key = (*poly_id, *kalshi_id);
+                let versions = (*poly_version, *kalshi_version);
+                let confidence = match self.scores.remove(&key) {
+                    Some(score) if score.versions == versions => {
+                        stats.cached += 1;
+                        score.confidence
+                    }
+                    _ => {
+                        stats.scored += 1;
+                        matcher.score_in(poly_features, kalshi_features, &self.corpus)
+                    }
+                };
+                if let Some(confidence) = &confidence {
+                    matches.push((i, j, confidence.clone()));
+                }
+                scores.insert(
+                    key,
+                    CachedScore {
+                        versions,
+                        confidence,
+                    },
+                );
+            }
+        }
+        // Pairs that are no longer candidates are forgotten
+        self.scores = scores;
+        stats.matches = matches.len();
+        self.stats = stats;
+
+        debug!(
+            "Matched {} x {} markets: {} candidates, {} scored, {} cached, {} matches",
+            poly_markets.len(),
+            kalshi_markets.len(),
+            stats.candidates,
+            stats.scored,
+            stats.cached,
+            stats.matches
+        );
+        matches
+    }
+
+    /// Each market's features, extracted again only if its title, close
+    /// time or rules changed, moving them from the cache into `features`.
+    fn refresh(
+        &mut self,
+        markets: &[&Market],
+        features: &mut HashMap<(Platform, String), CachedFeatures>,
+    ) -> Vec<(u32, u64, Arc<MarketFeatures>)> {
+        markets
+            .iter()
+            .map(|market| {
+                let key = (market.platform.clone(), market.id.clone());
+                let version = version(market);
+                let cached = match self.features.remove(&key) {
+                    Some(cached) if cached.version == version => cached,
+                    cached => {
+                        self.drift += 1;
+                        CachedFeatures {
+                            id: cached.map(|cached| cached.id).unwrap_or_else(|| {
+                                self.next_id += 1;
+                                self.next_id
+                            }),
+                            version,
+                            features: Arc::new(MarketFeatures::extract(market)),
+                        }
+                    }
+                };
+                let entry = (cached.id, cached.version, cached.features.clone());
+                features.insert(key, cached);
+                entry
+            })
+            .collect()
+    }
+
+    pub fn stats(&self) -> MatchStats {
+        self.stats
+    }
+
+    /// Number of markets whose features are cached.
+    pub fn cached_markets(&self) -> usize {
+        self.features.len()
+    }
+
+    /// Number of pairs whose scores are cached.
+    pub fn cached_pairs(&self) -> usize {
+        self.scores.len()
+    }
+}
+
+/// Identifies what the features and scores of `market` depend on.
+fn version(market: &Market) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    market.question.hash(&mut hasher);
+    market.end_time.hash(&mut hasher);
+    market.rules.hash(&mut hasher);
+    market.resolution_source.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn day(at: DateTime<Utc>) -> i64 {
+    at.timestamp().div_euclid(86_400)
+}
@@ -0,0 +1,358 @@
+pub mod events;
+pub mod features;
+pub mod index;
+pub mod resolution;
+pub mod text;
+
+pub use events::{match_events, EventMatch, OutcomeMatch};
+pub use features::{DateMention, Direction, MarketFeatures, Threshold, Unit};
+pub use index::{MatchIndex, MatchStats};
+pub use resolution::{Deadline, DeadlineKind, ResolutionCheck, ResolutionTerms};
+pub use text::{Corpus, Ensemble, Jaccard, Matcher, TfIdf, Trigram};
+
+use chrono::Duration;
+use std::{collections::HashSet, fmt};
+
+use crate::{config::MatchingConfig, models::Market};
+
+/// How much each component counts towards a match's confidence.
+const TEXT_WEIGHT: f64 = 3.0;
+const ENTITIES_WEIGHT: f64 = 2.0;
+const THRESHOLDS_WEIGHT: f64 = 2.0;
+const DIRECTION_WEIGHT: f64 = 1.0;
+const DATES_WEIGHT: f64 = 2.0;
+const CLOSE_WEIGHT: f64 = 1.0;
+const RESOLUTION_WEIGHT: f64 = 2.0;
+
+/// One thing two titles were compared on.
+#[derive(Debug, Clone)]
+pub struct Component {
+    pub name: &'static str,
+    /// From 0, nothing in common, to 1, the same.
+    pub score: f64,
+    pub weight: f64,
+    /// What agreed, e.g. the shared entities.
+    pub detail: String,
+}
+
+/// How confident the matcher is that two markets resolve together, and why.
+#[derive(Debug, Clone)]
+pub struct MatchConfidence {
+    /// Weighted mean of the component scores.
+    pub score: f64,
+    /// Components either title gave something to compare on.
+    pub components: Vec<Component>,
+    /// The structured feature the titles contradict each other on, if any.
+    pub conflict: Option<String>,
+    /// Where the markets' rules disagree on how they resolve. Unlike a
+    /// conflict this only lowers the confidence, since rules are free text.
+    pub resolution_risk: Vec<String>,
+}
+
+impl MatchConfidence {
+    pub fn agrees(&self) -> bool {
+        self.conflict.is_none()
+    }
+
+    pub fn has_resolution_risk(&self) -> bool {
+        !self.resolution_risk.is_empty()
+    }
+}
+
+impl fmt::Display for MatchConfidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let components: Vec<String> = self
+            .components
+            .iter()
+            .map(|component| {
+                format!(
+                    "{} {:.2} ({})",
+                    component.name, component.score, component.detail
+                )
+            })
+            .collect();
+        write!(f, "{}", components.join(", "))?;
+        if let Some(conflict) = &self.conflict {
+            write!(f, "; conflict: {}", conflict)?;
+        }
+        if self.has_resolution_risk() {
+            write!(f, "; resolution risk: {}", self.resolution_risk.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Decides whether a Polymarket and a Kalshi market are the same question.
+///
+/// The titles' text similarity comes from the scorers weighted under
+/// `[matching.scorers]`; those weighing rare words more need the corpus of
+/// open markets, and see every word as equally rare without one.
+///
+/// Each title is normalized and broken into structured features: named
+/// entities, thresholds with their units, the comparison direction and
+/// dates. A pair whose features contradict each other, or whose close times
+/// are too far apart, never matches. Otherwise its confidence is the
+/// weighted mean of the components both titles give, plus a zero for a
+/// threshold only one names, and it matches at `min_confidence` or above.
+///
+/// Where both venues publish rules, their resolution source, deadline, time
+/// zone and thresholds are compared too. Disagreements lower the confidence
+/// and are reported as resolution risk rather than ruling the pair out.
+pub struct MarketMatcher {
+    min_confidence: f64,
+    max_close_gap: Duration,
+    text: Ensemble,
+}
+
+impl MarketMatcher {
+    pub fn new(config: &MatchingConfig) -> Self {
+        Self {
+            min_confidence: config.min_confidence,
+            max_close_gap: Duration::hours((config.max_close_gap_days * 24.0) as i64),
+            text: Ensemble::new(&config.scorers),
+        }
+    }
+
+    /// Whether scores depend on the corpus of open markets.
+    pub fn uses_corpus(&self) -> bool {
+        self.text.uses_corpus()
+    }
+
+    /// Confidence a pair needs to match.
+    pub fn min_confidence(&self) -> f64 {
+        self.min_confidence
+    }
+
+    /// Markets closing further apart than this never match.
+    pub fn max_close_gap(&self) -> Duration {
+        self.max_close_gap
+    }
+
+    /// The pair's confidence, if it matches.
+    pub fn score(&self, market1: &Market, market2: &Market) -> Option<MatchConfidence> {
+        self.score_features(
+            &MarketFeatures::extract(market1),
+            &MarketFeatures::extract(market2),
+        )
+    }
+
+    pub fn score_features(
+        &self,
+        features1: &MarketFeatures,
+        features2: &MarketFeatures,
+    ) -> Option<MatchConfidence> {
+        self.score_in(features1, features2, &Corpus::default())
+    }
+
+    /// The pair's confidence, if it matches, with words weighted by how
+    /// rare they are in `corpus`.
+    pub fn score_in(
+        &self,
+        features1: &MarketFeatures,
+        features2: &MarketFeatures,
+        corpus: &Corpus,
+    ) -> Option<MatchConfidence> {
+        let confidence = self.compare_in(features1, features2, corpus);
+        (confidence.agrees() && confidence.score >= self.min_confidence).then_some(confidence)
+    }
+
+    /// Scores every component, whether or not the pair matches.
+    pub fn compare(
+        &self,
+        features1: &MarketFeatures,
+        features2: &MarketFeatures,
+    ) -> MatchConfidence {
+        self.compare_in(features1, features2, &Corpus::default())
+    }
+
+    pub fn compare_in(
+        &self,
+        features1: &MarketFeatures,
+        features2: &MarketFeatures,
+        corpus: &Corpus,
+    ) -> MatchConfidence {
+        let mut components = Vec::new();
+        let mut conflict = None;
+
+        let shared = features1.tokens.intersection(&features2.tokens).count();
+        let union = features1.tokens.union(&features2.tokens).count();
+        let mut detail = format!("{}/{} words", shared, union);
+        let similarities = self.text.similarities(features1, features2, corpus);
+        if similarities.len() > 1 {
+            for (name, similarity) in &similarities {
+                detail.push_str(&format!(", {} {:.2}", name, similarity));
+            }
+        }
+        components.push(Component {
+            name: "text",
+            score: self.text.combine(&similarities),
+            weight: TEXT_WEIGHT,
+            detail,
+        });
+
+        let (words1, words2) = (features1.entity_words(), features2.entity_words());
+        if !words1.is_empty() && !words2.is_empty() {
+            let mut shared: Vec<&str> = words1.intersection(&words2).copied().collect();
+            shared.sort_unstable();
+            if shared.is_empty() {
+                conflict = Some(format!(
+                    "entities {} vs {}",
+                    features1.entities.join(" / "),
+                    features2.entities.join(" / ")
+                ));
+            }
+            components.push(Component {
+                name: "entities",
+                score: shared.len() as f64 / words1.len().min(words2.len()) as f64,
+                weight: ENTITIES_WEIGHT,
+                detail: shared.join(" "),
+            });
+        }
+
+        if !features1.thresholds.is_empty() && !features2.thresholds.is_empty() {
+            let shared: Vec<&Threshold> = features1
+                .thresholds
+                .iter()
+                .filter(|threshold| {
+                    features2
+                        .thresholds
+                        .iter()
+                        .any(|other| threshold.agrees(other))
+                })
+                .collect();
+            if shared.is_empty() {
+                conflict.get_or_insert_with(|| {
+                    format!(
+                        "thresholds {} vs {}",
+                        list(&features1.thresholds),
+                        list(&features2.thresholds)
+                    )
+                });
+            }
+            let most = features1.thresholds.len().max(features2.thresholds.len());
+            components.push(Component {
+                name: "thresholds",
+                score: shared.len() as f64 / most as f64,
+                weight: THRESHOLDS_WEIGHT,
+                detail: list(shared),
+            });
+        } else if features1.thresholds.len() + features2.thresholds.len() > 0 {
+            // A threshold only one title names is not known to agree
+            components.push(Component {
+                name: "thresholds",
+                score: 0.0,
+                weight: THRESHOLDS_WEIGHT,
+                detail: format!(
+                    "only one title names {}",
+                    list(features1.thresholds.iter().chain(&features2.thresholds))
+                ),
+            });
+        }
+
+        if let (Some(direction1), Some(direction2)) = (features1.direction, features2.direction) {
+            let score = if direction1 == direction2 {
+                1.0
+            } else if direction1.is_upward() == direction2.is_upward() {
+                // Same side; differs only on whether the threshold counts
+                0.5
+            } else {
+                conflict.get_or_insert_with(|| {
+                    format!(
+                        "direction {} vs {}",
+                        direction1.as_str(),
+                        direction2.as_str()
+                    )
+                });
+                0.0
+            };
+            components.push(Component {
+                name: "direction",
+                score,
+                weight: DIRECTION_WEIGHT,
+                detail: if direction1 == direction2 {
+                    direction1.as_str().to_string()
+                } else {
+                    format!("{} vs {}", direction1.as_str(), direction2.as_str())
+                },
+            });
+        }
+
+        if !features1.dates.is_empty() && !features2.dates.is_empty() {
+            let shared: Vec<&DateMention> = features1
+                .dates
+                .iter()
+                .filter(|date| features2.dates.iter().any(|other| date.agrees(other)))
+                .collect();
+            if shared.is_empty() {
+                conflict.get_or_insert_with(|| {
+                    format!(
+                        "dates {} vs {}",
+                        list(&features1.dates),
+                        list(&features2.dates)
+                    )
+                });
+            }
+            components.push(Component {
+                name: "dates",
+                score: shared.len() as f64 / features1.dates.len() as f64,
+                weight: DATES_WEIGHT,
+                detail: list(shared),
+            });
+        }
+
+        // Within a day counts fully, falling to half at the widest gap allowed
+        let gap = (features1.closes - features2.closes).abs();
+        if gap > self.max_close_gap {
+            conflict.get_or_insert_with(|| format!("closes {} apart", days(gap)));
+        }
+        let excess = (gap - Duration::days(1)).max(Duration::zero());
+        let allowed = (self.max_close_gap - Duration::days(1)).max(Duration::seconds(1));
+        let ratio = (excess.num_seconds() as f64 / allowed.num_seconds() as f64).min(1.0);
+        components.push(Component {
+            name: "close",
+            score: 1.0 - 0.5 * ratio,
+            weight: CLOSE_WEIGHT,
+            detail: format!("{} apart", days(gap)),
+        });
+
+        let check = features1.resolution.compare(&features2.resolution);
+        if check.compared > 0 {
+            let agreed = check.compared - check.differences.len();
+            components.push(Component {
+                name: "resolution",
+                score: agreed as f64 / check.compared as f64,
+                weight: RESOLUTION_WEIGHT,
+                detail: format!("{}/{} terms agree", agreed, check.compared),
+            });
+        }
+
+        let weight: f64 = components.iter().map(|component| component.weight).sum();
+        let score = components
+            .iter()
+            .map(|component| component.score * component.weight)
+            .sum::<f64>()
+            / weight;
+
+        MatchConfidence {
+            score,
+            components,
+            conflict,
+            resolution_risk: check.differences,
+        }
+    }
+}
+
+/// `items` joined with slashes, each once.
+fn list<T: fmt::Display>(items: impl IntoIterator<Item = T>) -> String {
+    let mut seen = HashSet::new();
+    items
+        .into_iter()
+        .map(|item| item.to_string())
+        .filter(|item| seen.insert(item.clone()))
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+fn days(gap: Duration) -> String {
+    format!("{:.1} days", gap.num_minutes() as f64 / (24.0 * 60.0))
+}
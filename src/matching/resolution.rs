@@ -0,0 +1,439 @@
+use chrono::{Datelike, NaiveDate};
+use std::{collections::HashSet, fmt};
+
+use super::features::{date_at, lex, merge_units, DateMention, Lexeme, MarketFeatures, Threshold};
+use crate::models::Market;
+
+/// Phrases naming what decides the outcome, e.g. "as reported by the
+/// Bureau of Labor Statistics".
+const SOURCE_PHRASES: &[&[&str]] = &[
+    &["reported", "by"],
+    &["according", "to"],
+    &["published", "by"],
+    &["released", "by"],
+    &["determined", "by"],
+    &["source", "is"],
+    &["source", "will", "be"],
+];
+
+/// Words that can sit inside a source's name, e.g. "Bureau of Labor
+/// Statistics".
+const NAME_JOINERS: &[&str] = &["of", "and", "for", "the"];
+
+/// Words too generic to say two sources are the same.
+const GENERIC_SOURCE_WORDS: &[&str] = &[
+    "official",
+    "data",
+    "report",
+    "reports",
+    "website",
+    "information",
+    "sources",
+    "source",
+];
+
+/// Whether a market resolves on its deadline or at any point up to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadlineKind {
+    /// Anything up to and including the date counts.
+    By,
+    /// Only the state on the date counts.
+    On,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    pub kind: DeadlineKind,
+    pub date: DateMention,
+}
+
+impl fmt::Display for Deadline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            DeadlineKind::By => write!(f, "by {}", self.date),
+            DeadlineKind::On => write!(f, "on {}", self.date),
+        }
+    }
+}
+
+/// What a market's rules say decides it: the source, the deadline and its
+/// time zone, and the thresholds.
+#[derive(Debug, Clone, Default)]
+pub struct ResolutionTerms {
+    /// Words naming the source: URL domains, and names with their initials.
+    pub source: HashSet<String>,
+    /// The source as first named, for explanations.
+    pub source_label: Option<String>,
+    pub deadline: Option<Deadline>,
+    /// The time zone of the cut-off, e.g. "ET".
+    pub time_zone: Option<&'static str>,
+    pub thresholds: Vec<Threshold>,
+}
+
+/// Where two markets' resolution terms differ.
+#[derive(Debug, Clone, Default)]
+pub struct ResolutionCheck {
+    /// Terms both markets state.
+    pub compared: usize,
+    pub differences: Vec<String>,
+}
+
+impl ResolutionTerms {
+    pub fn extract(market: &Market) -> Self {
+        Self::parse(
+            market.rules.as_deref().unwrap_or(""),
+            market.resolution_source.as_deref(),
+        )
+    }
+
+    /// Reads `rules` and, where the venue gives one separately, `source`.
+    pub fn parse(rules: &str, source: Option<&str>) -> Self {
+        let mut terms = Self::default();
+        if rules.trim().is_empty() && source.is_none() {
+            return terms;
+        }
+        if let Some(source) = source.map(str::trim).filter(|source| !source.is_empty()) {
+            match domain(source) {
+                Some(domain) => terms.add_source(&domain, [domain.clone()]),
+                None => terms.add_source(source, name_words(&merge_units(lex(source)))),
+            }
+        }
+        for word in rules.split_whitespace() {
+            if let Some(domain) = domain(word) {
+                terms.add_source(&domain, [domain.clone()]);
+            }
+        }
+
+        let lexemes = merge_units(lex(rules));
+        for (name, words) in named_sources(&lexemes) {
+            terms.add_source(&name, words);
+        }
+        terms.deadline = deadline(&lexemes);
+        terms.time_zone = lexemes.iter().find_map(|lexeme| match lexeme {
+            Lexeme::Word {
+                text,
+                capitalized: true,
+            } => time_zone(text),
+            _ => None,
+        });
+        terms.thresholds = MarketFeatures::from_title(rules, Default::default()).thresholds;
+        terms
+    }
+
+    fn add_source(&mut self, label: &str, words: impl IntoIterator<Item = String>) {
+        let words: Vec<String> = words
+            .into_iter()
+            .filter(|word| !GENERIC_SOURCE_WORDS.contains(&word.as_str()))
+            .collect();
+        if words.is_empty() {
+            return;
+        }
+        self.source_label.get_or_insert_with(|| label.to_string());
+        self.source.extend(words);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.source.is_empty()
+            && self.deadline.is_none()
+            && self.time_zone.is_none()
+            && self.thresholds.is_empty()
+    }
+
+    /// Compares the terms both markets state.
+    pub fn compare(&self, other: &ResolutionTerms) -> ResolutionCheck {
+        let mut check = ResolutionCheck::default();
+
+        if !self.source.is_empty() && !other.source.is_empty() {
+            check.compared += 1;
+            if self.source.is_disjoint(&other.source) {
+                check.differences.push(format!(
+                    "source {} vs {}",
+                    self.source_label.as_deref().unwrap_or_default(),
+                    other.source_label.as_deref().unwrap_or_default()
+                ));
+            }
+        }
+
+        if let (Some(deadline), Some(other)) = (self.deadline, other.deadline) {
+            check.compared += 1;
+            if deadline.kind != other.kind || !deadline.date.agrees(&other.date) {
+                check
+                    .differences
+                    .push(format!("deadline {} vs {}", deadline, other));
+            }
+        }
+
+        if let (Some(zone), Some(other)) = (self.time_zone, other.time_zone) {
+            check.compared += 1;
+            if zone != other {
+                check
+                    .differences
+                    .push(format!("cut-off time zone {} vs {}", zone, other));
+            }
+        }
+
+        if !self.thresholds.is_empty() && !other.thresholds.is_empty() {
+            check.compared += 1;
+            let agree = self
+                .thresholds
+                .iter()
+                .any(|threshold| other.thresholds.iter().any(|other| threshold.agrees(other)));
+            if !agree {
+                check.differences.push(format!(
+                    "threshold {} vs {}",
+                    self.thresholds[0], other.thresholds[0]
+                ));
+            }
+        }
+
+        check
+    }
+}
+
+/// The name a URL's host gives its site, e.g. "bls" for
+/// https://www.bls.gov/cpi/.
+fn domain(word: &str) -> Option<String> {
+    let word = word.trim_matches(|c: char| !c.is_alphanumeric() && c != '/');
+    let host = match word.split_once("://") {
+        Some((_, rest)) => rest,
+        None if word.starts_with("www.") => word,
+        None => return None,
+    };
+    let host = host.split('/').next()?.to_lowercase();
+    let labels: Vec<&str> = host
+        .trim_start_matches("www.")
+        .split('.')
+        .filter(|label| !label.is_empty())
+        .collect();
+    match labels[..] {
+        [] => None,
+        [label] => Some(label.to_string()),
+        [.., name, _] => Some(name.to_string()),
+    }
+}
+
+/// Sources the rules name after a phrase such as "according to", each
+/// with the words identifying it.
+fn named_sources(lexemes: &[Lexeme]) -> Vec<(String, Vec<String>)> {
+    let words: Vec<Option<(&str, bool)>> = lexemes
+        .iter()
+        .map(|lexeme| match lexeme {
+            Lexeme::Word { text, capitalized } => Some((text.as_str(), *capitalized)),
+            _ => None,
+        })
+        .collect();
+    let text = |i: usize| words.get(i).copied().flatten().map(|(text, _)| text);
+
+    let mut sources = Vec::new();
+    for i in 0..words.len() {
+        let Some(phrase) = SOURCE_PHRASES.iter().find(|phrase| {
+            phrase
+                .iter()
+                .enumerate()
+                .all(|(k, word)| text(i + k) == Some(word))
+        }) else {
+            continue;
+        };
+
+        let mut j = i + phrase.len();
+        if text(j) == Some("the") {
+            j += 1;
+        }
+        let start = j;
+        while let Some(Some((word, capitalized))) = words.get(j) {
+            let joins =
+                NAME_JOINERS.contains(word) && matches!(words.get(j + 1), Some(Some((_, true))));
+            if !capitalized && !joins {
+                break;
+            }
+            j += 1;
+        }
+        if j > start {
+            sources.push((
+                words[start..j]
+                    .iter()
+                    .flatten()
+                    .map(|(word, _)| *word)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                name_words(&lexemes[start..j]),
+            ));
+        }
+    }
+    sources
+}
+
+/// A name's words, without joiners, and its initials, e.g. "bls" for
+/// Bureau of Labor Statistics.
+fn name_words(lexemes: &[Lexeme]) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut initials = String::new();
+    for lexeme in lexemes {
+        if let Lexeme::Word { text, capitalized } = lexeme {
+            if NAME_JOINERS.contains(&text.as_str()) {
+                continue;
+            }
+            words.push(text.clone());
+            if *capitalized {
+                initials.extend(text.chars().next());
+            }
+        }
+    }
+    if initials.chars().count() > 1 {
+        words.push(initials);
+    }
+    words
+}
+
+/// The first date the rules put a deadline on, e.g. "by December 31" or
+/// "on November 5". "Before" a date means by the day before it.
+fn deadline(lexemes: &[Lexeme]) -> Option<Deadline> {
+    (0..lexemes.len()).find_map(|i| {
+        let (date, _) = date_at(lexemes, i)?;
+        let mut before: Vec<&str> = lexemes[..i]
+            .iter()
+            .rev()
+            .take(3)
+            .map_while(|lexeme| match lexeme {
+                Lexeme::Word { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        if before.first() == Some(&"the") {
+            before.remove(0);
+        }
+
+        let (kind, exclusive) = match before[..] {
+            ["before", "or", "on", ..] => (DeadlineKind::By, false),
+            ["to", "prior", ..] | ["before", ..] => (DeadlineKind::By, true),
+            ["by" | "through" | "until", ..] => (DeadlineKind::By, false),
+            ["on" | "for", ..] => (DeadlineKind::On, false),
+            _ => return None,
+        };
+        let date = if exclusive { day_before(date) } else { date };
+        Some(Deadline { kind, date })
+    })
+}
+
+fn day_before(date: DateMention) -> DateMention {
+    let previous = date
+        .year
+        .zip(date.month)
+        .zip(date.day)
+        .and_then(|((year, month), day)| NaiveDate::from_ymd_opt(year, month, day))
+        .and_then(|date| date.pred_opt());
+    match previous {
+        Some(previous) => DateMention {
+            year: Some(previous.year()),
+            month: Some(previous.month()),
+            day: Some(previous.day()),
+        },
+        None => date,
+    }
+}
+
+fn time_zone(word: &str) -> Option<&'static str> {
+    match word {
+        "et" | "est" | "edt" => Some("ET"),
+        "ct" | "cst" | "cdt" => Some("CT"),
+        "mt" | "mst" | "mdt" => Some("MT"),
+        "pt" | "pst" | "pdt" => Some("PT"),
+        "utc" | "gmt" => Some("UTC"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> DateMention {
+        DateMention {
+            year: Some(year),
+            month: Some(month),
+            day: Some(day),
+        }
+    }
+
+    #[test]
+    fn test_sources_from_urls_names_and_initials() {
+        let terms = ResolutionTerms::parse(
+            "Resolves to the CPI figure as reported by the Bureau of Labor Statistics.",
+            Some("https://www.bls.gov/cpi/"),
+        );
+        assert_eq!(terms.source_label.as_deref(), Some("bls"));
+        assert!(terms.source.contains("bls"));
+        assert!(terms.source.contains("labor"));
+
+        let terms = ResolutionTerms::parse(
+            "According to the National Weather Service, see https://weather.gov/nyc.",
+            None,
+        );
+        assert!(terms.source.contains("nws"));
+        assert!(terms.source.contains("weather"));
+
+        // "Official" alone names nothing
+        assert!(ResolutionTerms::parse("", Some("Official results"))
+            .source
+            .contains("results"));
+        assert!(ResolutionTerms::parse("", Some("official"))
+            .source
+            .is_empty());
+    }
+
+    #[test]
+    fn test_deadlines_and_time_zones() {
+        let by = ResolutionTerms::parse(
+            "Resolves Yes if it happens by December 31, 2025, 11:59 PM ET.",
+            None,
+        );
+        assert_eq!(
+            by.deadline,
+            Some(Deadline {
+                kind: DeadlineKind::By,
+                date: date(2025, 12, 31),
+            })
+        );
+        assert_eq!(by.time_zone, Some("ET"));
+
+        let before = ResolutionTerms::parse("If this happens before January 1, 2026 UTC.", None);
+        assert_eq!(before.deadline, by.deadline);
+        assert_eq!(before.time_zone, Some("UTC"));
+
+        let on = ResolutionTerms::parse(
+            "Based on the closing price on the 31st of December 2025.",
+            None,
+        );
+        assert_eq!(
+            on.deadline.map(|deadline| deadline.kind),
+            Some(DeadlineKind::On)
+        );
+        assert!(ResolutionTerms::parse("Resolves to the winner.", None).is_empty());
+    }
+
+    #[test]
+    fn test_compare_reports_each_difference() {
+        let poly = ResolutionTerms::parse(
+            "Resolves Yes if the high is above 40°F on January 1, 2026, according to the National Weather Service.",
+            None,
+        );
+        let kalshi = ResolutionTerms::parse(
+            "If the high is above 41°F by January 1, 2026 as reported by AccuWeather, then Yes.",
+            None,
+        );
+        let check = poly.compare(&kalshi);
+        assert_eq!(check.compared, 3);
+        assert_eq!(
+            check.differences,
+            vec![
+                "source national weather service vs accuweather",
+                "deadline on jan 1 2026 vs by jan 1 2026",
+                "threshold 40° vs 41°",
+            ]
+        );
+
+        let check = poly.compare(&poly);
+        assert_eq!(check.compared, 3);
+        assert!(check.differences.is_empty());
+    }
+}
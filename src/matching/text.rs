@@ -0,0 +1,328 @@
+use std::collections::{HashMap, HashSet};
+
+use super::MarketFeatures;
+use crate::config::ScorerWeights;
+
+/// Scores how alike two titles are worded, from 0, nothing in common, to 1.
+pub trait Matcher: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn similarity(
+        &self,
+        features1: &MarketFeatures,
+        features2: &MarketFeatures,
+        corpus: &Corpus,
+    ) -> f64;
+
+    /// Whether scores change when the corpus does.
+    fn uses_corpus(&self) -> bool {
+        false
+    }
+}
+
+/// How many of the open markets each token appears in, so that words
+/// every title uses weigh less than the ones naming what a market is about.
+#[derive(Debug, Clone, Default)]
+pub struct Corpus {
+    documents: usize,
+    frequencies: HashMap<String, usize>,
+}
+
+impl Corpus {
+    pub fn new<'a>(markets: impl IntoIterator<Item = &'a MarketFeatures>) -> Self {
+        let mut corpus = Self::default();
+        for features in markets {
+            corpus.documents += 1;
+            for token in &features.tokens {
+                *corpus.frequencies.entry(token.clone()).or_default() += 1;
+            }
+        }
+        corpus
+    }
+
+    /// Number of markets the corpus was built from.
+    pub fn documents(&self) -> usize {
+        self.documents
+    }
+
+    /// Smoothed inverse document frequency: 1 for a token in every market,
+    /// or in an empty corpus, and higher the rarer it is.
+    pub fn idf(&self, token: &str) -> f64 {
+        let frequency = self.frequencies.get(token).copied().unwrap_or(0);
+        ((1 + self.documents) as f64 / (1 + frequency) as f64).ln() + 1.0
+    }
+}
+
+/// Shared tokens over all tokens: the matcher's original text score.
+pub struct Jaccard;
+
+impl Matcher for Jaccard {
+    fn name(&self) -> &'static str {
+        "jaccard"
+    }
+
+    fn similarity(
+        &self,
+        features1: &MarketFeatures,
+        features2: &MarketFeatures,
+        _: &Corpus,
+    ) -> f64 {
+        let shared = features1.tokens.intersection(&features2.tokens).count();
+        let union = features1.tokens.len() + features2.tokens.len() - shared;
+        if union == 0 {
+            0.0
+        } else {
+            shared as f64 / union as f64
+        }
+    }
+}
+
+/// Cosine of the titles' token vectors weighted by inverse document
+/// frequency, so sharing a rare name counts for more than sharing "win".
+pub struct TfIdf;
+
+impl Matcher for TfIdf {
+    fn name(&self) -> &'static str {
+        "tfidf"
+    }
+
+    fn similarity(
+        &self,
+        features1: &MarketFeatures,
+        features2: &MarketFeatures,
+        corpus: &Corpus,
+    ) -> f64 {
+        // A title names each token once, so its weight is the idf alone
+        let norm = |tokens: &HashSet<String>| {
+            tokens
+                .iter()
+                .map(|token| corpus.idf(token).powi(2))
+                .sum::<f64>()
+                .sqrt()
+        };
+        let norms = norm(&features1.tokens) * norm(&features2.tokens);
+        if norms == 0.0 {
+            return 0.0;
+        }
+        let dot: f64 = features1
+            .tokens
+            .intersection(&features2.tokens)
+            .map(|token| corpus.idf(token).powi(2))
+            .sum();
+        dot / norms
+    }
+
+    fn uses_corpus(&self) -> bool {
+        true
+    }
+}
+
+/// Dice coefficient of the titles' character trigrams, which still sees
+/// "rate" in "rates" and survives typos and run-together words.
+pub struct Trigram;
+
+impl Matcher for Trigram {
+    fn name(&self) -> &'static str {
+        "trigram"
+    }
+
+    fn similarity(
+        &self,
+        features1: &MarketFeatures,
+        features2: &MarketFeatures,
+        _: &Corpus,
+    ) -> f64 {
+        let (trigrams1, trigrams2) = (features1.trigrams(), features2.trigrams());
+        let total = trigrams1.len() + trigrams2.len();
+        if total == 0 {
+            return 0.0;
+        }
+        let shared = trigrams1.intersection(trigrams2).count();
+        2.0 * shared as f64 / total as f64
+    }
+}
+
+/// A weighted mean of other matchers, as configured under
+/// `[matching.scorers]`.
+pub struct Ensemble {
+    scorers: Vec<(Box<dyn Matcher>, f64)>,
+}
+
+impl Ensemble {
+    /// The scorers with a positive weight; Jaccard alone if none has one.
+    pub fn new(weights: &ScorerWeights) -> Self {
+        let candidates: [(Box<dyn Matcher>, f64); 3] = [
+            (Box::new(Jaccard), weights.jaccard),
+            (Box::new(TfIdf), weights.tfidf),
+            (Box::new(Trigram), weights.trigram),
+        ];
+        let scorers: Vec<(Box<dyn Matcher>, f64)> = candidates
+            .into_iter()
+            .filter(|(_, weight)| *weight > 0.0)
+            .collect();
+        if scorers.is_empty() {
+            return Self {
+                scorers: vec![(Box::new(Jaccard), 1.0)],
+            };
+        }
+        Self { scorers }
+    }
+
+    /// Each scorer's name and similarity.
+    pub fn similarities(
+        &self,
+        features1: &MarketFeatures,
+        features2: &MarketFeatures,
+        corpus: &Corpus,
+    ) -> Vec<(&'static str, f64)> {
+        self.scorers
+            .iter()
+            .map(|(scorer, _)| {
+                (
+                    scorer.name(),
+                    scorer.similarity(features1, features2, corpus),
+                )
+            })
+            .collect()
+    }
+
+    /// The weighted mean of `similarities`, as returned by `similarities`.
+    pub fn combine(&self, similarities: &[(&'static str, f64)]) -> f64 {
+        let weight: f64 = self.scorers.iter().map(|(_, weight)| weight).sum();
+        self.scorers
+            .iter()
+            .zip(similarities)
+            .map(|((_, weight), (_, similarity))| similarity * weight)
+            .sum::<f64>()
+            / weight
+    }
+}
+
+impl Matcher for Ensemble {
+    fn name(&self) -> &'static str {
+        "ensemble"
+    }
+
+    fn similarity(
+        &self,
+        features1: &MarketFeatures,
+        features2: &MarketFeatures,
+        corpus: &Corpus,
+    ) -> f64 {
+        self.combine(&self.similarities(features1, features2, corpus))
+    }
+
+    fn uses_corpus(&self) -> bool {
+        self.scorers.iter().any(|(scorer, _)| scorer.uses_corpus())
+    }
+}
+
+/// The character trigrams of each token, padded so that a word's first and
+/// last letters count, e.g. " ra", "rat", "ate", "te " for "rate".
+pub(super) fn trigrams(tokens: &HashSet<String>) -> HashSet<[char; 3]> {
+    let mut trigrams = HashSet::new();
+    for token in tokens {
+        let chars: Vec<char> = std::iter::once(' ')
+            .chain(token.chars())
+            .chain(std::iter::once(' '))
+            .collect();
+        trigrams.extend(
+            chars
+                .windows(3)
+                .map(|window| [window[0], window[1], window[2]]),
+        );
+    }
+    trigrams
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn features(title: &str) -> MarketFeatures {
+        MarketFeatures::from_title(title, Utc::now())
+    }
+
+    #[test]
+    fn test_tfidf_weighs_rare_tokens_more() {
+        let titles = [
+            "Will Trump win Iowa?",
+            "Will Haley win Iowa?",
+            "Will DeSantis win Iowa?",
+            "Will Trump win Nevada?",
+        ];
+        let markets: Vec<MarketFeatures> = titles.iter().map(|title| features(title)).collect();
+        let corpus = Corpus::new(&markets);
+        assert!(corpus.idf("trump") > corpus.idf("iowa"));
+
+        // Sharing the rare "trump" beats sharing the common "iowa"
+        let same_person = TfIdf.similarity(&markets[0], &markets[3], &corpus);
+        let same_state = TfIdf.similarity(&markets[0], &markets[1], &corpus);
+        assert!(
+            same_person > same_state,
+            "{} vs {}",
+            same_person,
+            same_state
+        );
+        assert_eq!(
+            Jaccard.similarity(&markets[0], &markets[3], &corpus),
+            Jaccard.similarity(&markets[0], &markets[1], &corpus)
+        );
+
+        // Without a corpus every token weighs the same
+        let empty = Corpus::default();
+        assert_eq!(empty.idf("trump"), 1.0);
+        assert!((TfIdf.similarity(&markets[0], &markets[0], &empty) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_trigrams_see_word_forms() {
+        let cut = features("Fed rate cut in March?");
+        let cuts = features("Fed cuts rates in March?");
+        let corpus = Corpus::default();
+        assert!(
+            Trigram.similarity(&cut, &cuts, &corpus) > Jaccard.similarity(&cut, &cuts, &corpus)
+        );
+        assert_eq!(Trigram.similarity(&cut, &cut, &corpus), 1.0);
+    }
+
+    #[test]
+    fn test_ensemble_is_weighted_mean_of_configured_scorers() {
+        let weights = ScorerWeights {
+            jaccard: 1.0,
+            tfidf: 0.0,
+            trigram: 3.0,
+        };
+        let ensemble = Ensemble::new(&weights);
+        let names: Vec<&str> = ensemble
+            .similarities(&features("a"), &features("b"), &Corpus::default())
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["jaccard", "trigram"]);
+        assert!(!ensemble.uses_corpus());
+
+        let (one, other) = (
+            features("Fed rate cut in March?"),
+            features("Fed cuts rates in March?"),
+        );
+        let corpus = Corpus::default();
+        let expected = (Jaccard.similarity(&one, &other, &corpus)
+            + 3.0 * Trigram.similarity(&one, &other, &corpus))
+            / 4.0;
+        assert!((ensemble.similarity(&one, &other, &corpus) - expected).abs() < 1e-9);
+
+        let none = Ensemble::new(&ScorerWeights {
+            jaccard: 0.0,
+            tfidf: 0.0,
+            trigram: 0.0,
+        });
+        let names: Vec<&str> = none
+            .similarities(&one, &other, &corpus)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["jaccard"]);
+    }
+}
@@ -1,7 +1,12 @@
 use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
+/// A binary market as seen on one venue.
+///
+/// `yes_price` and `no_price` are the best asks, i.e. what it costs right now
+/// to buy one contract of each outcome. A contract pays out 1 on resolution.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Market {
     pub id: String,
@@ -12,9 +17,220 @@ pub struct Market {
     pub volume: Decimal,
     pub liquidity: Decimal,
     pub end_time: DateTime<Utc>,
+    /// Polymarket CLOB token for the YES outcome; `None` on Kalshi.
+    pub yes_token_id: Option<String>,
+    /// Polymarket CLOB token for the NO outcome; `None` on Kalshi.
+    pub no_token_id: Option<String>,
+    /// How the venue says the market resolves, where it publishes that.
+    #[serde(default)]
+    pub rules: Option<String>,
+    /// Where the outcome is read from, usually a URL; Polymarket only.
+    #[serde(default)]
+    pub resolution_source: Option<String>,
+    /// Delay between close and settlement; Kalshi only.
+    #[serde(default)]
+    pub settlement_timer_seconds: Option<i64>,
+    /// The event this market is one outcome of: a Polymarket event ID or a
+    /// Kalshi event ticker.
+    #[serde(default)]
+    pub event_id: Option<String>,
+    #[serde(default)]
+    pub event_title: Option<String>,
+    /// What YES means within the event, e.g. the candidate in a race.
+    #[serde(default)]
+    pub outcome_name: Option<String>,
+    /// Orders settle through the NegRisk CTF Exchange; Polymarket only.
+    #[serde(default)]
+    pub neg_risk: bool,
+    /// Polymarket's condition ID, under which the venue holds positions;
+    /// `None` on Kalshi.
+    #[serde(default)]
+    pub condition_id: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+impl Market {
+    /// The ID positions and orders in this market are kept under: the
+    /// condition ID where there is one, else the market ID.
+    pub fn position_id(&self) -> &str {
+        self.condition_id.as_deref().unwrap_or(&self.id)
+    }
+
+    /// The same market with YES and NO swapped.
+    pub fn inverted(&self) -> Market {
+        Market {
+            yes_price: self.no_price,
+            no_price: self.yes_price,
+            yes_token_id: self.no_token_id.clone(),
+            no_token_id: self.yes_token_id.clone(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Markets a venue groups under one question, one binary market per
+/// outcome: a Polymarket event, or a Kalshi event ticker's markets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub id: String,
+    pub platform: Platform,
+    pub title: String,
+    /// At most one outcome resolves YES, as in a race with one winner.
+    /// Only known when the venue's event listing says so.
+    pub mutually_exclusive: bool,
+    pub markets: Vec<Market>,
+}
+
+impl Event {
+    /// Groups `markets` by their event, in the order events first appear,
+    /// for venues without an event listing. A market without an event is
+    /// an event of its own, under the market's ID.
+    pub fn group<'a>(markets: impl IntoIterator<Item = &'a Market>) -> Vec<Event> {
+        let mut events: Vec<Event> = Vec::new();
+        let mut index: HashMap<(&Platform, &str), usize> = HashMap::new();
+        for market in markets {
+            let Some(event_id) = market.event_id.as_deref() else {
+                events.push(Event {
+                    id: market.id.clone(),
+                    platform: market.platform.clone(),
+                    title: market.question.clone(),
+                    mutually_exclusive: false,
+                    markets: vec![market.clone()],
+                });
+                continue;
+            };
+            let i = *index.entry((&market.platform, event_id)).or_insert_with(|| {
+                events.push(Event {
+                    id: event_id.to_string(),
+                    platform: market.platform.clone(),
+                    title: market
+                        .event_title
+                        .clone()
+                        .unwrap_or_else(|| market.question.clone()),
+                    mutually_exclusive: false,
+                    markets: Vec::new(),
+                });
+                events.len() - 1
+            });
+            events[i].markets.push(market.clone());
+        }
+        events
+    }
+
+    /// Whether the event has more than one outcome market.
+    pub fn is_multi_outcome(&self) -> bool {
+        self.markets.len() > 1
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriceLevel {
+    pub price: Decimal,
+    pub size: Decimal,
+}
+
+/// Resting asks for both outcomes of a market, best (cheapest) first.
+///
+/// Venues that only publish bids are normalized here: a bid for YES at `p`
+/// is an ask for NO at `1 - p`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderBook {
+    pub platform: Platform,
+    pub market_id: String,
+    pub yes_asks: Vec<PriceLevel>,
+    pub no_asks: Vec<PriceLevel>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl OrderBook {
+    pub fn new(
+        platform: Platform,
+        market_id: String,
+        mut yes_asks: Vec<PriceLevel>,
+        mut no_asks: Vec<PriceLevel>,
+    ) -> Self {
+        yes_asks.retain(|level| level.size > Decimal::ZERO);
+        no_asks.retain(|level| level.size > Decimal::ZERO);
+        yes_asks.sort_by_key(|level| level.price);
+        no_asks.sort_by_key(|level| level.price);
+
+        Self {
+            platform,
+            market_id,
+            yes_asks,
+            no_asks,
+            timestamp: Utc::now(),
+        }
+    }
+
+    pub fn asks(&self, outcome: Outcome) -> &[PriceLevel] {
+        match outcome {
+            Outcome::Yes => &self.yes_asks,
+            Outcome::No => &self.no_asks,
+        }
+    }
+
+    /// The same book with YES and NO swapped.
+    pub fn inverted(&self) -> OrderBook {
+        OrderBook {
+            yes_asks: self.no_asks.clone(),
+            no_asks: self.yes_asks.clone(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Total contracts available across `levels`.
+pub fn book_depth(levels: &[PriceLevel]) -> Decimal {
+    levels.iter().map(|level| level.size).sum()
+}
+
+/// Cost of sweeping `contracts` from `levels`, along with the worst price
+/// touched. Returns `None` if the book is not deep enough.
+pub fn sweep_cost(levels: &[PriceLevel], contracts: Decimal) -> Option<(Decimal, Decimal)> {
+    let mut remaining = contracts;
+    let mut cost = Decimal::ZERO;
+    let mut worst_price = Decimal::ZERO;
+
+    for level in levels {
+        if remaining <= Decimal::ZERO {
+            break;
+        }
+        let take = remaining.min(level.size);
+        cost += take * level.price;
+        worst_price = level.price;
+        remaining -= take;
+    }
+
+    if remaining > Decimal::ZERO {
+        None
+    } else {
+        Some((cost, worst_price))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Outcome {
+    Yes,
+    No,
+}
+
+impl Outcome {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Outcome::Yes => "yes",
+            Outcome::No => "no",
+        }
+    }
+
+    pub fn opposite(&self) -> Outcome {
+        match self {
+            Outcome::Yes => Outcome::No,
+            Outcome::No => Outcome::Yes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Platform {
     Polymarket,
     Kalshi,
@@ -29,55 +245,418 @@ impl Platform {
     }
 }
 
+/// A locked-profit pair: buy YES on one venue and NO on the other.
+///
+/// Exactly one of the two legs pays out on resolution, so holding both
+/// returns `payout` per contract regardless of the outcome. Leg prices are
+/// volume-weighted over the book levels needed to fill `position_size`, and
+/// the limit prices are the worst levels touched. `gross_edge` and
+/// `net_edge` are per contract; `total_fees` covers both legs of the whole
+/// position.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArbitrageOpportunity {
     pub id: Option<i64>,
     pub polymarket_market_id: String,
     pub kalshi_market_id: String,
-    pub buy_platform: Platform,
-    pub sell_platform: Platform,
-    pub buy_price: Decimal,
-    pub sell_price: Decimal,
+    /// CLOB token for whichever outcome is bought on Polymarket.
+    pub polymarket_token_id: Option<String>,
+    /// The Polymarket market is neg-risk; see [`Market::neg_risk`].
+    #[serde(default)]
+    pub polymarket_neg_risk: bool,
+    /// See [`Market::condition_id`].
+    #[serde(default)]
+    pub polymarket_condition_id: Option<String>,
+    pub yes_platform: Platform,
+    pub no_platform: Platform,
+    pub yes_price: Decimal,
+    pub no_price: Decimal,
+    pub yes_limit_price: Decimal,
+    pub no_limit_price: Decimal,
+    pub payout: Decimal,
+    pub gross_edge: Decimal,
+    pub total_fees: Decimal,
+    pub net_edge: Decimal,
     pub profit_percentage: Decimal,
     pub estimated_profit: Decimal,
     pub position_size: Decimal,
     pub detected_at: DateTime<Utc>,
     pub executed: bool,
+    /// The Kalshi market's YES is the Polymarket market's NO, so each
+    /// outcome is bought as its opposite on Kalshi.
+    #[serde(default)]
+    pub kalshi_inverted: bool,
+    /// The Kalshi market's event ticker, where the venue listed one.
+    #[serde(default)]
+    pub kalshi_event_id: Option<String>,
+}
+
+impl ArbitrageOpportunity {
+    /// Combined cost of one YES contract plus one NO contract.
+    pub fn total_cost(&self) -> Decimal {
+        self.yes_price + self.no_price
+    }
+
+    /// The ID `platform` keeps this opportunity's positions and orders
+    /// under; see [`Market::position_id`].
+    pub fn position_id(&self, platform: &Platform) -> &str {
+        match platform {
+            Platform::Polymarket => self
+                .polymarket_condition_id
+                .as_deref()
+                .unwrap_or(&self.polymarket_market_id),
+            Platform::Kalshi => &self.kalshi_market_id,
+        }
+    }
+
+    /// The outcome `platform` trades for this opportunity's `outcome`.
+    /// Converting a venue's outcome back works the same way.
+    pub fn venue_outcome(&self, platform: &Platform, outcome: Outcome) -> Outcome {
+        match platform {
+            Platform::Kalshi if self.kalshi_inverted => outcome.opposite(),
+            _ => outcome,
+        }
+    }
 }
 
+/// One order the bot sent for an opportunity, as recorded in `trades`.
+///
+/// `price` and `amount` are what was asked for; `fill_price` is the
+/// average over the fills so far. `executed_at` is set once the order is
+/// completely filled.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trade {
     pub id: Option<i64>,
     pub opportunity_id: i64,
     pub platform: Platform,
     pub market_id: String,
+    /// The venue's order ID; `None` when the order was refused outright
+    /// or its placement is still unconfirmed.
+    pub order_id: Option<String>,
+    /// Our own ID for the order, by which it is found when the venue's
+    /// reply to it was lost.
+    pub client_order_id: Option<String>,
+    pub outcome: Outcome,
     pub side: TradeSide,
     pub price: Decimal,
     pub amount: Decimal,
-    pub status: TradeStatus,
+    pub filled_amount: Decimal,
+    pub fill_price: Option<Decimal>,
+    pub status: OrderStatus,
     pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
     pub executed_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TradeSide {
     Buy,
     Sell,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum TradeStatus {
-    Pending,
-    Executed,
-    Failed,
-    Cancelled,
+impl TradeSide {
+    pub fn as_str(&self) -> &str {
+        match self {
+            TradeSide::Buy => "buy",
+            TradeSide::Sell => "sell",
+        }
+    }
 }
 
+/// Contracts held in one outcome of a market.
+///
+/// `current_value` is the venue's mark where it reports one, and the cost
+/// basis otherwise.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Position {
     pub platform: Platform,
     pub market_id: String,
+    pub outcome: Outcome,
     pub amount: Decimal,
     pub entry_price: Decimal,
     pub current_value: Decimal,
 }
+
+/// A market the venue settled, paying out the account's winning contracts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Settlement {
+    pub platform: Platform,
+    pub market_id: String,
+    /// Paid out for the contracts held, in dollars.
+    pub revenue: Decimal,
+    /// What the contracts held at settlement cost.
+    pub cost: Decimal,
+    pub settled_at: DateTime<Utc>,
+}
+
+impl Settlement {
+    pub fn pnl(&self) -> Decimal {
+        self.revenue - self.cost
+    }
+}
+
+/// A limit order for `size` contracts of one outcome at `price` dollars.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderRequest {
+    pub market_id: String,
+    /// The venue's instrument for the outcome, where it is not identified
+    /// by the market and outcome alone (Polymarket's CLOB token).
+    pub token_id: Option<String>,
+    pub outcome: Outcome,
+    pub side: TradeSide,
+    pub price: Decimal,
+    pub size: Decimal,
+    /// The market is neg-risk, so Polymarket verifies the order against
+    /// the NegRisk CTF Exchange.
+    pub neg_risk: bool,
+    /// Our own ID for the order, by which `Exchange::find_order` looks it
+    /// up when the reply to `place_order` was lost.
+    pub client_order_id: Option<String>,
+}
+
+/// Where an order stands on its venue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OrderStatus {
+    /// Sent, but the venue's reply was lost, so it may or may not have
+    /// been placed.
+    Pending,
+    New,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    Rejected,
+    Expired,
+}
+
+impl OrderStatus {
+    /// No further fills can happen.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            OrderStatus::Filled | OrderStatus::Cancelled | OrderStatus::Rejected | OrderStatus::Expired
+        )
+    }
+
+    /// Whether a venue may report `next` after `self`. Orders only move
+    /// forward: `New` → `PartiallyFilled` (repeatedly) → `Filled`,
+    /// `Cancelled` or `Expired`. Only a `New` order can be `Rejected`, and
+    /// nothing follows a terminal state. A `Pending` order may turn out to
+    /// be in any state.
+    pub fn can_transition_to(&self, next: OrderStatus) -> bool {
+        match self {
+            OrderStatus::Pending => next != OrderStatus::Pending,
+            OrderStatus::New => !matches!(next, OrderStatus::Pending | OrderStatus::New),
+            OrderStatus::PartiallyFilled => !matches!(
+                next,
+                OrderStatus::Pending | OrderStatus::New | OrderStatus::Rejected
+            ),
+            _ => false,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            OrderStatus::Pending => "pending",
+            OrderStatus::New => "new",
+            OrderStatus::PartiallyFilled => "partially_filled",
+            OrderStatus::Filled => "filled",
+            OrderStatus::Cancelled => "cancelled",
+            OrderStatus::Rejected => "rejected",
+            OrderStatus::Expired => "expired",
+        }
+    }
+}
+
+/// A venue's view of one order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Order {
+    pub id: String,
+    pub platform: Platform,
+    pub market_id: String,
+    pub outcome: Outcome,
+    pub side: TradeSide,
+    pub price: Decimal,
+    pub size: Decimal,
+    pub filled_size: Decimal,
+    pub status: OrderStatus,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// One execution against an order. A single order can fill in several
+/// pieces at different prices.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fill {
+    /// The venue's trade ID.
+    pub id: String,
+    pub order_id: String,
+    pub platform: Platform,
+    pub market_id: String,
+    pub outcome: Outcome,
+    pub side: TradeSide,
+    pub price: Decimal,
+    pub size: Decimal,
+    pub filled_at: Option<DateTime<Utc>>,
+}
+
+/// Size-weighted average price of `fills`, or `None` if nothing filled.
+pub fn average_fill_price(fills: &[Fill]) -> Option<Decimal> {
+    let size: Decimal = fills.iter().map(|fill| fill.size).sum();
+    if size.is_zero() {
+        return None;
+    }
+
+    let cost: Decimal = fills.iter().map(|fill| fill.price * fill.size).sum();
+    Some(cost / size)
+}
+
+/// What to do when one leg of an arbitrage fills and the other does not.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LegRecovery {
+    /// Buy the missing contracts on the other venue, up to a price cap.
+    Chase,
+    /// Sell the contracts the filled leg bought.
+    #[default]
+    Unwind,
+    /// Keep the position and alert.
+    Hold,
+}
+
+impl LegRecovery {
+    pub fn as_str(&self) -> &str {
+        match self {
+            LegRecovery::Chase => "chase",
+            LegRecovery::Unwind => "unwind",
+            LegRecovery::Hold => "hold",
+        }
+    }
+}
+
+/// An arbitrage left with more contracts on one leg than the other.
+///
+/// `exposed_amount` contracts of `outcome` on `platform` were unhedged;
+/// recovery took care of `resolved_amount` of them. `cost` is what
+/// recovery lost relative to the planned trade: the extra paid to chase the
+/// missing leg, or the loss on unwinding the filled one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegRiskEvent {
+    pub id: Option<i64>,
+    pub opportunity_id: i64,
+    pub platform: Platform,
+    pub market_id: String,
+    pub outcome: Outcome,
+    pub exposed_amount: Decimal,
+    pub policy: LegRecovery,
+    pub resolved_amount: Decimal,
+    pub cost: Decimal,
+    pub detected_at: DateTime<Utc>,
+    pub resolved_at: DateTime<Utc>,
+}
+
+impl LegRiskEvent {
+    /// Contracts still unhedged after recovery.
+    pub fn remaining_amount(&self) -> Decimal {
+        self.exposed_amount - self.resolved_amount
+    }
+}
+
+/// One venue's market listing as it was fetched at `recorded_at`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketRecording {
+    pub platform: Platform,
+    pub recorded_at: DateTime<Utc>,
+    pub markets: Vec<Market>,
+    /// Events the venue listed as mutually exclusive, which grouping the
+    /// markets back into events cannot tell.
+    #[serde(default)]
+    pub exclusive_events: Vec<String>,
+}
+
+/// A venue's simulated cash in paper trading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperAccount {
+    pub platform: Platform,
+    pub starting_balance: Decimal,
+    pub balance: Decimal,
+}
+
+/// Why trading was halted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TripReason {
+    /// An operator halted trading.
+    Manual,
+    ConsecutiveRejections,
+    LegRisk,
+    DailyLoss,
+    VenueErrors,
+    StaleData,
+}
+
+impl TripReason {
+    pub fn as_str(&self) -> &str {
+        match self {
+            TripReason::Manual => "manual",
+            TripReason::ConsecutiveRejections => "consecutive_rejections",
+            TripReason::LegRisk => "leg_risk",
+            TripReason::DailyLoss => "daily_loss",
+            TripReason::VenueErrors => "venue_errors",
+            TripReason::StaleData => "stale_data",
+        }
+    }
+}
+
+/// An operator's verdict on a fuzzy-matched pair. Only approved pairs are
+/// traded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl MatchStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            MatchStatus::Pending => "pending",
+            MatchStatus::Approved => "approved",
+            MatchStatus::Rejected => "rejected",
+        }
+    }
+}
+
+/// A Polymarket/Kalshi pair found by fuzzy matching, as recorded in
+/// `market_matches` for review.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketMatch {
+    pub id: Option<i64>,
+    pub polymarket_market_id: String,
+    pub kalshi_market_id: String,
+    pub polymarket_question: String,
+    pub kalshi_question: String,
+    pub polymarket_end_time: DateTime<Utc>,
+    pub kalshi_end_time: DateTime<Utc>,
+    pub similarity: f64,
+    /// Each component of `similarity`, for the reviewer.
+    pub explanation: String,
+    pub polymarket_rules: Option<String>,
+    pub kalshi_rules: Option<String>,
+    /// Where the two markets' rules disagree on how they resolve, if they do.
+    pub resolution_risk: Option<String>,
+    pub status: MatchStatus,
+    /// Approved with YES on one venue being NO on the other.
+    pub inverted: bool,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub decided_at: Option<DateTime<Utc>>,
+}
+
+/// One halt of trading, open until `resumed_at` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreakerTrip {
+    pub id: Option<i64>,
+    pub reason: TripReason,
+    pub detail: String,
+    pub tripped_at: DateTime<Utc>,
+    pub resumed_at: Option<DateTime<Utc>>,
+}
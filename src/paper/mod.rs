@@ -0,0 +1,414 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use log::{error, warn};
+use rust_decimal::Decimal;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::time::{sleep, Duration};
+
+use crate::{
+    api::{ApiError, ApiResult, Exchange},
+    config::PaperConfig,
+    database::Database,
+    fees::{FeeModel, Liquidity},
+    models::{
+        Event, Fill, Market, Order, OrderBook, OrderRequest, OrderStatus, Outcome, Platform,
+        Position, PriceLevel, TradeSide,
+    },
+};
+
+/// A venue whose orders are simulated instead of sent.
+///
+/// Market data comes from the wrapped client. Orders are matched against
+/// its live order book `latency_ms` after they are placed, and again each
+/// time they are checked while they rest, taking only the depth resting at
+/// or better than the limit and paying taker fees. Depth the account has
+/// taken is held back from later matches until its price level leaves the
+/// book, so a resting order does not fill twice against the same
+/// liquidity. Balances, orders, fills
+/// and positions are kept in the database's `paper_*` tables, so an account
+/// carries over between runs. Markets are not settled, and positions are
+/// valued at cost.
+pub struct PaperExchange {
+    inner: Arc<dyn Exchange>,
+    fees: Box<dyn FeeModel>,
+    database: Database,
+    starting_balance: Decimal,
+    latency: Duration,
+    /// Every market the inner client has listed, by its position ID, so an
+    /// order's market ID can be turned back into the market its book is
+    /// fetched with.
+    markets: Mutex<HashMap<String, Market>>,
+    /// Contracts taken from each price level, by market and the outcome
+    /// whose asks were hit.
+    taken: Mutex<HashMap<(String, Outcome), HashMap<Decimal, Decimal>>>,
+    /// Held while a fill updates the account, so fills cannot interleave.
+    account: tokio::sync::Mutex<()>,
+}
+
+impl PaperExchange {
+    pub fn new(
+        inner: Arc<dyn Exchange>,
+        fees: Box<dyn FeeModel>,
+        database: Database,
+        config: &PaperConfig,
+    ) -> Self {
+        Self {
+            inner,
+            fees,
+            database,
+            starting_balance: Decimal::try_from(config.starting_balance).unwrap_or_default(),
+            latency: Duration::from_millis(config.latency_ms),
+            markets: Mutex::new(HashMap::new()),
+            taken: Mutex::new(HashMap::new()),
+            account: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    fn remember(&self, markets: &[Market]) {
+        let mut cache = self
+            .markets
+            .lock()
+            .expect("paper market cache lock poisoned");
+        for market in markets {
+            cache.insert(market.position_id().to_string(), market.clone());
+        }
+    }
+
+    fn market(&self, market_id: &str) -> ApiResult<Market> {
+        self.markets
+            .lock()
+            .expect("paper market cache lock poisoned")
+            .get(market_id)
+            .cloned()
+            .ok_or_else(|| ApiError::InvalidOrder {
+                reason: format!("unknown market {}", market_id),
+            })
+    }
+
+    async fn balance(&self) -> ApiResult<Decimal> {
+        let account = self
+            .database
+            .open_paper_account(&self.platform(), self.starting_balance)
+            .await
+            .map_err(storage_error)?;
+        Ok(account.balance)
+    }
+
+    async fn position(&self, market_id: &str, outcome: Outcome) -> ApiResult<Position> {
+        let held = self
+            .database
+            .get_paper_positions(&self.platform())
+            .await
+            .map_err(storage_error)?
+            .into_iter()
+            .find(|position| position.market_id == market_id && position.outcome == outcome);
+
+        Ok(held.unwrap_or(Position {
+            platform: self.platform(),
+            market_id: market_id.to_string(),
+            outcome,
+            amount: Decimal::ZERO,
+            entry_price: Decimal::ZERO,
+            current_value: Decimal::ZERO,
+        }))
+    }
+
+    /// Fills as much of an open order as the venue's current book allows
+    /// and returns the order as it then stands.
+    async fn match_order(&self, order_id: &str) -> ApiResult<Order> {
+        let market = self.market(&self.load_order(order_id).await?.market_id)?;
+        let book = self.inner.get_order_book(&market).await?;
+
+        let _account = self.account.lock().await;
+        let mut order = self.load_order(order_id).await?;
+        if order.status.is_terminal() {
+            return Ok(order);
+        }
+
+        let mut position = self.position(&order.market_id, order.outcome).await?;
+        let mut remaining = order.size - order.filled_size;
+        if order.side == TradeSide::Sell {
+            remaining = remaining.min(position.amount);
+        }
+
+        // A sell hits the bids, which are the other outcome's asks: a bid
+        // at `p` is an ask at `1 - p`
+        let asks_hit = match order.side {
+            TradeSide::Buy => order.outcome,
+            TradeSide::Sell => order.outcome.opposite(),
+        };
+        let key = (order.market_id.clone(), asks_hit);
+        let available = self.available(&key, book.asks(asks_hit));
+
+        let (mut size, mut notional) = (Decimal::ZERO, Decimal::ZERO);
+        let mut taking = Vec::new();
+        for level in &available {
+            let (price, crosses) = match order.side {
+                TradeSide::Buy => (level.price, level.price <= order.price),
+                TradeSide::Sell => (
+                    Decimal::ONE - level.price,
+                    level.price <= Decimal::ONE - order.price,
+                ),
+            };
+            if !crosses || remaining <= Decimal::ZERO {
+                break;
+            }
+            let take = remaining.min(level.size);
+            if take <= Decimal::ZERO {
+                continue;
+            }
+            size += take;
+            notional += take * price;
+            remaining -= take;
+            taking.push((level.price, take));
+        }
+        if size <= Decimal::ZERO {
+            return Ok(order);
+        }
+
+        let price = notional / size;
+        let fee = self.fees.order_fee(price, size, Liquidity::Taker);
+        let mut balance = self.balance().await?;
+
+        match order.side {
+            TradeSide::Buy => {
+                if notional + fee > balance {
+                    warn!(
+                        "Paper {} order {} cannot afford its fill of {} at {}",
+                        self.platform().as_str(),
+                        order.id,
+                        size,
+                        price
+                    );
+                    return Ok(order);
+                }
+                balance -= notional + fee;
+                position.entry_price =
+                    (position.amount * position.entry_price + notional) / (position.amount + size);
+                position.amount += size;
+            }
+            TradeSide::Sell => {
+                balance += notional - fee;
+                position.amount -= size;
+            }
+        }
+
+        order.filled_size += size;
+        order.status = if order.filled_size >= order.size {
+            OrderStatus::Filled
+        } else {
+            OrderStatus::PartiallyFilled
+        };
+
+        let fill = Fill {
+            id: format!("paper-fill-{:016x}", rand::random::<u64>()),
+            order_id: order.id.clone(),
+            platform: self.platform(),
+            market_id: order.market_id.clone(),
+            outcome: order.outcome,
+            side: order.side,
+            price,
+            size,
+            filled_at: Some(Utc::now()),
+        };
+        self.database
+            .record_paper_fill(&order, &fill, fee, balance, &position)
+            .await
+            .map_err(storage_error)?;
+
+        let mut taken = self.taken.lock().expect("paper book lock poisoned");
+        let levels = taken.entry(key).or_default();
+        for (price, take) in taking {
+            *levels.entry(price).or_default() += take;
+        }
+        Ok(order)
+    }
+
+    /// `asks` less what the account has already taken from them. Levels no
+    /// longer in the book are forgotten.
+    fn available(&self, key: &(String, Outcome), asks: &[PriceLevel]) -> Vec<PriceLevel> {
+        let mut taken = self.taken.lock().expect("paper book lock poisoned");
+        let levels = taken.entry(key.clone()).or_default();
+        levels.retain(|price, _| asks.iter().any(|level| level.price == *price));
+
+        asks.iter()
+            .map(|level| PriceLevel {
+                price: level.price,
+                size: (level.size - levels.get(&level.price).copied().unwrap_or_default())
+                    .max(Decimal::ZERO),
+            })
+            .collect()
+    }
+
+    async fn load_order(&self, order_id: &str) -> ApiResult<Order> {
+        self.database
+            .get_paper_order(order_id)
+            .await
+            .map_err(storage_error)?
+            .ok_or_else(|| ApiError::InvalidOrder {
+                reason: format!("unknown order {}", order_id),
+            })
+    }
+}
+
+/// Paper orders sent with a client order ID are saved under it, so they
+/// can be found again.
+fn paper_order_id(client_order_id: &str) -> String {
+    format!("paper-{}", client_order_id)
+}
+
+/// The paper account could not be read or written; surfaced as the
+/// simulated venue being unavailable.
+fn storage_error(e: anyhow::Error) -> ApiError {
+    error!("Paper account storage failed: {:#}", e);
+    ApiError::Server { status: 500 }
+}
+
+#[async_trait]
+impl Exchange for PaperExchange {
+    fn platform(&self) -> Platform {
+        self.inner.platform()
+    }
+
+    async fn get_markets(&self) -> ApiResult<Vec<Market>> {
+        let markets = self.inner.get_markets().await?;
+        self.remember(&markets);
+        Ok(markets)
+    }
+
+    async fn get_events(&self) -> ApiResult<Vec<Event>> {
+        let events = self.inner.get_events().await?;
+        for event in &events {
+            self.remember(&event.markets);
+        }
+        Ok(events)
+    }
+
+    async fn get_order_book(&self, market: &Market) -> ApiResult<OrderBook> {
+        self.remember(std::slice::from_ref(market));
+        self.inner.get_order_book(market).await
+    }
+
+    async fn place_order(&self, request: &OrderRequest) -> ApiResult<String> {
+        if request.price <= Decimal::ZERO
+            || request.price >= Decimal::ONE
+            || request.size <= Decimal::ZERO
+        {
+            return Err(ApiError::InvalidOrder {
+                reason: format!("{} contracts at {}", request.size, request.price),
+            });
+        }
+        self.market(&request.market_id)?;
+
+        match request.side {
+            TradeSide::Buy => {
+                let fee = self
+                    .fees
+                    .order_fee(request.price, request.size, Liquidity::Taker);
+                if request.price * request.size + fee > self.balance().await? {
+                    return Err(ApiError::InsufficientFunds);
+                }
+            }
+            TradeSide::Sell => {
+                let held = self.position(&request.market_id, request.outcome).await?;
+                if held.amount < request.size {
+                    return Err(ApiError::InvalidOrder {
+                        reason: format!("only {} contracts held", held.amount),
+                    });
+                }
+            }
+        }
+
+        let order = Order {
+            id: match &request.client_order_id {
+                Some(client_order_id) => paper_order_id(client_order_id),
+                None => format!("paper-{:016x}", rand::random::<u64>()),
+            },
+            platform: self.platform(),
+            market_id: request.market_id.clone(),
+            outcome: request.outcome,
+            side: request.side,
+            price: request.price,
+            size: request.size,
+            filled_size: Decimal::ZERO,
+            status: OrderStatus::New,
+            created_at: Some(Utc::now()),
+        };
+        self.database
+            .save_paper_order(&order)
+            .await
+            .map_err(storage_error)?;
+
+        sleep(self.latency).await;
+        if let Err(e) = self.match_order(&order.id).await {
+            warn!("Failed to match paper order {}: {}", order.id, e);
+        }
+
+        Ok(order.id)
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> ApiResult<()> {
+        let _account = self.account.lock().await;
+        let mut order = self.load_order(order_id).await?;
+        if order.status.is_terminal() {
+            return Err(ApiError::InvalidOrder {
+                reason: format!("order is {}", order.status.as_str()),
+            });
+        }
+
+        order.status = OrderStatus::Cancelled;
+        self.database
+            .save_paper_order(&order)
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn get_order(&self, order_id: &str) -> ApiResult<Order> {
+        let order = self.load_order(order_id).await?;
+        if order.status.is_terminal() {
+            return Ok(order);
+        }
+
+        // A resting order fills once the book comes to it
+        match self.match_order(order_id).await {
+            Ok(order) => Ok(order),
+            Err(e) => {
+                warn!("Failed to match paper order {}: {}", order_id, e);
+                Ok(order)
+            }
+        }
+    }
+
+    async fn find_order(&self, request: &OrderRequest) -> ApiResult<Option<Order>> {
+        let client_order_id = request.client_order_id.as_deref().ok_or_else(|| {
+            ApiError::InvalidOrder {
+                reason: "the order has no client order ID to look up".to_string(),
+            }
+        })?;
+        self.database
+            .get_paper_order(&paper_order_id(client_order_id))
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn list_fills(&self, order_id: &str) -> ApiResult<Vec<Fill>> {
+        self.database
+            .get_paper_fills(order_id)
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn get_positions(&self) -> ApiResult<Vec<Position>> {
+        self.database
+            .get_paper_positions(&self.platform())
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn get_balance(&self) -> ApiResult<Decimal> {
+        self.balance().await
+    }
+}
@@ -0,0 +1,330 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    sync::broadcast::error::RecvError,
+    time::{interval, Duration, Instant, MissedTickBehavior},
+};
+
+use crate::{
+    config::BreakerConfig,
+    database::Database,
+    execution::OrderTracker,
+    models::{BreakerTrip, OrderStatus, Platform, Trade, TripReason},
+};
+
+/// Outcomes of a venue's recent calls.
+struct VenueHealth {
+    recent: VecDeque<bool>,
+    last_success: Instant,
+}
+
+struct BreakerState {
+    trip: Option<BreakerTrip>,
+    consecutive_rejections: u32,
+    venues: HashMap<Platform, VenueHealth>,
+    /// Venues that have not answered yet count as healthy from here.
+    started: Instant,
+    resumed_at: Option<DateTime<Utc>>,
+}
+
+/// Halts trading, by operator request or when something looks wrong,
+/// until an operator resumes it.
+///
+/// A trip cancels every order the tracker is following and blocks new
+/// executions while the engine keeps monitoring. Trips are saved to
+/// `breaker_trips`, so a restarted bot stays halted. Operators halt with
+/// `--mode halt` or by creating `halt_file`, and resume with
+/// `--mode resume` or by creating `resume_file`.
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    tracker: OrderTracker,
+    database: Database,
+    config: BreakerConfig,
+    state: Arc<Mutex<BreakerState>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(tracker: OrderTracker, database: Database, config: &BreakerConfig) -> Self {
+        Self {
+            tracker,
+            database,
+            config: config.clone(),
+            state: Arc::new(Mutex::new(BreakerState {
+                trip: None,
+                consecutive_rejections: 0,
+                venues: HashMap::new(),
+                started: Instant::now(),
+                resumed_at: None,
+            })),
+        }
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        self.lock().trip.is_some()
+    }
+
+    pub fn active_trip(&self) -> Option<BreakerTrip> {
+        self.lock().trip.clone()
+    }
+
+    /// Halts trading for `reason`. Does nothing if trading is already
+    /// halted.
+    pub async fn trip(&self, reason: TripReason, detail: impl Into<String>) -> Result<()> {
+        let mut trip = BreakerTrip {
+            id: None,
+            reason,
+            detail: detail.into(),
+            tripped_at: Utc::now(),
+            resumed_at: None,
+        };
+
+        {
+            let mut state = self.lock();
+            if state.trip.is_some() {
+                return Ok(());
+            }
+            state.trip = Some(trip.clone());
+        }
+
+        error!(
+            "CIRCUIT BREAKER TRIPPED [{}]: {}",
+            reason.as_str(),
+            trip.detail
+        );
+        self.cancel_all().await;
+
+        trip.id = Some(self.database.save_breaker_trip(&trip).await?);
+        if let Some(active) = self.lock().trip.as_mut() {
+            active.id = trip.id;
+        }
+        Ok(())
+    }
+
+    /// Clears every trip and starts counting afresh.
+    pub async fn resume(&self) -> Result<()> {
+        let now = Utc::now();
+        self.database.resume_breaker_trips(now).await?;
+        self.reset(now);
+        Ok(())
+    }
+
+    /// Counts a rejection towards `max_consecutive_rejections`; a fill
+    /// starts the count again.
+    pub async fn record_order(&self, trade: &Trade) -> Result<()> {
+        let rejections = {
+            let mut state = self.lock();
+            match trade.status {
+                OrderStatus::Rejected => state.consecutive_rejections += 1,
+                OrderStatus::PartiallyFilled | OrderStatus::Filled => {
+                    state.consecutive_rejections = 0
+                }
+                _ => {}
+            }
+            state.consecutive_rejections
+        };
+
+        let limit = self.config.max_consecutive_rejections;
+        if limit > 0 && rejections >= limit {
+            self.trip(
+                TripReason::ConsecutiveRejections,
+                format!("{} orders rejected in a row", rejections),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Records whether a call to `platform` succeeded. Trips when the venue
+    /// has gone `max_data_staleness_seconds` without answering, or when
+    /// `max_error_rate` of its last `error_window` calls failed.
+    pub async fn record_venue_call(&self, platform: Platform, ok: bool) -> Result<()> {
+        let verdict = {
+            let mut state = self.lock();
+            let started = state.started;
+            let health = state
+                .venues
+                .entry(platform.clone())
+                .or_insert_with(|| VenueHealth {
+                    recent: VecDeque::new(),
+                    last_success: started,
+                });
+
+            health.recent.push_back(ok);
+            while health.recent.len() > self.config.error_window {
+                health.recent.pop_front();
+            }
+            if ok {
+                health.last_success = Instant::now();
+            }
+
+            let silent = health.last_success.elapsed();
+            let failures = health.recent.iter().filter(|ok| !**ok).count();
+            let max_staleness = Duration::from_secs(self.config.max_data_staleness_seconds);
+
+            if !ok && !max_staleness.is_zero() && silent >= max_staleness {
+                Some((
+                    TripReason::StaleData,
+                    format!(
+                        "no data from {} for {}s",
+                        platform.as_str(),
+                        silent.as_secs()
+                    ),
+                ))
+            } else if self.config.max_error_rate > 0.0
+                && self.config.error_window > 0
+                && health.recent.len() == self.config.error_window
+                && failures as f64 / health.recent.len() as f64 >= self.config.max_error_rate
+            {
+                Some((
+                    TripReason::VenueErrors,
+                    format!(
+                        "{} of the last {} {} calls failed",
+                        failures,
+                        health.recent.len(),
+                        platform.as_str()
+                    ),
+                ))
+            } else {
+                None
+            }
+        };
+
+        if let Some((reason, detail)) = verdict {
+            self.trip(reason, detail).await?;
+        }
+        Ok(())
+    }
+
+    /// Applies operator actions and counts today's leg-risk events.
+    pub async fn check(&self) -> Result<()> {
+        let halt_file = self.flag(&self.config.halt_file);
+
+        if self.is_tripped() {
+            if self.flag(&self.config.resume_file) {
+                fs::remove_file(&self.config.resume_file)?;
+                if halt_file {
+                    warn!(
+                        "Not resuming trading while {} exists",
+                        self.config.halt_file
+                    );
+                } else {
+                    self.resume().await?;
+                }
+            } else if self.persisted() && self.database.get_active_breaker_trip().await?.is_none() {
+                // Resumed from the command line
+                self.reset(Utc::now());
+            }
+            return Ok(());
+        }
+
+        if let Some(trip) = self.database.get_active_breaker_trip().await? {
+            error!("Trading halted [{}]: {}", trip.reason.as_str(), trip.detail);
+            self.lock().trip = Some(trip);
+            self.cancel_all().await;
+            return Ok(());
+        }
+
+        if halt_file {
+            return self
+                .trip(
+                    TripReason::Manual,
+                    format!("{} exists", self.config.halt_file),
+                )
+                .await;
+        }
+
+        let limit = self.config.max_leg_risk_events;
+        if limit > 0 {
+            let since = self.counting_since().await?;
+            let events = self.database.get_leg_risk_events_since(since).await?.len();
+            if events >= limit as usize {
+                self.trip(
+                    TripReason::LegRisk,
+                    format!("{} leg-risk events since {}", events, since.to_rfc3339()),
+                )
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks every `check_interval_ms` and follows the tracker's orders
+    /// until the task is dropped.
+    pub async fn run(self) {
+        let mut updates = self.tracker.subscribe();
+        let mut ticker = interval(Duration::from_millis(self.config.check_interval_ms.max(1)));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            let result = tokio::select! {
+                _ = ticker.tick() => self.check().await,
+                update = updates.recv() => match update {
+                    Ok(trade) => self.record_order(&trade).await,
+                    Err(RecvError::Lagged(_)) => Ok(()),
+                    Err(RecvError::Closed) => return,
+                },
+            };
+
+            if let Err(e) = result {
+                warn!("Circuit breaker check failed: {:#}", e);
+            }
+        }
+    }
+
+    async fn cancel_all(&self) {
+        let cancelled = self.tracker.cancel_all().await;
+        if cancelled > 0 {
+            info!("Cancelling {} resting orders", cancelled);
+        }
+    }
+
+    fn reset(&self, now: DateTime<Utc>) {
+        let mut state = self.lock();
+        state.trip = None;
+        state.consecutive_rejections = 0;
+        state.venues.clear();
+        state.started = Instant::now();
+        state.resumed_at = Some(now);
+        info!("Trading resumed");
+    }
+
+    /// Leg-risk events are counted from midnight UTC, or from the last
+    /// resume if that was later. Resumes are read back from
+    /// `breaker_trips`, so a restart does not count events an operator
+    /// already resumed past.
+    async fn counting_since(&self) -> Result<DateTime<Utc>> {
+        let midnight = Utc::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight is a valid time")
+            .and_utc();
+
+        let saved = self.database.get_last_breaker_resume().await?;
+        let resumed_at = self.lock().resumed_at.max(saved);
+        Ok(resumed_at.map_or(midnight, |resumed_at| resumed_at.max(midnight)))
+    }
+
+    /// Whether the active trip made it to the database.
+    fn persisted(&self) -> bool {
+        self.lock()
+            .trip
+            .as_ref()
+            .is_some_and(|trip| trip.id.is_some())
+    }
+
+    fn flag(&self, path: &str) -> bool {
+        !path.is_empty() && Path::new(path).exists()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        self.state.lock().expect("circuit breaker lock poisoned")
+    }
+}
@@ -0,0 +1,474 @@
+pub mod breaker;
+
+pub use breaker::CircuitBreaker;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use rust_decimal::Decimal;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::Arc,
+};
+
+use crate::{
+    api::Exchange,
+    config::{FeesConfig, RiskConfig},
+    database::Database,
+    execution::OrderTracker,
+    fees::{FeeModel, KalshiFeeModel, Liquidity, PolymarketFeeModel},
+    models::{ArbitrageOpportunity, Platform, Settlement, Trade, TradeSide},
+};
+
+/// Why the risk check blocked or shrank a trade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RiskCode {
+    /// Today's losses have reached `max_daily_loss`.
+    DailyLoss,
+    /// The trade would open more than `max_open_positions` markets.
+    OpenPositions,
+    /// A leg would push one market past `max_market_exposure`.
+    MarketExposure,
+    /// The pair would push its event past `max_event_exposure`.
+    EventExposure,
+    /// A venue does not hold enough cash for its leg.
+    Balance,
+    /// The trade would commit more than `position_size_percentage` of the
+    /// combined balance.
+    PositionSize,
+    /// Balances or positions could not be fetched.
+    Unavailable,
+}
+
+impl RiskCode {
+    pub fn as_str(&self) -> &str {
+        match self {
+            RiskCode::DailyLoss => "daily_loss",
+            RiskCode::OpenPositions => "open_positions",
+            RiskCode::MarketExposure => "market_exposure",
+            RiskCode::EventExposure => "event_exposure",
+            RiskCode::Balance => "balance",
+            RiskCode::PositionSize => "position_size",
+            RiskCode::Unavailable => "unavailable",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RiskDecision {
+    /// Trade `size` contracts of each leg; `limited_by` lists the limits
+    /// that cut it below the opportunity's size.
+    Approve {
+        size: Decimal,
+        limited_by: Vec<RiskCode>,
+    },
+    Reject {
+        code: RiskCode,
+        reason: String,
+    },
+}
+
+/// Account state the limits are checked against.
+#[derive(Debug, Clone, Default)]
+pub struct RiskSnapshot {
+    /// Cash available to trade on each venue, in dollars.
+    pub balances: HashMap<Platform, Decimal>,
+    /// Capital committed per venue market: the cost basis of positions
+    /// held plus the unfilled part of resting buy orders.
+    pub exposure: HashMap<(Platform, String), Decimal>,
+    /// Event each market in `exposure` belongs to.
+    pub events: HashMap<(Platform, String), String>,
+    /// Gain or loss locked in since midnight UTC: contracts sold above or
+    /// below what they were bought for and markets settled, less the fees
+    /// of every fill.
+    pub realized_pnl: Decimal,
+    /// Mark-to-market gain or loss of the positions held.
+    pub unrealized_pnl: Decimal,
+}
+
+impl RiskSnapshot {
+    pub fn daily_pnl(&self) -> Decimal {
+        self.realized_pnl + self.unrealized_pnl
+    }
+
+    /// Markets holding a position or a resting buy order.
+    pub fn open_positions(&self) -> usize {
+        self.exposure
+            .values()
+            .filter(|amount| **amount > Decimal::ZERO)
+            .count()
+    }
+
+    pub fn market_exposure(&self, platform: Platform, market_id: &str) -> Decimal {
+        self.exposure
+            .get(&(platform, market_id.to_string()))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn event_exposure(&self, event: &str) -> Decimal {
+        self.exposure
+            .iter()
+            .filter(|(market, _)| self.events.get(*market).map(String::as_str) == Some(event))
+            .map(|(_, amount)| *amount)
+            .sum()
+    }
+}
+
+/// The event a Kalshi market belongs to: `event_id`, the event ticker
+/// the venue listed it under, or without one its ticker less the final
+/// `-`-separated segment (`KXHIGHNY-24DEC05-T45` is in `KXHIGHNY-24DEC05`).
+pub fn kalshi_event<'a>(ticker: &'a str, event_id: Option<&'a str>) -> &'a str {
+    event_id.unwrap_or_else(|| {
+        ticker
+            .rsplit_once('-')
+            .map(|(event, _)| event)
+            .unwrap_or(ticker)
+    })
+}
+
+/// What `trades` paid on average for the contracts `sale` sells, if they
+/// bought any.
+fn average_buy_price(trades: &[Trade], sale: &Trade) -> Option<Decimal> {
+    let (contracts, cost) = trades
+        .iter()
+        .filter(|trade| {
+            trade.side == TradeSide::Buy
+                && trade.platform == sale.platform
+                && trade.market_id == sale.market_id
+                && trade.outcome == sale.outcome
+        })
+        .fold((Decimal::ZERO, Decimal::ZERO), |(contracts, cost), trade| {
+            let price = trade.fill_price.unwrap_or(trade.price);
+            (
+                contracts + trade.filled_amount,
+                cost + trade.filled_amount * price,
+            )
+        });
+    (contracts > Decimal::ZERO).then(|| cost / contracts)
+}
+
+/// Applies `RiskConfig` to every opportunity before it is executed.
+///
+/// Each check takes a fresh snapshot of both venues' balances, positions
+/// and settlements, the tracker's resting orders and today's fills, then
+/// blocks the trade or shrinks it to the largest whole number of contracts
+/// that fits every limit. A Polymarket market counts towards the event of
+/// the Kalshi market it has been paired with.
+#[derive(Clone)]
+pub struct RiskManager {
+    polymarket: Arc<dyn Exchange>,
+    kalshi: Arc<dyn Exchange>,
+    tracker: OrderTracker,
+    database: Database,
+    max_daily_loss: Decimal,
+    max_open_positions: usize,
+    position_size_percentage: Decimal,
+    max_market_exposure: Decimal,
+    max_event_exposure: Decimal,
+    polymarket_fees: Arc<dyn FeeModel>,
+    kalshi_fees: Arc<dyn FeeModel>,
+}
+
+impl RiskManager {
+    pub fn new(
+        polymarket: Arc<dyn Exchange>,
+        kalshi: Arc<dyn Exchange>,
+        tracker: OrderTracker,
+        database: Database,
+        config: &RiskConfig,
+    ) -> Self {
+        Self {
+            polymarket,
+            kalshi,
+            tracker,
+            database,
+            max_daily_loss: Decimal::try_from(config.max_daily_loss).unwrap_or_default(),
+            max_open_positions: config.max_open_positions,
+            position_size_percentage: Decimal::try_from(config.position_size_percentage)
+                .unwrap_or_default(),
+            max_market_exposure: Decimal::try_from(config.max_market_exposure).unwrap_or_default(),
+            max_event_exposure: Decimal::try_from(config.max_event_exposure).unwrap_or_default(),
+            polymarket_fees: Arc::new(PolymarketFeeModel::new(&FeesConfig::default().polymarket)),
+            kalshi_fees: Arc::new(KalshiFeeModel::new(&FeesConfig::default().kalshi)),
+        }
+    }
+
+    /// Charges fills by `config`'s schedules rather than the default ones.
+    pub fn with_fees(mut self, config: &FeesConfig) -> Self {
+        self.polymarket_fees = Arc::new(PolymarketFeeModel::new(&config.polymarket));
+        self.kalshi_fees = Arc::new(KalshiFeeModel::new(&config.kalshi));
+        self
+    }
+
+    /// Decides how much of `opportunity` may be traded and logs the reason
+    /// for every rejection or reduction. A trade is rejected when the
+    /// account state cannot be fetched.
+    pub async fn check(&self, opportunity: &ArbitrageOpportunity) -> RiskDecision {
+        let decision = match self.snapshot().await {
+            Ok(snapshot) => self.evaluate(opportunity, &snapshot),
+            Err(e) => RiskDecision::Reject {
+                code: RiskCode::Unavailable,
+                reason: e.to_string(),
+            },
+        };
+
+        match &decision {
+            RiskDecision::Reject { code, reason } => warn!(
+                "Risk check rejected {} / {} [{}]: {}",
+                opportunity.polymarket_market_id,
+                opportunity.kalshi_market_id,
+                code.as_str(),
+                reason
+            ),
+            RiskDecision::Approve { size, limited_by } if !limited_by.is_empty() => info!(
+                "Risk check reduced {} / {} from {} to {} contracts [{}]",
+                opportunity.polymarket_market_id,
+                opportunity.kalshi_market_id,
+                opportunity.position_size,
+                size,
+                limited_by
+                    .iter()
+                    .map(|code| code.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            RiskDecision::Approve { .. } => {}
+        }
+
+        decision
+    }
+
+    pub async fn snapshot(&self) -> Result<RiskSnapshot> {
+        let (polymarket_balance, kalshi_balance, polymarket_positions, kalshi_positions) =
+            tokio::try_join!(
+                self.polymarket.get_balance(),
+                self.kalshi.get_balance(),
+                self.polymarket.get_positions(),
+                self.kalshi.get_positions(),
+            )?;
+
+        let mut snapshot = RiskSnapshot::default();
+        snapshot
+            .balances
+            .insert(Platform::Polymarket, polymarket_balance);
+        snapshot.balances.insert(Platform::Kalshi, kalshi_balance);
+
+        for position in polymarket_positions.iter().chain(&kalshi_positions) {
+            if position.amount <= Decimal::ZERO {
+                continue;
+            }
+            let cost = position.amount * position.entry_price;
+            *snapshot
+                .exposure
+                .entry((position.platform.clone(), position.market_id.clone()))
+                .or_default() += cost;
+            snapshot.unrealized_pnl += position.current_value - cost;
+        }
+
+        for trade in self.tracker.live_orders() {
+            if trade.side != TradeSide::Buy {
+                continue;
+            }
+            *snapshot
+                .exposure
+                .entry((trade.platform.clone(), trade.market_id.clone()))
+                .or_default() += (trade.amount - trade.filled_amount) * trade.price;
+        }
+
+        let midnight = Utc::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight is a valid time")
+            .and_utc();
+        snapshot.realized_pnl = self.realized_pnl(midnight).await?;
+
+        let pairs: HashMap<String, String> = self
+            .database
+            .get_market_pairs()
+            .await?
+            .into_iter()
+            .collect();
+        let kalshi_events: HashMap<String, String> = self
+            .database
+            .get_kalshi_events()
+            .await?
+            .into_iter()
+            .collect();
+        let event_of = |ticker: &str| kalshi_events.get(ticker).map(String::as_str);
+        for (platform, market_id) in snapshot.exposure.keys() {
+            let event = match platform {
+                Platform::Kalshi => kalshi_event(market_id, event_of(market_id)),
+                Platform::Polymarket => pairs
+                    .get(market_id)
+                    .map(|ticker| kalshi_event(ticker, event_of(ticker)))
+                    .unwrap_or(market_id),
+            };
+            snapshot
+                .events
+                .insert((platform.clone(), market_id.clone()), event.to_string());
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Gain or loss locked in from `since` on. A sale counts against the
+    /// average price its opportunity bought the same contracts at; buys
+    /// count once their market settles, or as unrealized P&L until then.
+    /// Fills are charged as takers, which the crossing limit orders are.
+    async fn realized_pnl(&self, since: DateTime<Utc>) -> Result<Decimal> {
+        let (polymarket_settlements, kalshi_settlements) = tokio::try_join!(
+            self.polymarket.get_settlements(since),
+            self.kalshi.get_settlements(since),
+        )?;
+        let mut pnl: Decimal = polymarket_settlements
+            .iter()
+            .chain(&kalshi_settlements)
+            .map(Settlement::pnl)
+            .sum();
+
+        let mut opportunity_trades: HashMap<i64, Vec<Trade>> = HashMap::new();
+        for trade in self.database.get_trades_between(since, Utc::now()).await? {
+            if trade.filled_amount <= Decimal::ZERO {
+                continue;
+            }
+            let price = trade.fill_price.unwrap_or(trade.price);
+            pnl -= self
+                .fee_model(&trade.platform)
+                .order_fee(price, trade.filled_amount, Liquidity::Taker);
+
+            if trade.side != TradeSide::Sell {
+                continue;
+            }
+            let trades = match opportunity_trades.entry(trade.opportunity_id) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(
+                    self.database
+                        .get_trades_for_opportunity(trade.opportunity_id)
+                        .await?,
+                ),
+            };
+            if let Some(entry_price) = average_buy_price(trades, &trade) {
+                pnl += trade.filled_amount * (price - entry_price);
+            }
+        }
+
+        Ok(pnl)
+    }
+
+    /// Checks `opportunity` against every limit given the account state in
+    /// `snapshot`. Limits configured as zero are not enforced.
+    pub fn evaluate(
+        &self,
+        opportunity: &ArbitrageOpportunity,
+        snapshot: &RiskSnapshot,
+    ) -> RiskDecision {
+        let daily_pnl = snapshot.daily_pnl();
+        if self.max_daily_loss > Decimal::ZERO && -daily_pnl >= self.max_daily_loss {
+            return RiskDecision::Reject {
+                code: RiskCode::DailyLoss,
+                reason: format!(
+                    "daily P&L {} has reached the {} loss limit",
+                    daily_pnl, self.max_daily_loss
+                ),
+            };
+        }
+
+        // A leg may fill as deep as its limit price, and pays the taker fee
+        // on top
+        let legs = [
+            (opportunity.yes_platform.clone(), opportunity.yes_limit_price),
+            (opportunity.no_platform.clone(), opportunity.no_limit_price),
+        ]
+        .map(|(platform, limit_price)| {
+            let market_id = opportunity.position_id(&platform).to_string();
+            let size = opportunity.position_size;
+            let fee = if size > Decimal::ZERO {
+                self.fee_model(&platform).order_fee(limit_price, size, Liquidity::Taker) / size
+            } else {
+                Decimal::ZERO
+            };
+            (platform, market_id, limit_price + fee)
+        });
+
+        let opened = legs
+            .iter()
+            .filter(|(platform, market_id, _)| {
+                snapshot.market_exposure(platform.clone(), market_id) <= Decimal::ZERO
+            })
+            .count();
+        let open = snapshot.open_positions();
+        if self.max_open_positions > 0 && opened > 0 && open + opened > self.max_open_positions {
+            return RiskDecision::Reject {
+                code: RiskCode::OpenPositions,
+                reason: format!(
+                    "{} open positions plus {} new exceeds the limit of {}",
+                    open, opened, self.max_open_positions
+                ),
+            };
+        }
+
+        let mut size = opportunity.position_size;
+        let mut limited_by = Vec::new();
+        let mut cap = |code: RiskCode, capital: Decimal, per_contract: Decimal| {
+            if per_contract <= Decimal::ZERO {
+                return;
+            }
+            let limit = (capital / per_contract).max(Decimal::ZERO);
+            if limit < size {
+                size = limit;
+                if !limited_by.contains(&code) {
+                    limited_by.push(code);
+                }
+            }
+        };
+
+        for (platform, market_id, per_contract) in &legs {
+            let balance = snapshot.balances.get(platform).copied().unwrap_or_default();
+            cap(RiskCode::Balance, balance, *per_contract);
+
+            if self.max_market_exposure > Decimal::ZERO {
+                let room = self.max_market_exposure
+                    - snapshot.market_exposure(platform.clone(), market_id);
+                cap(RiskCode::MarketExposure, room, *per_contract);
+            }
+        }
+
+        if self.position_size_percentage > Decimal::ZERO {
+            let total_balance: Decimal = snapshot.balances.values().copied().sum();
+            cap(
+                RiskCode::PositionSize,
+                total_balance * self.position_size_percentage,
+                opportunity.total_cost(),
+            );
+        }
+
+        if self.max_event_exposure > Decimal::ZERO {
+            let event = kalshi_event(
+                &opportunity.kalshi_market_id,
+                opportunity.kalshi_event_id.as_deref(),
+            );
+            let room = self.max_event_exposure - snapshot.event_exposure(event);
+            cap(RiskCode::EventExposure, room, opportunity.total_cost());
+        }
+
+        // Kalshi, which takes one of the legs, trades whole contracts only
+        let size = size.floor();
+        if size < Decimal::ONE {
+            let code = limited_by.last().copied().unwrap_or(RiskCode::PositionSize);
+            return RiskDecision::Reject {
+                code,
+                reason: format!("no whole contract fits within the {} limit", code.as_str()),
+            };
+        }
+
+        RiskDecision::Approve { size, limited_by }
+    }
+
+    fn fee_model(&self, platform: &Platform) -> &dyn FeeModel {
+        match platform {
+            Platform::Polymarket => self.polymarket_fees.as_ref(),
+            Platform::Kalshi => self.kalshi_fees.as_ref(),
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use anyhow::Context;
+use futures::{SinkExt, StreamExt};
+use log::{debug, info, warn};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use tokio::sync::mpsc;
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{
+        client::IntoClientRequest,
+        http::{HeaderName, HeaderValue},
+        Message,
+    },
+};
+
+use super::{next_reconnect_delay, BookEvent, BookStore, StreamError, INITIAL_RECONNECT_DELAY};
+use crate::{
+    api::{kalshi::order_book_from_bids, KalshiClient},
+    models::Platform,
+};
+
+/// Path signed for the websocket handshake.
+const WS_PATH: &str = "/trade-api/ws/v2";
+
+/// Streams `orderbook_delta` for a fixed set of tickers into a `BookStore`.
+///
+/// Kalshi numbers messages per subscription. A snapshot starts the
+/// sequence and every delta must follow it by exactly one; on a gap the
+/// connection is dropped and resubscribed, which delivers fresh snapshots.
+pub struct KalshiStream {
+    client: KalshiClient,
+    url: String,
+    tickers: Vec<String>,
+    store: BookStore,
+    events: mpsc::Sender<BookEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum KalshiMessage {
+    OrderbookSnapshot {
+        sid: u64,
+        seq: u64,
+        msg: SnapshotMessage,
+    },
+    OrderbookDelta {
+        sid: u64,
+        seq: u64,
+        msg: DeltaMessage,
+    },
+    Error {
+        msg: serde_json::Value,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct SnapshotMessage {
+    market_ticker: String,
+    #[serde(default)]
+    yes: Option<Vec<[i64; 2]>>,
+    #[serde(default)]
+    no: Option<Vec<[i64; 2]>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeltaMessage {
+    market_ticker: String,
+    price: i64,
+    delta: i64,
+    side: String,
+}
+
+/// Resting bids by price in cents, as Kalshi reports them.
+#[derive(Debug, Default)]
+struct Ladder {
+    yes: BTreeMap<i64, i64>,
+    no: BTreeMap<i64, i64>,
+}
+
+impl Ladder {
+    fn from_snapshot(snapshot: &SnapshotMessage) -> Self {
+        let levels = |bids: &Option<Vec<[i64; 2]>>| {
+            bids.iter()
+                .flatten()
+                .map(|[price, quantity]| (*price, *quantity))
+                .filter(|(_, quantity)| *quantity > 0)
+                .collect()
+        };
+
+        Self {
+            yes: levels(&snapshot.yes),
+            no: levels(&snapshot.no),
+        }
+    }
+
+    fn apply_delta(&mut self, delta: &DeltaMessage) -> Result<(), StreamError> {
+        let side = match delta.side.as_str() {
+            "yes" => &mut self.yes,
+            "no" => &mut self.no,
+            other => {
+                return Err(StreamError::Other(anyhow::anyhow!(
+                    "Unknown orderbook side: {}",
+                    other
+                )))
+            }
+        };
+
+        let quantity = side.entry(delta.price).or_insert(0);
+        *quantity += delta.delta;
+        if *quantity <= 0 {
+            side.remove(&delta.price);
+        }
+
+        Ok(())
+    }
+}
+
+impl KalshiStream {
+    pub fn new(
+        client: KalshiClient,
+        url: String,
+        tickers: Vec<String>,
+        store: BookStore,
+        events: mpsc::Sender<BookEvent>,
+    ) -> Self {
+        Self {
+            client,
+            url,
+            tickers,
+            store,
+            events,
+        }
+    }
+
+    /// Keeps the subscription alive until the task is dropped.
+    pub async fn run(self) {
+        let mut delay = INITIAL_RECONNECT_DELAY;
+
+        loop {
+            let mut ladders = HashMap::new();
+            let error = match self.session(&mut ladders).await {
+                Ok(()) => StreamError::Closed,
+                Err(e) => e,
+            };
+
+            // Never leave books behind that may have missed updates
+            for ticker in &self.tickers {
+                self.store.remove(&Platform::Kalshi, ticker);
+            }
+
+            if !ladders.is_empty() {
+                delay = INITIAL_RECONNECT_DELAY;
+            }
+
+            if error.needs_resnapshot() {
+                warn!("Kalshi stream: {}; resubscribing", error);
+                continue;
+            }
+
+            warn!("Kalshi stream: {}; reconnecting in {:?}", error, delay);
+            tokio::time::sleep(delay).await;
+            delay = next_reconnect_delay(delay);
+        }
+    }
+
+    async fn session(&self, ladders: &mut HashMap<String, Ladder>) -> Result<(), StreamError> {
+        let mut request = self
+            .url
+            .as_str()
+            .into_client_request()
+            .context("Invalid Kalshi websocket URL")?;
+
+        for (name, value) in self.client.auth_headers("GET", WS_PATH) {
+            request.headers_mut().insert(
+                HeaderName::from_bytes(name.as_bytes()).context("Invalid header name")?,
+                HeaderValue::from_str(&value).context("Invalid header value")?,
+            );
+        }
+
+        let (socket, _) = connect_async(request).await?;
+        let (mut write, mut read) = socket.split();
+
+        let subscribe = json!({
+            "id": 1,
+            "cmd": "subscribe",
+            "params": {
+                "channels": ["orderbook_delta"],
+                "market_tickers": self.tickers,
+            }
+        });
+        write.send(Message::Text(subscribe.to_string())).await?;
+        info!("Subscribed to {} Kalshi order books", self.tickers.len());
+
+        let mut sequences: HashMap<u64, u64> = HashMap::new();
+
+        while let Some(message) = read.next().await {
+            match message? {
+                Message::Text(text) => self.handle_message(&text, ladders, &mut sequences).await?,
+                Message::Close(_) => return Err(StreamError::Closed),
+                _ => {}
+            }
+        }
+
+        Err(StreamError::Closed)
+    }
+
+    async fn handle_message(
+        &self,
+        text: &str,
+        ladders: &mut HashMap<String, Ladder>,
+        sequences: &mut HashMap<u64, u64>,
+    ) -> Result<(), StreamError> {
+        let message: KalshiMessage =
+            serde_json::from_str(text).context("Failed to parse Kalshi stream message")?;
+
+        match message {
+            KalshiMessage::OrderbookSnapshot { sid, seq, msg } => {
+                sequences.insert(sid, seq);
+                let ladder = Ladder::from_snapshot(&msg);
+                self.publish(&msg.market_ticker, &ladder).await;
+                ladders.insert(msg.market_ticker, ladder);
+            }
+            KalshiMessage::OrderbookDelta { sid, seq, msg } => {
+                let expected = sequences.get(&sid).map_or(1, |last| last + 1);
+                if seq != expected {
+                    return Err(StreamError::SequenceGap {
+                        sid,
+                        expected,
+                        received: seq,
+                    });
+                }
+                sequences.insert(sid, seq);
+
+                let ladder = ladders
+                    .get_mut(&msg.market_ticker)
+                    .ok_or_else(|| StreamError::MissingSnapshot(msg.market_ticker.clone()))?;
+                ladder.apply_delta(&msg)?;
+                self.publish(&msg.market_ticker, ladder).await;
+            }
+            KalshiMessage::Error { msg } => {
+                return Err(StreamError::Other(anyhow::anyhow!(
+                    "Kalshi stream error: {}",
+                    msg
+                )));
+            }
+            KalshiMessage::Other => debug!("Ignoring Kalshi stream message: {}", text),
+        }
+
+        Ok(())
+    }
+
+    async fn publish(&self, ticker: &str, ladder: &Ladder) {
+        let book = order_book_from_bids(
+            ticker,
+            ladder.yes.iter().map(|(price, quantity)| (*price, *quantity)),
+            ladder.no.iter().map(|(price, quantity)| (*price, *quantity)),
+        );
+        self.store.insert(book);
+
+        let event = BookEvent {
+            platform: Platform::Kalshi,
+            market_id: ticker.to_string(),
+        };
+        if self.events.send(event).await.is_err() {
+            debug!("Book event receiver dropped");
+        }
+    }
+}
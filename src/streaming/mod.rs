@@ -0,0 +1,99 @@
+pub mod kalshi;
+pub mod polymarket;
+
+pub use kalshi::KalshiStream;
+pub use polymarket::PolymarketStream;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use crate::models::{OrderBook, Platform};
+
+/// Delay before reconnecting after a dropped connection, doubled on each
+/// consecutive failure up to `MAX_RECONNECT_DELAY`.
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Emitted whenever a streamed order book changes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BookEvent {
+    pub platform: Platform,
+    pub market_id: String,
+}
+
+/// Latest streamed order book per venue and market, shared between the
+/// stream tasks that write it and the engine that reads it.
+#[derive(Debug, Clone, Default)]
+pub struct BookStore {
+    books: Arc<RwLock<HashMap<(Platform, String), OrderBook>>>,
+}
+
+impl BookStore {
+    pub fn get(&self, platform: &Platform, market_id: &str) -> Option<OrderBook> {
+        self.books
+            .read()
+            .expect("book store lock poisoned")
+            .get(&(platform.clone(), market_id.to_string()))
+            .cloned()
+    }
+
+    pub fn insert(&self, book: OrderBook) {
+        self.books
+            .write()
+            .expect("book store lock poisoned")
+            .insert((book.platform.clone(), book.market_id.clone()), book);
+    }
+
+    pub fn remove(&self, platform: &Platform, market_id: &str) {
+        self.books
+            .write()
+            .expect("book store lock poisoned")
+            .remove(&(platform.clone(), market_id.to_string()));
+    }
+}
+
+/// Why a streaming session ended.
+#[derive(Debug, thiserror::Error)]
+pub enum StreamError {
+    /// A delta arrived out of order; the books must be rebuilt from a
+    /// fresh snapshot.
+    #[error("sequence gap on subscription {sid}: expected {expected}, got {received}")]
+    SequenceGap {
+        sid: u64,
+        expected: u64,
+        received: u64,
+    },
+    /// An update referenced a book with no snapshot yet.
+    #[error("update for {0} before its snapshot")]
+    MissingSnapshot(String),
+    #[error("connection closed")]
+    Closed,
+    #[error(transparent)]
+    WebSocket(Box<tokio_tungstenite::tungstenite::Error>),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for StreamError {
+    fn from(error: tokio_tungstenite::tungstenite::Error) -> Self {
+        StreamError::WebSocket(Box::new(error))
+    }
+}
+
+impl StreamError {
+    /// Gaps are recovered by resubscribing straight away; anything else
+    /// backs off first.
+    fn needs_resnapshot(&self) -> bool {
+        matches!(
+            self,
+            StreamError::SequenceGap { .. } | StreamError::MissingSnapshot(_)
+        )
+    }
+}
+
+fn next_reconnect_delay(current: Duration) -> Duration {
+    (current * 2).min(MAX_RECONNECT_DELAY)
+}
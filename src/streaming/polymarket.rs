@@ -0,0 +1,304 @@
+use anyhow::Context;
+use futures::{SinkExt, StreamExt};
+use log::{debug, info, warn};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+use tokio::sync::mpsc;
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+use super::{next_reconnect_delay, BookEvent, BookStore, StreamError, INITIAL_RECONNECT_DELAY};
+use crate::{
+    api::polymarket::order_book_from_yes_levels,
+    models::{Platform, PriceLevel},
+};
+
+/// The market channel drops connections that stay silent for too long.
+const PING_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Streams the CLOB `market` channel for the YES token of each market.
+///
+/// Every subscription starts with a `book` snapshot per asset; afterwards
+/// `price_change` events carry the new absolute size at each touched level.
+pub struct PolymarketStream {
+    url: String,
+    /// YES token id -> market id
+    assets: HashMap<String, String>,
+    store: BookStore,
+    events: mpsc::Sender<BookEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "event_type", rename_all = "snake_case")]
+enum PolymarketEvent {
+    Book {
+        asset_id: String,
+        #[serde(default, alias = "buys")]
+        bids: Vec<StreamLevel>,
+        #[serde(default, alias = "sells")]
+        asks: Vec<StreamLevel>,
+    },
+    PriceChange {
+        #[serde(default)]
+        asset_id: Option<String>,
+        #[serde(default)]
+        changes: Vec<PriceChange>,
+        #[serde(default)]
+        price_changes: Vec<PriceChange>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamLevel {
+    price: String,
+    size: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PriceChange {
+    #[serde(default)]
+    asset_id: Option<String>,
+    price: String,
+    side: String,
+    size: String,
+}
+
+/// YES-token bids and asks keyed by price.
+#[derive(Debug, Default)]
+struct Ladder {
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+}
+
+impl Ladder {
+    fn from_levels(bids: &[StreamLevel], asks: &[StreamLevel]) -> Result<Self, StreamError> {
+        let levels = |levels: &[StreamLevel]| {
+            levels
+                .iter()
+                .map(|level| Ok((parse_decimal(&level.price)?, parse_decimal(&level.size)?)))
+                .filter(|level| !matches!(level, Ok((_, size)) if size.is_zero()))
+                .collect::<Result<BTreeMap<_, _>, StreamError>>()
+        };
+
+        Ok(Self {
+            bids: levels(bids)?,
+            asks: levels(asks)?,
+        })
+    }
+
+    fn apply_change(&mut self, change: &PriceChange) -> Result<(), StreamError> {
+        let side = match change.side.to_ascii_uppercase().as_str() {
+            "BUY" => &mut self.bids,
+            "SELL" => &mut self.asks,
+            other => {
+                return Err(StreamError::Other(anyhow::anyhow!(
+                    "Unknown price change side: {}",
+                    other
+                )))
+            }
+        };
+
+        let price = parse_decimal(&change.price)?;
+        let size = parse_decimal(&change.size)?;
+        if size.is_zero() {
+            side.remove(&price);
+        } else {
+            side.insert(price, size);
+        }
+
+        Ok(())
+    }
+
+    fn levels(side: &BTreeMap<Decimal, Decimal>) -> Vec<PriceLevel> {
+        side.iter()
+            .map(|(price, size)| PriceLevel {
+                price: *price,
+                size: *size,
+            })
+            .collect()
+    }
+}
+
+fn parse_decimal(value: &str) -> Result<Decimal, StreamError> {
+    value
+        .parse()
+        .with_context(|| format!("Invalid decimal in Polymarket stream: {}", value))
+        .map_err(StreamError::from)
+}
+
+impl PolymarketStream {
+    /// `assets` maps each subscribed YES token id to its market id.
+    pub fn new(
+        url: String,
+        assets: HashMap<String, String>,
+        store: BookStore,
+        events: mpsc::Sender<BookEvent>,
+    ) -> Self {
+        Self {
+            url,
+            assets,
+            store,
+            events,
+        }
+    }
+
+    /// Keeps the subscription alive until the task is dropped.
+    pub async fn run(self) {
+        let mut delay = INITIAL_RECONNECT_DELAY;
+
+        loop {
+            let mut ladders = HashMap::new();
+            let error = match self.session(&mut ladders).await {
+                Ok(()) => StreamError::Closed,
+                Err(e) => e,
+            };
+
+            // Never leave books behind that may have missed updates
+            for market_id in self.assets.values() {
+                self.store.remove(&Platform::Polymarket, market_id);
+            }
+
+            if !ladders.is_empty() {
+                delay = INITIAL_RECONNECT_DELAY;
+            }
+
+            if error.needs_resnapshot() {
+                warn!("Polymarket stream: {}; resubscribing", error);
+                continue;
+            }
+
+            warn!("Polymarket stream: {}; reconnecting in {:?}", error, delay);
+            tokio::time::sleep(delay).await;
+            delay = next_reconnect_delay(delay);
+        }
+    }
+
+    async fn session(&self, ladders: &mut HashMap<String, Ladder>) -> Result<(), StreamError> {
+        let (socket, _) = connect_async(self.url.as_str()).await?;
+        let (mut write, mut read) = socket.split();
+
+        let asset_ids: Vec<&String> = self.assets.keys().collect();
+        let subscribe = json!({
+            "assets_ids": asset_ids,
+            "type": "market",
+        });
+        write.send(Message::Text(subscribe.to_string())).await?;
+        info!("Subscribed to {} Polymarket order books", asset_ids.len());
+
+        let mut ping = tokio::time::interval(PING_INTERVAL);
+        ping.tick().await;
+
+        loop {
+            tokio::select! {
+                message = read.next() => match message {
+                    Some(message) => match message? {
+                        Message::Text(text) => self.handle_message(&text, ladders).await?,
+                        Message::Close(_) => return Err(StreamError::Closed),
+                        _ => {}
+                    },
+                    None => return Err(StreamError::Closed),
+                },
+                _ = ping.tick() => {
+                    write.send(Message::Text("PING".to_string())).await?;
+                }
+            }
+        }
+    }
+
+    async fn handle_message(
+        &self,
+        text: &str,
+        ladders: &mut HashMap<String, Ladder>,
+    ) -> Result<(), StreamError> {
+        // Keep-alive replies are plain text
+        if text == "PONG" {
+            return Ok(());
+        }
+
+        // Events arrive either one at a time or batched in an array
+        let value: serde_json::Value =
+            serde_json::from_str(text).context("Failed to parse Polymarket stream message")?;
+        let events = match value {
+            serde_json::Value::Array(events) => events,
+            event => vec![event],
+        };
+
+        for event in events {
+            let event: PolymarketEvent =
+                serde_json::from_value(event).context("Failed to parse Polymarket event")?;
+
+            match event {
+                PolymarketEvent::Book {
+                    asset_id,
+                    bids,
+                    asks,
+                } => {
+                    if !self.assets.contains_key(&asset_id) {
+                        continue;
+                    }
+                    ladders.insert(asset_id.clone(), Ladder::from_levels(&bids, &asks)?);
+                    self.publish(&asset_id, &ladders[&asset_id]).await;
+                }
+                PolymarketEvent::PriceChange {
+                    asset_id,
+                    changes,
+                    price_changes,
+                } => {
+                    let mut touched = Vec::new();
+
+                    for change in changes.iter().chain(&price_changes) {
+                        let Some(asset_id) = change.asset_id.as_ref().or(asset_id.as_ref()) else {
+                            continue;
+                        };
+                        if !self.assets.contains_key(asset_id) {
+                            continue;
+                        }
+
+                        ladders
+                            .get_mut(asset_id)
+                            .ok_or_else(|| StreamError::MissingSnapshot(asset_id.clone()))?
+                            .apply_change(change)?;
+
+                        if !touched.contains(asset_id) {
+                            touched.push(asset_id.clone());
+                        }
+                    }
+
+                    for asset_id in touched {
+                        self.publish(&asset_id, &ladders[&asset_id]).await;
+                    }
+                }
+                PolymarketEvent::Other => debug!("Ignoring Polymarket stream event"),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn publish(&self, asset_id: &str, ladder: &Ladder) {
+        let Some(market_id) = self.assets.get(asset_id) else {
+            return;
+        };
+
+        let book = order_book_from_yes_levels(
+            market_id,
+            Ladder::levels(&ladder.bids),
+            Ladder::levels(&ladder.asks),
+        );
+        self.store.insert(book);
+
+        let event = BookEvent {
+            platform: Platform::Polymarket,
+            market_id: market_id.clone(),
+        };
+        if self.events.send(event).await.is_err() {
+            debug!("Book event receiver dropped");
+        }
+    }
+}
@@ -0,0 +1,391 @@
+//! Fixtures shared by the integration tests.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use polymarket_kalshi_arbitrage_bot::{
+    api::{ApiError, ApiResult, Exchange},
+    models::{
+        ArbitrageOpportunity, Event, Fill, Market, Order, OrderBook, OrderRequest, OrderStatus,
+        Outcome, Platform, Position, PriceLevel, Settlement, TradeSide,
+    },
+};
+use rust_decimal::Decimal;
+
+/// YES on Kalshi at 0.45 and NO on Polymarket at 0.50, ten contracts.
+pub fn opportunity() -> ArbitrageOpportunity {
+    ArbitrageOpportunity {
+        id: None,
+        polymarket_market_id: "0xcondition".to_string(),
+        kalshi_market_id: "RAIN-25MAY01-T50".to_string(),
+        polymarket_token_id: Some("456".to_string()),
+        polymarket_neg_risk: false,
+        polymarket_condition_id: None,
+        yes_platform: Platform::Kalshi,
+        no_platform: Platform::Polymarket,
+        yes_price: Decimal::new(45, 2),
+        no_price: Decimal::new(50, 2),
+        yes_limit_price: Decimal::new(45, 2),
+        no_limit_price: Decimal::new(50, 2),
+        payout: Decimal::ONE,
+        gross_edge: Decimal::new(5, 2),
+        total_fees: Decimal::ZERO,
+        net_edge: Decimal::new(5, 2),
+        profit_percentage: Decimal::new(5, 2),
+        estimated_profit: Decimal::new(5, 1),
+        position_size: Decimal::from(10),
+        detected_at: Utc::now(),
+        executed: false,
+        kalshi_inverted: false,
+        kalshi_event_id: None,
+    }
+}
+
+/// How the venue treats the next order: refuse it, or move it straight
+/// to a status with some contracts filled.
+pub type Response = Result<(OrderStatus, i64), ApiError>;
+
+/// A venue held in memory, set up with the `with_*` methods.
+///
+/// Markets are listed as given, each with a book 50 deep at its listed
+/// prices unless the test sets one book for all of them. Orders follow the
+/// scripted responses; an order the venue did not place reports the
+/// scripted states in turn, repeating the last. A market data only venue
+/// panics on anything about orders or the account.
+///
+/// Orders are found by client order ID among those placed. A venue losing
+/// replies places an order refused with a retryable error all the same,
+/// filled in full.
+pub struct MockExchange {
+    platform: Platform,
+    markets: Vec<Market>,
+    exclusive_events: bool,
+    book: Mutex<Option<(Vec<PriceLevel>, Vec<PriceLevel>)>>,
+    market_data_only: bool,
+    responses: Mutex<VecDeque<Response>>,
+    lost_replies: bool,
+    lookups_fail: bool,
+    states: Mutex<VecDeque<(OrderStatus, Decimal)>>,
+    fills: Vec<Fill>,
+    orders: Mutex<HashMap<String, (OrderRequest, OrderStatus, Decimal)>>,
+    cancelled: Mutex<Vec<String>>,
+    balance: Option<Decimal>,
+    pub positions: Vec<Position>,
+    settlements: Vec<Settlement>,
+    /// Holding it keeps `get_order` waiting, after it notifies `asked`.
+    pub gate: tokio::sync::Mutex<()>,
+    pub asked: tokio::sync::Notify,
+}
+
+impl MockExchange {
+    pub fn new(platform: Platform) -> Self {
+        Self {
+            platform,
+            markets: Vec::new(),
+            exclusive_events: false,
+            book: Mutex::new(None),
+            market_data_only: false,
+            responses: Mutex::new(VecDeque::new()),
+            lost_replies: false,
+            lookups_fail: false,
+            states: Mutex::new(VecDeque::new()),
+            fills: Vec::new(),
+            orders: Mutex::new(HashMap::new()),
+            cancelled: Mutex::new(Vec::new()),
+            balance: Some(Decimal::ZERO),
+            positions: Vec::new(),
+            settlements: Vec::new(),
+            gate: tokio::sync::Mutex::new(()),
+            asked: tokio::sync::Notify::new(),
+        }
+    }
+
+    pub fn with_markets(mut self, markets: Vec<Market>) -> Self {
+        self.markets = markets;
+        self
+    }
+
+    /// Says the outcomes of every listed event are mutually exclusive.
+    pub fn with_exclusive_events(mut self) -> Self {
+        self.exclusive_events = true;
+        self
+    }
+
+    pub fn with_book(self, yes_asks: Vec<PriceLevel>, no_asks: Vec<PriceLevel>) -> Self {
+        self.set_book(yes_asks, no_asks);
+        self
+    }
+
+    pub fn market_data_only(mut self) -> Self {
+        self.market_data_only = true;
+        self
+    }
+
+    pub fn with_responses(mut self, responses: Vec<Response>) -> Self {
+        self.responses = Mutex::new(responses.into());
+        self
+    }
+
+    pub fn losing_replies(mut self) -> Self {
+        self.lost_replies = true;
+        self
+    }
+
+    /// Looking orders up by client order ID fails.
+    pub fn without_lookups(mut self) -> Self {
+        self.lookups_fail = true;
+        self
+    }
+
+    pub fn with_states(mut self, states: &[(OrderStatus, i64)]) -> Self {
+        self.states = Mutex::new(
+            states
+                .iter()
+                .map(|(status, filled)| (*status, Decimal::from(*filled)))
+                .collect(),
+        );
+        self
+    }
+
+    /// Fills reported for every order, in place of one fill at the order's
+    /// price for what it filled.
+    pub fn with_fills(mut self, fills: Vec<Fill>) -> Self {
+        self.fills = fills;
+        self
+    }
+
+    /// Cash available, or an error for `None`.
+    pub fn with_balance(mut self, balance: Option<Decimal>) -> Self {
+        self.balance = balance;
+        self
+    }
+
+    pub fn holding(mut self, market_id: &str, amount: i64, entry_price: Decimal) -> Self {
+        let amount = Decimal::from(amount);
+        self.positions.push(Position {
+            platform: self.platform.clone(),
+            market_id: market_id.to_string(),
+            outcome: Outcome::Yes,
+            amount,
+            entry_price,
+            current_value: amount * entry_price,
+        });
+        self
+    }
+
+    pub fn with_settlements(mut self, settlements: Vec<Settlement>) -> Self {
+        self.settlements = settlements;
+        self
+    }
+
+    pub fn set_book(&self, yes_asks: Vec<PriceLevel>, no_asks: Vec<PriceLevel>) {
+        *self.book.lock().unwrap() = Some((yes_asks, no_asks));
+    }
+
+    /// The orders placed, oldest first.
+    pub fn requests(&self) -> Vec<OrderRequest> {
+        let orders = self.orders.lock().unwrap();
+        let mut ids: Vec<&String> = orders.keys().collect();
+        ids.sort();
+        ids.into_iter().map(|id| orders[id].0.clone()).collect()
+    }
+
+    pub fn cancelled(&self) -> Vec<String> {
+        self.cancelled.lock().unwrap().clone()
+    }
+
+    fn orders_reach_venue(&self) {
+        assert!(!self.market_data_only, "orders must not reach the venue");
+    }
+
+    fn account_reaches_venue(&self) {
+        assert!(!self.market_data_only, "the account must not come from the venue");
+    }
+}
+
+#[async_trait]
+impl Exchange for MockExchange {
+    fn platform(&self) -> Platform {
+        self.platform.clone()
+    }
+
+    async fn get_markets(&self) -> ApiResult<Vec<Market>> {
+        Ok(self.markets.clone())
+    }
+
+    async fn get_events(&self) -> ApiResult<Vec<Event>> {
+        Ok(Event::group(&self.markets)
+            .into_iter()
+            .map(|event| Event {
+                mutually_exclusive: self.exclusive_events,
+                ..event
+            })
+            .collect())
+    }
+
+    async fn get_order_book(&self, market: &Market) -> ApiResult<OrderBook> {
+        let (yes_asks, no_asks) = match self.book.lock().unwrap().clone() {
+            Some(book) => book,
+            None => match self.markets.iter().find(|m| m.id == market.id) {
+                Some(listed) => {
+                    let level = |price: Decimal| {
+                        vec![PriceLevel {
+                            price,
+                            size: Decimal::from(50),
+                        }]
+                    };
+                    (level(listed.yes_price), level(listed.no_price))
+                }
+                None => (Vec::new(), Vec::new()),
+            },
+        };
+        Ok(OrderBook::new(self.platform(), market.id.clone(), yes_asks, no_asks))
+    }
+
+    async fn place_order(&self, order: &OrderRequest) -> ApiResult<String> {
+        self.orders_reach_venue();
+        let response = self
+            .responses
+            .lock()
+            .unwrap()
+            .pop_front()
+            .expect("unexpected order");
+        let (status, filled, reply) = match response {
+            Ok((status, filled)) => (status, Decimal::from(filled), None),
+            Err(e) if e.is_retryable() && self.lost_replies => {
+                (OrderStatus::Filled, order.size, Some(e))
+            }
+            Err(e) => return Err(e),
+        };
+
+        let mut orders = self.orders.lock().unwrap();
+        let id = format!("{}-{}", self.platform.as_str(), orders.len());
+        orders.insert(id.clone(), (order.clone(), status, filled));
+        match reply {
+            Some(e) => Err(e),
+            None => Ok(id),
+        }
+    }
+
+    async fn cancel_order(&self, order_id: &str) -> ApiResult<()> {
+        self.orders_reach_venue();
+        self.cancelled.lock().unwrap().push(order_id.to_string());
+        if let Some((_, status, _)) = self.orders.lock().unwrap().get_mut(order_id) {
+            if !status.is_terminal() {
+                *status = OrderStatus::Cancelled;
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_order(&self, order_id: &str) -> ApiResult<Order> {
+        self.orders_reach_venue();
+        self.asked.notify_one();
+        drop(self.gate.lock().await);
+
+        if let Some((request, status, filled)) = self.orders.lock().unwrap().get(order_id) {
+            return Ok(Order {
+                id: order_id.to_string(),
+                platform: self.platform.clone(),
+                market_id: request.market_id.clone(),
+                outcome: request.outcome,
+                side: request.side,
+                price: request.price,
+                size: request.size,
+                filled_size: *filled,
+                status: *status,
+                created_at: None,
+            });
+        }
+
+        let (status, filled_size) = {
+            let mut states = self.states.lock().unwrap();
+            match states.len() {
+                0 => return Err(ApiError::MarketClosed),
+                1 => states[0],
+                _ => states.pop_front().unwrap(),
+            }
+        };
+        Ok(Order {
+            id: order_id.to_string(),
+            platform: self.platform.clone(),
+            market_id: "RAIN-25".to_string(),
+            outcome: Outcome::Yes,
+            side: TradeSide::Buy,
+            price: Decimal::new(45, 2),
+            size: Decimal::from(10),
+            filled_size,
+            status,
+            created_at: None,
+        })
+    }
+
+    async fn find_order(&self, order: &OrderRequest) -> ApiResult<Option<Order>> {
+        self.orders_reach_venue();
+        if self.lookups_fail {
+            return Err(ApiError::Server { status: 503 });
+        }
+
+        let placed = self
+            .orders
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, (request, _, _))| {
+                request.client_order_id.is_some()
+                    && request.client_order_id == order.client_order_id
+            })
+            .map(|(id, _)| id.clone());
+        match placed {
+            Some(id) => self.get_order(&id).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    async fn list_fills(&self, order_id: &str) -> ApiResult<Vec<Fill>> {
+        self.orders_reach_venue();
+        if !self.fills.is_empty() {
+            return Ok(self.fills.clone());
+        }
+
+        let orders = self.orders.lock().unwrap();
+        let Some((request, _, filled)) = orders.get(order_id) else {
+            return Ok(Vec::new());
+        };
+        Ok(vec![Fill {
+            id: format!("{}-fill", order_id),
+            order_id: order_id.to_string(),
+            platform: self.platform.clone(),
+            market_id: request.market_id.clone(),
+            outcome: request.outcome,
+            side: request.side,
+            price: request.price,
+            size: *filled,
+            filled_at: Some(Utc::now()),
+        }])
+    }
+
+    async fn get_positions(&self) -> ApiResult<Vec<Position>> {
+        self.account_reaches_venue();
+        Ok(self.positions.clone())
+    }
+
+    async fn get_settlements(&self, since: DateTime<Utc>) -> ApiResult<Vec<Settlement>> {
+        self.account_reaches_venue();
+        Ok(self
+            .settlements
+            .iter()
+            .filter(|settlement| settlement.settled_at >= since)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_balance(&self) -> ApiResult<Decimal> {
+        self.account_reaches_venue();
+        self.balance.ok_or(ApiError::Unauthorized)
+    }
+}
@@ -3,6 +3,8 @@ use polymarket_kalshi_arbitrage_bot::{
     database::Database,
 };
 
+mod common;
+
 #[tokio::test]
 async fn test_database_initialization() {
     let db = Database::new("sqlite::memory:")
@@ -18,7 +20,6 @@ async fn test_database_initialization() {
 async fn test_config_loading() {
     std::env::set_var("POLYMARKET_API_KEY", "test_key");
     std::env::set_var("KALSHI_API_KEY", "test_key");
-    std::env::set_var("KALSHI_API_SECRET", "test_secret");
     
     let config = Config::load("config/default.toml")
         .expect("Failed to load config");
@@ -31,40 +32,4955 @@ async fn test_config_loading() {
 mod arbitrage_tests {
     use super::*;
     use rust_decimal::Decimal;
-    use polymarket_kalshi_arbitrage_bot::models::{Market, Platform};
+    use polymarket_kalshi_arbitrage_bot::{
+        arbitrage::ArbitrageEngine,
+        models::{ArbitrageOpportunity, Market, OrderBook, Platform, PriceLevel},
+    };
     use chrono::Utc;
 
-    fn create_test_market(platform: Platform, yes_price: f64) -> Market {
+    fn create_test_market(platform: Platform, yes_price: f64, no_price: f64) -> Market {
         Market {
             id: "test_market".to_string(),
             question: "Will it rain tomorrow?".to_string(),
             platform,
             yes_price: Decimal::try_from(yes_price).unwrap(),
-            no_price: Decimal::try_from(1.0 - yes_price).unwrap(),
+            no_price: Decimal::try_from(no_price).unwrap(),
             volume: Decimal::from(10000),
             liquidity: Decimal::from(5000),
             end_time: Utc::now() + chrono::Duration::days(1),
+            yes_token_id: None,
+            no_token_id: None,
+            rules: None,
+            resolution_source: None,
+            settlement_timer_seconds: None,
+            event_id: None,
+            event_title: None,
+            outcome_name: None,
+            neg_risk: false,
+            condition_id: None,
         }
     }
 
-    #[test]
-    fn test_arbitrage_detection() {
-        let poly_market = create_test_market(Platform::Polymarket, 0.45);
-        let kalshi_market = create_test_market(Platform::Kalshi, 0.55);
+    fn levels(levels: &[(f64, f64)]) -> Vec<PriceLevel> {
+        levels
+            .iter()
+            .map(|&(price, size)| PriceLevel {
+                price: Decimal::try_from(price).unwrap(),
+                size: Decimal::try_from(size).unwrap(),
+            })
+            .collect()
+    }
 
-        // There should be an arbitrage opportunity here
-        // Buy at 0.45 on Polymarket, sell at 0.55 on Kalshi
-        let profit = (0.55 - 0.45) / 0.45;
-        assert!(profit > 0.02); // More than 2% profit
+    /// A book with effectively unlimited depth at the market's quoted asks.
+    fn create_test_book(market: &Market) -> OrderBook {
+        OrderBook::new(
+            market.platform.clone(),
+            market.id.clone(),
+            vec![PriceLevel { price: market.yes_price, size: Decimal::from(1_000_000) }],
+            vec![PriceLevel { price: market.no_price, size: Decimal::from(1_000_000) }],
+        )
     }
 
-    #[test]
-    fn test_no_arbitrage() {
-        let poly_market = create_test_market(Platform::Polymarket, 0.50);
-        let kalshi_market = create_test_market(Platform::Kalshi, 0.51);
+    fn calculate(engine: &ArbitrageEngine, poly_market: &Market, kalshi_market: &Market) -> Option<ArbitrageOpportunity> {
+        engine.calculate_arbitrage(
+            poly_market,
+            kalshi_market,
+            &create_test_book(poly_market),
+            &create_test_book(kalshi_market),
+        )
+    }
+
+    async fn create_test_engine() -> ArbitrageEngine {
+        let mut config = Config::load("config/default.toml").expect("Failed to load config");
+        config.bot.min_profit_percentage = 2.0;
+        config.bot.max_position_size = 1000.0;
+
+        let db = Database::new("sqlite::memory:")
+            .await
+            .expect("Failed to create database");
+
+        ArbitrageEngine::new(config, db, false)
+            .await
+            .expect("Failed to create engine")
+    }
+
+    #[tokio::test]
+    async fn test_arbitrage_detection() {
+        let engine = create_test_engine().await;
+        let poly_market = create_test_market(Platform::Polymarket, 0.45, 0.56);
+        let kalshi_market = create_test_market(Platform::Kalshi, 0.52, 0.50);
+
+        // YES on Polymarket (0.45) + NO on Kalshi (0.50) costs 0.95 for a payout of 1
+        let opportunity = calculate(&engine, &poly_market, &kalshi_market)
+            .expect("Expected an opportunity");
+
+        assert_eq!(opportunity.yes_platform, Platform::Polymarket);
+        assert_eq!(opportunity.no_platform, Platform::Kalshi);
+        assert_eq!(opportunity.yes_price, Decimal::try_from(0.45).unwrap());
+        assert_eq!(opportunity.no_price, Decimal::try_from(0.50).unwrap());
+        assert_eq!(opportunity.payout, Decimal::ONE);
+        assert_eq!(opportunity.position_size, Decimal::from(1052));
+        assert_eq!(opportunity.gross_edge, Decimal::try_from(0.05).unwrap());
+        // Kalshi taker fee: 0.07 * 1052 * 0.50 * 0.50 = 18.41
+        assert_eq!(opportunity.total_fees, Decimal::try_from(18.41).unwrap());
+        assert_eq!(opportunity.estimated_profit, Decimal::try_from(34.19).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_fees_consume_edge() {
+        let engine = create_test_engine().await;
+        let poly_market = create_test_market(Platform::Polymarket, 0.49, 0.56);
+        let kalshi_market = create_test_market(Platform::Kalshi, 0.52, 0.48);
+
+        // 3% gross edge, but Kalshi's fee on NO at 0.48 takes it under 2% net
+        assert!(calculate(&engine, &poly_market, &kalshi_market).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_arbitrage_detection_reverse_direction() {
+        let engine = create_test_engine().await;
+        let poly_market = create_test_market(Platform::Polymarket, 0.62, 0.55);
+        let kalshi_market = create_test_market(Platform::Kalshi, 0.40, 0.61);
+
+        let opportunity = calculate(&engine, &poly_market, &kalshi_market)
+            .expect("Expected an opportunity");
+
+        assert_eq!(opportunity.yes_platform, Platform::Kalshi);
+        assert_eq!(opportunity.no_platform, Platform::Polymarket);
+        assert_eq!(opportunity.total_cost(), Decimal::try_from(0.95).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_no_arbitrage() {
+        let engine = create_test_engine().await;
+        let poly_market = create_test_market(Platform::Polymarket, 0.50, 0.51);
+        let kalshi_market = create_test_market(Platform::Kalshi, 0.50, 0.51);
+
+        // Both complements cost 1.01, which loses money
+        assert!(calculate(&engine, &poly_market, &kalshi_market).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_yes_price_gap_is_not_arbitrage() {
+        let engine = create_test_engine().await;
+        // A YES ask gap alone is not tradable when each venue's spread closes it
+        let poly_market = create_test_market(Platform::Polymarket, 0.45, 0.56);
+        let kalshi_market = create_test_market(Platform::Kalshi, 0.55, 0.56);
+
+        assert!(calculate(&engine, &poly_market, &kalshi_market).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_position_sized_by_book_depth() {
+        let engine = create_test_engine().await;
+        let poly_market = create_test_market(Platform::Polymarket, 0.45, 0.60);
+        let kalshi_market = create_test_market(Platform::Kalshi, 0.60, 0.50);
+
+        let poly_book = OrderBook::new(
+            Platform::Polymarket,
+            poly_market.id.clone(),
+            levels(&[(0.45, 100.0), (0.48, 200.0)]),
+            levels(&[(0.60, 1000.0)]),
+        );
+        let kalshi_book = OrderBook::new(
+            Platform::Kalshi,
+            kalshi_market.id.clone(),
+            levels(&[(0.60, 1000.0)]),
+            levels(&[(0.50, 150.0), (0.53, 500.0)]),
+        );
+
+        let opportunity = engine
+            .calculate_arbitrage(&poly_market, &kalshi_market, &poly_book, &kalshi_book)
+            .expect("Expected an opportunity");
+
+        // The second levels together cost 1.01, so the size stops shortly
+        // after the cheap Kalshi level is exhausted
+        assert!(opportunity.position_size > Decimal::from(150));
+        assert!(opportunity.position_size < Decimal::from(200));
+        assert!(opportunity.profit_percentage > Decimal::try_from(0.02).unwrap());
+        assert_eq!(opportunity.yes_limit_price, Decimal::try_from(0.48).unwrap());
+        assert_eq!(opportunity.no_limit_price, Decimal::try_from(0.53).unwrap());
+        assert!(opportunity.yes_price > Decimal::try_from(0.45).unwrap());
+        assert!(opportunity.yes_price < Decimal::try_from(0.48).unwrap());
+
+        // Without the second Kalshi level, depth alone caps the size
+        let mut thin_kalshi = kalshi_book.clone();
+        thin_kalshi.no_asks.truncate(1);
+        let capped = engine
+            .calculate_arbitrage(&poly_market, &kalshi_market, &poly_book, &thin_kalshi)
+            .expect("Expected an opportunity");
+        assert_eq!(capped.position_size, Decimal::from(150));
+    }
+}
+
+#[cfg(test)]
+mod pagination_tests {
+    use mockito::Matcher;
+    use polymarket_kalshi_arbitrage_bot::api::{
+        polymarket_signing::ApiCredentials, KalshiClient, KalshiMarketQuery, PolymarketClient,
+        PolymarketMarketQuery,
+    };
+    use serde_json::json;
+
+    fn kalshi_market(ticker: &str) -> serde_json::Value {
+        json!({
+            "ticker": ticker,
+            "title": "Will it rain tomorrow?",
+            "yes_bid": 44,
+            "yes_ask": 46,
+            "volume": 100,
+            "open_interest": 50,
+            "close_time": "2030-01-01T00:00:00Z"
+        })
+    }
+
+    fn polymarket_market(id: &str) -> serde_json::Value {
+        json!({
+            "id": id,
+            "question": "Will it rain tomorrow?",
+            "bestBid": "0.44",
+            "bestAsk": "0.46",
+            "volume": "100",
+            "liquidity": "50",
+            "endDate": "2030-01-01T00:00:00Z"
+        })
+    }
+
+    #[tokio::test]
+    async fn test_kalshi_follows_cursor() {
+        let mut server = mockito::Server::new_async().await;
+        let first = server
+            .mock("GET", "/trade-api/v2/markets")
+            .match_query(Matcher::Exact(
+                "status=open&limit=2&series_ticker=RAIN".to_string(),
+            ))
+            .with_body(
+                json!({ "markets": [kalshi_market("A"), kalshi_market("B")], "cursor": "next" })
+                    .to_string(),
+            )
+            .create_async()
+            .await;
+        let second = server
+            .mock("GET", "/trade-api/v2/markets")
+            .match_query(Matcher::Exact(
+                "status=open&limit=2&series_ticker=RAIN&cursor=next".to_string(),
+            ))
+            .with_body(json!({ "markets": [kalshi_market("C")], "cursor": "" }).to_string())
+            .create_async()
+            .await;
+
+        let client = KalshiClient::new(String::new(), None, server.url()).with_market_query(
+            KalshiMarketQuery {
+                series_ticker: Some("RAIN".to_string()),
+                page_size: 2,
+                ..KalshiMarketQuery::default()
+            },
+        );
+
+        let markets = client.get_markets().await.expect("Failed to list markets");
+        let ids: Vec<_> = markets.iter().map(|m| m.id.as_str()).collect();
+
+        assert_eq!(ids, vec!["A", "B", "C"]);
+        first.assert_async().await;
+        second.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_kalshi_stops_at_page_cap() {
+        let mut server = mockito::Server::new_async().await;
+        let page = server
+            .mock("GET", "/trade-api/v2/markets")
+            .match_query(Matcher::Any)
+            .with_body(json!({ "markets": [kalshi_market("A")], "cursor": "more" }).to_string())
+            .expect(3)
+            .create_async()
+            .await;
+
+        let client = KalshiClient::new(String::new(), None, server.url()).with_market_query(
+            KalshiMarketQuery {
+                max_pages: 3,
+                ..KalshiMarketQuery::default()
+            },
+        );
+
+        let markets = client.get_markets().await.expect("Failed to list markets");
+
+        assert_eq!(markets.len(), 3);
+        page.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_polymarket_pages_by_offset() {
+        let mut server = mockito::Server::new_async().await;
+        let first = server
+            .mock("GET", "/markets")
+            .match_query(Matcher::Exact(
+                "active=true&closed=false&limit=2&offset=0".to_string(),
+            ))
+            .with_body(
+                json!({ "markets": [polymarket_market("1"), polymarket_market("2")] }).to_string(),
+            )
+            .create_async()
+            .await;
+        let second = server
+            .mock("GET", "/markets")
+            .match_query(Matcher::Exact(
+                "active=true&closed=false&limit=2&offset=2".to_string(),
+            ))
+            .with_body(json!({ "markets": [polymarket_market("3")] }).to_string())
+            .create_async()
+            .await;
+
+        let client = PolymarketClient::new(ApiCredentials::default(), None, server.url())
+            .with_market_query(PolymarketMarketQuery {
+                page_size: 2,
+                ..PolymarketMarketQuery::default()
+            });
+
+        let markets = client.get_markets().await.expect("Failed to list markets");
+
+        assert_eq!(markets.len(), 3);
+        first.assert_async().await;
+        second.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_polymarket_follows_next_cursor() {
+        let mut server = mockito::Server::new_async().await;
+        let first = server
+            .mock("GET", "/markets")
+            .match_query(Matcher::Exact(
+                "active=true&closed=false&limit=500&tag_id=7&offset=0".to_string(),
+            ))
+            .with_body(
+                json!({ "markets": [polymarket_market("1")], "next_cursor": "MQ==" }).to_string(),
+            )
+            .create_async()
+            .await;
+        let second = server
+            .mock("GET", "/markets")
+            .match_query(Matcher::Exact(
+                "active=true&closed=false&limit=500&tag_id=7&next_cursor=MQ%3D%3D".to_string(),
+            ))
+            .with_body(
+                json!({ "markets": [polymarket_market("2")], "next_cursor": "LTE=" }).to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = PolymarketClient::new(ApiCredentials::default(), None, server.url())
+            .with_market_query(PolymarketMarketQuery {
+                tag_id: Some("7".to_string()),
+                ..PolymarketMarketQuery::default()
+            });
+
+        let markets = client.get_markets().await.expect("Failed to list markets");
+
+        assert_eq!(markets.len(), 2);
+        first.assert_async().await;
+        second.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_kalshi_reads_rules_and_settlement_timer() {
+        let mut server = mockito::Server::new_async().await;
+        let mut market = kalshi_market("A");
+        market["rules_primary"] = json!("If it rains in NYC by Jan 1, 2030, then Yes. ");
+        market["rules_secondary"] = json!("Per the National Weather Service.");
+        market["settlement_timer_seconds"] = json!(3600);
+        let mut bare = kalshi_market("B");
+        bare["rules_secondary"] = json!("");
+        server
+            .mock("GET", "/trade-api/v2/markets")
+            .match_query(Matcher::Any)
+            .with_body(json!({ "markets": [market, bare], "cursor": "" }).to_string())
+            .create_async()
+            .await;
+
+        let client = KalshiClient::new(String::new(), None, server.url());
+        let markets = client.get_markets().await.expect("Failed to list markets");
+
+        assert_eq!(
+            markets[0].rules.as_deref(),
+            Some("If it rains in NYC by Jan 1, 2030, then Yes.\n\nPer the National Weather Service.")
+        );
+        assert_eq!(markets[0].settlement_timer_seconds, Some(3600));
+        assert_eq!(markets[0].resolution_source, None);
+        assert_eq!(markets[1].rules, None);
+        assert_eq!(markets[1].settlement_timer_seconds, None);
+    }
+
+    #[tokio::test]
+    async fn test_polymarket_reads_description_and_resolution_source() {
+        let mut server = mockito::Server::new_async().await;
+        let mut market = polymarket_market("1");
+        market["description"] = json!("Resolves Yes if it rains in NYC by Jan 1, 2030.");
+        market["resolutionSource"] = json!("https://www.weather.gov");
+        market["conditionId"] = json!("0xrain");
+        let mut bare = polymarket_market("2");
+        bare["description"] = json!("");
+        bare["resolutionSource"] = json!("");
+        server
+            .mock("GET", "/markets")
+            .match_query(Matcher::Any)
+            .with_body(json!({ "markets": [market, bare] }).to_string())
+            .create_async()
+            .await;
+
+        let client = PolymarketClient::new(ApiCredentials::default(), None, server.url());
+        let markets = client.get_markets().await.expect("Failed to list markets");
+
+        assert_eq!(
+            markets[0].rules.as_deref(),
+            Some("Resolves Yes if it rains in NYC by Jan 1, 2030.")
+        );
+        assert_eq!(
+            markets[0].resolution_source.as_deref(),
+            Some("https://www.weather.gov")
+        );
+        assert_eq!(markets[1].rules, None);
+        assert_eq!(markets[1].resolution_source, None);
+
+        // Positions are held under the condition ID
+        assert_eq!(markets[0].position_id(), "0xrain");
+        assert_eq!(markets[1].position_id(), "2");
+    }
+
+    #[tokio::test]
+    async fn test_kalshi_lists_events_with_open_outcomes() {
+        let mut server = mockito::Server::new_async().await;
+        let outcome = |ticker: &str, name: &str, status: &str| {
+            let mut market = kalshi_market(ticker);
+            market["event_ticker"] = json!("KXMAYOR-25");
+            market["yes_sub_title"] = json!(name);
+            market["status"] = json!(status);
+            market
+        };
+        let listing = server
+            .mock("GET", "/trade-api/v2/events")
+            .match_query(Matcher::Exact(
+                "status=open&limit=200&with_nested_markets=true".to_string(),
+            ))
+            .with_body(
+                json!({
+                    "events": [{
+                        "event_ticker": "KXMAYOR-25",
+                        "title": "Who will win the NYC mayoral election?",
+                        "mutually_exclusive": true,
+                        "markets": [
+                            outcome("KXMAYOR-25-ZM", "Zohran Mamdani", "active"),
+                            outcome("KXMAYOR-25-AC", "Andrew Cuomo", "active"),
+                            outcome("KXMAYOR-25-EA", "Eric Adams", "finalized")
+                        ]
+                    }],
+                    "cursor": ""
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = KalshiClient::new(String::new(), None, server.url());
+        let events = client.get_events().await.expect("Failed to list events");
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id, "KXMAYOR-25");
+        assert!(events[0].mutually_exclusive);
+        let names: Vec<_> = events[0]
+            .markets
+            .iter()
+            .map(|m| m.outcome_name.as_deref().unwrap())
+            .collect();
+        assert_eq!(names, vec!["Zohran Mamdani", "Andrew Cuomo"]);
+        assert_eq!(
+            events[0].markets[0].event_title.as_deref(),
+            Some("Who will win the NYC mayoral election?")
+        );
+        listing.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_kalshi_markets_carry_their_event() {
+        let mut server = mockito::Server::new_async().await;
+        let mut market = kalshi_market("KXMAYOR-25-ZM");
+        market["event_ticker"] = json!("KXMAYOR-25");
+        market["yes_sub_title"] = json!("Zohran Mamdani");
+        server
+            .mock("GET", "/trade-api/v2/markets")
+            .match_query(Matcher::Any)
+            .with_body(json!({ "markets": [market, kalshi_market("B")], "cursor": "" }).to_string())
+            .create_async()
+            .await;
+
+        let client = KalshiClient::new(String::new(), None, server.url());
+        let markets = client.get_markets().await.expect("Failed to list markets");
+
+        assert_eq!(markets[0].event_id.as_deref(), Some("KXMAYOR-25"));
+        assert_eq!(markets[0].outcome_name.as_deref(), Some("Zohran Mamdani"));
+        assert_eq!(markets[1].event_id, None);
+        assert_eq!(markets[1].outcome_name, None);
+    }
+
+    #[tokio::test]
+    async fn test_polymarket_lists_events_with_open_outcomes() {
+        let mut server = mockito::Server::new_async().await;
+        let outcome = |id: &str, name: &str, closed: bool| {
+            let mut market = polymarket_market(id);
+            market["groupItemTitle"] = json!(name);
+            market["closed"] = json!(closed);
+            market
+        };
+        let listing = server
+            .mock("GET", "/events")
+            .match_query(Matcher::Exact(
+                "active=true&closed=false&limit=500&offset=0".to_string(),
+            ))
+            .with_body(
+                json!({
+                    "events": [{
+                        "id": "903",
+                        "title": "New York City Mayoral Election",
+                        "negRisk": true,
+                        "markets": [
+                            outcome("1", "Zohran Mamdani", false),
+                            outcome("2", "Andrew Cuomo", false),
+                            outcome("3", "Eric Adams", true)
+                        ]
+                    }]
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = PolymarketClient::new(ApiCredentials::default(), None, server.url());
+        let events = client.get_events().await.expect("Failed to list events");
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].id, "903");
+        assert!(events[0].mutually_exclusive);
+        let names: Vec<_> = events[0]
+            .markets
+            .iter()
+            .map(|m| m.outcome_name.as_deref().unwrap())
+            .collect();
+        assert_eq!(names, vec!["Zohran Mamdani", "Andrew Cuomo"]);
+        assert_eq!(events[0].markets[1].event_id.as_deref(), Some("903"));
+        // Outcomes of a neg-risk event trade on the NegRisk CTF Exchange
+        assert!(events[0].markets.iter().all(|m| m.neg_risk));
+        listing.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_polymarket_markets_carry_their_event() {
+        let mut server = mockito::Server::new_async().await;
+        let mut market = polymarket_market("1");
+        market["groupItemTitle"] = json!("Zohran Mamdani");
+        market["events"] = json!([{ "id": "903", "title": "New York City Mayoral Election" }]);
+        server
+            .mock("GET", "/markets")
+            .match_query(Matcher::Any)
+            .with_body(json!({ "markets": [market, polymarket_market("2")] }).to_string())
+            .create_async()
+            .await;
+
+        let client = PolymarketClient::new(ApiCredentials::default(), None, server.url());
+        let markets = client.get_markets().await.expect("Failed to list markets");
+
+        assert_eq!(markets[0].event_id.as_deref(), Some("903"));
+        assert_eq!(
+            markets[0].event_title.as_deref(),
+            Some("New York City Mayoral Election")
+        );
+        assert_eq!(markets[0].outcome_name.as_deref(), Some("Zohran Mamdani"));
+        assert_eq!(markets[1].event_id, None);
+    }
+}
+
+#[cfg(test)]
+mod streaming_tests {
+    use futures::{SinkExt, StreamExt};
+    use polymarket_kalshi_arbitrage_bot::{
+        api::KalshiClient,
+        models::{OrderBook, Platform, PriceLevel},
+        streaming::{BookEvent, BookStore, KalshiStream, PolymarketStream},
+    };
+    use rust_decimal::Decimal;
+    use serde_json::json;
+    use std::{collections::HashMap, time::Duration};
+    use tokio::{net::TcpListener, sync::mpsc};
+    use tokio_tungstenite::{accept_async, tungstenite::Message};
+
+    /// Serves one scripted connection per entry: records the client's
+    /// subscribe message, sends the script, then holds the socket open.
+    async fn mock_server(scripts: Vec<Vec<String>>) -> (String, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let (subscriptions_tx, subscriptions_rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            for script in scripts {
+                let (tcp, _) = listener.accept().await.unwrap();
+                let mut socket = accept_async(tcp).await.unwrap();
+
+                if let Some(Ok(Message::Text(subscribe))) = socket.next().await {
+                    subscriptions_tx.send(subscribe).unwrap();
+                }
+                for message in script {
+                    socket.send(Message::Text(message)).await.unwrap();
+                }
+
+                tokio::spawn(async move { while let Some(Ok(_)) = socket.next().await {} });
+            }
+        });
+
+        (url, subscriptions_rx)
+    }
+
+    /// Waits for book events until the stored book satisfies `done`.
+    async fn wait_for_book(
+        events: &mut mpsc::Receiver<BookEvent>,
+        store: &BookStore,
+        platform: Platform,
+        market_id: &str,
+        done: impl Fn(&OrderBook) -> bool,
+    ) -> OrderBook {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let event = events.recv().await.expect("stream stopped");
+                assert_eq!(event.platform, platform);
+                if let Some(book) = store.get(&platform, market_id) {
+                    if done(&book) {
+                        return book;
+                    }
+                }
+            }
+        })
+        .await
+        .expect("timed out waiting for book")
+    }
+
+    fn level(price: &str, size: i64) -> PriceLevel {
+        PriceLevel {
+            price: price.parse().unwrap(),
+            size: Decimal::from(size),
+        }
+    }
+
+    fn kalshi_snapshot(sid: u64, seq: u64, yes: serde_json::Value, no: serde_json::Value) -> String {
+        json!({
+            "type": "orderbook_snapshot",
+            "sid": sid,
+            "seq": seq,
+            "msg": { "market_ticker": "RAIN-25", "yes": yes, "no": no }
+        })
+        .to_string()
+    }
+
+    fn kalshi_delta(sid: u64, seq: u64, side: &str, price: i64, delta: i64) -> String {
+        json!({
+            "type": "orderbook_delta",
+            "sid": sid,
+            "seq": seq,
+            "msg": { "market_ticker": "RAIN-25", "price": price, "delta": delta, "side": side }
+        })
+        .to_string()
+    }
+
+    fn spawn_kalshi(url: String) -> (BookStore, mpsc::Receiver<BookEvent>) {
+        let store = BookStore::default();
+        let (events_tx, events_rx) = mpsc::channel(64);
+        let client = KalshiClient::new(String::new(), None, "http://127.0.0.1".to_string());
+
+        let stream = KalshiStream::new(
+            client,
+            url,
+            vec!["RAIN-25".to_string()],
+            store.clone(),
+            events_tx,
+        );
+        tokio::spawn(stream.run());
+
+        (store, events_rx)
+    }
+
+    #[tokio::test]
+    async fn test_kalshi_snapshot_and_deltas() {
+        let (url, mut subscriptions) = mock_server(vec![vec![
+            kalshi_snapshot(1, 1, json!([[40, 100]]), json!([[55, 50]])),
+            kalshi_delta(1, 2, "no", 55, -20),
+            kalshi_delta(1, 3, "yes", 42, 10),
+        ]])
+        .await;
+        let (store, mut events) = spawn_kalshi(url);
+
+        let book = wait_for_book(&mut events, &store, Platform::Kalshi, "RAIN-25", |book| {
+            book.no_asks.len() == 2
+        })
+        .await;
+
+        // NO bids become YES asks at 100 - p and vice versa
+        assert_eq!(book.yes_asks, vec![level("0.45", 30)]);
+        assert_eq!(book.no_asks, vec![level("0.58", 10), level("0.60", 100)]);
+
+        let subscribe: serde_json::Value =
+            serde_json::from_str(&subscriptions.recv().await.unwrap()).unwrap();
+        assert_eq!(subscribe["cmd"], "subscribe");
+        assert_eq!(subscribe["params"]["channels"], json!(["orderbook_delta"]));
+        assert_eq!(subscribe["params"]["market_tickers"], json!(["RAIN-25"]));
+    }
+
+    #[tokio::test]
+    async fn test_kalshi_sequence_gap_resnapshots() {
+        let (url, mut subscriptions) = mock_server(vec![
+            vec![
+                kalshi_snapshot(1, 1, json!([[40, 100]]), json!([[55, 50]])),
+                // seq 3 is missing, and the delta after the gap must not apply
+                kalshi_delta(1, 2, "no", 55, -20),
+                kalshi_delta(1, 4, "no", 55, -30),
+            ],
+            vec![kalshi_snapshot(2, 1, json!([[41, 70]]), json!([[56, 25]]))],
+        ])
+        .await;
+        let (store, mut events) = spawn_kalshi(url);
+
+        let book = wait_for_book(&mut events, &store, Platform::Kalshi, "RAIN-25", |book| {
+            book.no_asks.first().map(|level| level.price) == Some("0.59".parse().unwrap())
+        })
+        .await;
+
+        assert_eq!(book.yes_asks, vec![level("0.44", 25)]);
+        assert_eq!(book.no_asks, vec![level("0.59", 70)]);
+
+        // The client subscribed again on a fresh connection
+        subscriptions.recv().await.unwrap();
+        subscriptions.recv().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_polymarket_book_and_price_changes() {
+        let book = json!([{
+            "event_type": "book",
+            "asset_id": "yes-token",
+            "market": "0xcondition",
+            "bids": [{ "price": "0.40", "size": "100" }, { "price": "0.38", "size": "50" }],
+            "asks": [{ "price": "0.45", "size": "80" }]
+        }]);
+        let legacy_change = json!({
+            "event_type": "price_change",
+            "asset_id": "yes-token",
+            "changes": [{ "price": "0.40", "side": "BUY", "size": "0" }]
+        });
+        let batched_change = json!({
+            "event_type": "price_change",
+            "market": "0xcondition",
+            "price_changes": [
+                { "asset_id": "yes-token", "price": "0.44", "side": "SELL", "size": "15" },
+                { "asset_id": "other-token", "price": "0.10", "side": "SELL", "size": "1" }
+            ]
+        });
+        let (url, mut subscriptions) = mock_server(vec![vec![
+            book.to_string(),
+            legacy_change.to_string(),
+            batched_change.to_string(),
+        ]])
+        .await;
+
+        let store = BookStore::default();
+        let (events_tx, mut events) = mpsc::channel(64);
+        let assets = HashMap::from([("yes-token".to_string(), "market-1".to_string())]);
+        tokio::spawn(PolymarketStream::new(url, assets, store.clone(), events_tx).run());
+
+        let book = wait_for_book(&mut events, &store, Platform::Polymarket, "market-1", |book| {
+            book.yes_asks.len() == 2
+        })
+        .await;
+
+        assert_eq!(book.yes_asks, vec![level("0.44", 15), level("0.45", 80)]);
+        // YES bids become NO asks at 1 - p; the 0.40 bid was removed
+        assert_eq!(book.no_asks, vec![level("0.62", 50)]);
+
+        let subscribe: serde_json::Value =
+            serde_json::from_str(&subscriptions.recv().await.unwrap()).unwrap();
+        assert_eq!(subscribe, json!({ "assets_ids": ["yes-token"], "type": "market" }));
+    }
+}
+
+#[cfg(test)]
+mod staleness_tests {
+    use super::*;
+    use mockito::Matcher;
+    use polymarket_kalshi_arbitrage_bot::arbitrage::ArbitrageEngine;
+    use serde_json::json;
+
+    async fn create_engine(server: &mockito::Server, max_staleness_seconds: u64) -> ArbitrageEngine {
+        let mut config = Config::load("config/default.toml").expect("Failed to load config");
+        config.polymarket.base_url = server.url();
+        config.kalshi.base_url = server.url();
+        config.bot.max_market_staleness_seconds = max_staleness_seconds;
+        config.polymarket.http.max_retries = 0;
+        config.kalshi.http.max_retries = 0;
+
+        let db = Database::new("sqlite::memory:")
+            .await
+            .expect("Failed to create database");
+
+        ArbitrageEngine::new(config, db, false)
+            .await
+            .expect("Failed to create engine")
+    }
+
+    async fn mock_polymarket(server: &mut mockito::Server) -> mockito::Mock {
+        server
+            .mock("GET", "/events")
+            .match_query(Matcher::Any)
+            .with_body(
+                json!({
+                    "events": [{
+                        "id": "rain",
+                        "title": "Rain tomorrow",
+                        "markets": [{
+                            "id": "poly-1",
+                            "question": "Will it rain tomorrow?",
+                            "bestBid": "0.44",
+                            "bestAsk": "0.46",
+                            "volume": "100",
+                            "liquidity": "50",
+                            "endDate": "2030-01-01T00:00:00Z"
+                        }]
+                    }]
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await
+    }
+
+    async fn mock_kalshi(server: &mut mockito::Server, status: usize) -> mockito::Mock {
+        server
+            .mock("GET", "/trade-api/v2/events")
+            .match_query(Matcher::Any)
+            .with_status(status)
+            .with_body(
+                json!({
+                    "events": [{
+                        "event_ticker": "RAIN",
+                        "title": "Will it rain tomorrow?",
+                        "markets": [{
+                            "ticker": "RAIN-25",
+                            "title": "Will it rain tomorrow?",
+                            "yes_bid": 44,
+                            "yes_ask": 46,
+                            "volume": 100,
+                            "open_interest": 50,
+                            "close_time": "2030-01-01T00:00:00Z"
+                        }]
+                    }],
+                    "cursor": ""
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_failed_venue_falls_back_to_last_listing() {
+        let mut server = mockito::Server::new_async().await;
+        let _polymarket = mock_polymarket(&mut server).await;
+        let kalshi_up = mock_kalshi(&mut server, 200).await;
+        let engine = create_engine(&server, 60).await;
+
+        let (polymarket, kalshi) = engine.fetch_markets().await;
+        assert!(!polymarket.unwrap().stale);
+        assert!(!kalshi.unwrap().stale);
+
+        kalshi_up.remove_async().await;
+        let _kalshi_down = mock_kalshi(&mut server, 500).await;
+
+        let (polymarket, kalshi) = engine.fetch_markets().await;
+        let polymarket = polymarket.expect("Polymarket listing should be fresh");
+        let kalshi = kalshi.expect("Kalshi listing should fall back to the cache");
+        assert!(!polymarket.stale);
+        assert!(kalshi.stale);
+        assert_eq!(kalshi.markets[0].id, "RAIN-25");
+    }
+
+    #[tokio::test]
+    async fn test_listing_past_max_staleness_is_suppressed() {
+        let mut server = mockito::Server::new_async().await;
+        let _polymarket = mock_polymarket(&mut server).await;
+        let kalshi_up = mock_kalshi(&mut server, 200).await;
+        let engine = create_engine(&server, 0).await;
+
+        assert!(engine.fetch_markets().await.1.is_some());
+
+        kalshi_up.remove_async().await;
+        let _kalshi_down = mock_kalshi(&mut server, 500).await;
+
+        let (polymarket, kalshi) = engine.fetch_markets().await;
+        assert!(polymarket.is_some());
+        assert!(kalshi.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failed_venue_without_cache_is_suppressed() {
+        let mut server = mockito::Server::new_async().await;
+        let _polymarket = mock_polymarket(&mut server).await;
+        let _kalshi_down = mock_kalshi(&mut server, 500).await;
+        let engine = create_engine(&server, 60).await;
+
+        let (polymarket, kalshi) = engine.fetch_markets().await;
+        assert!(polymarket.is_some());
+        assert!(kalshi.is_none());
+    }
+}
+
+#[cfg(test)]
+mod api_error_tests {
+    use mockito::Matcher;
+    use polymarket_kalshi_arbitrage_bot::{
+        api::{
+            polymarket_signing::{
+                parse_address, ApiCredentials, Eip712Domain, OrderSigner, PolymarketSigner,
+                SignatureType, CTF_EXCHANGE_ADDRESS, POLYGON_CHAIN_ID,
+            },
+            ApiError, KalshiClient, KalshiSigner, PolymarketClient,
+        },
+        config::HttpConfig,
+        models::{Market, OrderRequest, Outcome, Platform, TradeSide},
+    };
+    use rust_decimal::Decimal;
+    use serde_json::json;
+    use std::time::Duration;
+
+    const POLYMARKET_TEST_KEY: &str =
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    fn no_retries() -> HttpConfig {
+        HttpConfig {
+            max_retries: 0,
+            ..HttpConfig::default()
+        }
+    }
+
+    fn kalshi_client(server: &mockito::Server) -> KalshiClient {
+        let signer = KalshiSigner::from_file("tests/fixtures/kalshi_test_key.pem").unwrap();
+        KalshiClient::new("key-id".to_string(), Some(signer), server.url())
+            .with_http_config(&no_retries())
+    }
+
+    fn order(market_id: &str, token_id: Option<&str>) -> OrderRequest {
+        OrderRequest {
+            market_id: market_id.to_string(),
+            token_id: token_id.map(str::to_string),
+            outcome: Outcome::Yes,
+            side: TradeSide::Buy,
+            price: Decimal::new(45, 2),
+            size: Decimal::from(10),
+            neg_risk: false,
+            client_order_id: None,
+        }
+    }
+
+    fn polymarket_client(server: &mockito::Server) -> PolymarketClient {
+        let signer = OrderSigner::new(
+            PolymarketSigner::from_hex(POLYMARKET_TEST_KEY).unwrap(),
+            None,
+            SignatureType::Eoa,
+            Eip712Domain::ctf_exchange(POLYGON_CHAIN_ID, parse_address(CTF_EXCHANGE_ADDRESS).unwrap()),
+        )
+        .unwrap();
+        let credentials = ApiCredentials {
+            api_key: "api-key".to_string(),
+            secret: "c2VjcmV0".to_string(),
+            passphrase: "passphrase".to_string(),
+        };
+        PolymarketClient::new(credentials, Some(signer), server.url())
+            .with_http_config(&no_retries())
+    }
+
+    fn polymarket_market() -> Market {
+        Market {
+            id: "poly-1".to_string(),
+            question: "Will it rain tomorrow?".to_string(),
+            platform: Platform::Polymarket,
+            yes_price: Decimal::new(45, 2),
+            no_price: Decimal::new(56, 2),
+            volume: Decimal::ZERO,
+            liquidity: Decimal::ZERO,
+            end_time: chrono::Utc::now(),
+            yes_token_id: Some("123".to_string()),
+            no_token_id: Some("456".to_string()),
+            rules: None,
+            resolution_source: None,
+            settlement_timer_seconds: None,
+            event_id: None,
+            event_title: None,
+            outcome_name: None,
+            neg_risk: false,
+            condition_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_kalshi_rate_limit_carries_retry_after() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/trade-api/v2/markets")
+            .match_query(Matcher::Any)
+            .with_status(429)
+            .with_header("Retry-After", "2")
+            .with_body(json!({ "error": { "code": "too_many_requests", "message": "slow down" } }).to_string())
+            .create_async()
+            .await;
+
+        let error = kalshi_client(&server).get_markets().await.unwrap_err();
+
+        assert!(error.is_retryable());
+        assert!(matches!(
+            error,
+            ApiError::RateLimited { retry_after: Some(d) } if d == Duration::from_secs(2)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_kalshi_insufficient_balance() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/trade-api/v2/portfolio/orders")
+            .with_status(400)
+            .with_body(
+                json!({ "error": { "code": "insufficient_balance", "message": "Insufficient balance" } })
+                    .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let error = kalshi_client(&server)
+            .place_order(&order("RAIN-25", None))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, ApiError::InsufficientFunds));
+        assert!(!error.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_kalshi_rejected_price_keeps_reason() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/trade-api/v2/portfolio/orders")
+            .with_status(400)
+            .with_body(
+                json!({ "error": { "code": "invalid_parameters", "message": "yes_price must be between 1 and 99" } })
+                    .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let error = kalshi_client(&server)
+            .place_order(&order("RAIN-25", None))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            error,
+            ApiError::InvalidOrder { reason } if reason == "yes_price must be between 1 and 99"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_kalshi_order_without_key_is_unauthorized() {
+        let server = mockito::Server::new_async().await;
+        let client = KalshiClient::new(String::new(), None, server.url());
+
+        let error = client.place_order(&order("RAIN-25", None)).await.unwrap_err();
+
+        assert!(matches!(error, ApiError::Unauthorized));
+    }
+
+    #[tokio::test]
+    async fn test_polymarket_rejected_order_is_classified() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/order")
+            .with_body(
+                json!({ "success": false, "orderID": "", "errorMsg": "not enough balance / allowance" })
+                    .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let error = polymarket_client(&server)
+            .place_order(&order("0xcondition", Some("123")))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, ApiError::InsufficientFunds));
+    }
+
+    #[tokio::test]
+    async fn test_polymarket_error_payload_is_classified() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("POST", "/order")
+            .with_status(400)
+            .with_body(
+                json!({ "error": "the market is not yet ready to process new orders" }).to_string(),
+            )
+            .create_async()
+            .await;
+
+        let error = polymarket_client(&server)
+            .place_order(&order("0xcondition", Some("123")))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, ApiError::MarketClosed));
+    }
+
+    #[tokio::test]
+    async fn test_polymarket_server_and_decode_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let unavailable = server
+            .mock("GET", "/book")
+            .match_query(Matcher::Any)
+            .with_status(503)
+            .create_async()
+            .await;
+
+        let client = polymarket_client(&server);
+        let error = client.get_order_book(&polymarket_market()).await.unwrap_err();
+        assert!(matches!(error, ApiError::Server { status: 503 }));
+
+        unavailable.remove_async().await;
+        let _garbled = server
+            .mock("GET", "/book")
+            .match_query(Matcher::Any)
+            .with_body("<html>maintenance</html>")
+            .create_async()
+            .await;
+
+        let error = client.get_order_book(&polymarket_market()).await.unwrap_err();
+        assert!(matches!(error, ApiError::Decode { body } if body == "<html>maintenance</html>"));
+    }
+}
+
+#[cfg(test)]
+mod retry_tests {
+    use mockito::Matcher;
+    use polymarket_kalshi_arbitrage_bot::{
+        api::{ApiError, KalshiClient, KalshiSigner},
+        config::HttpConfig,
+        models::{OrderRequest, Outcome, TradeSide},
+    };
+    use rust_decimal::Decimal;
+    use serde_json::json;
+    use std::time::{Duration, Instant};
+
+    fn kalshi_client(server: &mockito::Server, max_retries: u32) -> KalshiClient {
+        let signer = KalshiSigner::from_file("tests/fixtures/kalshi_test_key.pem").unwrap();
+        KalshiClient::new("key-id".to_string(), Some(signer), server.url()).with_http_config(
+            &HttpConfig {
+                max_retries,
+                initial_backoff_ms: 10,
+                max_backoff_ms: 50,
+                ..HttpConfig::default()
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_reads_are_retried() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/trade-api/v2/markets")
+            .match_query(Matcher::Any)
+            .with_status(503)
+            .expect(3)
+            .create_async()
+            .await;
+
+        let error = kalshi_client(&server, 2).get_markets().await.unwrap_err();
+
+        assert!(matches!(error, ApiError::Server { status: 503 }));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_rejections_are_not_retried() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/trade-api/v2/markets")
+            .match_query(Matcher::Any)
+            .with_status(400)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let error = kalshi_client(&server, 2).get_markets().await.unwrap_err();
+
+        assert!(matches!(error, ApiError::InvalidOrder { .. }));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_orders_are_never_retried() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/trade-api/v2/portfolio/orders")
+            .with_status(503)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let error = kalshi_client(&server, 3)
+            .place_order(&OrderRequest {
+                market_id: "RAIN-25".to_string(),
+                token_id: None,
+                outcome: Outcome::Yes,
+                side: TradeSide::Buy,
+                price: Decimal::new(45, 2),
+                size: Decimal::from(10),
+                neg_risk: false,
+                client_order_id: None,
+            })
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, ApiError::Server { status: 503 }));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_retry_waits_for_retry_after() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/trade-api/v2/markets")
+            .match_query(Matcher::Any)
+            .with_status(429)
+            .with_header("Retry-After", "1")
+            .with_body(json!({ "error": { "code": "too_many_requests" } }).to_string())
+            .expect(2)
+            .create_async()
+            .await;
+
+        let start = Instant::now();
+        let error = kalshi_client(&server, 1).get_markets().await.unwrap_err();
+
+        assert!(matches!(error, ApiError::RateLimited { .. }));
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        mock.assert_async().await;
+    }
+}
+
+#[cfg(test)]
+mod exchange_tests {
+    use crate::common::MockExchange;
+    use mockito::Matcher;
+    use polymarket_kalshi_arbitrage_bot::{
+        api::{
+            polymarket_signing::{
+                parse_address, ApiCredentials, Eip712Domain, OrderSigner, PolymarketSigner,
+                SignatureType, CTF_EXCHANGE_ADDRESS, POLYGON_CHAIN_ID,
+            },
+            ApiError, KalshiClient, KalshiSigner, PolymarketClient,
+        },
+        arbitrage::ArbitrageEngine,
+        config::{Config, HttpConfig},
+        database::Database,
+        models::{Market, OrderRequest, OrderStatus, Outcome, Platform, TradeSide},
+    };
+    use rust_decimal::Decimal;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    const POLYMARKET_TEST_KEY: &str =
+        "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const POLYMARKET_TEST_ADDRESS: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+    fn no_retries() -> HttpConfig {
+        HttpConfig {
+            max_retries: 0,
+            ..HttpConfig::default()
+        }
+    }
+
+    fn kalshi_client(server: &mockito::Server) -> KalshiClient {
+        let signer = KalshiSigner::from_file("tests/fixtures/kalshi_test_key.pem").unwrap();
+        KalshiClient::new("key-id".to_string(), Some(signer), server.url())
+            .with_http_config(&no_retries())
+    }
+
+    fn polymarket_client(server: &mockito::Server) -> PolymarketClient {
+        let signer = OrderSigner::new(
+            PolymarketSigner::from_hex(POLYMARKET_TEST_KEY).unwrap(),
+            None,
+            SignatureType::Eoa,
+            Eip712Domain::ctf_exchange(POLYGON_CHAIN_ID, parse_address(CTF_EXCHANGE_ADDRESS).unwrap()),
+        )
+        .unwrap();
+        let credentials = ApiCredentials {
+            api_key: "api-key".to_string(),
+            secret: "c2VjcmV0".to_string(),
+            passphrase: "passphrase".to_string(),
+        };
+        PolymarketClient::new(credentials, Some(signer), server.url())
+            .with_http_config(&no_retries())
+            .with_data_api_url(server.url())
+    }
+
+    #[tokio::test]
+    async fn test_kalshi_order_status() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/trade-api/v2/portfolio/orders/ord-1")
+            .match_header("KALSHI-ACCESS-KEY", "key-id")
+            .with_body(
+                json!({ "order": {
+                    "order_id": "ord-1",
+                    "ticker": "RAIN-25",
+                    "status": "resting",
+                    "side": "no",
+                    "action": "buy",
+                    "yes_price": 55,
+                    "no_price": 45,
+                    "initial_count": 10,
+                    "fill_count": 4,
+                    "remaining_count": 6,
+                    "created_time": "2024-05-01T12:00:00Z"
+                } })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let order = kalshi_client(&server).get_order("ord-1").await.unwrap();
+
+        assert_eq!(order.market_id, "RAIN-25");
+        assert_eq!(order.outcome, Outcome::No);
+        assert_eq!(order.side, TradeSide::Buy);
+        assert_eq!(order.price, Decimal::new(45, 2));
+        assert_eq!(order.size, Decimal::from(10));
+        assert_eq!(order.filled_size, Decimal::from(4));
+        assert_eq!(order.status, OrderStatus::PartiallyFilled);
+        assert!(order.created_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_kalshi_finds_order_by_client_order_id() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/trade-api/v2/portfolio/orders")
+            .match_query(Matcher::UrlEncoded("ticker".into(), "RAIN-25".into()))
+            .with_body(
+                json!({ "orders": [
+                    {
+                        "order_id": "ord-1",
+                        "client_order_id": "other",
+                        "ticker": "RAIN-25",
+                        "status": "resting",
+                        "side": "yes",
+                        "action": "buy",
+                        "yes_price": 45
+                    },
+                    {
+                        "order_id": "ord-2",
+                        "client_order_id": "mine",
+                        "ticker": "RAIN-25",
+                        "status": "executed",
+                        "side": "yes",
+                        "action": "buy",
+                        "yes_price": 45,
+                        "initial_count": 10,
+                        "fill_count": 10
+                    }
+                ], "cursor": "" })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = kalshi_client(&server);
+        let mut request = OrderRequest {
+            market_id: "RAIN-25".to_string(),
+            token_id: None,
+            outcome: Outcome::Yes,
+            side: TradeSide::Buy,
+            price: Decimal::new(45, 2),
+            size: Decimal::from(10),
+            neg_risk: false,
+            client_order_id: Some("mine".to_string()),
+        };
+
+        let order = client.find_order(&request).await.unwrap().unwrap();
+        assert_eq!(order.id, "ord-2");
+        assert_eq!(order.status, OrderStatus::Filled);
+
+        request.client_order_id = Some("lost".to_string());
+        assert!(client.find_order(&request).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_kalshi_cancel_positions_and_balance() {
+        let mut server = mockito::Server::new_async().await;
+        let cancel = server
+            .mock("DELETE", "/trade-api/v2/portfolio/orders/ord-1")
+            .with_body(
+                json!({ "order": { "order_id": "ord-1", "status": "canceled" } }).to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let _positions = server
+            .mock("GET", "/trade-api/v2/portfolio/positions")
+            .match_query(Matcher::Any)
+            .with_body(
+                json!({
+                    "market_positions": [
+                        { "ticker": "RAIN-25", "position": -20, "market_exposure": 900 },
+                        { "ticker": "SNOW-25", "position": 0, "market_exposure": 0 }
+                    ],
+                    "cursor": ""
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let _balance = server
+            .mock("GET", "/trade-api/v2/portfolio/balance")
+            .with_body(json!({ "balance": 123456 }).to_string())
+            .create_async()
+            .await;
+
+        let client = kalshi_client(&server);
+        client.cancel_order("ord-1").await.unwrap();
+        cancel.assert_async().await;
+
+        let positions = client.get_positions().await.unwrap();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].market_id, "RAIN-25");
+        assert_eq!(positions[0].outcome, Outcome::No);
+        assert_eq!(positions[0].amount, Decimal::from(20));
+        assert_eq!(positions[0].entry_price, Decimal::new(45, 2));
+
+        assert_eq!(client.get_balance().await.unwrap(), Decimal::new(123456, 2));
+    }
+
+    #[tokio::test]
+    async fn test_kalshi_settlements_since() {
+        let mut server = mockito::Server::new_async().await;
+        let since = chrono::DateTime::parse_from_rfc3339("2025-05-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let settlements = server
+            .mock("GET", "/trade-api/v2/portfolio/settlements")
+            .match_query(Matcher::UrlEncoded(
+                "min_ts".to_string(),
+                since.timestamp().to_string(),
+            ))
+            .with_body(
+                json!({
+                    "settlements": [{
+                        "ticker": "RAIN-25",
+                        "market_result": "no",
+                        "yes_count": 0,
+                        "yes_total_cost": 0,
+                        "no_count": 20,
+                        "no_total_cost": 900,
+                        "revenue": 2000,
+                        "settled_time": "2025-05-01T16:00:00Z"
+                    }],
+                    "cursor": ""
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let listed = kalshi_client(&server).get_settlements(since).await.unwrap();
+        settlements.assert_async().await;
+
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].market_id, "RAIN-25");
+        assert_eq!(listed[0].revenue, Decimal::from(20));
+        assert_eq!(listed[0].cost, Decimal::from(9));
+        assert_eq!(listed[0].pnl(), Decimal::from(11));
+    }
+
+    #[tokio::test]
+    async fn test_kalshi_rejects_fractional_orders_locally() {
+        let server = mockito::Server::new_async().await;
+
+        let error = kalshi_client(&server)
+            .place_order(&OrderRequest {
+                market_id: "RAIN-25".to_string(),
+                token_id: None,
+                outcome: Outcome::Yes,
+                side: TradeSide::Buy,
+                price: Decimal::new(455, 3),
+                size: Decimal::from(10),
+                neg_risk: false,
+                client_order_id: None,
+            })
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, ApiError::InvalidOrder { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_polymarket_order_status() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/data/order/0xabc")
+            .match_header("POLY_API_KEY", "api-key")
+            .with_body(
+                json!({
+                    "id": "0xabc",
+                    "status": "MATCHED",
+                    "market": "0xcondition",
+                    "asset_id": "123",
+                    "side": "BUY",
+                    "original_size": "10",
+                    "size_matched": "10",
+                    "price": "0.45",
+                    "outcome": "Yes",
+                    "expiration": "0",
+                    "created_at": 1714564800
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let order = polymarket_client(&server).get_order("0xabc").await.unwrap();
+
+        assert_eq!(order.platform, Platform::Polymarket);
+        assert_eq!(order.market_id, "0xcondition");
+        assert_eq!(order.outcome, Outcome::Yes);
+        assert_eq!(order.price, Decimal::new(45, 2));
+        assert_eq!(order.filled_size, Decimal::from(10));
+        assert_eq!(order.status, OrderStatus::Filled);
+        assert!(order.created_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_polymarket_cancel_reports_reason() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("DELETE", "/order")
+            .match_body(Matcher::Json(json!({ "orderID": "0xabc" })))
+            .with_body(
+                json!({ "canceled": [], "not_canceled": { "0xabc": "order already matched" } })
+                    .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let error = polymarket_client(&server).cancel_order("0xabc").await.unwrap_err();
+
+        assert!(matches!(
+            error,
+            ApiError::InvalidOrder { reason } if reason == "order already matched"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_polymarket_balance_and_positions() {
+        let mut server = mockito::Server::new_async().await;
+        let _balance = server
+            .mock("GET", "/balance-allowance")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("asset_type".into(), "COLLATERAL".into()),
+                Matcher::UrlEncoded("signature_type".into(), "0".into()),
+            ]))
+            .with_body(json!({ "balance": "2500500000", "allowance": "0" }).to_string())
+            .create_async()
+            .await;
+        let _positions = server
+            .mock("GET", "/positions")
+            .match_query(Matcher::UrlEncoded("user".into(), POLYMARKET_TEST_ADDRESS.into()))
+            .with_body(
+                json!([{
+                    "conditionId": "0xcondition",
+                    "asset": "456",
+                    "outcome": "No",
+                    "size": 20.0,
+                    "avgPrice": 0.55,
+                    "currentValue": 12.0
+                }])
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = polymarket_client(&server);
+
+        assert_eq!(client.get_balance().await.unwrap(), Decimal::new(25005, 1));
+
+        let positions = client.get_positions().await.unwrap();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].market_id, "0xcondition");
+        assert_eq!(positions[0].outcome, Outcome::No);
+        assert_eq!(positions[0].amount, Decimal::from(20));
+        assert_eq!(positions[0].entry_price, Decimal::new(55, 2));
+    }
+
+    #[tokio::test]
+    async fn test_polymarket_retries_are_signed_afresh() {
+        let mut server = mockito::Server::new_async().await;
+        let first = std::sync::Arc::new(std::sync::Mutex::new(None::<String>));
+        let timestamp = |request: &mockito::Request| {
+            request.header("POLY_TIMESTAMP")[0].to_str().unwrap().to_string()
+        };
+
+        // The first signature is rate limited; only a fresh one is served
+        let _limited = server
+            .mock("GET", "/balance-allowance")
+            .match_query(Matcher::Any)
+            .match_request({
+                let first = first.clone();
+                move |request| {
+                    let mut first = first.lock().unwrap();
+                    first.get_or_insert_with(|| timestamp(request)) == &timestamp(request)
+                }
+            })
+            .with_status(429)
+            .with_header("Retry-After", "1")
+            .create_async()
+            .await;
+        let _balance = server
+            .mock("GET", "/balance-allowance")
+            .match_query(Matcher::Any)
+            .match_request({
+                let first = first.clone();
+                move |request| {
+                    first.lock().unwrap().as_ref().is_some_and(|first| *first != timestamp(request))
+                }
+            })
+            .with_body(json!({ "balance": "1000000", "allowance": "0" }).to_string())
+            .create_async()
+            .await;
+
+        let client = polymarket_client(&server).with_http_config(&HttpConfig {
+            max_retries: 1,
+            ..HttpConfig::default()
+        });
+
+        assert_eq!(client.get_balance().await.unwrap(), Decimal::ONE);
+    }
+
+    #[tokio::test]
+    async fn test_polymarket_order_requires_token() {
+        let server = mockito::Server::new_async().await;
+
+        let error = polymarket_client(&server)
+            .place_order(&OrderRequest {
+                market_id: "0xcondition".to_string(),
+                token_id: None,
+                outcome: Outcome::Yes,
+                side: TradeSide::Buy,
+                price: Decimal::new(45, 2),
+                size: Decimal::from(10),
+                neg_risk: false,
+                client_order_id: None,
+            })
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, ApiError::InvalidOrder { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_kalshi_fills_for_order() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/trade-api/v2/portfolio/fills")
+            .match_query(Matcher::UrlEncoded("order_id".into(), "ord-1".into()))
+            .with_body(
+                json!({
+                    "fills": [
+                        { "trade_id": "t2", "order_id": "ord-1", "ticker": "RAIN-25", "side": "no",
+                          "action": "buy", "count": 6, "yes_price": 54, "no_price": 46,
+                          "created_time": "2024-05-01T12:00:05Z" },
+                        { "trade_id": "t1", "order_id": "ord-1", "ticker": "RAIN-25", "side": "no",
+                          "action": "buy", "count": 4, "yes_price": 55, "no_price": 45,
+                          "created_time": "2024-05-01T12:00:00Z" }
+                    ],
+                    "cursor": ""
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let fills = kalshi_client(&server).list_fills("ord-1").await.unwrap();
+
+        assert_eq!(fills.len(), 2);
+        assert_eq!(fills[0].id, "t1");
+        assert_eq!(fills[0].price, Decimal::new(45, 2));
+        assert_eq!(fills[1].size, Decimal::from(6));
+        assert_eq!(fills[1].outcome, Outcome::No);
+    }
+
+    #[tokio::test]
+    async fn test_polymarket_fills_as_taker_and_maker() {
+        let mut server = mockito::Server::new_async().await;
+        let _order = server
+            .mock("GET", "/data/order/0xabc")
+            .with_body(
+                json!({
+                    "id": "0xabc", "status": "LIVE", "market": "0xcondition", "side": "BUY",
+                    "original_size": "10", "size_matched": "7", "price": "0.45", "outcome": "Yes",
+                    "associate_trades": ["trade-1", "trade-2"]
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let _taker = server
+            .mock("GET", "/data/trades")
+            .match_query(Matcher::UrlEncoded("id".into(), "trade-1".into()))
+            .with_body(
+                json!({ "data": [{
+                    "id": "trade-1", "taker_order_id": "0xabc", "market": "0xcondition",
+                    "side": "BUY", "size": "5", "price": "0.44", "outcome": "Yes",
+                    "match_time": "1714564800", "maker_orders": []
+                }] })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let _maker = server
+            .mock("GET", "/data/trades")
+            .match_query(Matcher::UrlEncoded("id".into(), "trade-2".into()))
+            .with_body(
+                json!({ "data": [{
+                    "id": "trade-2", "taker_order_id": "0xother", "market": "0xcondition",
+                    "side": "SELL", "size": "9", "price": "0.55", "outcome": "No",
+                    "match_time": "1714564860",
+                    "maker_orders": [
+                        { "order_id": "0xabc", "matched_amount": "2", "price": "0.45",
+                          "outcome": "Yes", "side": "BUY" }
+                    ]
+                }] })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let fills = polymarket_client(&server).list_fills("0xabc").await.unwrap();
+
+        assert_eq!(fills.len(), 2);
+        assert_eq!((fills[0].price, fills[0].size), (Decimal::new(44, 2), Decimal::from(5)));
+        assert_eq!((fills[1].price, fills[1].size), (Decimal::new(45, 2), Decimal::from(2)));
+        assert!(fills.iter().all(|fill| fill.outcome == Outcome::Yes));
+    }
+
+    fn market(platform: Platform, id: &str) -> Market {
+        Market {
+            id: id.to_string(),
+            question: "Will it rain tomorrow?".to_string(),
+            platform,
+            yes_price: Decimal::new(45, 2),
+            no_price: Decimal::new(56, 2),
+            volume: Decimal::ZERO,
+            liquidity: Decimal::ZERO,
+            end_time: chrono::Utc::now(),
+            yes_token_id: None,
+            no_token_id: None,
+            rules: None,
+            resolution_source: None,
+            settlement_timer_seconds: None,
+            event_id: None,
+            event_title: None,
+            outcome_name: None,
+            neg_risk: false,
+            condition_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_engine_runs_on_injected_exchanges() {
+        let config = Config::load("config/default.toml").unwrap();
+        let db = Database::new("sqlite::memory:").await.unwrap();
+
+        let engine = ArbitrageEngine::with_exchanges(
+            config,
+            db,
+            false,
+            Arc::new(
+                MockExchange::new(Platform::Polymarket)
+                    .with_markets(vec![market(Platform::Polymarket, "poly-1")]),
+            ),
+            Arc::new(
+                MockExchange::new(Platform::Kalshi)
+                    .with_markets(vec![market(Platform::Kalshi, "RAIN-25")]),
+            ),
+        );
+
+        let (polymarket, kalshi) = engine.fetch_markets().await;
+
+        assert_eq!(polymarket.unwrap().markets[0].id, "poly-1");
+        assert_eq!(kalshi.unwrap().markets[0].id, "RAIN-25");
+    }
+}
+
+#[cfg(test)]
+mod order_tracking_tests {
+    use crate::common::{opportunity, MockExchange};
+    use chrono::Utc;
+    use polymarket_kalshi_arbitrage_bot::{
+        config::Config,
+        database::Database,
+        execution::OrderTracker,
+        models::{average_fill_price, Fill, OrderStatus, Outcome, Platform, Trade, TradeSide},
+    };
+    use rust_decimal::Decimal;
+    use std::sync::Arc;
+
+    /// A Kalshi venue reporting the scripted states of every order.
+    fn scripted(states: &[(OrderStatus, i64)], fills: Vec<Fill>) -> Arc<MockExchange> {
+        Arc::new(
+            MockExchange::new(Platform::Kalshi)
+                .with_states(states)
+                .with_fills(fills),
+        )
+    }
+
+    fn fill(id: &str, price: i64, size: i64) -> Fill {
+        Fill {
+            id: id.to_string(),
+            order_id: "ord-1".to_string(),
+            platform: Platform::Kalshi,
+            market_id: "RAIN-25".to_string(),
+            outcome: Outcome::Yes,
+            side: TradeSide::Buy,
+            price: Decimal::new(price, 2),
+            size: Decimal::from(size),
+            filled_at: Some(Utc::now()),
+        }
+    }
+
+    fn trade(order_id: Option<&str>, status: OrderStatus) -> Trade {
+        let now = Utc::now();
+        Trade {
+            id: None,
+            opportunity_id: 1,
+            platform: Platform::Kalshi,
+            market_id: "RAIN-25".to_string(),
+            order_id: order_id.map(str::to_string),
+            client_order_id: None,
+            outcome: Outcome::Yes,
+            side: TradeSide::Buy,
+            price: Decimal::new(45, 2),
+            amount: Decimal::from(10),
+            filled_amount: Decimal::ZERO,
+            fill_price: None,
+            status,
+            created_at: now,
+            updated_at: now,
+            executed_at: None,
+        }
+    }
+
+    async fn tracker(exchange: Arc<MockExchange>, timeout_seconds: u64) -> OrderTracker {
+        let mut config = Config::load("config/default.toml").unwrap();
+        config.bot.order_timeout_seconds = timeout_seconds;
+
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.run_migrations().await.unwrap();
+        // The opportunity every test trade belongs to
+        db.save_opportunity(&opportunity()).await.unwrap();
+
+        OrderTracker::new(exchange.clone(), exchange, db, &config.bot)
+    }
+
+    #[test]
+    fn test_order_state_machine() {
+        use OrderStatus::*;
+
+        assert!(New.can_transition_to(PartiallyFilled));
+        assert!(New.can_transition_to(Rejected));
+        assert!(PartiallyFilled.can_transition_to(PartiallyFilled));
+        assert!(PartiallyFilled.can_transition_to(Cancelled));
+        assert!(!PartiallyFilled.can_transition_to(New));
+        assert!(!PartiallyFilled.can_transition_to(Rejected));
+        for terminal in [Filled, Cancelled, Rejected, Expired] {
+            assert!(terminal.is_terminal());
+            assert!(!terminal.can_transition_to(Filled));
+        }
+    }
+
+    #[test]
+    fn test_average_fill_price() {
+        let fills = [fill("t1", 44, 5), fill("t2", 46, 15)];
+
+        assert_eq!(average_fill_price(&fills), Some(Decimal::new(455, 3)));
+        assert_eq!(average_fill_price(&[]), None);
+    }
+
+    #[tokio::test]
+    async fn test_tracks_order_to_filled() {
+        let exchange = scripted(
+            &[
+                (OrderStatus::PartiallyFilled, 4),
+                (OrderStatus::Filled, 10),
+            ],
+            vec![fill("t1", 44, 4), fill("t2", 45, 6)],
+        );
+        let tracker = tracker(exchange, 60).await;
+        let mut updates = tracker.subscribe();
+
+        let id = tracker
+            .track(trade(Some("ord-1"), OrderStatus::New))
+            .await
+            .unwrap();
+        assert_eq!(updates.recv().await.unwrap().id, Some(id));
+        assert_eq!(tracker.live_orders().len(), 1);
+
+        tracker.poll().await;
+        let partial = updates.recv().await.unwrap();
+        assert_eq!(partial.status, OrderStatus::PartiallyFilled);
+        assert_eq!(partial.filled_amount, Decimal::from(4));
+        assert!(partial.executed_at.is_none());
+
+        tracker.poll().await;
+        let filled = updates.recv().await.unwrap();
+        assert_eq!(filled.status, OrderStatus::Filled);
+        assert_eq!(filled.fill_price, Some(Decimal::new(446, 3)));
+        assert!(filled.executed_at.is_some());
+        assert!(tracker.live_orders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_order_is_not_republished() {
+        let exchange = scripted(&[(OrderStatus::New, 0)], Vec::new());
+        let tracker = tracker(exchange, 60).await;
+
+        tracker
+            .track(trade(Some("ord-1"), OrderStatus::New))
+            .await
+            .unwrap();
+        let mut updates = tracker.subscribe();
+
+        tracker.poll().await;
+        tracker.poll().await;
+
+        assert!(updates.try_recv().is_err());
+        assert_eq!(tracker.live_orders().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_backwards_transition_is_ignored() {
+        let exchange = scripted(
+            &[(OrderStatus::New, 0), (OrderStatus::Cancelled, 0)],
+            Vec::new(),
+        );
+        let tracker = tracker(exchange, 60).await;
+
+        let mut partially_filled = trade(Some("ord-1"), OrderStatus::PartiallyFilled);
+        partially_filled.filled_amount = Decimal::from(3);
+        tracker.track(partially_filled).await.unwrap();
+
+        tracker.poll().await;
+        assert_eq!(tracker.live_orders()[0].status, OrderStatus::PartiallyFilled);
+
+        tracker.poll().await;
+        assert!(tracker.live_orders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resting_order_is_cancelled_after_timeout() {
+        let exchange = scripted(
+            &[(OrderStatus::New, 0), (OrderStatus::Cancelled, 0)],
+            Vec::new(),
+        );
+        let tracker = tracker(exchange.clone(), 1).await;
+
+        tracker
+            .track(trade(Some("ord-1"), OrderStatus::New))
+            .await
+            .unwrap();
+
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        tracker.poll().await;
+        assert_eq!(exchange.cancelled(), vec!["ord-1".to_string()]);
+
+        tracker.poll().await;
+        assert!(tracker.live_orders().is_empty());
+        assert_eq!(exchange.cancelled().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_during_poll_is_kept() {
+        let exchange = scripted(&[(OrderStatus::New, 0)], Vec::new());
+        let tracker = tracker(exchange.clone(), 1).await;
+        tracker
+            .track(trade(Some("ord-1"), OrderStatus::New))
+            .await
+            .unwrap();
+
+        // Cancel everything while the poll waits on the venue
+        let gate = exchange.gate.lock().await;
+        let poll = tokio::spawn({
+            let tracker = tracker.clone();
+            async move { tracker.poll().await }
+        });
+        exchange.asked.notified().await;
+        assert_eq!(tracker.cancel_all().await, 1);
+        drop(gate);
+        poll.await.unwrap();
+
+        // Already asked to cancel, so the timeout does not ask again
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        tracker.poll().await;
+        assert_eq!(exchange.cancelled(), vec!["ord-1".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_order_is_recorded_but_not_followed() {
+        let exchange = scripted(&[(OrderStatus::New, 0)], Vec::new());
+        let tracker = tracker(exchange, 60).await;
+
+        let id = tracker
+            .track(trade(None, OrderStatus::Rejected))
+            .await
+            .unwrap();
+
+        assert!(id > 0);
+        assert!(tracker.live_orders().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod leg_risk_tests {
+    use crate::common::{opportunity, MockExchange, Response};
+    use polymarket_kalshi_arbitrage_bot::{
+        api::ApiError,
+        config::Config,
+        database::Database,
+        execution::{ExecutionCoordinator, OrderTracker},
+        models::{LegRecovery, OrderStatus, Platform, TradeSide, TripReason},
+        risk::CircuitBreaker,
+    };
+    use rust_decimal::Decimal;
+    use std::sync::Arc;
+
+    fn venue(platform: Platform, responses: Vec<Response>) -> Arc<MockExchange> {
+        Arc::new(MockExchange::new(platform).with_responses(responses))
+    }
+
+    async fn coordinator(
+        recovery: LegRecovery,
+        kalshi: Arc<MockExchange>,
+        polymarket: Arc<MockExchange>,
+    ) -> (ExecutionCoordinator, Database, i64) {
+        let (coordinator, tracker, db, id) = untracked(recovery, kalshi, polymarket).await;
+        tokio::spawn(tracker.run());
+        (coordinator, db, id)
+    }
+
+    /// A coordinator whose tracker never polls, so every order update is
+    /// missed.
+    async fn untracked(
+        recovery: LegRecovery,
+        kalshi: Arc<MockExchange>,
+        polymarket: Arc<MockExchange>,
+    ) -> (ExecutionCoordinator, OrderTracker, Database, i64) {
+        let mut config = Config::load("config/default.toml").unwrap();
+        config.bot.order_poll_interval_ms = 10;
+        config.execution.leg_timeout_seconds = 1;
+        config.execution.leg_recovery = recovery;
+
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.run_migrations().await.unwrap();
+        let opportunity_id = db.save_opportunity(&opportunity()).await.unwrap();
+
+        let tracker = OrderTracker::new(polymarket.clone(), kalshi.clone(), db.clone(), &config.bot);
+        let coordinator = ExecutionCoordinator::new(
+            polymarket,
+            kalshi,
+            tracker.clone(),
+            db.clone(),
+            &config.execution,
+        );
+        (coordinator, tracker, db, opportunity_id)
+    }
+
+    #[tokio::test]
+    async fn test_both_legs_filled() {
+        let kalshi = venue(Platform::Kalshi, vec![Ok((OrderStatus::Filled, 10))]);
+        let polymarket = venue(Platform::Polymarket, vec![Ok((OrderStatus::Filled, 10))]);
+        let (coordinator, db, id) = coordinator(LegRecovery::Unwind, kalshi, polymarket).await;
+
+        let report = coordinator.execute(&opportunity(), id).await.unwrap();
+
+        assert!(report.leg_risk.is_none());
+        assert_eq!(report.trades.len(), 2);
+        assert!(report.trades.iter().all(|trade| trade.status == OrderStatus::Filled));
+        assert!(db.get_recent_leg_risk_events(10).await.unwrap().is_empty());
+
+        assert!(report.executed);
+        assert!(db.get_recent_opportunities(1).await.unwrap()[0].executed);
+
+        let trades = db.get_trades_for_opportunity(id).await.unwrap();
+        assert_eq!(trades.len(), 2);
+        for trade in &trades {
+            assert_eq!(trade.status, OrderStatus::Filled);
+            assert_eq!(trade.filled_amount, Decimal::from(10));
+            assert_eq!(trade.fill_price, Some(trade.price));
+            assert!(trade.order_id.is_some());
+            assert!(trade.executed_at.is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_missed_updates_are_recorded_from_the_venue() {
+        let kalshi = venue(Platform::Kalshi, vec![Ok((OrderStatus::Filled, 10))]);
+        let polymarket = venue(Platform::Polymarket, vec![Ok((OrderStatus::Filled, 10))]);
+        let (coordinator, _tracker, db, id) =
+            untracked(LegRecovery::Unwind, kalshi, polymarket).await;
+
+        let report = coordinator.execute(&opportunity(), id).await.unwrap();
+
+        assert!(report.leg_risk.is_none());
+        for trade in &report.trades {
+            assert_eq!(trade.status, OrderStatus::Filled);
+            assert_eq!(trade.fill_price, Some(trade.price));
+        }
+
+        let trades = db.get_trades_for_opportunity(id).await.unwrap();
+        assert_eq!(trades.len(), 2);
+        for trade in &trades {
+            assert_eq!(trade.status, OrderStatus::Filled);
+            assert_eq!(trade.filled_amount, Decimal::from(10));
+            assert_eq!(trade.fill_price, Some(trade.price));
+            assert!(trade.executed_at.is_some());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refused_leg_is_unwound() {
+        let kalshi = venue(
+            Platform::Kalshi,
+            vec![Ok((OrderStatus::Filled, 10)), Ok((OrderStatus::Filled, 10))],
+        );
+        let polymarket = venue(Platform::Polymarket, vec![Err(ApiError::InsufficientFunds)]);
+        let (coordinator, db, id) =
+            coordinator(LegRecovery::Unwind, kalshi.clone(), polymarket).await;
+
+        let error = coordinator.execute(&opportunity(), id).await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<ApiError>(), Some(ApiError::InsufficientFunds)));
+
+        // The YES contracts are sold back, at most 0.05 under their cost
+        let unwind = &kalshi.requests()[1];
+        assert_eq!(unwind.side, TradeSide::Sell);
+        assert_eq!(unwind.price, Decimal::new(40, 2));
+        assert_eq!(unwind.size, Decimal::from(10));
+
+        let events = db.get_recent_leg_risk_events(10).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].platform, Platform::Kalshi);
+        assert_eq!(events[0].policy, LegRecovery::Unwind);
+        assert_eq!(events[0].exposed_amount, Decimal::from(10));
+        assert_eq!(events[0].resolved_amount, Decimal::from(10));
+        assert_eq!(events[0].cost, Decimal::new(50, 2));
+
+        // The refused leg is on record, and nothing is left to call executed
+        let trades = db.get_trades_for_opportunity(id).await.unwrap();
+        assert_eq!(trades.len(), 3);
+        assert!(trades
+            .iter()
+            .any(|trade| trade.status == OrderStatus::Rejected && trade.order_id.is_none()));
+        assert!(!db.get_recent_opportunities(1).await.unwrap()[0].executed);
+    }
+
+    #[tokio::test]
+    async fn test_partial_leg_is_chased_after_timeout() {
+        let kalshi = venue(Platform::Kalshi, vec![Ok((OrderStatus::Filled, 10))]);
+        let polymarket = venue(
+            Platform::Polymarket,
+            vec![
+                Ok((OrderStatus::PartiallyFilled, 4)),
+                Ok((OrderStatus::Filled, 6)),
+            ],
+        );
+        let (coordinator, _db, id) =
+            coordinator(LegRecovery::Chase, kalshi, polymarket.clone()).await;
+
+        let report = coordinator.execute(&opportunity(), id).await.unwrap();
+
+        assert_eq!(polymarket.cancelled(), vec!["polymarket-0".to_string()]);
+
+        // Capped at 0.02 over the planned 0.50, below break-even at 0.55
+        let chase = &polymarket.requests()[1];
+        assert_eq!(chase.side, TradeSide::Buy);
+        assert_eq!(chase.price, Decimal::new(52, 2));
+        assert_eq!(chase.size, Decimal::from(6));
+        assert_eq!(chase.token_id.as_deref(), Some("456"));
+
+        let event = report.leg_risk.unwrap();
+        assert_eq!(event.exposed_amount, Decimal::from(6));
+        assert_eq!(event.resolved_amount, Decimal::from(6));
+        assert_eq!(event.cost, Decimal::new(12, 2));
+        assert_eq!(report.trades.len(), 3);
+        assert!(report.executed);
+    }
+
+    #[tokio::test]
+    async fn test_hold_leaves_position_and_records_it() {
+        let kalshi = venue(Platform::Kalshi, vec![Ok((OrderStatus::Filled, 10))]);
+        let polymarket = venue(Platform::Polymarket, vec![Err(ApiError::MarketClosed)]);
+        let (coordinator, db, id) = coordinator(LegRecovery::Hold, kalshi.clone(), polymarket).await;
+
+        assert!(coordinator.execute(&opportunity(), id).await.is_err());
+
+        assert_eq!(kalshi.requests().len(), 1);
+        let events = db.get_recent_leg_risk_events(10).await.unwrap();
+        assert_eq!(events[0].remaining_amount(), Decimal::from(10));
+        assert_eq!(events[0].cost, Decimal::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_retryable_refusal_is_looked_up_before_unwinding() {
+        let kalshi = venue(
+            Platform::Kalshi,
+            vec![Ok((OrderStatus::Filled, 10)), Ok((OrderStatus::Filled, 10))],
+        );
+        let polymarket = venue(Platform::Polymarket, vec![Err(ApiError::Server { status: 503 })]);
+        let (coordinator, db, id) =
+            coordinator(LegRecovery::Unwind, kalshi.clone(), polymarket).await;
+
+        let error = coordinator.execute(&opportunity(), id).await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<ApiError>(), Some(ApiError::Server { .. })));
+
+        // Polymarket never took the order, so the YES contracts are sold
+        assert_eq!(kalshi.requests()[1].side, TradeSide::Sell);
+        let trades = db.get_trades_for_opportunity(id).await.unwrap();
+        let refused = trades
+            .iter()
+            .find(|trade| trade.platform == Platform::Polymarket)
+            .unwrap();
+        assert_eq!(refused.status, OrderStatus::Rejected);
+        assert!(refused.client_order_id.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_lost_reply_is_confirmed_instead_of_unwound() {
+        let kalshi = venue(Platform::Kalshi, vec![Ok((OrderStatus::Filled, 10))]);
+        let polymarket = Arc::new(
+            MockExchange::new(Platform::Polymarket)
+                .with_responses(vec![Err(ApiError::Server { status: 503 })])
+                .losing_replies(),
+        );
+        let (coordinator, db, id) =
+            coordinator(LegRecovery::Unwind, kalshi.clone(), polymarket).await;
+
+        let report = coordinator.execute(&opportunity(), id).await.unwrap();
+
+        assert!(report.executed);
+        assert!(report.leg_risk.is_none());
+        assert_eq!(kalshi.requests().len(), 1);
+
+        let trades = db.get_trades_for_opportunity(id).await.unwrap();
+        let confirmed = trades
+            .iter()
+            .find(|trade| trade.platform == Platform::Polymarket)
+            .unwrap();
+        assert_eq!(confirmed.status, OrderStatus::Filled);
+        assert_eq!(confirmed.filled_amount, Decimal::from(10));
+        assert_eq!(confirmed.order_id.as_deref(), Some("polymarket-0"));
+        assert_eq!(confirmed.fill_price, Some(Decimal::new(50, 2)));
+    }
+
+    #[tokio::test]
+    async fn test_unconfirmed_leg_trips_the_breaker_without_recovering() {
+        let kalshi = venue(
+            Platform::Kalshi,
+            vec![Ok((OrderStatus::Filled, 10)), Ok((OrderStatus::Filled, 10))],
+        );
+        let polymarket = Arc::new(
+            MockExchange::new(Platform::Polymarket)
+                .with_responses(vec![Err(ApiError::Server { status: 503 })])
+                .without_lookups(),
+        );
+        let (coordinator, tracker, db, id) =
+            untracked(LegRecovery::Unwind, kalshi.clone(), polymarket).await;
+        let config = Config::load("config/default.toml").unwrap();
+        let breaker = CircuitBreaker::new(tracker.clone(), db.clone(), &config.breaker);
+        let coordinator = coordinator.with_breaker(breaker.clone());
+        tokio::spawn(tracker.run());
+
+        assert!(coordinator.execute(&opportunity(), id).await.is_err());
+
+        assert_eq!(kalshi.requests().len(), 1);
+        assert!(db.get_recent_leg_risk_events(10).await.unwrap().is_empty());
+        assert_eq!(breaker.active_trip().unwrap().reason, TripReason::LegRisk);
+
+        let trades = db.get_trades_for_opportunity(id).await.unwrap();
+        let unconfirmed = trades
+            .iter()
+            .find(|trade| trade.platform == Platform::Polymarket)
+            .unwrap();
+        assert_eq!(unconfirmed.status, OrderStatus::Pending);
+        assert!(unconfirmed.order_id.is_none());
+        assert!(unconfirmed.client_order_id.is_some());
+    }
+}
+
+#[cfg(test)]
+mod trade_persistence_tests {
+    use crate::common::opportunity;
+    use chrono::{Duration, Utc};
+    use polymarket_kalshi_arbitrage_bot::{
+        database::Database,
+        models::{OrderStatus, Outcome, Platform, Trade, TradeSide},
+    };
+    use rust_decimal::Decimal;
+
+    fn trade(opportunity_id: i64, created_at: chrono::DateTime<Utc>) -> Trade {
+        Trade {
+            id: None,
+            opportunity_id,
+            platform: Platform::Polymarket,
+            market_id: "0xcondition".to_string(),
+            order_id: Some("0xabc".to_string()),
+            client_order_id: None,
+            outcome: Outcome::No,
+            side: TradeSide::Buy,
+            price: Decimal::new(50, 2),
+            amount: Decimal::from(10),
+            filled_amount: Decimal::ZERO,
+            fill_price: None,
+            status: OrderStatus::New,
+            created_at,
+            updated_at: created_at,
+            executed_at: None,
+        }
+    }
+
+    async fn database() -> Database {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.run_migrations().await.unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn test_trade_round_trip_and_update() {
+        let db = database().await;
+        let opportunity_id = db.save_opportunity(&opportunity()).await.unwrap();
+
+        let mut saved = trade(opportunity_id, Utc::now());
+        saved.id = Some(db.save_trade(&saved).await.unwrap());
+
+        saved.status = OrderStatus::Filled;
+        saved.filled_amount = Decimal::from(10);
+        saved.fill_price = Some(Decimal::new(495, 3));
+        saved.executed_at = Some(Utc::now());
+        db.update_trade_status(&saved).await.unwrap();
+
+        let trades = db.get_trades_for_opportunity(opportunity_id).await.unwrap();
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].id, saved.id);
+        assert_eq!(trades[0].platform, Platform::Polymarket);
+        assert_eq!(trades[0].outcome, Outcome::No);
+        assert_eq!(trades[0].status, OrderStatus::Filled);
+        assert_eq!(trades[0].fill_price, Some(Decimal::new(495, 3)));
+        assert!(trades[0].executed_at.is_some());
+
+        assert!(db.get_trades_for_opportunity(opportunity_id + 1).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_trades_between() {
+        let db = database().await;
+        let opportunity_id = db.save_opportunity(&opportunity()).await.unwrap();
+        let now = Utc::now();
+
+        for age in [3, 2, 1] {
+            db.save_trade(&trade(opportunity_id, now - Duration::hours(age)))
+                .await
+                .unwrap();
+        }
+
+        let trades = db
+            .get_trades_between(now - Duration::minutes(150), now - Duration::minutes(30))
+            .await
+            .unwrap();
+
+        assert_eq!(trades.len(), 2);
+        assert!(trades[0].created_at < trades[1].created_at);
+    }
+
+    /// `opportunities` and `trades` as first released.
+    const BASELINE_SCHEMA: &str = r#"
+        CREATE TABLE opportunities (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            polymarket_market_id TEXT NOT NULL,
+            kalshi_market_id TEXT NOT NULL,
+            buy_platform TEXT NOT NULL,
+            sell_platform TEXT NOT NULL,
+            buy_price TEXT NOT NULL,
+            sell_price TEXT NOT NULL,
+            profit_percentage TEXT NOT NULL,
+            estimated_profit TEXT NOT NULL,
+            position_size TEXT NOT NULL,
+            detected_at TEXT NOT NULL,
+            executed INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE TABLE trades (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            opportunity_id INTEGER NOT NULL,
+            platform TEXT NOT NULL,
+            market_id TEXT NOT NULL,
+            side TEXT NOT NULL,
+            price TEXT NOT NULL,
+            amount TEXT NOT NULL,
+            status TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            executed_at TEXT,
+            FOREIGN KEY (opportunity_id) REFERENCES opportunities(id)
+        );
+        INSERT INTO opportunities (
+            polymarket_market_id, kalshi_market_id, buy_platform, sell_platform,
+            buy_price, sell_price, profit_percentage, estimated_profit,
+            position_size, detected_at
+        ) VALUES (
+            '0xold', 'OLD-25', 'polymarket', 'kalshi', '0.40', '0.55', '5', '0.5',
+            '10', '2024-01-01T00:00:00+00:00'
+        );
+    "#;
+
+    #[tokio::test]
+    async fn test_baseline_database_migrates() {
+        let path = std::env::temp_dir().join(format!("baseline-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let url = format!("sqlite://{}?mode=rwc", path.display());
+
+        let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+        sqlx::raw_sql(BASELINE_SCHEMA).execute(&pool).await.unwrap();
+        pool.close().await;
+
+        let db = Database::new(&url).await.unwrap();
+        db.run_migrations().await.unwrap();
+        // Nothing left to migrate the second time
+        db.run_migrations().await.unwrap();
+
+        let opportunity_id = db.save_opportunity(&opportunity()).await.unwrap();
+        db.save_trade(&trade(opportunity_id, Utc::now())).await.unwrap();
+
+        let opportunities = db.get_recent_opportunities(10).await.unwrap();
+        assert_eq!(opportunities.len(), 2);
+        assert_eq!(opportunities[0].id, Some(opportunity_id));
+        assert_eq!(opportunities[0].net_edge, Decimal::new(5, 2));
+
+        let old = &opportunities[1];
+        assert_eq!(old.yes_platform, Platform::Polymarket);
+        assert_eq!(old.no_platform, Platform::Kalshi);
+        assert_eq!(old.yes_price, Decimal::new(40, 2));
+        assert_eq!(old.yes_limit_price, Decimal::new(40, 2));
+        assert_eq!(old.no_limit_price, Decimal::new(55, 2));
+        assert_eq!(old.payout, Decimal::ONE);
+        assert_eq!(db.get_trades_for_opportunity(opportunity_id).await.unwrap().len(), 1);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_unsaved_trade_cannot_be_updated() {
+        let db = database().await;
+
+        assert!(db.update_trade_status(&trade(1, Utc::now())).await.is_err());
+    }
+}
+
+#[cfg(test)]
+mod risk_tests {
+    use crate::common::{opportunity, MockExchange};
+    use chrono::Utc;
+    use polymarket_kalshi_arbitrage_bot::{
+        api::Exchange,
+        config::Config,
+        database::Database,
+        execution::OrderTracker,
+        models::{OrderStatus, Outcome, Platform, Settlement, Trade, TradeSide},
+        risk::{kalshi_event, RiskCode, RiskDecision, RiskManager},
+    };
+    use rust_decimal::Decimal;
+    use std::sync::Arc;
+
+    /// A venue holding `balance` dollars and nothing else.
+    fn account(platform: Platform, balance: i64) -> MockExchange {
+        MockExchange::new(platform).with_balance(Some(Decimal::from(balance)))
+    }
+
+    /// A Kalshi order for `amount` YES contracts of the opportunity's
+    /// market, filled at `fill_price` cents.
+    fn filled(opportunity_id: i64, side: TradeSide, amount: i64, fill_price: i64) -> Trade {
+        let now = Utc::now();
+        Trade {
+            id: None,
+            opportunity_id,
+            platform: Platform::Kalshi,
+            market_id: "RAIN-25MAY01-T50".to_string(),
+            order_id: Some(format!("{}-{}", side.as_str(), fill_price)),
+            client_order_id: None,
+            outcome: Outcome::Yes,
+            side,
+            price: Decimal::new(fill_price, 2),
+            amount: Decimal::from(amount),
+            filled_amount: Decimal::from(amount),
+            fill_price: Some(Decimal::new(fill_price, 2)),
+            status: OrderStatus::Filled,
+            created_at: now,
+            updated_at: now,
+            executed_at: Some(now),
+        }
+    }
+
+    /// Limits loose enough that only the one a test tightens applies.
+    fn config() -> Config {
+        let mut config = Config::load("config/default.toml").unwrap();
+        config.risk.max_daily_loss = 500.0;
+        config.risk.max_open_positions = 10;
+        config.risk.position_size_percentage = 1.0;
+        config.risk.max_market_exposure = 0.0;
+        config.risk.max_event_exposure = 0.0;
+        config
+    }
+
+    async fn manager(
+        config: &Config,
+        kalshi: MockExchange,
+        polymarket: MockExchange,
+    ) -> (RiskManager, Database) {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.run_migrations().await.unwrap();
+
+        let polymarket: Arc<dyn Exchange> = Arc::new(polymarket);
+        let kalshi: Arc<dyn Exchange> = Arc::new(kalshi);
+        let tracker =
+            OrderTracker::new(polymarket.clone(), kalshi.clone(), db.clone(), &config.bot);
+        let manager = RiskManager::new(polymarket, kalshi, tracker, db.clone(), &config.risk)
+            .with_fees(&config.fees);
+        (manager, db)
+    }
+
+    fn rejection(decision: RiskDecision) -> RiskCode {
+        match decision {
+            RiskDecision::Reject { code, .. } => code,
+            approved => panic!("expected a rejection, got {:?}", approved),
+        }
+    }
+
+    #[test]
+    fn test_kalshi_event() {
+        assert_eq!(kalshi_event("KXHIGHNY-24DEC05-T45", None), "KXHIGHNY-24DEC05");
+        assert_eq!(kalshi_event("RAIN", None), "RAIN");
+        assert_eq!(kalshi_event("RAIN-25MAY01-T50", Some("RAIN-25MAY")), "RAIN-25MAY");
+    }
+
+    #[tokio::test]
+    async fn test_approves_full_size_within_limits() {
+        let config = config();
+        let (manager, _db) = manager(
+            &config,
+            account(Platform::Kalshi, 100),
+            account(Platform::Polymarket, 100),
+        )
+        .await;
+
+        assert_eq!(
+            manager.check(&opportunity()).await,
+            RiskDecision::Approve {
+                size: Decimal::from(10),
+                limited_by: Vec::new(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_downsizes_to_venue_balance() {
+        let config = config();
+        // $2.50 buys five 0.45 YES contracts on Kalshi, at 0.018 in fees
+        // each, rounded down
+        let (manager, _db) = manager(
+            &config,
+            MockExchange::new(Platform::Kalshi).with_balance(Some(Decimal::new(250, 2))),
+            account(Platform::Polymarket, 100),
+        )
+        .await;
+
+        assert_eq!(
+            manager.check(&opportunity()).await,
+            RiskDecision::Approve {
+                size: Decimal::from(5),
+                limited_by: vec![RiskCode::Balance],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_balance_covers_the_limit_price_and_fees() {
+        let config = config();
+        let (manager, _db) = manager(
+            &config,
+            MockExchange::new(Platform::Kalshi).with_balance(Some(Decimal::new(250, 2))),
+            account(Platform::Polymarket, 100),
+        )
+        .await;
+
+        // The book averages 0.45 but the order may fill up to 0.50, and
+        // Kalshi charges 0.018 a contract on top: four fit in $2.50
+        let mut opportunity = opportunity();
+        opportunity.yes_limit_price = Decimal::new(50, 2);
+
+        assert_eq!(
+            manager.check(&opportunity).await,
+            RiskDecision::Approve {
+                size: Decimal::from(4),
+                limited_by: vec![RiskCode::Balance],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_downsizes_to_position_size_percentage() {
+        let mut config = config();
+        config.risk.position_size_percentage = 0.02;
+        // 2% of $200 is $4, four pairs at 0.95
+        let (manager, _db) = manager(
+            &config,
+            account(Platform::Kalshi, 100),
+            account(Platform::Polymarket, 100),
+        )
+        .await;
+
+        assert_eq!(
+            manager.check(&opportunity()).await,
+            RiskDecision::Approve {
+                size: Decimal::from(4),
+                limited_by: vec![RiskCode::PositionSize],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_once_daily_loss_is_reached() {
+        let mut config = config();
+        config.risk.max_daily_loss = 5.0;
+        let (manager, db) = manager(
+            &config,
+            account(Platform::Kalshi, 100),
+            account(Platform::Polymarket, 100),
+        )
+        .await;
+
+        // A hundred contracts bought at 0.45 and unwound at 0.41 lose
+        // $4.00, and the two fills' fees take it past $5.00
+        let opportunity_id = db.save_opportunity(&opportunity()).await.unwrap();
+        db.save_trade(&filled(opportunity_id, TradeSide::Buy, 100, 45))
+            .await
+            .unwrap();
+        assert!(matches!(manager.check(&opportunity()).await, RiskDecision::Approve { .. }));
+
+        db.save_trade(&filled(opportunity_id, TradeSide::Sell, 100, 41))
+            .await
+            .unwrap();
+        let snapshot = manager.snapshot().await.unwrap();
+        // Kalshi's 7% taker fee: $1.74 on the buy and $1.70 on the sale
+        assert_eq!(snapshot.realized_pnl, Decimal::new(-744, 2));
+        assert_eq!(rejection(manager.check(&opportunity()).await), RiskCode::DailyLoss);
+    }
+
+    #[tokio::test]
+    async fn test_settlements_count_towards_daily_pnl() {
+        let mut config = config();
+        config.risk.max_daily_loss = 5.0;
+        config.fees.kalshi.taker_fee_rate = 0.0;
+        let settlement = |revenue: i64, cost: i64, hours_ago: i64| Settlement {
+            platform: Platform::Kalshi,
+            market_id: "SNOW-25-T1".to_string(),
+            revenue: Decimal::from(revenue),
+            cost: Decimal::from(cost),
+            settled_at: Utc::now() - chrono::Duration::hours(hours_ago),
+        };
+        // Yesterday's loss is behind the limit; today's counts
+        let kalshi = account(Platform::Kalshi, 100)
+            .with_settlements(vec![settlement(0, 50, 30), settlement(0, 6, 0)]);
+        let (manager, _db) =
+            manager(&config, kalshi, account(Platform::Polymarket, 100)).await;
+
+        assert_eq!(manager.snapshot().await.unwrap().realized_pnl, Decimal::from(-6));
+        assert_eq!(rejection(manager.check(&opportunity()).await), RiskCode::DailyLoss);
+    }
+
+    #[tokio::test]
+    async fn test_unrealized_loss_counts_towards_daily_loss() {
+        let mut config = config();
+        config.risk.max_daily_loss = 5.0;
+        // Twenty contracts bought for $10.00 are now marked at $4.00
+        let mut kalshi =
+            account(Platform::Kalshi, 100).holding("SNOW-25-T1", 20, Decimal::new(50, 2));
+        kalshi.positions[0].current_value = Decimal::from(4);
+        let (manager, _db) =
+            manager(&config, kalshi, account(Platform::Polymarket, 100)).await;
+
+        assert_eq!(rejection(manager.check(&opportunity()).await), RiskCode::DailyLoss);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_beyond_open_position_limit() {
+        let mut config = config();
+        config.risk.max_open_positions = 2;
+        let (manager, _db) = manager(
+            &config,
+            account(Platform::Kalshi, 100).holding("SNOW-25-T1", 1, Decimal::new(50, 2)),
+            account(Platform::Polymarket, 100),
+        )
+        .await;
+
+        assert_eq!(rejection(manager.check(&opportunity()).await), RiskCode::OpenPositions);
+    }
+
+    #[tokio::test]
+    async fn test_adding_to_held_markets_opens_no_position() {
+        let mut config = config();
+        config.risk.max_open_positions = 2;
+        let (manager, _db) = manager(
+            &config,
+            account(Platform::Kalshi, 100).holding("RAIN-25MAY01-T50", 1, Decimal::new(45, 2)),
+            account(Platform::Polymarket, 100).holding("0xcondition", 1, Decimal::new(50, 2)),
+        )
+        .await;
+
+        assert!(matches!(manager.check(&opportunity()).await, RiskDecision::Approve { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_market_exposure_limits_each_leg() {
+        let mut config = config();
+        config.risk.max_market_exposure = 5.0;
+        // $2.00 already in the Polymarket market leaves room for six at 0.50
+        let (manager, _db) = manager(
+            &config,
+            account(Platform::Kalshi, 100),
+            account(Platform::Polymarket, 100).holding("0xcondition", 4, Decimal::new(50, 2)),
+        )
+        .await;
+
+        assert_eq!(
+            manager.check(&opportunity()).await,
+            RiskDecision::Approve {
+                size: Decimal::from(6),
+                limited_by: vec![RiskCode::MarketExposure],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_held_polymarket_position_blocks_its_market() {
+        let mut config = config();
+        config.risk.max_market_exposure = 5.0;
+        let (manager, _db) = manager(
+            &config,
+            account(Platform::Kalshi, 100),
+            account(Platform::Polymarket, 100).holding("0xcondition", 10, Decimal::new(50, 2)),
+        )
+        .await;
+
+        // Listed under its Gamma ID, the market is held under its condition
+        let mut opportunity = opportunity();
+        opportunity.polymarket_market_id = "512".to_string();
+        opportunity.polymarket_condition_id = Some("0xcondition".to_string());
+
+        assert_eq!(rejection(manager.check(&opportunity).await), RiskCode::MarketExposure);
+    }
+
+    #[tokio::test]
+    async fn test_event_exposure_includes_paired_polymarket_markets() {
+        let mut config = config();
+        config.risk.max_event_exposure = 10.0;
+        let (manager, db) = manager(
+            &config,
+            account(Platform::Kalshi, 100).holding("RAIN-25MAY01-T60", 5, Decimal::new(40, 2)),
+            account(Platform::Polymarket, 100).holding("0xother", 5, Decimal::new(60, 2)),
+        )
+        .await;
+
+        // 0xother was paired with a market in the same event as this one
+        let mut sibling = opportunity();
+        sibling.polymarket_market_id = "513".to_string();
+        sibling.polymarket_condition_id = Some("0xother".to_string());
+        sibling.kalshi_market_id = "RAIN-25MAY01-T60".to_string();
+        db.save_opportunity(&sibling).await.unwrap();
+
+        // $5.00 of the $10.00 event limit is used: five pairs at 0.95
+        assert_eq!(
+            manager.check(&opportunity()).await,
+            RiskDecision::Approve {
+                size: Decimal::from(5),
+                limited_by: vec![RiskCode::EventExposure],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_event_exposure_follows_listed_event_tickers() {
+        let mut config = config();
+        config.risk.max_event_exposure = 10.0;
+        // The tickers' prefixes differ, but Kalshi lists both markets under
+        // one event
+        let (manager, db) = manager(
+            &config,
+            account(Platform::Kalshi, 100).holding("RAIN-25MAY02-T60", 5, Decimal::new(40, 2)),
+            account(Platform::Polymarket, 100),
+        )
+        .await;
+
+        let mut sibling = opportunity();
+        sibling.polymarket_market_id = "0xother".to_string();
+        sibling.kalshi_market_id = "RAIN-25MAY02-T60".to_string();
+        sibling.kalshi_event_id = Some("RAIN-25MAY".to_string());
+        db.save_opportunity(&sibling).await.unwrap();
+
+        let mut opportunity = opportunity();
+        opportunity.kalshi_event_id = Some("RAIN-25MAY".to_string());
+
+        // $2.00 of the $10.00 event limit is used: eight pairs at 0.95
+        assert_eq!(
+            manager.check(&opportunity).await,
+            RiskDecision::Approve {
+                size: Decimal::from(8),
+                limited_by: vec![RiskCode::EventExposure],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_when_account_is_unavailable() {
+        let config = config();
+        let polymarket = MockExchange::new(Platform::Polymarket).with_balance(None);
+        let (manager, _db) =
+            manager(&config, account(Platform::Kalshi, 100), polymarket).await;
+
+        assert_eq!(rejection(manager.check(&opportunity()).await), RiskCode::Unavailable);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_when_no_whole_contract_fits() {
+        let config = config();
+        let (manager, _db) = manager(
+            &config,
+            account(Platform::Kalshi, 100),
+            account(Platform::Polymarket, 0),
+        )
+        .await;
+
+        assert_eq!(rejection(manager.check(&opportunity()).await), RiskCode::Balance);
+    }
+}
+
+#[cfg(test)]
+mod breaker_tests {
+    use crate::common::{opportunity, MockExchange};
+    use chrono::Utc;
+    use polymarket_kalshi_arbitrage_bot::{
+        config::Config,
+        database::Database,
+        execution::OrderTracker,
+        models::{
+            LegRecovery, LegRiskEvent, OrderStatus, Outcome, Platform, Trade, TradeSide,
+            TripReason,
+        },
+        risk::CircuitBreaker,
+    };
+    use rust_decimal::Decimal;
+    use std::{path::PathBuf, sync::Arc};
+
+    fn trade(opportunity_id: i64, order_id: Option<&str>, status: OrderStatus) -> Trade {
+        let now = Utc::now();
+        Trade {
+            id: None,
+            opportunity_id,
+            platform: Platform::Kalshi,
+            market_id: "RAIN-25".to_string(),
+            order_id: order_id.map(str::to_string),
+            client_order_id: None,
+            outcome: Outcome::Yes,
+            side: TradeSide::Buy,
+            price: Decimal::new(45, 2),
+            amount: Decimal::from(10),
+            filled_amount: Decimal::ZERO,
+            fill_price: None,
+            status,
+            created_at: now,
+            updated_at: now,
+            executed_at: None,
+        }
+    }
+
+    /// A path under the temp directory that no other test uses.
+    fn flag_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("breaker-{}-{}", std::process::id(), name))
+    }
+
+    struct Harness {
+        breaker: CircuitBreaker,
+        tracker: OrderTracker,
+        kalshi: Arc<MockExchange>,
+        db: Database,
+        opportunity_id: i64,
+    }
+
+    async fn harness(configure: impl FnOnce(&mut Config)) -> Harness {
+        let mut config = Config::load("config/default.toml").unwrap();
+        config.breaker.halt_file = String::new();
+        config.breaker.resume_file = String::new();
+        configure(&mut config);
+
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.run_migrations().await.unwrap();
+        let opportunity_id = db.save_opportunity(&opportunity()).await.unwrap();
+
+        let polymarket = Arc::new(MockExchange::new(Platform::Polymarket));
+        let kalshi = Arc::new(MockExchange::new(Platform::Kalshi));
+        let tracker = OrderTracker::new(polymarket, kalshi.clone(), db.clone(), &config.bot);
+        let breaker = CircuitBreaker::new(tracker.clone(), db.clone(), &config.breaker);
+
+        Harness {
+            breaker,
+            tracker,
+            kalshi,
+            db,
+            opportunity_id,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_consecutive_rejections_trip_and_cancel_resting_orders() {
+        let h = harness(|config| config.breaker.max_consecutive_rejections = 2).await;
+        h.tracker
+            .track(trade(h.opportunity_id, Some("resting"), OrderStatus::New))
+            .await
+            .unwrap();
+
+        let rejected = trade(h.opportunity_id, None, OrderStatus::Rejected);
+        h.breaker.record_order(&rejected).await.unwrap();
+        assert!(!h.breaker.is_tripped());
+        h.breaker.record_order(&rejected).await.unwrap();
+
+        let trip = h.breaker.active_trip().unwrap();
+        assert_eq!(trip.reason, TripReason::ConsecutiveRejections);
+        assert_eq!(h.kalshi.cancelled(), vec!["resting".to_string()]);
+
+        let persisted = h.db.get_active_breaker_trip().await.unwrap().unwrap();
+        assert_eq!(persisted.reason, TripReason::ConsecutiveRejections);
+        assert_eq!(persisted.detail, "2 orders rejected in a row");
+    }
+
+    #[tokio::test]
+    async fn test_fill_resets_rejection_count() {
+        let h = harness(|config| config.breaker.max_consecutive_rejections = 2).await;
+        let rejected = trade(h.opportunity_id, None, OrderStatus::Rejected);
+
+        h.breaker.record_order(&rejected).await.unwrap();
+        h.breaker
+            .record_order(&trade(h.opportunity_id, Some("1"), OrderStatus::Filled))
+            .await
+            .unwrap();
+        h.breaker.record_order(&rejected).await.unwrap();
+
+        assert!(!h.breaker.is_tripped());
+    }
+
+    #[tokio::test]
+    async fn test_venue_error_rate_trips_once_window_is_full() {
+        let h = harness(|config| {
+            config.breaker.error_window = 4;
+            config.breaker.max_error_rate = 0.5;
+        })
+        .await;
+
+        for ok in [false, true, false] {
+            h.breaker.record_venue_call(Platform::Kalshi, ok).await.unwrap();
+        }
+        assert!(!h.breaker.is_tripped());
+
+        // Failures on the other venue are counted separately
+        h.breaker.record_venue_call(Platform::Polymarket, false).await.unwrap();
+        assert!(!h.breaker.is_tripped());
+
+        h.breaker.record_venue_call(Platform::Kalshi, true).await.unwrap();
+        assert_eq!(h.breaker.active_trip().unwrap().reason, TripReason::VenueErrors);
+    }
+
+    #[tokio::test]
+    async fn test_silent_venue_trips_stale_data() {
+        let h = harness(|config| config.breaker.max_data_staleness_seconds = 1).await;
+
+        h.breaker.record_venue_call(Platform::Polymarket, true).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        h.breaker.record_venue_call(Platform::Polymarket, false).await.unwrap();
+
+        assert_eq!(h.breaker.active_trip().unwrap().reason, TripReason::StaleData);
+    }
+
+    #[tokio::test]
+    async fn test_leg_risk_events_trip_on_check() {
+        let h = harness(|config| config.breaker.max_leg_risk_events = 1).await;
+        h.breaker.check().await.unwrap();
+        assert!(!h.breaker.is_tripped());
+
+        h.db.save_leg_risk_event(&LegRiskEvent {
+            id: None,
+            opportunity_id: h.opportunity_id,
+            platform: Platform::Kalshi,
+            market_id: "RAIN-25".to_string(),
+            outcome: Outcome::Yes,
+            exposed_amount: Decimal::from(10),
+            policy: LegRecovery::Hold,
+            resolved_amount: Decimal::ZERO,
+            cost: Decimal::ZERO,
+            detected_at: Utc::now(),
+            resolved_at: Utc::now(),
+        })
+        .await
+        .unwrap();
+        h.breaker.check().await.unwrap();
+        assert_eq!(h.breaker.active_trip().unwrap().reason, TripReason::LegRisk);
+
+        // Events before the resume no longer count
+        h.breaker.resume().await.unwrap();
+        h.breaker.check().await.unwrap();
+        assert!(!h.breaker.is_tripped());
+    }
+
+    #[tokio::test]
+    async fn test_resume_survives_restart() {
+        let h = harness(|config| config.breaker.max_leg_risk_events = 1).await;
+        h.db.save_leg_risk_event(&LegRiskEvent {
+            id: None,
+            opportunity_id: h.opportunity_id,
+            platform: Platform::Kalshi,
+            market_id: "RAIN-25".to_string(),
+            outcome: Outcome::Yes,
+            exposed_amount: Decimal::from(10),
+            policy: LegRecovery::Hold,
+            resolved_amount: Decimal::ZERO,
+            cost: Decimal::ZERO,
+            detected_at: Utc::now(),
+            resolved_at: Utc::now(),
+        })
+        .await
+        .unwrap();
+        h.breaker.check().await.unwrap();
+        assert!(h.breaker.is_tripped());
+        h.breaker.resume().await.unwrap();
+
+        // A new breaker on the same database, as after a restart, still
+        // counts from the resume
+        let mut config = Config::load("config/default.toml").unwrap();
+        config.breaker.halt_file = String::new();
+        config.breaker.resume_file = String::new();
+        config.breaker.max_leg_risk_events = 1;
+        let restarted = CircuitBreaker::new(h.tracker.clone(), h.db.clone(), &config.breaker);
+        restarted.check().await.unwrap();
+        assert!(!restarted.is_tripped());
+    }
+
+    #[tokio::test]
+    async fn test_trip_survives_restart_until_resumed() {
+        let h = harness(|_| {}).await;
+        h.breaker.trip(TripReason::DailyLoss, "lost 500").await.unwrap();
+
+        // A new breaker on the same database, as after a restart
+        let config = Config::load("config/default.toml").unwrap();
+        let mut breaker_config = config.breaker.clone();
+        breaker_config.halt_file = String::new();
+        breaker_config.resume_file = String::new();
+        let restarted = CircuitBreaker::new(h.tracker.clone(), h.db.clone(), &breaker_config);
+        restarted.check().await.unwrap();
+        let trip = restarted.active_trip().unwrap();
+        assert_eq!(trip.reason, TripReason::DailyLoss);
+        assert_eq!(trip.detail, "lost 500");
+
+        // `--mode resume` clears the database, and running breakers follow
+        h.db.resume_breaker_trips(Utc::now()).await.unwrap();
+        restarted.check().await.unwrap();
+        h.breaker.check().await.unwrap();
+        assert!(!restarted.is_tripped());
+        assert!(!h.breaker.is_tripped());
+    }
+
+    #[tokio::test]
+    async fn test_halt_and_resume_files() {
+        let halt = flag_path("halt");
+        let resume = flag_path("resume");
+        let h = harness(|config| {
+            config.breaker.halt_file = halt.to_string_lossy().into_owned();
+            config.breaker.resume_file = resume.to_string_lossy().into_owned();
+        })
+        .await;
+
+        std::fs::write(&halt, "").unwrap();
+        h.breaker.check().await.unwrap();
+        assert_eq!(h.breaker.active_trip().unwrap().reason, TripReason::Manual);
+
+        // Removing the halt file is not enough on its own
+        std::fs::remove_file(&halt).unwrap();
+        h.breaker.check().await.unwrap();
+        assert!(h.breaker.is_tripped());
+
+        std::fs::write(&resume, "").unwrap();
+        h.breaker.check().await.unwrap();
+        assert!(!h.breaker.is_tripped());
+        assert!(!resume.exists());
+        assert!(h.db.get_active_breaker_trip().await.unwrap().is_none());
+    }
+}
+
+#[cfg(test)]
+mod paper_tests {
+    use crate::common::{opportunity, MockExchange};
+    use chrono::Utc;
+    use polymarket_kalshi_arbitrage_bot::{
+        api::{ApiError, Exchange},
+        config::Config,
+        database::Database,
+        execution::{ExecutionCoordinator, OrderTracker},
+        fees::{FeeModel, KalshiFeeModel, Liquidity, PolymarketFeeModel},
+        models::{
+            ArbitrageOpportunity, Market, OrderRequest, OrderStatus, Outcome, Platform, PriceLevel,
+            TradeSide,
+        },
+        paper::PaperExchange,
+    };
+    use rust_decimal::Decimal;
+    use std::sync::Arc;
+
+    /// Market data only: one market whose book the test sets, empty until
+    /// then.
+    fn books(platform: Platform) -> Arc<MockExchange> {
+        let id = match platform {
+            Platform::Polymarket => "0xcondition",
+            Platform::Kalshi => "RAIN-25",
+        };
+        let market = Market {
+            id: id.to_string(),
+            question: "Will it rain?".to_string(),
+            platform: platform.clone(),
+            yes_price: Decimal::new(45, 2),
+            no_price: Decimal::new(55, 2),
+            volume: Decimal::ZERO,
+            liquidity: Decimal::ZERO,
+            end_time: Utc::now(),
+            yes_token_id: Some("123".to_string()),
+            no_token_id: Some("456".to_string()),
+            rules: None,
+            resolution_source: None,
+            settlement_timer_seconds: None,
+            event_id: None,
+            event_title: None,
+            outcome_name: None,
+            neg_risk: false,
+            condition_id: None,
+        };
+        Arc::new(
+            MockExchange::new(platform)
+                .with_markets(vec![market])
+                .with_book(Vec::new(), Vec::new())
+                .market_data_only(),
+        )
+    }
+
+    fn level(price: i64, size: i64) -> PriceLevel {
+        PriceLevel {
+            price: Decimal::new(price, 2),
+            size: Decimal::from(size),
+        }
+    }
+
+    fn order(side: TradeSide, price: i64, size: i64) -> OrderRequest {
+        OrderRequest {
+            market_id: "RAIN-25".to_string(),
+            token_id: None,
+            outcome: Outcome::Yes,
+            side,
+            price: Decimal::new(price, 2),
+            size: Decimal::from(size),
+            neg_risk: false,
+            client_order_id: None,
+        }
+    }
+
+    fn config() -> Config {
+        let mut config = Config::load("config/default.toml").unwrap();
+        config.paper.latency_ms = 0;
+        config.paper.starting_balance = 100.0;
+        config
+    }
+
+    async fn database() -> Database {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.run_migrations().await.unwrap();
+        db
+    }
+
+    /// A paper Kalshi account whose markets have already been listed.
+    async fn kalshi(config: &Config, db: &Database) -> (PaperExchange, Arc<MockExchange>) {
+        let books = books(Platform::Kalshi);
+        let paper = PaperExchange::new(
+            books.clone(),
+            Box::new(KalshiFeeModel::new(&config.fees.kalshi)),
+            db.clone(),
+            &config.paper,
+        );
+        paper.get_markets().await.unwrap();
+        (paper, books)
+    }
+
+    #[tokio::test]
+    async fn test_buy_sweeps_depth_up_to_limit_and_pays_fees() {
+        let config = config();
+        let db = database().await;
+        let (paper, books) = kalshi(&config, &db).await;
+        books.set_book(vec![level(40, 5), level(42, 5), level(50, 10)], Vec::new());
+
+        let id = paper.place_order(&order(TradeSide::Buy, 45, 8)).await.unwrap();
+
+        let placed = paper.get_order(&id).await.unwrap();
+        assert_eq!(placed.status, OrderStatus::Filled);
+        assert_eq!(placed.filled_size, Decimal::from(8));
+
+        // Five at 0.40 and three at 0.42
+        let notional = Decimal::new(326, 2);
+        let price = notional / Decimal::from(8);
+        let fills = paper.list_fills(&id).await.unwrap();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].price, price);
+
+        let fee = KalshiFeeModel::new(&config.fees.kalshi).order_fee(
+            price,
+            Decimal::from(8),
+            Liquidity::Taker,
+        );
+        assert!(fee > Decimal::ZERO);
+        assert_eq!(
+            paper.get_balance().await.unwrap(),
+            Decimal::from(100) - notional - fee
+        );
+
+        let positions = paper.get_positions().await.unwrap();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].amount, Decimal::from(8));
+        assert_eq!(positions[0].entry_price, price);
+    }
+
+    #[tokio::test]
+    async fn test_resting_order_fills_when_book_reaches_it() {
+        let config = config();
+        let db = database().await;
+        let (paper, books) = kalshi(&config, &db).await;
+        books.set_book(vec![level(40, 3), level(50, 10)], Vec::new());
+
+        let id = paper.place_order(&order(TradeSide::Buy, 45, 5)).await.unwrap();
+        let resting = paper.get_order(&id).await.unwrap();
+        assert_eq!(resting.status, OrderStatus::PartiallyFilled);
+        assert_eq!(resting.filled_size, Decimal::from(3));
+
+        books.set_book(vec![level(44, 10)], Vec::new());
+        let filled = paper.get_order(&id).await.unwrap();
+        assert_eq!(filled.status, OrderStatus::Filled);
+        assert_eq!(paper.list_fills(&id).await.unwrap().len(), 2);
+
+        let position = &paper.get_positions().await.unwrap()[0];
+        assert_eq!(position.amount, Decimal::from(5));
+        assert_eq!(position.entry_price, Decimal::new(416, 3));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_order_stops_filling() {
+        let config = config();
+        let db = database().await;
+        let (paper, books) = kalshi(&config, &db).await;
+
+        let id = paper.place_order(&order(TradeSide::Buy, 45, 5)).await.unwrap();
+        paper.cancel_order(&id).await.unwrap();
+
+        books.set_book(vec![level(40, 10)], Vec::new());
+        let cancelled = paper.get_order(&id).await.unwrap();
+        assert_eq!(cancelled.status, OrderStatus::Cancelled);
+        assert_eq!(cancelled.filled_size, Decimal::ZERO);
+        assert!(matches!(
+            paper.cancel_order(&id).await,
+            Err(ApiError::InvalidOrder { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_sell_fills_against_bids_from_opposite_asks() {
+        let config = config();
+        let db = database().await;
+        let (paper, books) = kalshi(&config, &db).await;
+        books.set_book(vec![level(40, 10)], Vec::new());
+        paper.place_order(&order(TradeSide::Buy, 40, 10)).await.unwrap();
+        let after_buy = paper.get_balance().await.unwrap();
+
+        // NO offered at 0.55 is YES bid at 0.45
+        books.set_book(Vec::new(), vec![level(55, 4), level(70, 10)]);
+        let id = paper.place_order(&order(TradeSide::Sell, 44, 6)).await.unwrap();
+
+        let sold = paper.get_order(&id).await.unwrap();
+        assert_eq!(sold.status, OrderStatus::PartiallyFilled);
+        assert_eq!(sold.filled_size, Decimal::from(4));
+        assert_eq!(paper.list_fills(&id).await.unwrap()[0].price, Decimal::new(45, 2));
+        assert!(paper.get_balance().await.unwrap() > after_buy);
+        assert_eq!(paper.get_positions().await.unwrap()[0].amount, Decimal::from(6));
+    }
+
+    #[tokio::test]
+    async fn test_refuses_what_the_account_cannot_cover() {
+        let config = config();
+        let db = database().await;
+        let (paper, _books) = kalshi(&config, &db).await;
+
+        assert!(matches!(
+            paper.place_order(&order(TradeSide::Buy, 45, 1000)).await,
+            Err(ApiError::InsufficientFunds)
+        ));
+        assert!(matches!(
+            paper.place_order(&order(TradeSide::Sell, 45, 1)).await,
+            Err(ApiError::InvalidOrder { .. })
+        ));
+        assert_eq!(paper.get_balance().await.unwrap(), Decimal::from(100));
+        assert_eq!(db.get_paper_accounts().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_coordinator_executes_against_paper_venues() {
+        let mut config = config();
+        config.bot.order_poll_interval_ms = 10;
+        config.execution.leg_timeout_seconds = 1;
+        let db = database().await;
+
+        let (kalshi, kalshi_books) = kalshi(&config, &db).await;
+        kalshi_books.set_book(vec![level(45, 20)], Vec::new());
+        let polymarket_books = books(Platform::Polymarket);
+        polymarket_books.set_book(Vec::new(), vec![level(50, 20)]);
+        let polymarket = PaperExchange::new(
+            polymarket_books,
+            Box::new(PolymarketFeeModel::new(&config.fees.polymarket)),
+            db.clone(),
+            &config.paper,
+        );
+        polymarket.get_markets().await.unwrap();
+
+        // The paper Kalshi market, not the fixture's
+        let opportunity = ArbitrageOpportunity {
+            kalshi_market_id: "RAIN-25".to_string(),
+            ..opportunity()
+        };
+        let opportunity_id = db.save_opportunity(&opportunity).await.unwrap();
+
+        let polymarket: Arc<dyn Exchange> = Arc::new(polymarket);
+        let kalshi: Arc<dyn Exchange> = Arc::new(kalshi);
+        let tracker =
+            OrderTracker::new(polymarket.clone(), kalshi.clone(), db.clone(), &config.bot);
+        tokio::spawn(tracker.clone().run());
+        let coordinator =
+            ExecutionCoordinator::new(polymarket, kalshi, tracker, db.clone(), &config.execution);
+
+        let report = coordinator.execute(&opportunity, opportunity_id).await.unwrap();
+        assert!(report.executed);
+        assert!(report.leg_risk.is_none());
+
+        let accounts = db.get_paper_accounts().await.unwrap();
+        assert_eq!(accounts.len(), 2);
+        let polymarket_account = accounts
+            .iter()
+            .find(|account| account.platform == Platform::Polymarket)
+            .unwrap();
+        assert_eq!(polymarket_account.balance, Decimal::from(95));
+        let no_held = db.get_paper_positions(&Platform::Polymarket).await.unwrap();
+        assert_eq!(no_held[0].outcome, Outcome::No);
+        assert_eq!(no_held[0].amount, Decimal::from(10));
+    }
+}
+
+#[cfg(test)]
+mod backtest_tests {
+    use crate::common::MockExchange;
+    use chrono::{DateTime, Duration, Utc};
+    use polymarket_kalshi_arbitrage_bot::{
+        api::Exchange,
+        backtest::{Backtester, RecordingExchange},
+        config::Config,
+        database::Database,
+        models::{Market, MarketMatch, MatchStatus, Platform, PriceLevel},
+    };
+    use rust_decimal::Decimal;
+    use std::sync::Arc;
+
+    /// Market data only: one market whose book the test sets, empty until
+    /// then.
+    fn venue(platform: Platform) -> Arc<MockExchange> {
+        let (id, yes_price, no_price) = match platform {
+            Platform::Polymarket => ("0xcondition", 40, 60),
+            Platform::Kalshi => ("RAIN-25", 55, 45),
+        };
+        let market = Market {
+            id: id.to_string(),
+            question: "Will it rain in London tomorrow?".to_string(),
+            platform: platform.clone(),
+            yes_price: Decimal::new(yes_price, 2),
+            no_price: Decimal::new(no_price, 2),
+            volume: Decimal::ZERO,
+            liquidity: Decimal::ZERO,
+            end_time: Utc::now() + Duration::days(1),
+            yes_token_id: Some("123".to_string()),
+            no_token_id: Some("456".to_string()),
+            rules: None,
+            resolution_source: None,
+            settlement_timer_seconds: None,
+            event_id: None,
+            event_title: None,
+            outcome_name: None,
+            neg_risk: false,
+            condition_id: None,
+        };
+        Arc::new(
+            MockExchange::new(platform)
+                .with_markets(vec![market])
+                .with_book(Vec::new(), Vec::new())
+                .market_data_only(),
+        )
+    }
+
+    fn level(price: i64, size: i64) -> PriceLevel {
+        PriceLevel {
+            price: Decimal::new(price, 2),
+            size: Decimal::from(size),
+        }
+    }
+
+    async fn database() -> Database {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.run_migrations().await.unwrap();
+        db
+    }
+
+    /// Records two polling cycles: the first with no edge in the books, the
+    /// second with YES at 0.40 on Polymarket and NO at 0.45 on Kalshi.
+    /// Returns when the second cycle began.
+    async fn record(db: &Database) -> DateTime<Utc> {
+        let polymarket_venue = venue(Platform::Polymarket);
+        let kalshi_venue = venue(Platform::Kalshi);
+        let polymarket = RecordingExchange::new(polymarket_venue.clone(), db.clone());
+        let kalshi = RecordingExchange::new(kalshi_venue.clone(), db.clone());
+
+        let books = [
+            (vec![level(60, 50)], vec![level(60, 50)]),
+            (vec![level(40, 50)], vec![level(45, 50)]),
+        ];
+        let mut second = Utc::now();
+        for (i, (yes_asks, no_asks)) in books.into_iter().enumerate() {
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+            if i == 1 {
+                second = Utc::now();
+            }
+            polymarket_venue.set_book(yes_asks, Vec::new());
+            kalshi_venue.set_book(Vec::new(), no_asks);
+
+            let poly_market = polymarket.get_markets().await.unwrap().remove(0);
+            let kalshi_market = kalshi.get_markets().await.unwrap().remove(0);
+            polymarket.get_order_book(&poly_market).await.unwrap();
+            kalshi.get_order_book(&kalshi_market).await.unwrap();
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+        second
+    }
+
+    fn config() -> Config {
+        let mut config = Config::load("config/default.toml").unwrap();
+        config.bot.min_profit_percentage = 1.0;
+        config
+    }
+
+    /// Approves the recorded pair, as an operator would have in review.
+    async fn approve(db: &Database) {
+        let now = Utc::now();
+        let recorded = db
+            .record_match_candidate(&MarketMatch {
+                id: None,
+                polymarket_market_id: "0xcondition".to_string(),
+                kalshi_market_id: "RAIN-25".to_string(),
+                polymarket_question: "Will it rain in London tomorrow?".to_string(),
+                kalshi_question: "Will it rain in London tomorrow?".to_string(),
+                polymarket_end_time: now,
+                kalshi_end_time: now,
+                similarity: 1.0,
+                explanation: String::new(),
+                polymarket_rules: None,
+                kalshi_rules: None,
+                resolution_risk: None,
+                status: MatchStatus::Pending,
+                inverted: false,
+                first_seen_at: now,
+                last_seen_at: now,
+                decided_at: None,
+            })
+            .await
+            .unwrap();
+        db.decide_market_match(recorded.id.unwrap(), MatchStatus::Approved, false, now)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_recording_exchange_saves_what_it_fetches() {
+        let db = database().await;
+        record(&db).await;
+
+        let from = Utc::now() - Duration::hours(1);
+        let listings = db.get_market_recordings(from, Utc::now()).await.unwrap();
+        assert_eq!(listings.len(), 4);
+        assert_eq!(listings[0].platform, Platform::Polymarket);
+        assert_eq!(listings[0].markets[0].id, "0xcondition");
+
+        let books = db.get_book_recordings(from, Utc::now()).await.unwrap();
+        assert_eq!(books.len(), 4);
+        assert_eq!(books[2].yes_asks, vec![level(40, 50)]);
+        assert_eq!(books[3].no_asks, vec![level(45, 50)]);
+    }
+
+    #[tokio::test]
+    async fn test_recording_exchange_keeps_which_events_are_exclusive() {
+        let db = database().await;
+        let mut market = venue(Platform::Kalshi).get_markets().await.unwrap().remove(0);
+        market.event_id = Some("RAIN".to_string());
+        let venue = Arc::new(
+            MockExchange::new(Platform::Kalshi)
+                .with_markets(vec![market])
+                .with_exclusive_events(),
+        );
+        let kalshi = RecordingExchange::new(venue, db.clone());
+
+        let events = kalshi.get_events().await.unwrap();
+        assert!(events[0].mutually_exclusive);
+        kalshi.get_markets().await.unwrap();
+
+        let from = Utc::now() - Duration::hours(1);
+        let listings = db.get_market_recordings(from, Utc::now()).await.unwrap();
+        assert_eq!(listings.len(), 2);
+        assert_eq!(listings[0].exclusive_events, vec!["RAIN".to_string()]);
+        assert_eq!(listings[0].markets[0].event_id.as_deref(), Some("RAIN"));
+        assert!(listings[1].exclusive_events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_backtest_replays_opportunities_into_fills_and_pnl() {
+        let db = database().await;
+        record(&db).await;
+        approve(&db).await;
+
+        let backtester = Backtester::load(&db, Utc::now() - Duration::hours(1), Utc::now())
+            .await
+            .unwrap();
+        assert_eq!(backtester.cycles(), 2);
+
+        let report = backtester.run("default", &config()).await.unwrap();
+        assert_eq!(report.label, "default");
+        assert_eq!(report.cycles, 2);
+        assert_eq!(report.opportunities, 1);
+        assert_eq!(report.executed, 1);
+        assert_eq!(report.orders, 2);
+        assert_eq!(report.fills, 2);
+        assert!(report.fees > Decimal::ZERO);
+        assert!(report.cash_pnl < Decimal::ZERO);
+        assert!(report.pnl > Decimal::ZERO);
+        assert!(report.pnl < -report.cash_pnl);
+
+        // Each run starts from an empty paper account
+        let again = backtester.run("again", &config()).await.unwrap();
+        assert_eq!(again.pnl, report.pnl);
+        assert!(db.get_paper_accounts().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_backtest_does_not_trade_unreviewed_pairs() {
+        let db = database().await;
+        record(&db).await;
+
+        let backtester = Backtester::load(&db, Utc::now() - Duration::hours(1), Utc::now())
+            .await
+            .unwrap();
+        let report = backtester.run("unreviewed", &config()).await.unwrap();
+        assert_eq!(report.opportunities, 1);
+        assert_eq!(report.executed, 0);
+        assert_eq!(report.orders, 0);
+    }
+
+    #[tokio::test]
+    async fn test_backtest_compares_configurations_over_a_window() {
+        let db = database().await;
+        let second = record(&db).await;
+
+        let backtester = Backtester::load(&db, Utc::now() - Duration::hours(1), Utc::now())
+            .await
+            .unwrap();
+        let mut strict = config();
+        strict.bot.min_profit_percentage = 50.0;
+        let report = backtester.run("strict", &strict).await.unwrap();
+        assert_eq!(report.opportunities, 0);
+        assert_eq!(report.fills, 0);
+        assert_eq!(report.pnl, Decimal::ZERO);
+
+        // Only the first cycle, whose books offer no edge
+        let before = Backtester::load(&db, Utc::now() - Duration::hours(1), second)
+            .await
+            .unwrap();
+        assert_eq!(before.cycles(), 1);
+        assert_eq!(before.run("before", &config()).await.unwrap().opportunities, 0);
+    }
+}
+
+#[cfg(test)]
+mod mapping_tests {
+    use crate::common::MockExchange;
+    use chrono::{Duration, Utc};
+    use polymarket_kalshi_arbitrage_bot::{
+        arbitrage::{ArbitrageEngine, MappingFile, PairMapping},
+        config::Config,
+        database::Database,
+        fees::{KalshiFeeModel, PolymarketFeeModel},
+        models::{Market, Outcome, Platform},
+        paper::PaperExchange,
+    };
+    use rust_decimal::Decimal;
+    use std::{fs, path::PathBuf, sync::Arc};
+
+    fn market(platform: Platform, id: &str, question: &str, yes: i64, no: i64) -> Market {
+        let tokens = matches!(platform, Platform::Polymarket);
+        Market {
+            id: id.to_string(),
+            question: question.to_string(),
+            platform,
+            yes_price: Decimal::new(yes, 2),
+            no_price: Decimal::new(no, 2),
+            volume: Decimal::ZERO,
+            liquidity: Decimal::ZERO,
+            end_time: Utc::now() + Duration::days(1),
+            yes_token_id: tokens.then(|| "123".to_string()),
+            no_token_id: tokens.then(|| "456".to_string()),
+            rules: None,
+            resolution_source: None,
+            settlement_timer_seconds: None,
+            event_id: None,
+            event_title: None,
+            outcome_name: None,
+            neg_risk: false,
+            condition_id: None,
+        }
+    }
+
+    /// Market data only: one market with a book at its listed prices.
+    fn venue(market: Market) -> Arc<MockExchange> {
+        Arc::new(
+            MockExchange::new(market.platform.clone())
+                .with_markets(vec![market])
+                .market_data_only(),
+        )
+    }
+
+    fn mapping_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("pairs-{}-{}", std::process::id(), name))
+    }
+
+    async fn database() -> Database {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.run_migrations().await.unwrap();
+        db
+    }
+
+    /// Runs one polling cycle over a paper account on both venues and
+    /// returns the database.
+    async fn check(
+        mapping: &str,
+        name: &str,
+        poly_market: Market,
+        kalshi_market: Market,
+    ) -> Database {
+        let path = mapping_path(name);
+        fs::write(&path, mapping).unwrap();
+
+        let mut config = Config::load("config/default.toml").unwrap();
+        config.matching.mapping_file = path.to_string_lossy().into_owned();
+        config.bot.min_profit_percentage = 1.0;
+        config.bot.order_poll_interval_ms = 10;
+        config.execution.leg_timeout_seconds = 1;
+        config.paper.latency_ms = 0;
+        config.breaker.halt_file.clear();
+
+        let db = database().await;
+        let polymarket = Arc::new(PaperExchange::new(
+            venue(poly_market),
+            Box::new(PolymarketFeeModel::new(&config.fees.polymarket)),
+            db.clone(),
+            &config.paper,
+        ));
+        let kalshi = Arc::new(PaperExchange::new(
+            venue(kalshi_market),
+            Box::new(KalshiFeeModel::new(&config.fees.kalshi)),
+            db.clone(),
+            &config.paper,
+        ));
+        let engine = ArbitrageEngine::with_exchanges(config, db.clone(), true, polymarket, kalshi);
+        let tracker = tokio::spawn(engine.tracker().clone().run());
+
+        engine.check_opportunities().await.unwrap();
+        tracker.abort();
+        fs::remove_file(&path).unwrap();
+        db
+    }
+
+    #[test]
+    fn test_mapping_reads_toml_and_json() {
+        let toml_path = mapping_path("read.toml");
+        fs::write(
+            &toml_path,
+            r#"
+            [[pair]]
+            polymarket = "0xabc"
+            kalshi = "HIGHNY-T40"
+            inverted = true
+
+            [[deny]]
+            polymarket = "0xdef"
+            kalshi = "HIGHNY-B40"
+            "#,
+        )
+        .unwrap();
+        let from_toml = PairMapping::load(&toml_path).unwrap();
+        fs::remove_file(&toml_path).unwrap();
+
+        let json_path = mapping_path("read.json");
+        fs::write(
+            &json_path,
+            r#"{
+                "pair": [{"polymarket": "0xabc", "kalshi": "HIGHNY-T40", "inverted": true}],
+                "deny": [{"polymarket": "0xdef", "kalshi": "HIGHNY-B40"}]
+            }"#,
+        )
+        .unwrap();
+        let from_json = PairMapping::load(&json_path).unwrap();
+        fs::remove_file(&json_path).unwrap();
+
+        for mapping in [from_toml, from_json] {
+            assert_eq!(mapping.pairs.len(), 1);
+            assert!(mapping.pairs[0].inverted);
+            assert_eq!(mapping.denied[0].kalshi, "HIGHNY-B40");
+        }
+    }
+
+    #[test]
+    fn test_mapping_file_reloads_when_changed() {
+        let path = mapping_path("reload.toml");
+        let file = MappingFile::new(&path);
+        assert!(file.current().is_empty());
+
+        fs::write(&path, "[[pair]]\npolymarket = \"0xabc\"\nkalshi = \"A\"\n").unwrap();
+        assert_eq!(file.current().pairs.len(), 1);
+
+        fs::write(
+            &path,
+            "[[pair]]\npolymarket = \"0xabc\"\nkalshi = \"A\"\n\n\
+             [[pair]]\npolymarket = \"0xdef\"\nkalshi = \"B\"\n",
+        )
+        .unwrap();
+        assert_eq!(file.current().pairs.len(), 2);
+
+        // A broken edit keeps the last good mapping
+        fs::write(&path, "[[pair]\n").unwrap();
+        assert_eq!(file.current().pairs.len(), 2);
+
+        fs::remove_file(&path).unwrap();
+        assert!(file.current().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_curated_pair_overrides_fuzzy_matching() {
+        // Titles with nothing in common
+        let db = check(
+            "[[pair]]\npolymarket = \"123\"\nkalshi = \"HIGHNY-T40\"\n",
+            "curated.toml",
+            market(Platform::Polymarket, "0xabc", "NYC above 40°F on Jan 1?", 40, 60),
+            market(Platform::Kalshi, "HIGHNY-T40", "Highest temperature in Central Park", 55, 45),
+        )
+        .await;
+
+        let opportunities = db.get_recent_opportunities(10).await.unwrap();
+        assert_eq!(opportunities.len(), 1);
+        assert_eq!(opportunities[0].kalshi_market_id, "HIGHNY-T40");
+        assert!(!opportunities[0].kalshi_inverted);
+    }
+
+    #[tokio::test]
+    async fn test_curated_pair_names_a_market_by_condition_id() {
+        // Listed under its Gamma ID, mapped by its condition ID
+        let mut poly_market =
+            market(Platform::Polymarket, "512", "NYC above 40°F on Jan 1?", 40, 60);
+        poly_market.condition_id = Some("0xabc".to_string());
+        let db = check(
+            "[[pair]]\npolymarket = \"0xabc\"\nkalshi = \"HIGHNY-T40\"\n",
+            "condition.toml",
+            poly_market,
+            market(Platform::Kalshi, "HIGHNY-T40", "Highest temperature in Central Park", 55, 45),
+        )
+        .await;
+
+        let opportunities = db.get_recent_opportunities(10).await.unwrap();
+        assert_eq!(opportunities.len(), 1);
+        assert_eq!(opportunities[0].polymarket_market_id, "512");
+        assert_eq!(opportunities[0].kalshi_market_id, "HIGHNY-T40");
+    }
+
+    #[tokio::test]
+    async fn test_denied_pair_is_never_matched() {
+        let question = "Will the high in NYC be above 40 on Jan 1?";
+        let db = check(
+            "[[deny]]\npolymarket = \"0xabc\"\nkalshi = \"HIGHNY-T40\"\n",
+            "denied.toml",
+            market(Platform::Polymarket, "0xabc", question, 40, 60),
+            market(Platform::Kalshi, "HIGHNY-T40", question, 55, 45),
+        )
+        .await;
+
+        assert!(db.get_recent_opportunities(10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_inverted_pair_buys_the_same_outcome_on_both_venues() {
+        // YES above 40 on Polymarket is NO below 40 on Kalshi, so YES at
+        // 0.40 plus Kalshi's YES at 0.45 is the complement
+        let db = check(
+            "[[pair]]\npolymarket = \"0xabc\"\nkalshi = \"HIGHNY-B40\"\ninverted = true\n",
+            "inverted.toml",
+            market(Platform::Polymarket, "0xabc", "NYC above 40 on Jan 1?", 40, 65),
+            market(Platform::Kalshi, "HIGHNY-B40", "NYC below 40 on Jan 1?", 45, 60),
+        )
+        .await;
+
+        let opportunities = db.get_recent_opportunities(10).await.unwrap();
+        assert_eq!(opportunities.len(), 1);
+        let opportunity = &opportunities[0];
+        assert!(opportunity.kalshi_inverted);
+        assert_eq!(opportunity.yes_platform, Platform::Polymarket);
+        assert_eq!(opportunity.no_price, Decimal::new(45, 2));
+        assert!(opportunity.executed);
+
+        let kalshi = db.get_paper_positions(&Platform::Kalshi).await.unwrap();
+        let polymarket = db.get_paper_positions(&Platform::Polymarket).await.unwrap();
+        assert_eq!(kalshi[0].outcome, Outcome::Yes);
+        assert_eq!(polymarket[0].outcome, Outcome::Yes);
+        assert_eq!(kalshi[0].amount, opportunity.position_size);
+        assert_eq!(polymarket[0].amount, opportunity.position_size);
+    }
+}
+
+#[cfg(test)]
+mod review_tests {
+    use crate::common::MockExchange;
+    use chrono::{Duration, Utc};
+    use polymarket_kalshi_arbitrage_bot::{
+        arbitrage::ArbitrageEngine,
+        config::Config,
+        database::Database,
+        fees::{KalshiFeeModel, PolymarketFeeModel},
+        models::{Market, MatchStatus, Outcome, Platform},
+        paper::PaperExchange,
+    };
+    use rust_decimal::Decimal;
+    use std::sync::Arc;
+
+    const QUESTION: &str = "Will the high in NYC be above 40 on Jan 1?";
+
+    /// Market data only: one market with a book at its listed prices.
+    fn venue(platform: Platform, id: &str, yes: i64, no: i64) -> Arc<MockExchange> {
+        let tokens = matches!(platform, Platform::Polymarket);
+        let market = Market {
+            id: id.to_string(),
+            question: QUESTION.to_string(),
+            platform,
+            yes_price: Decimal::new(yes, 2),
+            no_price: Decimal::new(no, 2),
+            volume: Decimal::ZERO,
+            liquidity: Decimal::ZERO,
+            end_time: Utc::now() + Duration::days(1),
+            yes_token_id: tokens.then(|| "123".to_string()),
+            no_token_id: tokens.then(|| "456".to_string()),
+            rules: None,
+            resolution_source: None,
+            settlement_timer_seconds: None,
+            event_id: None,
+            event_title: None,
+            outcome_name: None,
+            neg_risk: false,
+            condition_id: None,
+        };
+        Arc::new(
+            MockExchange::new(market.platform.clone())
+                .with_markets(vec![market])
+                .market_data_only(),
+        )
+    }
+
+    async fn database() -> Database {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.run_migrations().await.unwrap();
+        db
+    }
+
+    /// An executing engine over paper accounts on venues listing one market
+    /// each: YES at `poly_yes` on Polymarket and YES at `kalshi_yes` on
+    /// Kalshi, each outcome's NO priced at 5 cents over its complement.
+    fn engine(db: &Database, poly_yes: i64, kalshi_yes: i64) -> ArbitrageEngine {
+        let mut config = Config::load("config/default.toml").unwrap();
+        config.matching.mapping_file = "config/no-such-pairs.toml".to_string();
+        config.bot.min_profit_percentage = 1.0;
+        config.bot.order_poll_interval_ms = 10;
+        config.execution.leg_timeout_seconds = 1;
+        config.paper.latency_ms = 0;
+        config.breaker.halt_file.clear();
+
+        let polymarket = Arc::new(PaperExchange::new(
+            venue(Platform::Polymarket, "0xabc", poly_yes, 105 - poly_yes),
+            Box::new(PolymarketFeeModel::new(&config.fees.polymarket)),
+            db.clone(),
+            &config.paper,
+        ));
+        let kalshi = Arc::new(PaperExchange::new(
+            venue(Platform::Kalshi, "HIGHNY-T40", kalshi_yes, 105 - kalshi_yes),
+            Box::new(KalshiFeeModel::new(&config.fees.kalshi)),
+            db.clone(),
+            &config.paper,
+        ));
+        ArbitrageEngine::with_exchanges(config, db.clone(), true, polymarket, kalshi)
+    }
+
+    async fn check(engine: &ArbitrageEngine) {
+        let tracker = tokio::spawn(engine.tracker().clone().run());
+        engine.check_opportunities().await.unwrap();
+        tracker.abort();
+    }
+
+    async fn decide(db: &Database, status: MatchStatus, inverted: bool) {
+        let pending = db.get_market_matches(Some(MatchStatus::Pending)).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert!(db
+            .decide_market_match(pending[0].id.unwrap(), status, inverted, Utc::now())
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_fuzzy_match_is_recorded_pending_and_not_traded() {
+        let db = database().await;
+        let engine = engine(&db, 40, 55);
+        check(&engine).await;
+
+        let pending = db.get_market_matches(Some(MatchStatus::Pending)).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].polymarket_market_id, "0xabc");
+        assert_eq!(pending[0].kalshi_market_id, "HIGHNY-T40");
+        assert_eq!(pending[0].kalshi_question, QUESTION);
+        assert_eq!(pending[0].similarity, 1.0);
+
+        let opportunities = db.get_recent_opportunities(10).await.unwrap();
+        assert_eq!(opportunities.len(), 1);
+        assert!(!opportunities[0].executed);
+        assert!(db.get_paper_accounts().await.unwrap().is_empty());
+
+        // Seeing the pair again does not add a second candidate
+        check(&engine).await;
+        assert_eq!(db.get_market_matches(None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_approved_match_is_traded_and_keeps_its_decision() {
+        let db = database().await;
+        let engine = engine(&db, 40, 55);
+        check(&engine).await;
+        decide(&db, MatchStatus::Approved, false).await;
+
+        check(&engine).await;
+        let opportunities = db.get_recent_opportunities(10).await.unwrap();
+        assert!(opportunities.iter().any(|opportunity| opportunity.executed));
+
+        let matches = db.get_market_matches(None).await.unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].status, MatchStatus::Approved);
+        assert!(matches[0].decided_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_rejected_match_is_no_longer_paired() {
+        let db = database().await;
+        let engine = engine(&db, 40, 55);
+        check(&engine).await;
+        decide(&db, MatchStatus::Rejected, false).await;
+
+        check(&engine).await;
+        assert_eq!(db.get_recent_opportunities(10).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_inverted_match_buys_the_same_outcome_on_both_venues() {
+        // Read straight, NO costs 0.65 and 0.60: no complement. Inverted,
+        // YES at 0.40 on Polymarket pairs with YES at 0.45 on Kalshi.
+        let db = database().await;
+        let engine = engine(&db, 40, 45);
+        check(&engine).await;
+        assert!(db.get_recent_opportunities(10).await.unwrap().is_empty());
+        decide(&db, MatchStatus::Approved, true).await;
+
+        check(&engine).await;
+        let opportunities = db.get_recent_opportunities(10).await.unwrap();
+        assert_eq!(opportunities.len(), 1);
+        assert!(opportunities[0].kalshi_inverted);
+        assert!(opportunities[0].executed);
+
+        let kalshi = db.get_paper_positions(&Platform::Kalshi).await.unwrap();
+        assert_eq!(kalshi[0].outcome, Outcome::Yes);
+    }
+}
+
+#[cfg(test)]
+mod matching_tests {
+    use chrono::{DateTime, Duration, Utc};
+    use polymarket_kalshi_arbitrage_bot::{
+        config::MatchingConfig,
+        matching::{MarketFeatures, MarketMatcher},
+    };
+
+    fn matcher() -> MarketMatcher {
+        MarketMatcher::new(&MatchingConfig::default())
+    }
+
+    fn features(title: &str, closes: DateTime<Utc>) -> MarketFeatures {
+        MarketFeatures::from_title(title, closes)
+    }
+
+    #[test]
+    fn test_same_question_worded_differently_matches() {
+        let closes = Utc::now() + Duration::days(30);
+        let confidence = matcher()
+            .score_features(
+                &features("Will Trump win the election on Nov 5?", closes),
+                &features("Trump's win in the election on November 5, 2024", closes),
+            )
+            .expect("same question should match");
+
+        assert!(confidence.score > 0.9, "{}", confidence);
+        let names: Vec<&str> = confidence
+            .components
+            .iter()
+            .map(|component| component.name)
+            .collect();
+        assert_eq!(names, vec!["text", "entities", "dates", "close"]);
+        assert!(confidence.to_string().contains("entities 1.00 (trump)"));
+    }
+
+    #[test]
+    fn test_threshold_and_unit_must_agree() {
+        let closes = Utc::now();
+        let matcher = matcher();
+        let above = |title: &str| features(title, closes);
+
+        assert!(matcher
+            .score_features(
+                &above("Bitcoin above $100k on Dec 31?"),
+                &above("Bitcoin above $100,000 on Dec 31?"),
+            )
+            .is_some());
+
+        let confidence = matcher.compare(
+            &above("Bitcoin above $100k on Dec 31?"),
+            &above("Bitcoin above $90k on Dec 31?"),
+        );
+        assert!(!confidence.agrees());
+        assert_eq!(
+            confidence.conflict.as_deref(),
+            Some("thresholds $100000 vs $90000")
+        );
+
+        let confidence = matcher.compare(
+            &above("Inflation above 3% in 2025?"),
+            &above("Inflation above 3 in 2025?"),
+        );
+        assert!(confidence.agrees(), "a unit given on one side only agrees");
+        let confidence = matcher.compare(
+            &above("Inflation above 3% in 2025?"),
+            &above("Inflation above $3 in 2025?"),
+        );
+        assert!(!confidence.agrees());
+    }
+
+    #[test]
+    fn test_opposite_directions_conflict() {
+        let closes = Utc::now();
+        let matcher = matcher();
+
+        let confidence = matcher.compare(
+            &features("NYC high above 40°F on Jan 1?", closes),
+            &features("NYC high below 40°F on Jan 1?", closes),
+        );
+        assert_eq!(
+            confidence.conflict.as_deref(),
+            Some("direction above vs below")
+        );
+
+        // Same side, differing only on the threshold itself: weaker, not a conflict
+        let confidence = matcher.compare(
+            &features("NYC high above 40°F on Jan 1?", closes),
+            &features("NYC high 40°F or above on Jan 1?", closes),
+        );
+        assert!(confidence.agrees());
+        let direction = confidence
+            .components
+            .iter()
+            .find(|component| component.name == "direction")
+            .unwrap();
+        assert_eq!(direction.score, 0.5);
+    }
+
+    #[test]
+    fn test_different_entities_or_dates_conflict() {
+        let closes = Utc::now();
+        let matcher = matcher();
+
+        let confidence = matcher.compare(
+            &features("Will Biden win the election?", closes),
+            &features("Will Trump win the election?", closes),
+        );
+        assert_eq!(confidence.conflict.as_deref(), Some("entities biden vs trump"));
+
+        let confidence = matcher.compare(
+            &features("Fed cuts rates on March 19?", closes),
+            &features("Fed cuts rates on May 7?", closes),
+        );
+        assert_eq!(confidence.conflict.as_deref(), Some("dates mar 19 vs may 7"));
+    }
+
+    #[test]
+    fn test_markets_closing_far_apart_do_not_match() {
+        let closes = Utc::now();
+        let title = "Will the Fed cut rates?";
+        let matcher = matcher();
+
+        let near = matcher.score_features(
+            &features(title, closes),
+            &features(title, closes + Duration::hours(20)),
+        );
+        assert_eq!(near.map(|confidence| confidence.score), Some(1.0));
+
+        let far = matcher.compare(
+            &features(title, closes),
+            &features(title, closes + Duration::days(30)),
+        );
+        assert_eq!(far.conflict.as_deref(), Some("closes 30.0 days apart"));
+    }
+}
+
+#[cfg(test)]
+mod resolution_tests {
+    use chrono::{Duration, TimeZone, Utc};
+    use polymarket_kalshi_arbitrage_bot::{
+        config::MatchingConfig,
+        database::Database,
+        matching::{MarketFeatures, MarketMatcher},
+        models::{Market, MarketMatch, MatchStatus, Platform},
+    };
+    use rust_decimal::Decimal;
+
+    const TITLE: &str = "NYC high above 40°F on Jan 1, 2026?";
+    const RULES: &str = "Resolves Yes if the high in Central Park is above 40°F on January 1, \
+        2026, as reported by the National Weather Service.";
+
+    fn market(platform: Platform, rules: &str, resolution_source: Option<&str>) -> Market {
+        Market {
+            id: format!("{}-1", platform.as_str()),
+            question: TITLE.to_string(),
+            platform,
+            yes_price: Decimal::new(50, 2),
+            no_price: Decimal::new(50, 2),
+            volume: Decimal::ZERO,
+            liquidity: Decimal::ZERO,
+            end_time: Utc.with_ymd_and_hms(2026, 1, 2, 5, 0, 0).unwrap(),
+            yes_token_id: None,
+            no_token_id: None,
+            rules: Some(rules.to_string()),
+            resolution_source: resolution_source.map(str::to_string),
+            settlement_timer_seconds: None,
+            event_id: None,
+            event_title: None,
+            outcome_name: None,
+            neg_risk: false,
+            condition_id: None,
+        }
+    }
+
+    fn matcher() -> MarketMatcher {
+        MarketMatcher::new(&MatchingConfig::default())
+    }
+
+    #[test]
+    fn test_agreeing_rules_raise_confidence() {
+        let matcher = matcher();
+        let poly = market(Platform::Polymarket, RULES, Some("https://www.weather.gov"));
+        let kalshi = market(
+            Platform::Kalshi,
+            "If the NWS reports a Central Park high above 40°F on Jan 1, 2026, then Yes.",
+            None,
+        );
+
+        let confidence = matcher.score(&poly, &kalshi).expect("same market");
+        let resolution = confidence
+            .components
+            .iter()
+            .find(|component| component.name == "resolution")
+            .expect("both venues publish rules");
+        assert_eq!(resolution.score, 1.0, "{}", confidence);
+        assert!(!confidence.has_resolution_risk());
+
+        // Titles alone give no resolution component
+        let mut bare = kalshi.clone();
+        bare.rules = None;
+        let confidence = matcher.score(&poly, &bare).unwrap();
+        assert!(confidence
+            .components
+            .iter()
+            .all(|component| component.name != "resolution"));
+    }
+
+    #[test]
+    fn test_disagreeing_rules_lower_confidence_and_flag_risk() {
+        let matcher = matcher();
+        let poly = market(Platform::Polymarket, RULES, None);
+        let agreeing = market(Platform::Kalshi, RULES, None);
+        let disagreeing = market(
+            Platform::Kalshi,
+            "Resolves Yes if the high is above 40°F at any time by January 1, 2026, \
+             11:59 PM ET, according to AccuWeather.",
+            None,
+        );
+
+        let agreed = matcher.score(&poly, &agreeing).unwrap();
+        let risky = matcher.compare(
+            &MarketFeatures::extract(&poly),
+            &MarketFeatures::extract(&disagreeing),
+        );
+        assert!(risky.agrees(), "rules never rule a pair out: {}", risky);
+        assert!(risky.score < agreed.score);
+        assert_eq!(
+            risky.resolution_risk,
+            vec![
+                "source national weather service vs accuweather",
+                "deadline on jan 1 2026 vs by jan 1 2026",
+            ]
+        );
+        assert!(risky
+            .to_string()
+            .contains("; resolution risk: source national weather service vs accuweather, "));
+    }
+
+    #[tokio::test]
+    async fn test_review_record_keeps_rules_and_risk() {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.run_migrations().await.unwrap();
+        let now = Utc::now();
+        let candidate = MarketMatch {
+            id: None,
+            polymarket_market_id: "0xcondition".to_string(),
+            kalshi_market_id: "HIGHNY-26JAN01".to_string(),
+            polymarket_question: TITLE.to_string(),
+            kalshi_question: TITLE.to_string(),
+            polymarket_end_time: now,
+            kalshi_end_time: now + Duration::hours(1),
+            similarity: 0.8,
+            explanation: String::new(),
+            polymarket_rules: Some(RULES.to_string()),
+            kalshi_rules: None,
+            resolution_risk: Some("source nws vs accuweather".to_string()),
+            status: MatchStatus::Pending,
+            inverted: false,
+            first_seen_at: now,
+            last_seen_at: now,
+            decided_at: None,
+        };
+
+        let recorded = db.record_match_candidate(&candidate).await.unwrap();
+        assert_eq!(recorded.polymarket_rules.as_deref(), Some(RULES));
+        assert_eq!(recorded.kalshi_rules, None);
+        assert_eq!(
+            recorded.resolution_risk.as_deref(),
+            Some("source nws vs accuweather")
+        );
+
+        // The risk is cleared once the rules agree again
+        let recorded = db
+            .record_match_candidate(&MarketMatch {
+                resolution_risk: None,
+                ..candidate
+            })
+            .await
+            .unwrap();
+        assert_eq!(recorded.resolution_risk, None);
+    }
+}
+
+#[cfg(test)]
+mod match_index_tests {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use polymarket_kalshi_arbitrage_bot::{
+        config::MatchingConfig,
+        matching::{MarketFeatures, MarketMatcher, MatchIndex},
+        models::{Market, Platform},
+    };
+    use rust_decimal::Decimal;
+
+    const ASSETS: &[&str] = &["Bitcoin", "Ethereum", "Solana", "Dogecoin", "Cardano"];
+    const MONTHS: &[&str] = &["Jan", "Mar", "May", "Jul", "Sep", "Nov"];
+
+    fn market(platform: Platform, id: String, question: String, closes: DateTime<Utc>) -> Market {
+        Market {
+            id,
+            question,
+            platform,
+            yes_price: Decimal::new(50, 2),
+            no_price: Decimal::new(50, 2),
+            volume: Decimal::ZERO,
+            liquidity: Decimal::ZERO,
+            end_time: closes,
+            yes_token_id: None,
+            no_token_id: None,
+            rules: None,
+            resolution_source: None,
+            settlement_timer_seconds: None,
+            event_id: None,
+            event_title: None,
+            outcome_name: None,
+            neg_risk: false,
+            condition_id: None,
+        }
+    }
+
+    /// A ladder of price markets per asset and month on each venue, worded
+    /// differently on each, plus unrelated markets.
+    fn universe() -> (Vec<Market>, Vec<Market>) {
+        let mut polymarket = Vec::new();
+        let mut kalshi = Vec::new();
+        for (a, asset) in ASSETS.iter().enumerate() {
+            for (m, month) in MONTHS.iter().enumerate() {
+                let closes = Utc.with_ymd_and_hms(2025, 2 * m as u32 + 1, 15, 0, 0, 0).unwrap();
+                for k in [50, 75, 100, 125] {
+                    polymarket.push(market(
+                        Platform::Polymarket,
+                        format!("poly-{}-{}-{}", a, m, k),
+                        format!("Will {} be above ${}k on {} 15?", asset, k, month),
+                        closes,
+                    ));
+                    kalshi.push(market(
+                        Platform::Kalshi,
+                        format!("KX-{}-{}-{}", a, m, k),
+                        format!("{} price above ${},000 on {} 15, 2025", asset, k, month),
+                        closes + Duration::hours(4),
+                    ));
+                }
+                polymarket.push(market(
+                    Platform::Polymarket,
+                    format!("poly-{}-{}-news", a, m),
+                    format!("Will {} trend on social media in {}?", asset, month),
+                    closes,
+                ));
+            }
+        }
+        (polymarket, kalshi)
+    }
+
+    fn exhaustive(
+        matcher: &MarketMatcher,
+        polymarket: &[&Market],
+        kalshi: &[&Market],
+    ) -> Vec<(usize, usize)> {
+        let kalshi_features: Vec<MarketFeatures> =
+            kalshi.iter().map(|market| MarketFeatures::extract(market)).collect();
+        let mut matches = Vec::new();
+        for (i, poly_market) in polymarket.iter().enumerate() {
+            let poly_features = MarketFeatures::extract(poly_market);
+            for (j, features) in kalshi_features.iter().enumerate() {
+                if matcher.score_features(&poly_features, features).is_some() {
+                    matches.push((i, j));
+                }
+            }
+        }
+        matches
+    }
+
+    fn pairs(matches: Vec<(usize, usize, impl Sized)>) -> Vec<(usize, usize)> {
+        let mut pairs: Vec<(usize, usize)> = matches.into_iter().map(|(i, j, _)| (i, j)).collect();
+        pairs.sort_unstable();
+        pairs
+    }
+
+    #[test]
+    fn test_index_finds_what_comparing_every_pair_finds() {
+        let matcher = MarketMatcher::new(&MatchingConfig::default());
+        let (polymarket, kalshi) = universe();
+        let polymarket: Vec<&Market> = polymarket.iter().collect();
+        let kalshi: Vec<&Market> = kalshi.iter().collect();
+
+        let mut index = MatchIndex::new();
+        let found = pairs(index.matches(&matcher, &polymarket, &kalshi));
+
+        assert_eq!(found, exhaustive(&matcher, &polymarket, &kalshi));
+        assert_eq!(found.len(), ASSETS.len() * MONTHS.len() * 4);
+        for (i, j) in found {
+            assert_eq!(polymarket[i].id.replace("poly", "KX"), kalshi[j].id);
+        }
+
+        // Only a fraction of the pairs was scored
+        let stats = index.stats();
+        assert!(stats.candidates * 5 < polymarket.len() * kalshi.len());
+        assert_eq!(stats.scored, stats.candidates);
+    }
+
+    #[test]
+    fn test_only_changed_markets_are_rescored() {
+        let matcher = MarketMatcher::new(&MatchingConfig::default());
+        let (mut polymarket, kalshi) = universe();
+        let kalshi: Vec<&Market> = kalshi.iter().collect();
+        let mut index = MatchIndex::new();
+
+        let first = {
+            let polymarket: Vec<&Market> = polymarket.iter().collect();
+            pairs(index.matches(&matcher, &polymarket, &kalshi))
+        };
+
+        // Unchanged: every candidate comes from the cache
+        let again = {
+            let polymarket: Vec<&Market> = polymarket.iter().collect();
+            pairs(index.matches(&matcher, &polymarket, &kalshi))
+        };
+        assert_eq!(again, first);
+        let stats = index.stats();
+        assert_eq!(stats.scored, 0);
+        assert_eq!(stats.cached, stats.candidates);
+
+        // One retitled market: only its candidates are scored, and it no
+        // longer matches its Jan 15 counterpart
+        polymarket[0].question = "Will Bitcoin be above $50k on Jan 16?".to_string();
+        let retitled = {
+            let polymarket: Vec<&Market> = polymarket.iter().collect();
+            pairs(index.matches(&matcher, &polymarket, &kalshi))
+        };
+        let stats = index.stats();
+        assert!(stats.scored > 0);
+        assert!(stats.scored < kalshi.len());
+        assert_eq!(retitled.len(), first.len() - 1);
+        assert!(!retitled.contains(&(0, 0)));
+
+        // Delisted markets are forgotten
+        polymarket.truncate(10);
+        let polymarket: Vec<&Market> = polymarket.iter().collect();
+        index.matches(&matcher, &polymarket, &kalshi);
+        assert_eq!(index.cached_markets(), kalshi.len() + 10);
+    }
+
+    #[test]
+    fn test_corpus_is_refit_once_enough_markets_change() {
+        let mut config = MatchingConfig::default();
+        config.scorers.tfidf = 1.0;
+        let matcher = MarketMatcher::new(&config);
+        let (mut polymarket, kalshi) = universe();
+        let kalshi: Vec<&Market> = kalshi.iter().collect();
+        let mut index = MatchIndex::new();
+
+        {
+            let polymarket: Vec<&Market> = polymarket.iter().collect();
+            index.matches(&matcher, &polymarket, &kalshi);
+        }
+        assert!(index.stats().refit);
+
+        // A market or two changing keeps the corpus and the cached scores
+        polymarket[0].question = "Will Bitcoin be above $50k on Jan 16?".to_string();
+        {
+            let polymarket: Vec<&Market> = polymarket.iter().collect();
+            index.matches(&matcher, &polymarket, &kalshi);
+        }
+        let stats = index.stats();
+        assert!(!stats.refit);
+        assert!(stats.cached > 0);
+
+        // Over a tenth of the markets gone: refit, and everything rescored
+        polymarket.truncate(polymarket.len() / 2);
+        let polymarket: Vec<&Market> = polymarket.iter().collect();
+        index.matches(&matcher, &polymarket, &kalshi);
+        let stats = index.stats();
+        assert!(stats.refit);
+        assert_eq!(stats.cached, 0);
+        assert_eq!(stats.scored, stats.candidates);
+    }
+
+    #[test]
+    fn test_markets_closing_far_apart_are_not_candidates() {
+        let matcher = MarketMatcher::new(&MatchingConfig::default());
+        let closes = Utc::now();
+        let question = "Will Bitcoin be above $100k?".to_string();
+        let poly_market = market(Platform::Polymarket, "poly".to_string(), question.clone(), closes);
+        let kalshi_market = market(
+            Platform::Kalshi,
+            "KX".to_string(),
+            question,
+            closes + Duration::days(30),
+        );
+
+        let mut index = MatchIndex::new();
+        assert!(index
+            .matches(&matcher, &[&poly_market], &[&kalshi_market])
+            .is_empty());
+        assert_eq!(index.stats().candidates, 0);
+    }
+}
+
+#[cfg(test)]
+mod scorer_tests {
+    use chrono::Utc;
+    use polymarket_kalshi_arbitrage_bot::{
+        config::MatchingConfig,
+        matching::{
+            Corpus, Ensemble, Jaccard, MarketFeatures, MarketMatcher, Matcher, TfIdf, Trigram,
+        },
+    };
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct Fixture {
+        pair: Vec<LabeledPair>,
+    }
+
+    #[derive(Deserialize)]
+    struct LabeledPair {
+        polymarket: String,
+        kalshi: String,
+        same: bool,
+    }
+
+    /// How one scorer separates the labeled pairs at its best threshold.
+    struct Report {
+        name: &'static str,
+        threshold: f64,
+        precision: f64,
+        recall: f64,
+        f1: f64,
+    }
+
+    /// Each labeled pair's features and whether it is the same question.
+    fn fixture() -> Vec<(MarketFeatures, MarketFeatures, bool)> {
+        let content = std::fs::read_to_string("tests/fixtures/title_pairs.toml").unwrap();
+        let fixture: Fixture = toml::from_str(&content).unwrap();
+        let closes = Utc::now();
+        fixture
+            .pair
+            .into_iter()
+            .map(|pair| {
+                (
+                    MarketFeatures::from_title(&pair.polymarket, closes),
+                    MarketFeatures::from_title(&pair.kalshi, closes),
+                    pair.same,
+                )
+            })
+            .collect()
+    }
+
+    fn precision_recall(scores: &[(f64, bool)], threshold: f64) -> (f64, f64) {
+        let predicted = scores.iter().filter(|(score, _)| *score >= threshold);
+        let (mut true_positives, mut positives) = (0, 0);
+        for (_, same) in predicted {
+            positives += 1;
+            if *same {
+                true_positives += 1;
+            }
+        }
+        let relevant = scores.iter().filter(|(_, same)| *same).count();
+        let precision = if positives == 0 {
+            1.0
+        } else {
+            true_positives as f64 / positives as f64
+        };
+        (precision, true_positives as f64 / relevant as f64)
+    }
+
+    /// The threshold, in steps of 0.05, with the best F1 score.
+    fn evaluate(name: &'static str, scores: &[(f64, bool)]) -> Report {
+        (1..20)
+            .map(|step| {
+                let threshold = step as f64 * 0.05;
+                let (precision, recall) = precision_recall(scores, threshold);
+                let f1 = if precision + recall == 0.0 {
+                    0.0
+                } else {
+                    2.0 * precision * recall / (precision + recall)
+                };
+                Report {
+                    name,
+                    threshold,
+                    precision,
+                    recall,
+                    f1,
+                }
+            })
+            .max_by(|a, b| a.f1.total_cmp(&b.f1))
+            .unwrap()
+    }
+
+    /// Prints each scorer's precision and recall on the labeled pairs; run
+    /// with `--nocapture` to see them when tuning `[matching]`.
+    #[test]
+    fn test_scorer_precision_and_recall() {
+        let pairs = fixture();
+        let corpus = Corpus::new(pairs.iter().flat_map(|(poly, kalshi, _)| [poly, kalshi]));
+        let config = MatchingConfig::default();
+        let scorers: Vec<Box<dyn Matcher>> = vec![
+            Box::new(Jaccard),
+            Box::new(TfIdf),
+            Box::new(Trigram),
+            Box::new(Ensemble::new(&config.scorers)),
+        ];
+
+        let reports: Vec<Report> = scorers
+            .iter()
+            .map(|scorer| {
+                let scores: Vec<(f64, bool)> = pairs
+                    .iter()
+                    .map(|(poly, kalshi, same)| (scorer.similarity(poly, kalshi, &corpus), *same))
+                    .collect();
+                evaluate(scorer.name(), &scores)
+            })
+            .collect();
+
+        // The whole matcher, structured features included, at its own threshold
+        let matcher = MarketMatcher::new(&config);
+        let scores: Vec<(f64, bool)> = pairs
+            .iter()
+            .map(|(poly, kalshi, same)| {
+                let score = matcher
+                    .score_in(poly, kalshi, &corpus)
+                    .map(|confidence| confidence.score)
+                    .unwrap_or(0.0);
+                (score, *same)
+            })
+            .collect();
+        let (precision, recall) = precision_recall(&scores, config.min_confidence);
+        let matcher_report = Report {
+            name: "matcher",
+            threshold: config.min_confidence,
+            precision,
+            recall,
+            f1: 2.0 * precision * recall / (precision + recall),
+        };
+
+        println!(
+            "{} labeled pairs, {} the same question",
+            pairs.len(),
+            pairs.iter().filter(|(_, _, same)| *same).count()
+        );
+        println!("scorer    threshold  precision  recall  f1");
+        for report in reports.iter().chain([&matcher_report]) {
+            println!(
+                "{:<9} {:>9.2} {:>10.2} {:>7.2} {:>4.2}",
+                report.name, report.threshold, report.precision, report.recall, report.f1
+            );
+        }
+
+        // Floors just under the current results, so a change making any
+        // scorer worse at this shows up here
+        for report in &reports {
+            assert!(report.f1 > 0.75, "{} f1 {:.2}", report.name, report.f1);
+        }
+        assert!(
+            matcher_report.precision >= 0.8,
+            "{:.2}",
+            matcher_report.precision
+        );
+        assert!(matcher_report.recall >= 0.9, "{:.2}", matcher_report.recall);
+    }
+}
+
+#[cfg(test)]
+mod event_tests {
+    use crate::common::MockExchange;
+    use chrono::{Duration, TimeZone, Utc};
+    use polymarket_kalshi_arbitrage_bot::{
+        arbitrage::ArbitrageEngine,
+        config::{Config, MatchingConfig},
+        database::Database,
+        fees::{KalshiFeeModel, PolymarketFeeModel},
+        matching::{match_events, MarketMatcher},
+        models::{Event, Market, MatchStatus, Platform},
+        paper::PaperExchange,
+    };
+    use rust_decimal::Decimal;
+    use std::sync::Arc;
+
+    const POLY_EVENT: &str = "New York City Mayoral Election";
+    /// Kalshi titles every outcome market of an event alike.
+    const KALSHI_QUESTION: &str = "Who will win the NYC mayoral election?";
+
+    fn outcome(
+        platform: Platform,
+        id: &str,
+        question: &str,
+        event_id: &str,
+        outcome_name: &str,
+        yes: i64,
+    ) -> Market {
+        let tokens = matches!(platform, Platform::Polymarket);
+        Market {
+            id: id.to_string(),
+            question: question.to_string(),
+            platform,
+            yes_price: Decimal::new(yes, 2),
+            no_price: Decimal::new(105 - yes, 2),
+            volume: Decimal::ZERO,
+            liquidity: Decimal::ZERO,
+            end_time: Utc.with_ymd_and_hms(2025, 11, 4, 12, 0, 0).unwrap(),
+            yes_token_id: tokens.then(|| format!("{}-yes", id)),
+            no_token_id: tokens.then(|| format!("{}-no", id)),
+            rules: None,
+            resolution_source: None,
+            settlement_timer_seconds: None,
+            event_id: Some(event_id.to_string()),
+            event_title: None,
+            outcome_name: Some(outcome_name.to_string()),
+            neg_risk: false,
+            condition_id: None,
+        }
+    }
+
+    /// The race on Polymarket, one question per candidate with their
+    /// full name, at `yes` cents each.
+    fn polymarket_race(yes: i64) -> Vec<Market> {
+        ["Zohran Mamdani", "Andrew Cuomo", "Curtis Sliwa"]
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| Market {
+                event_title: Some(POLY_EVENT.to_string()),
+                ..outcome(
+                    Platform::Polymarket,
+                    &format!("0x{}", i),
+                    &format!("Will {} win the NYC mayoral election?", name),
+                    "903",
+                    name,
+                    yes,
+                )
+            })
+            .collect()
+    }
+
+    /// The race on Kalshi, candidates by surname only, at `yes` cents each.
+    fn kalshi_race(yes: i64) -> Vec<Market> {
+        [("ZM", "Mamdani"), ("AC", "Cuomo"), ("CS", "Sliwa")]
+            .into_iter()
+            .map(|(suffix, name)| {
+                outcome(
+                    Platform::Kalshi,
+                    &format!("KXMAYORNYC-25-{}", suffix),
+                    KALSHI_QUESTION,
+                    "KXMAYORNYC-25",
+                    name,
+                    yes,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_markets_group_into_events() {
+        let mut markets = kalshi_race(50);
+        let mut single = markets[0].clone();
+        single.id = "KXSINGLE".to_string();
+        single.event_id = Some("KXSINGLE-EVENT".to_string());
+        let mut loose = markets[0].clone();
+        loose.id = "LOOSE".to_string();
+        loose.event_id = None;
+        markets.insert(1, single);
+        markets.push(loose);
+
+        let events = Event::group(&markets);
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].id, "KXMAYORNYC-25");
+        assert_eq!(events[0].title, KALSHI_QUESTION);
+        assert_eq!(events[0].markets.len(), 3);
+        assert!(events[0].is_multi_outcome());
+        assert!(!events[0].mutually_exclusive);
+        assert_eq!(events[1].id, "KXSINGLE-EVENT");
+        assert!(!events[1].is_multi_outcome());
+        // A market without an event is its own
+        assert_eq!(events[2].id, "LOOSE");
+        assert_eq!(events[2].markets[0].id, "LOOSE");
+    }
+
+    #[test]
+    fn test_events_match_outcome_by_outcome() {
+        let matcher = MarketMatcher::new(&MatchingConfig::default());
+        let poly_events = Event::group(&polymarket_race(50));
+
+        // The next race has the same candidates but closes years later
+        let later: Vec<Market> = kalshi_race(50)
+            .into_iter()
+            .map(|market| Market {
+                id: market.id.replace("-25-", "-29-"),
+                question: "Who will win the 2029 NYC mayoral election?".to_string(),
+                end_time: market.end_time + Duration::days(4 * 365),
+                event_id: Some("KXMAYORNYC-29".to_string()),
+                ..market
+            })
+            .collect();
+        let mut kalshi_markets = later;
+        kalshi_markets.extend(kalshi_race(50));
+        let kalshi_events = Event::group(&kalshi_markets);
+
+        let matches = match_events(&matcher, &poly_events, &kalshi_events);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].kalshi.id, "KXMAYORNYC-25");
+        let mut outcomes: Vec<(&str, &str, &str)> = matches[0]
+            .outcomes
+            .iter()
+            .map(|outcome| {
+                (
+                    outcome.name.as_str(),
+                    outcome.polymarket.id.as_str(),
+                    outcome.kalshi.id.as_str(),
+                )
+            })
+            .collect();
+        outcomes.sort();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("Andrew Cuomo", "0x1", "KXMAYORNYC-25-AC"),
+                ("Curtis Sliwa", "0x2", "KXMAYORNYC-25-CS"),
+                ("Zohran Mamdani", "0x0", "KXMAYORNYC-25-ZM"),
+            ]
+        );
+        assert!(matches[0].to_string().contains("3/3 outcomes mapped"));
+
+        // Sharing one candidate is not enough
+        let mut other_race = kalshi_race(50);
+        other_race[1].outcome_name = Some("Eric Adams".to_string());
+        other_race[2].outcome_name = Some("Jim Walden".to_string());
+        assert!(match_events(&matcher, &poly_events, &Event::group(&other_race)).is_empty());
+    }
+
+    /// Market data only: a listing with a book at each market's prices,
+    /// whose events say their outcomes are mutually exclusive.
+    fn venue(platform: Platform, markets: Vec<Market>) -> MockExchange {
+        MockExchange::new(platform)
+            .with_markets(markets)
+            .with_exclusive_events()
+            .market_data_only()
+    }
+
+    #[tokio::test]
+    async fn test_each_candidate_is_a_pair_to_review_and_trade() {
+        let db = Database::new("sqlite::memory:").await.unwrap();
+        db.run_migrations().await.unwrap();
+
+        let mut config = Config::load("config/default.toml").unwrap();
+        config.matching.mapping_file = "config/no-such-pairs.toml".to_string();
+        config.bot.min_profit_percentage = 1.0;
+        config.bot.order_poll_interval_ms = 10;
+        config.execution.leg_timeout_seconds = 1;
+        config.paper.latency_ms = 0;
+        config.breaker.halt_file.clear();
+
+        // YES at 0.40 on Polymarket and NO at 0.50 on Kalshi, for every
+        // candidate
+        let polymarket = Arc::new(PaperExchange::new(
+            Arc::new(venue(Platform::Polymarket, polymarket_race(40))),
+            Box::new(PolymarketFeeModel::new(&config.fees.polymarket)),
+            db.clone(),
+            &config.paper,
+        ));
+        let kalshi = Arc::new(PaperExchange::new(
+            Arc::new(venue(Platform::Kalshi, kalshi_race(55))),
+            Box::new(KalshiFeeModel::new(&config.fees.kalshi)),
+            db.clone(),
+            &config.paper,
+        ));
+        let engine = ArbitrageEngine::with_exchanges(config, db.clone(), true, polymarket, kalshi);
+
+        let check = || async {
+            let tracker = tokio::spawn(engine.tracker().clone().run());
+            engine.check_opportunities().await.unwrap();
+            tracker.abort();
+        };
+        check().await;
+
+        let mut pending: Vec<(String, String)> = db
+            .get_market_matches(Some(MatchStatus::Pending))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|candidate| (candidate.polymarket_market_id, candidate.kalshi_market_id))
+            .collect();
+        pending.sort();
+        let expected: Vec<(String, String)> = [
+            ("0x0", "KXMAYORNYC-25-ZM"),
+            ("0x1", "KXMAYORNYC-25-AC"),
+            ("0x2", "KXMAYORNYC-25-CS"),
+        ]
+        .iter()
+        .map(|(poly, kalshi)| (poly.to_string(), kalshi.to_string()))
+        .collect();
+        assert_eq!(pending, expected);
+        assert!(db.get_paper_accounts().await.unwrap().is_empty());
+
+        // Approving one candidate trades that candidate alone
+        let cuomo = db
+            .get_market_matches(Some(MatchStatus::Pending))
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|candidate| candidate.polymarket_market_id == "0x1")
+            .unwrap();
+        assert!(cuomo.explanation.starts_with("outcome Andrew Cuomo of "));
+        // The venues' event listings say only one candidate can win
+        assert!(
+            cuomo.explanation.contains("3/3 outcomes mapped, mutually exclusive"),
+            "{}",
+            cuomo.explanation
+        );
+        assert!(db
+            .decide_market_match(cuomo.id.unwrap(), MatchStatus::Approved, false, Utc::now())
+            .await
+            .unwrap());
+        check().await;
 
-        // Only 2% profit, might not be worth fees
-        let profit = (0.51 - 0.50) / 0.50;
-        assert!(profit <= 0.02);
+        let executed: Vec<String> = db
+            .get_recent_opportunities(10)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|opportunity| opportunity.executed)
+            .map(|opportunity| opportunity.kalshi_market_id)
+            .collect();
+        assert_eq!(executed, vec!["KXMAYORNYC-25-AC"]);
     }
 }